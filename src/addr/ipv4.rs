@@ -1,5 +1,6 @@
 use crate::{Domain};
-use crate::addr::ToSockAddr;
+use crate::addr::{ToSockAddr, AddrRedaction, addr_redaction};
+use crate::error::AddrError;
 
 /// IPv4 address family marker.
 ///
@@ -16,7 +17,9 @@ impl Domain for Ipv4 {
 }
 
 /// IPv4 socket address (IP + port).
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+///
+/// `Debug` honors `set_addr_redaction()`.
+#[derive(Clone, Copy, PartialEq, Eq)]
 pub struct SocketAddrV4 {
 	ip: [u8; 4],
 	port: u16,
@@ -70,15 +73,32 @@ impl SocketAddrV4 {
 }
 
 
+impl std::fmt::Debug for SocketAddrV4 {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		let [a, b, c, _] = self.ip;
+		match addr_redaction() {
+			AddrRedaction::Off => f.debug_struct("SocketAddrV4")
+				.field("ip", &self.ip)
+				.field("port", &self.port)
+				.finish(),
+			AddrRedaction::Truncate => f.debug_struct("SocketAddrV4")
+				.field("ip", &format_args!("{a}.{b}.{c}.x"))
+				.field("port", &self.port)
+				.finish(),
+			AddrRedaction::Full => f.write_str("SocketAddrV4(<redacted>)"),
+		}
+	}
+}
+
 impl ToSockAddr for SocketAddrV4 {
-	fn with_raw<F, R>(&self, f: F) -> Option<R>
+	fn with_raw<F, R>(&self, f: F) -> Result<R, AddrError>
 	where
 		F: FnOnce(*const libc::sockaddr, libc::socklen_t) -> R,
 	{
 		let raw = self.to_raw();  // sockaddr_in lives on THIS stack frame
 		let ptr = &raw as *const _ as *const libc::sockaddr;
 		let len = std::mem::size_of::<libc::sockaddr_in>() as libc::socklen_t;
-		Some(f(ptr, len))  // call the closure while raw is still alive
+		Ok(f(ptr, len))  // call the closure while raw is still alive
 	}  // raw dropped here, but closure already finished
 }
 
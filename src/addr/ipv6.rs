@@ -1,5 +1,7 @@
+use std::str::FromStr;
 use crate::{Domain};
-use crate::addr::ToSockAddr;
+use crate::addr::{ToSockAddr, AddrRedaction, SocketAddrV4, addr_redaction};
+use crate::error::{SocketError, AddrError};
 
 /// IPv6 address family marker.
 ///
@@ -19,7 +21,9 @@ impl Domain for Ipv6 {
 
 
 /// IPv6 socket address (IP + port + scope).
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+///
+/// `Debug` honors `set_addr_redaction()`.
+#[derive(Clone, Copy, PartialEq, Eq)]
 pub struct SocketAddrV6 {
 	ip: [u8; 16],
 	port: u16,
@@ -41,6 +45,39 @@ impl SocketAddrV6 {
 		Self { ip, port, scope_id }
 	}
 	
+	/// Creates from a textual IPv6 address and a port.
+	///
+	/// Accepts the standard forms (`::1`, `fe80::1`, `::ffff:192.0.2.1`),
+	/// optionally followed by `%scope`, where scope is an interface name
+	/// (`fe80::1%eth0`) or a numeric index (`fe80::1%2`).
+	pub fn parse_ip(literal: &str, port: u16) -> Result<Self, SocketError> {
+		let (ip, scope) = match literal.split_once('%') {
+			Some((ip, scope)) => (ip, Some(scope)),
+			None => (literal, None),
+		};
+
+		let ip: std::net::Ipv6Addr = ip
+			.parse()
+			.map_err(|_| SocketError::InvalidAddress { reason: "invalid IPv6 address" })?;
+
+		let scope_id = match scope {
+			None => 0,
+			Some(scope) => parse_scope(scope)?,
+		};
+
+		Ok(Self::with_scope(ip.octets(), port, scope_id))
+	}
+
+	/// The loopback address `[::1]:port`.
+	pub fn localhost(port: u16) -> Self {
+		Self::new(std::net::Ipv6Addr::LOCALHOST.octets(), port)
+	}
+
+	/// The unspecified address `[::]:port`, for binding all interfaces.
+	pub fn unspecified(port: u16) -> Self {
+		Self::new([0; 16], port)
+	}
+
 	/// Returns the IP bytes.
 	pub fn ip(&self) -> [u8; 16] {
 		self.ip
@@ -55,6 +92,35 @@ impl SocketAddrV6 {
 	pub fn scope_id(&self) -> u32 {
 		self.scope_id
 	}
+
+	/// The IPv4-mapped form `[::ffff:a.b.c.d]:port` of an IPv4 address.
+	///
+	/// How a dual-stack IPv6 socket sees IPv4 peers.
+	pub fn from_v4_mapped(addr: SocketAddrV4) -> Self {
+		let mut ip = [0u8; 16];
+		ip[10] = 0xff;
+		ip[11] = 0xff;
+		ip[12..].copy_from_slice(&addr.ip());
+		Self::new(ip, addr.port())
+	}
+
+	/// Returns true for IPv4-mapped addresses (`::ffff:0:0/96`).
+	pub fn is_v4_mapped(&self) -> bool {
+		self.ip[..10] == [0; 10] && self.ip[10..12] == [0xff, 0xff]
+	}
+
+	/// The IPv4 address behind an IPv4-mapped address, or `None`.
+	///
+	/// A dual-stack listener reports IPv4 clients as `::ffff:a.b.c.d`;
+	/// use this to log them or match them against IPv4 access rules.
+	pub fn to_v4_mapped(&self) -> Option<SocketAddrV4> {
+		if !self.is_v4_mapped() {
+			return None;
+		}
+		let mut ip = [0u8; 4];
+		ip.copy_from_slice(&self.ip[12..]);
+		Some(SocketAddrV4::new(ip, self.port))
+	}
 	
 	/// Converts to the raw sockaddr_in6 for syscalls.
 	pub(crate) fn to_raw(&self) -> libc::sockaddr_in6 {
@@ -79,6 +145,45 @@ impl SocketAddrV6 {
 	}
 }
 
+/// Parses `[addr]:port` or `[addr%scope]:port`, as printed by most tools.
+///
+/// # Example
+/// ```ignore
+/// let addr: SocketAddrV6 = "[::1]:8080".parse()?;
+/// let link: SocketAddrV6 = "[fe80::1%eth0]:8080".parse()?;
+/// ```
+impl FromStr for SocketAddrV6 {
+	type Err = SocketError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let rest = s
+			.strip_prefix('[')
+			.ok_or(SocketError::InvalidAddress { reason: "IPv6 address must be in brackets" })?;
+		let (ip, port) = rest
+			.split_once("]:")
+			.ok_or(SocketError::InvalidAddress { reason: "missing port after IPv6 address" })?;
+		let port = port
+			.parse()
+			.map_err(|_| SocketError::InvalidAddress { reason: "invalid port" })?;
+
+		Self::parse_ip(ip, port)
+	}
+}
+
+/// Resolves a `%scope` suffix: numeric index first, then interface name.
+fn parse_scope(scope: &str) -> Result<u32, SocketError> {
+	if let Ok(index) = scope.parse::<u32>() {
+		return Ok(index);
+	}
+
+	let name = std::ffi::CString::new(scope)
+		.map_err(|_| SocketError::InvalidAddress { reason: "invalid scope" })?;
+	match unsafe { libc::if_nametoindex(name.as_ptr()) } {
+		0 => Err(SocketError::InvalidAddress { reason: "unknown interface in scope" }),
+		index => Ok(index),
+	}
+}
+
 /*
 explaination for above-
  What this does:
@@ -114,14 +219,36 @@ Two extra fields compared to IPv4:
  fe80::)
 */
 
+impl std::fmt::Debug for SocketAddrV6 {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match addr_redaction() {
+			AddrRedaction::Off => f.debug_struct("SocketAddrV6")
+				.field("ip", &self.ip)
+				.field("port", &self.port)
+				.field("scope_id", &self.scope_id)
+				.finish(),
+			AddrRedaction::Truncate => {
+				// Keep the /48 routing prefix: the first three groups.
+				let group = |i: usize| u16::from_be_bytes([self.ip[i], self.ip[i + 1]]);
+				f.debug_struct("SocketAddrV6")
+					.field("ip", &format_args!("{:x}:{:x}:{:x}::x", group(0), group(2), group(4)))
+					.field("port", &self.port)
+					.field("scope_id", &self.scope_id)
+					.finish()
+			}
+			AddrRedaction::Full => f.write_str("SocketAddrV6(<redacted>)"),
+		}
+	}
+}
+
 impl ToSockAddr for SocketAddrV6 {
-	fn with_raw<F, R>(&self, f: F) -> Option<R>
+	fn with_raw<F, R>(&self, f: F) -> Result<R, AddrError>
 	where
 		F: FnOnce(*const libc::sockaddr, libc::socklen_t) -> R,
 	{
 		let raw = self.to_raw();
 		let ptr = &raw as *const _ as *const libc::sockaddr;
 		let len = std::mem::size_of::<libc::sockaddr_in6>() as libc::socklen_t;
-		Some(f(ptr, len))
+		Ok(f(ptr, len))
 	}
 }
\ No newline at end of file
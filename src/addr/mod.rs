@@ -1,16 +1,25 @@
 //! Address families and related types.
 //!
-//! This module defines the three address families supported:
+//! This module defines the address families supported:
 //! - `Ipv4` — Internet Protocol version 4
 //! - `Ipv6` — Internet Protocol version 6
 //! - `Unix` — Unix domain sockets (local only)
+//! - `Netlink` — kernel communication (routing, interfaces)
 
 mod ipv4;
 mod ipv6;
 mod unix;
+mod netlink;
+mod redact;
+mod port;
+mod std_net;
 pub use self::ipv4::{Ipv4, SocketAddrV4};
 pub use self::ipv6::{Ipv6, SocketAddrV6};
 pub use self::unix::{Unix, UnixAddr};
+pub use self::netlink::{Netlink, NetlinkAddr};
+pub use self::redact::{AddrRedaction, set_addr_redaction, addr_redaction};
+pub use self::port::{Port, Ipv4Octets, Ipv6Segments};
+use crate::error::{SocketError, AddrError};
 
 /// Trait for address family markers.
 ///
@@ -37,11 +46,42 @@ the address parameter.
  */
 
 
+/// Trait for addresses that carry a port (IPv4, IPv6).
+///
+/// Lets port-walking helpers like `bind_with_port_fallback()` work for
+/// both families.
+pub trait PortAddr: Sized {
+	fn port(&self) -> u16;
+	/// Returns a copy of this address with the port replaced.
+	fn with_port(self, port: u16) -> Self;
+}
+
+impl PortAddr for SocketAddrV4 {
+	fn port(&self) -> u16 {
+		SocketAddrV4::port(self)
+	}
+
+	fn with_port(self, port: u16) -> Self {
+		SocketAddrV4::new(self.ip(), port)
+	}
+}
+
+impl PortAddr for SocketAddrV6 {
+	fn port(&self) -> u16 {
+		SocketAddrV6::port(self)
+	}
+
+	fn with_port(self, port: u16) -> Self {
+		SocketAddrV6::with_scope(self.ip(), port, self.scope_id())
+	}
+}
+
 /// Trait for address types that can be converted to raw sockaddr for syscalls.
 pub trait ToSockAddr {
 	/// Calls the provided closure with a pointer to the raw sockaddr and its size.
-	/// Returns None if the address is invalid (e.g., path too long for Unix).
-	fn with_raw<F, R>(&self, f: F) -> Option<R>
+	/// Fails without calling it if the address doesn't fit (e.g. a Unix
+	/// path longer than sun_path).
+	fn with_raw<F, R>(&self, f: F) -> Result<R, AddrError>
 	where
 		F: FnOnce(*const libc::sockaddr, libc::socklen_t) -> R;
 }
@@ -49,7 +89,7 @@ pub trait ToSockAddr {
 - <F, R> — two generic type parameters (placeholders)
   - &self — takes a reference to the struct
   - f: F — a parameter named f of type F (we'll define what F is below)
-  - -> Option<R> — returns Option containing type R
+  - -> Result<R, AddrError> — returns R, or why the address didn't fit
 So F and R are unknowns. The where clause constrains what F can be:
 This says: "F must be something callable that takes two arguments and returns R."
 FnOnce means "a function or closure that can be called once."
@@ -76,16 +116,51 @@ The closure lets us use the stack-allocated struct while it's still alive.
 
 /// Trait for address types that can be created from raw sockaddr.
 pub trait FromSockAddr: Sized {
+	/// The address family this type decodes (AF_INET, AF_INET6, ...).
+	const FAMILY: libc::c_int;
+
 	/// Creates address from raw sockaddr storage.
 	///
+	/// Returns `None` if it's too short or of another family.
+	///
 	/// # Safety
-	/// The sockaddr must be of the correct family for this type.
+	/// `addr` must point to at least `len` readable bytes.
 	unsafe fn from_sockaddr(addr: *const libc::sockaddr, len: libc::socklen_t) -> Option<Self>;
 }
 
+/// Decodes an address the kernel returned for `A`.
+///
+/// Fails with `WrongFamily` when the sockaddr is of another family
+/// (e.g. AF_INET6 where an `Ipv4` address was expected), otherwise with
+/// `InvalidAddress { reason }`.
+///
+/// # Safety
+/// `addr` must point to at least `len` readable bytes.
+pub(crate) unsafe fn decode_sockaddr<A: FromSockAddr>(addr: *const libc::sockaddr, len: libc::socklen_t, reason: &'static str) -> Result<A, SocketError> {
+	if let Some(decoded) = unsafe { A::from_sockaddr(addr, len) } {
+		return Ok(decoded);
+	}
+	if let Some(found) = unsafe { sockaddr_family(addr, len) } && found != A::FAMILY {
+		return Err(SocketError::WrongFamily { expected: A::FAMILY, found });
+	}
+	Err(SocketError::InvalidAddress { reason })
+}
+
+/// The sa_family of a sockaddr, if it's long enough to have one.
+unsafe fn sockaddr_family(addr: *const libc::sockaddr, len: libc::socklen_t) -> Option<libc::c_int> {
+	if len < std::mem::size_of::<libc::sa_family_t>() as libc::socklen_t {
+		return None;
+	}
+	Some(unsafe { (*addr).sa_family } as libc::c_int)
+}
+
 impl FromSockAddr for SocketAddrV4 {
+	const FAMILY: libc::c_int = libc::AF_INET;
+
 	unsafe fn from_sockaddr(addr: *const libc::sockaddr, len: libc::socklen_t) -> Option<Self> {
-		if len < std::mem::size_of::<libc::sockaddr_in>() as libc::socklen_t {
+		if len < std::mem::size_of::<libc::sockaddr_in>() as libc::socklen_t
+			|| unsafe { sockaddr_family(addr, len) } != Some(libc::AF_INET)
+		{
 			return None;
 		}
 		let raw = unsafe { &*(addr as *const libc::sockaddr_in) };
@@ -94,8 +169,16 @@ impl FromSockAddr for SocketAddrV4 {
 }
 
 impl FromSockAddr for SocketAddrV6 {
+	const FAMILY: libc::c_int = libc::AF_INET6;
+
 	unsafe fn from_sockaddr(addr: *const libc::sockaddr, len: libc::socklen_t) -> Option<Self> {
-		if len < std::mem::size_of::<libc::sockaddr_in6>() as libc::socklen_t {
+		// An AF_INET address on an IPv6 socket is an IPv4 peer; report it
+		// the way dual-stack sockets do, as ::ffff:a.b.c.d.
+		let family = unsafe { sockaddr_family(addr, len) };
+		if family == Some(libc::AF_INET) {
+			return unsafe { SocketAddrV4::from_sockaddr(addr, len) }.map(SocketAddrV6::from_v4_mapped);
+		}
+		if len < std::mem::size_of::<libc::sockaddr_in6>() as libc::socklen_t || family != Some(libc::AF_INET6) {
 			return None;
 		}
 		let raw =unsafe { &*(addr as *const libc::sockaddr_in6) };
@@ -104,11 +187,13 @@ impl FromSockAddr for SocketAddrV6 {
 }
 
 impl FromSockAddr for UnixAddr {
+	const FAMILY: libc::c_int = libc::AF_UNIX;
+
 	unsafe fn from_sockaddr(addr: *const libc::sockaddr, len: libc::socklen_t) -> Option<Self> {
-		if len < std::mem::size_of::<libc::sa_family_t>() as libc::socklen_t {
+		if unsafe { sockaddr_family(addr, len) } != Some(libc::AF_UNIX) {
 			return None;
 		}
 		let raw = unsafe {&*(addr as *const libc::sockaddr_un) };
-		Some(Self::from_raw(raw))
+		Some(Self::from_raw(raw, len))
 	}
 }
\ No newline at end of file
@@ -0,0 +1,98 @@
+use crate::{Domain};
+use crate::addr::{ToSockAddr, FromSockAddr};
+use crate::error::AddrError;
+
+/// Netlink address family marker.
+///
+/// Netlink sockets talk to the kernel (routing, interfaces, ...) rather
+/// than to a network peer. Use with `Datagram` and a `NETLINK_*` protocol.
+pub struct Netlink;
+
+impl Domain for Netlink {
+	type Addr = NetlinkAddr;
+
+	#[inline]
+	fn raw() -> libc::c_int {
+		libc::AF_NETLINK
+	}
+}
+
+/// Netlink socket address (port id + multicast groups).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NetlinkAddr {
+	/// Port id. 0 is the kernel, or "let the kernel assign" when binding.
+	pid: u32,
+	/// Bitmask of multicast groups to receive notifications from.
+	groups: u32,
+}
+
+impl NetlinkAddr {
+	/// Creates a new netlink address.
+	pub fn new(pid: u32, groups: u32) -> Self {
+		Self { pid, groups }
+	}
+
+	/// The kernel's address (pid 0, no groups).
+	///
+	/// Also the usual bind address: the kernel then assigns a unique pid.
+	pub fn kernel() -> Self {
+		Self { pid: 0, groups: 0 }
+	}
+
+	/// Returns the port id.
+	pub fn pid(&self) -> u32 {
+		self.pid
+	}
+
+	/// Returns the multicast group mask.
+	pub fn groups(&self) -> u32 {
+		self.groups
+	}
+
+	/// Converts to the raw sockaddr_nl for syscalls.
+	pub(crate) fn to_raw(self) -> libc::sockaddr_nl {
+		let mut raw: libc::sockaddr_nl = unsafe { std::mem::zeroed() };
+		raw.nl_family = libc::AF_NETLINK as libc::sa_family_t;
+		raw.nl_pid = self.pid;
+		raw.nl_groups = self.groups;
+		raw
+	}
+
+	/// Creates from raw sockaddr_nl.
+	pub(crate) fn from_raw(raw: &libc::sockaddr_nl) -> Self {
+		Self { pid: raw.nl_pid, groups: raw.nl_groups }
+	}
+}
+
+impl ToSockAddr for NetlinkAddr {
+	fn with_raw<F, R>(&self, f: F) -> Result<R, AddrError>
+	where
+		F: FnOnce(*const libc::sockaddr, libc::socklen_t) -> R,
+	{
+		let raw = self.to_raw();
+		let ptr = &raw as *const _ as *const libc::sockaddr;
+		let len = std::mem::size_of::<libc::sockaddr_nl>() as libc::socklen_t;
+		Ok(f(ptr, len))
+	}
+}
+
+impl FromSockAddr for NetlinkAddr {
+	const FAMILY: libc::c_int = libc::AF_NETLINK;
+
+	unsafe fn from_sockaddr(addr: *const libc::sockaddr, len: libc::socklen_t) -> Option<Self> {
+		if len < std::mem::size_of::<libc::sockaddr_nl>() as libc::socklen_t
+			|| unsafe { (*addr).sa_family } != libc::AF_NETLINK as libc::sa_family_t
+		{
+			return None;
+		}
+		let raw = unsafe { &*(addr as *const libc::sockaddr_nl) };
+		Some(Self::from_raw(raw))
+	}
+}
+
+/*
+No IP, no port — a netlink address is just:
+- nl_pid:    who (0 = kernel; for user sockets, usually assigned on bind)
+- nl_groups: which multicast notification groups to join (bitmask)
+Messages themselves carry their own headers (nlmsghdr), see route.rs.
+*/
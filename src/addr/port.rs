@@ -0,0 +1,206 @@
+use std::num::NonZeroU16;
+use std::str::FromStr;
+use crate::addr::{SocketAddrV4, SocketAddrV6};
+use crate::error::SocketError;
+
+/// A TCP/UDP port number.
+///
+/// Zero isn't a port: binding to it asks the kernel to pick one, and
+/// connecting to it fails. `Port` keeps that case apart as `Port::ANY`,
+/// so a 0 that slipped in from a config file or an unset variable is
+/// rejected where it's parsed instead of silently binding a random port.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Port(Option<NonZeroU16>);
+
+impl Port {
+	/// "Any free port": the kernel picks an ephemeral one at bind time.
+	pub const ANY: Port = Port(None);
+
+	/// A specific port; `None` for 0.
+	pub const fn new(port: u16) -> Option<Self> {
+		match NonZeroU16::new(port) {
+			Some(port) => Some(Port(Some(port))),
+			None => None,
+		}
+	}
+
+	/// The port number (0 for `ANY`).
+	pub const fn get(self) -> u16 {
+		match self.0 {
+			Some(port) => port.get(),
+			None => 0,
+		}
+	}
+
+	/// Returns true for `Port::ANY`.
+	pub const fn is_any(self) -> bool {
+		self.0.is_none()
+	}
+
+	/// The ports `first..=last` in order, leaving out 0; for
+	/// `bind_with_port_fallback()`.
+	pub fn range(first: u16, last: u16) -> impl Iterator<Item = Port> {
+		(first..=last).filter_map(Port::new)
+	}
+}
+
+impl From<NonZeroU16> for Port {
+	fn from(port: NonZeroU16) -> Self {
+		Port(Some(port))
+	}
+}
+
+impl From<Port> for u16 {
+	fn from(port: Port) -> Self {
+		port.get()
+	}
+}
+
+impl TryFrom<u16> for Port {
+	type Error = SocketError;
+
+	/// Fails for 0; use `Port::ANY` to mean "any port".
+	fn try_from(port: u16) -> Result<Self, SocketError> {
+		Port::new(port).ok_or(SocketError::InvalidAddress { reason: "port 0 (use Port::ANY for any port)" })
+	}
+}
+
+impl FromStr for Port {
+	type Err = SocketError;
+
+	/// Parses a decimal port, 1-65535.
+	fn from_str(s: &str) -> Result<Self, SocketError> {
+		let port: u16 = s.parse().map_err(|_| SocketError::InvalidAddress { reason: "invalid port number" })?;
+		Port::try_from(port)
+	}
+}
+
+impl std::fmt::Debug for Port {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self.0 {
+			Some(port) => write!(f, "Port({})", port),
+			None => f.write_str("Port::ANY"),
+		}
+	}
+}
+
+impl std::fmt::Display for Port {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{}", self.get())
+	}
+}
+
+/// The four octets of an IPv4 address, most significant first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Ipv4Octets(pub [u8; 4]);
+
+impl Ipv4Octets {
+	/// 127.0.0.1
+	pub const LOCALHOST: Ipv4Octets = Ipv4Octets([127, 0, 0, 1]);
+	/// 0.0.0.0, for binding all interfaces.
+	pub const UNSPECIFIED: Ipv4Octets = Ipv4Octets([0, 0, 0, 0]);
+	/// 255.255.255.255
+	pub const BROADCAST: Ipv4Octets = Ipv4Octets([255, 255, 255, 255]);
+
+	/// `a.b.c.d`, e.g. `Ipv4Octets::new(10, 0, 0, 1)`.
+	pub const fn new(a: u8, b: u8, c: u8, d: u8) -> Self {
+		Ipv4Octets([a, b, c, d])
+	}
+
+	/// The address as 4 bytes in network order.
+	pub const fn octets(self) -> [u8; 4] {
+		self.0
+	}
+}
+
+impl From<[u8; 4]> for Ipv4Octets {
+	fn from(octets: [u8; 4]) -> Self {
+		Ipv4Octets(octets)
+	}
+}
+
+impl From<std::net::Ipv4Addr> for Ipv4Octets {
+	fn from(ip: std::net::Ipv4Addr) -> Self {
+		Ipv4Octets(ip.octets())
+	}
+}
+
+/// The eight 16-bit segments of an IPv6 address, as written
+/// (`2001:db8::1` is `[0x2001, 0xdb8, 0, 0, 0, 0, 0, 1]`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Ipv6Segments(pub [u16; 8]);
+
+impl Ipv6Segments {
+	/// ::1
+	pub const LOCALHOST: Ipv6Segments = Ipv6Segments([0, 0, 0, 0, 0, 0, 0, 1]);
+	/// ::, for binding all interfaces.
+	pub const UNSPECIFIED: Ipv6Segments = Ipv6Segments([0; 8]);
+
+	/// `a:b:c:d:e:f:g:h`, e.g. `Ipv6Segments::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1)`.
+	#[allow(clippy::too_many_arguments)] // One per segment, like Ipv6Addr::new().
+	pub const fn new(a: u16, b: u16, c: u16, d: u16, e: u16, f: u16, g: u16, h: u16) -> Self {
+		Ipv6Segments([a, b, c, d, e, f, g, h])
+	}
+
+	/// The eight segments, most significant first.
+	pub const fn segments(self) -> [u16; 8] {
+		self.0
+	}
+
+	/// The address as 16 bytes in network order.
+	pub fn octets(self) -> [u8; 16] {
+		let mut bytes = [0u8; 16];
+		for (chunk, segment) in bytes.chunks_exact_mut(2).zip(self.0) {
+			chunk.copy_from_slice(&segment.to_be_bytes());
+		}
+		bytes
+	}
+}
+
+impl From<[u16; 8]> for Ipv6Segments {
+	fn from(segments: [u16; 8]) -> Self {
+		Ipv6Segments(segments)
+	}
+}
+
+impl From<std::net::Ipv6Addr> for Ipv6Segments {
+	fn from(ip: std::net::Ipv6Addr) -> Self {
+		Ipv6Segments(ip.segments())
+	}
+}
+
+impl SocketAddrV4 {
+	/// Creates an address from typed parts.
+	///
+	/// Unlike `new()`, neither argument can be mistaken for the other,
+	/// and port 0 has to be spelled `Port::ANY`.
+	pub fn from_parts(ip: Ipv4Octets, port: Port) -> Self {
+		SocketAddrV4::new(ip.octets(), port.get())
+	}
+
+	/// The port, with 0 as `Port::ANY`.
+	pub fn typed_port(&self) -> Port {
+		Port::new(self.port()).unwrap_or(Port::ANY)
+	}
+}
+
+impl SocketAddrV6 {
+	/// Creates an address from typed parts (scope ID 0).
+	pub fn from_parts(ip: Ipv6Segments, port: Port) -> Self {
+		SocketAddrV6::new(ip.octets(), port.get())
+	}
+
+	/// The port, with 0 as `Port::ANY`.
+	pub fn typed_port(&self) -> Port {
+		Port::new(self.port()).unwrap_or(Port::ANY)
+	}
+}
+
+/*
+Why Option<NonZeroU16> and not NonZeroU16 with a separate "any" type?
+Port 0 is legitimate exactly once — binding, to get an ephemeral port —
+and everywhere an address is stored it has to fit in the same field as
+a real port. Option<NonZeroU16> is still two bytes, makes the "any"
+case a named constant instead of a magic number, and leaves the u16
+constructors in place for code that already has a validated port.
+*/
@@ -0,0 +1,51 @@
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// How socket addresses appear in `Debug` output and error messages.
+///
+/// Applies process-wide: bind/connect errors render addresses through
+/// `Debug`, so one setting covers logs and errors alike.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AddrRedaction {
+	/// Full addresses (default).
+	#[default]
+	Off,
+
+	/// Keep only the network part: IPv4 /24, IPv6 /48. Ports are kept,
+	/// Unix paths are local and shown as-is.
+	Truncate,
+
+	/// Hide addresses entirely.
+	Full,
+}
+
+static MODE: AtomicU8 = AtomicU8::new(0);
+
+/// Sets address redaction for the whole process.
+///
+/// Call once at startup, before any errors are formatted.
+pub fn set_addr_redaction(mode: AddrRedaction) {
+	let raw = match mode {
+		AddrRedaction::Off => 0,
+		AddrRedaction::Truncate => 1,
+		AddrRedaction::Full => 2,
+	};
+	MODE.store(raw, Ordering::Relaxed);
+}
+
+/// Returns the current address redaction mode.
+pub fn addr_redaction() -> AddrRedaction {
+	match MODE.load(Ordering::Relaxed) {
+		1 => AddrRedaction::Truncate,
+		2 => AddrRedaction::Full,
+		_ => AddrRedaction::Off,
+	}
+}
+
+/*
+Why a global and not a per-error option?
+Addresses are formatted deep inside bind()/connect() when the error is
+built, long before the caller sees it. Threading a setting through every
+constructor would touch the whole API for what is a deployment decision,
+made once. Relaxed ordering is enough: a log line racing the setter may
+see either mode, and nothing else depends on the value.
+*/
@@ -0,0 +1,103 @@
+//! Conversions between wirelane and `std::net` addresses.
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use crate::addr::{SocketAddrV4, SocketAddrV6};
+use crate::error::SocketError;
+
+impl From<std::net::SocketAddrV4> for SocketAddrV4 {
+	fn from(addr: std::net::SocketAddrV4) -> Self {
+		SocketAddrV4::new(addr.ip().octets(), addr.port())
+	}
+}
+
+impl From<SocketAddrV4> for std::net::SocketAddrV4 {
+	fn from(addr: SocketAddrV4) -> Self {
+		std::net::SocketAddrV4::new(Ipv4Addr::from(addr.ip()), addr.port())
+	}
+}
+
+/// The flow label is dropped: wirelane addresses don't carry one.
+impl From<std::net::SocketAddrV6> for SocketAddrV6 {
+	fn from(addr: std::net::SocketAddrV6) -> Self {
+		SocketAddrV6::with_scope(addr.ip().octets(), addr.port(), addr.scope_id())
+	}
+}
+
+impl From<SocketAddrV6> for std::net::SocketAddrV6 {
+	fn from(addr: SocketAddrV6) -> Self {
+		std::net::SocketAddrV6::new(Ipv6Addr::from(addr.ip()), addr.port(), 0, addr.scope_id())
+	}
+}
+
+impl From<SocketAddrV4> for std::net::SocketAddr {
+	fn from(addr: SocketAddrV4) -> Self {
+		std::net::SocketAddr::V4(addr.into())
+	}
+}
+
+impl From<SocketAddrV6> for std::net::SocketAddr {
+	fn from(addr: SocketAddrV6) -> Self {
+		std::net::SocketAddr::V6(addr.into())
+	}
+}
+
+/// Fails for IPv6 addresses — no implicit unmapping of `::ffff:a.b.c.d`.
+impl TryFrom<std::net::SocketAddr> for SocketAddrV4 {
+	type Error = SocketError;
+
+	fn try_from(addr: std::net::SocketAddr) -> Result<Self, SocketError> {
+		match addr {
+			std::net::SocketAddr::V4(addr) => Ok(addr.into()),
+			std::net::SocketAddr::V6(_) => Err(SocketError::InvalidAddress { reason: "expected an IPv4 address" }),
+		}
+	}
+}
+
+/// Fails for IPv4 addresses — map them explicitly if a dual-stack
+/// socket should reach them.
+impl TryFrom<std::net::SocketAddr> for SocketAddrV6 {
+	type Error = SocketError;
+
+	fn try_from(addr: std::net::SocketAddr) -> Result<Self, SocketError> {
+		match addr {
+			std::net::SocketAddr::V6(addr) => Ok(addr.into()),
+			std::net::SocketAddr::V4(_) => Err(SocketError::InvalidAddress { reason: "expected an IPv6 address" }),
+		}
+	}
+}
+
+impl From<(Ipv4Addr, u16)> for SocketAddrV4 {
+	fn from((ip, port): (Ipv4Addr, u16)) -> Self {
+		SocketAddrV4::new(ip.octets(), port)
+	}
+}
+
+impl From<(Ipv6Addr, u16)> for SocketAddrV6 {
+	fn from((ip, port): (Ipv6Addr, u16)) -> Self {
+		SocketAddrV6::new(ip.octets(), port)
+	}
+}
+
+impl TryFrom<(IpAddr, u16)> for SocketAddrV4 {
+	type Error = SocketError;
+
+	fn try_from((ip, port): (IpAddr, u16)) -> Result<Self, SocketError> {
+		std::net::SocketAddr::new(ip, port).try_into()
+	}
+}
+
+impl TryFrom<(IpAddr, u16)> for SocketAddrV6 {
+	type Error = SocketError;
+
+	fn try_from((ip, port): (IpAddr, u16)) -> Result<Self, SocketError> {
+		std::net::SocketAddr::new(ip, port).try_into()
+	}
+}
+
+/*
+Why no From<std::net::SocketAddr> in either direction for one type?
+A std SocketAddr is "v4 or v6", while every wirelane address is tied to
+one Domain. The family has to be checked, so it's TryFrom — and mapped
+addresses stay as they are, because whether ::ffff:a.b.c.d should
+become a v4 address depends on the socket it's used with.
+*/
@@ -1,5 +1,13 @@
+use std::ffi::OsStr;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
 use crate::{Domain};
-use crate::addr::ToSockAddr;
+use crate::addr::{ToSockAddr, AddrRedaction, addr_redaction};
+use crate::error::AddrError;
+
+/// Longest filesystem path that fits sun_path with its null terminator.
+const MAX_PATH_LEN: usize = 107;
 
 /// Unix domain socket address (file path).
 
@@ -9,7 +17,9 @@ use crate::addr::ToSockAddr;
 /// Only works on the same machine.
 pub struct Unix;
 /// Unix domain socket address (file path or abstract).
-#[derive(Debug, Clone, PartialEq, Eq)]
+///
+/// `Debug` honors `set_addr_redaction()`.
+#[derive(Clone, PartialEq, Eq)]
 pub struct UnixAddr {
 	path: Vec<u8>,
 	/// True if this is an abstract socket (Linux-only, no filesystem entry).
@@ -18,21 +28,55 @@ pub struct UnixAddr {
 
 impl UnixAddr {
 	/// Creates a new Unix address from a filesystem path.
+	///
+	/// Doesn't check the path; a NUL byte in it is only caught when the
+	/// address is used.
+	#[deprecated(note = "use UnixAddr::try_new(), which rejects NUL bytes and over-long paths")]
 	pub fn new<P: AsRef<[u8]>>(path: P) -> Self {
-		Self {
-			path: path.as_ref().to_vec(),
-			is_abstract: false,
-		}
+		Self::from_path_bytes(path.as_ref().to_vec())
 	}
 	
 	/// Creates from a string path.
+	#[deprecated(note = "use UnixAddr::try_new(), which rejects NUL bytes and over-long paths")]
 	pub fn from_str(path: &str) -> Self {
+		Self::from_path_bytes(path.as_bytes().to_vec())
+	}
+
+	/// Creates a filesystem address, checking the path up front.
+	///
+	/// Fails if the path is empty (use `unnamed()` for that), contains a
+	/// NUL byte (the kernel would silently cut it there) or is longer than
+	/// sun_path allows.
+	pub fn try_new<P: AsRef<[u8]>>(path: P) -> Result<Self, AddrError> {
+		let path = path.as_ref();
+		if path.is_empty() {
+			return Err(AddrError::EmptyPath);
+		}
+		check_path(path)?;
+		Ok(Self::from_path_bytes(path.to_vec()))
+	}
+
+	/// A filesystem address from bytes already known to be valid.
+	pub(crate) fn from_path_bytes(path: Vec<u8>) -> Self {
 		Self {
-			path: path.as_bytes().to_vec(),
+			path,
 			is_abstract: false,
 		}
 	}
 	
+	/// Creates a filesystem address from a path; same checks as `try_new()`.
+	pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, AddrError> {
+		Self::try_new(path.as_ref().as_os_str().as_bytes())
+	}
+
+	/// Returns the filesystem path, or `None` for abstract and unnamed addresses.
+	pub fn as_path(&self) -> Option<&Path> {
+		if self.is_abstract || self.path.is_empty() {
+			return None;
+		}
+		Some(Path::new(OsStr::from_bytes(&self.path)))
+	}
+
 	/// Creates an abstract socket address (Linux-only).
 	///
 	/// Abstract sockets exist only in memory — no filesystem entry.
@@ -45,6 +89,43 @@ impl UnixAddr {
 		}
 	}
 	
+	/// Creates an abstract address no other socket is using: `prefix`,
+	/// then the pid and a random suffix (`"{prefix}-{pid}-{hex}"`).
+	///
+	/// For test suites and per-process control sockets — nothing to
+	/// clean up, and parallel runs don't collide. Keep `prefix` under
+	/// 80 bytes or bind() fails with an address-too-long error.
+	pub fn abstract_unique<P: AsRef<[u8]>>(prefix: P) -> Self {
+		static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+		// The pid and counter already make names unique within this boot;
+		// the random part only guards against pid reuse.
+		let mut random = [0u8; 8];
+		crate::fill_random(&mut random);
+		let suffix = u64::from_ne_bytes(random) ^ COUNTER.fetch_add(1, Ordering::Relaxed);
+
+		let mut name = prefix.as_ref().to_vec();
+		name.extend_from_slice(format!("-{}-{:016x}", std::process::id(), suffix).as_bytes());
+		Self::abstract_socket(name)
+	}
+
+	/// The unnamed address: no path, no abstract name.
+	///
+	/// Peers that never bound (e.g. a client that only connected, or one
+	/// end of `pair()`) report this. Binding to it makes the kernel
+	/// autobind a unique abstract name (see `ConnectedStream::local_addr()`).
+	pub fn unnamed() -> Self {
+		Self {
+			path: Vec::new(),
+			is_abstract: false,
+		}
+	}
+
+	/// Returns true for the unnamed address.
+	pub fn is_unnamed(&self) -> bool {
+		!self.is_abstract && self.path.is_empty()
+	}
+
 	/// Returns true if this is an abstract socket.
 	pub fn is_abstract(&self) -> bool {
 		self.is_abstract
@@ -56,14 +137,14 @@ impl UnixAddr {
 	}
 	
 	/// Converts to the raw sockaddr_un for syscalls.
-	pub(crate) fn to_raw(&self) -> Option<libc::sockaddr_un> {
+	pub(crate) fn to_raw(&self) -> Result<libc::sockaddr_un, AddrError> {
 		let mut addr: libc::sockaddr_un = unsafe { std::mem::zeroed() };
 		addr.sun_family = libc::AF_UNIX as libc::sa_family_t;
 		
 		if self.is_abstract {
 			// Abstract: first byte is null, then the name
 			if self.path.len() + 1 >= addr.sun_path.len() {
-				return None;
+				return Err(AddrError::PathTooLong { len: self.path.len(), max: addr.sun_path.len() - 2 });
 			}
 			// sun_path[0] is already 0 from zeroed()
 			for (i, &byte) in self.path.iter().enumerate() {
@@ -71,19 +152,24 @@ impl UnixAddr {
 			}
 		} else {
 			// Filesystem path: null-terminated
-			if self.path.len() >= addr.sun_path.len() {
-				return None;
-			}
+			check_path(&self.path)?;
 			for (i, &byte) in self.path.iter().enumerate() {
 				addr.sun_path[i] = byte as libc::c_char;
 			}
 		}
 		
-		Some(addr)
+		Ok(addr)
 	}
 	
 	/// Creates from raw sockaddr_un.
-	pub(crate) fn from_raw(raw: &libc::sockaddr_un) -> Self {
+	///
+	/// `len` is the address length the kernel reported: only the family
+	/// means unnamed, whatever sun_path happens to contain.
+	pub(crate) fn from_raw(raw: &libc::sockaddr_un, len: libc::socklen_t) -> Self {
+		if len as usize <= std::mem::size_of::<libc::sa_family_t>() {
+			return Self::unnamed();
+		}
+
 		// Check if abstract (first byte is null but there's more data)
 		if raw.sun_path[0] == 0 {
 			// Abstract socket — find the end
@@ -115,28 +201,64 @@ impl UnixAddr {
 	}
 }
 
-
+/// A filesystem path must fit sun_path with its terminator, and a NUL
+/// inside it would end the path early.
+fn check_path(path: &[u8]) -> Result<(), AddrError> {
+	if let Some(at) = path.iter().position(|&b| b == 0) {
+		return Err(AddrError::PathContainsNul { at });
+	}
+	if path.len() > MAX_PATH_LEN {
+		return Err(AddrError::PathTooLong { len: path.len(), max: MAX_PATH_LEN });
+	}
+	Ok(())
+}
 
 /*
 - No port — Unix sockets don't use ports
 - Path instead — like /tmp/app.sock
 - Vec<u8> — paths can vary in length
 
-Why Option? Unix socket paths have a maximum length
+Why can to_raw() fail? Unix socket paths have a maximum length
 (typically 108 bytes, with one reserved for null terminator).
-If the path is too long, we return None rather than silently
-truncating or causing undefined behavior.
+If the path is too long, we return AddrError::PathTooLong rather
+than silently truncating or causing undefined behavior.
 */
 
+impl std::fmt::Debug for UnixAddr {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match addr_redaction() {
+			AddrRedaction::Off | AddrRedaction::Truncate => f.debug_struct("UnixAddr")
+				.field("path", &self.path)
+				.field("is_abstract", &self.is_abstract)
+				.finish(),
+			AddrRedaction::Full => f.write_str("UnixAddr(<redacted>)"),
+		}
+	}
+}
+
+impl TryFrom<&Path> for UnixAddr {
+	type Error = AddrError;
+
+	/// Same as `UnixAddr::from_path()`.
+	fn try_from(path: &Path) -> Result<Self, AddrError> {
+		Self::from_path(path)
+	}
+}
+
 impl ToSockAddr for UnixAddr {
-	fn with_raw<F, R>(&self, f: F) -> Option<R>
+	fn with_raw<F, R>(&self, f: F) -> Result<R, AddrError>
 	where
 		F: FnOnce(*const libc::sockaddr, libc::socklen_t) -> R,
 	{
-		let raw = self.to_raw()?;  // Returns None if path too long
+		let raw = self.to_raw()?;  // Fails if the path is too long
 		let ptr = &raw as *const _ as *const libc::sockaddr;
-		let len = std::mem::size_of::<libc::sockaddr_un>() as libc::socklen_t;
-		Some(f(ptr, len))
+		// Family only: bind() autobinds, the kernel's "unnamed" encoding.
+		let len = if self.is_unnamed() {
+			std::mem::size_of::<libc::sa_family_t>()
+		} else {
+			std::mem::size_of::<libc::sockaddr_un>()
+		} as libc::socklen_t;
+		Ok(f(ptr, len))
 	}
 }
 impl Domain for Unix {
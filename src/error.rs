@@ -24,6 +24,43 @@ pub enum SocketError {
     
     #[error("invalid address: {reason}")]
     InvalidAddress { reason: &'static str },
+
+    #[error("invalid address: {0}")]
+    Address(#[from] AddrError),
+
+    #[error("invalid argument: {reason}")]
+    InvalidInput { reason: &'static str },
+
+    #[error("bind({addr}) failed: no free port in {first}..={last}")]
+    NoFreePort { addr: String, first: u16, last: u16 },
+
+    #[error("socket is not {expected}")]
+    WrongSocketKind { expected: &'static str },
+
+    #[error("address family mismatch: expected {}, got {}", family_to_str(*.expected), family_to_str(*.found))]
+    WrongFamily { expected: i32, found: i32 },
+
+    #[error("dup() failed: {}", errno_to_str(*.errno))]
+    Dup { errno: i32 },
+
+    #[error("mmap({region}) failed: {}", errno_to_str(*.errno))]
+    Mmap { errno: i32, region: &'static str },
+
+    #[error("resolving {host} failed: {}", gai_to_str(*.code, *.errno))]
+    Resolve { host: String, code: i32, errno: i32 },
+}
+
+/// Why an address can't be turned into a sockaddr.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum AddrError {
+    #[error("Unix socket path is {len} bytes, at most {max} fit in sun_path")]
+    PathTooLong { len: usize, max: usize },
+
+    #[error("Unix socket path has a NUL byte at offset {at}, where the kernel would cut it")]
+    PathContainsNul { at: usize },
+
+    #[error("Unix socket path is empty; use UnixAddr::unnamed() for the unnamed address")]
+    EmptyPath,
 }
 
 /// I/O operation errors.
@@ -37,12 +74,36 @@ pub enum IoError {
     
     #[error("connection closed by peer")]
     ConnectionClosed,
+
+    #[error("peer has gone away, nothing more can be written")]
+    PeerClosed,
     
     #[error("operation would block")]
     WouldBlock,
     
     #[error("interrupted by signal")]
     Interrupted,
+    
+    #[error("poll() failed: {}", errno_to_str(*.errno))]
+    Poll { errno: i32 },
+
+    #[error("frame of {len} bytes exceeds limit of {max}")]
+    FrameTooLarge { len: usize, max: usize },
+
+    #[error("malformed frame: {reason}")]
+    MalformedFrame { reason: &'static str },
+
+    #[error("operation cancelled")]
+    Cancelled,
+
+    #[error("file ended at offset {offset}, inside the range being sent")]
+    FileEnded { offset: u64 },
+
+    #[error("datagram carried no {cmsg} control message; is the option enabled?")]
+    MissingControlMessage { cmsg: &'static str },
+
+    #[error("control data truncated (MSG_CTRUNC), passed fds were lost")]
+    ControlTruncated,
 }
 
 /// Returns current errno value.
@@ -75,6 +136,27 @@ fn errno_to_str(errno: i32) -> String {
     }
 }
 
+/// Describes a getaddrinfo() failure; EAI_SYSTEM carries an errno.
+fn gai_to_str(code: i32, errno: i32) -> String {
+    if code == libc::EAI_SYSTEM {
+        return errno_to_str(errno);
+    }
+    let msg = unsafe { std::ffi::CStr::from_ptr(libc::gai_strerror(code)) };
+    msg.to_string_lossy().into_owned()
+}
+
+/// Converts an address family to a readable name.
+fn family_to_str(family: i32) -> String {
+    match family {
+        libc::AF_INET => "AF_INET".into(),
+        libc::AF_INET6 => "AF_INET6".into(),
+        libc::AF_UNIX => "AF_UNIX".into(),
+        libc::AF_NETLINK => "AF_NETLINK".into(),
+        libc::AF_PACKET => "AF_PACKET".into(),
+        _ => format!("family {}", family),
+    }
+}
+
 /// Maps errno to std::io::ErrorKind.
 fn errno_to_kind(errno: i32) -> std::io::ErrorKind {
     match errno {
@@ -104,6 +186,21 @@ impl From<SocketError> for std::io::Error {
             SocketError::SetOption { errno, .. } => *errno,
             SocketError::GetOption { errno, .. } => *errno,
             SocketError::InvalidAddress { .. } => libc::EINVAL,
+            SocketError::Address(_) => libc::EINVAL,
+            SocketError::InvalidInput { .. } => libc::EINVAL,
+            SocketError::NoFreePort { .. } => libc::EADDRINUSE,
+            SocketError::WrongSocketKind { .. } => libc::EINVAL,
+            SocketError::WrongFamily { .. } => libc::EAFNOSUPPORT,
+            SocketError::Dup { errno } => *errno,
+            SocketError::Mmap { errno, .. } => *errno,
+            SocketError::Resolve { code: libc::EAI_SYSTEM, errno, .. } => *errno,
+            SocketError::Resolve { code, .. } => {
+                let kind = match *code {
+                    libc::EAI_NONAME => std::io::ErrorKind::NotFound,
+                    _ => std::io::ErrorKind::Other,
+                };
+                return std::io::Error::new(kind, err);
+            }
         };
         std::io::Error::new(errno_to_kind(errno), err)
     }
@@ -115,9 +212,23 @@ impl From<IoError> for std::io::Error {
             IoError::Read { errno } => errno_to_kind(*errno),
             IoError::Write { errno } => errno_to_kind(*errno),
             IoError::ConnectionClosed => std::io::ErrorKind::ConnectionReset,
+            IoError::PeerClosed => std::io::ErrorKind::BrokenPipe,
             IoError::WouldBlock => std::io::ErrorKind::WouldBlock,
             IoError::Interrupted => std::io::ErrorKind::Interrupted,
+            IoError::Poll { errno } => errno_to_kind(*errno),
+            IoError::FrameTooLarge { .. } => std::io::ErrorKind::InvalidData,
+            IoError::MalformedFrame { .. } => std::io::ErrorKind::InvalidData,
+            IoError::Cancelled => std::io::ErrorKind::Other,
+            IoError::FileEnded { .. } => std::io::ErrorKind::UnexpectedEof,
+            IoError::MissingControlMessage { .. } => std::io::ErrorKind::InvalidInput,
+            IoError::ControlTruncated => std::io::ErrorKind::InvalidData,
         };
         std::io::Error::new(kind, err)
     }
+}
+
+impl From<AddrError> for std::io::Error {
+    fn from(err: AddrError) -> Self {
+        SocketError::Address(err).into()
+    }
 }
\ No newline at end of file
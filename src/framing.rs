@@ -0,0 +1,543 @@
+//! Message framing over connected sockets.
+//!
+//! Streams have no message boundaries, so protocols add their own.
+//! The codecs here keep partial-read state between calls, so they work on
+//! non-blocking sockets: a `WouldBlock` mid-frame just means "call again".
+
+use crate::addr::{Domain, Unix};
+use crate::error::IoError;
+use crate::socket::{ConnectedStream, ConnectedDatagram, ConnectedSeqPacket, RecvFlags, poll_fd};
+
+/// Largest UDP payload; the receive buffer for one datagram frame.
+const MAX_DATAGRAM: usize = 65535;
+
+/// A connected socket that frames can be read from and written to.
+///
+/// Implemented for `ConnectedStream` (byte stream, frames are reassembled)
+/// and `ConnectedDatagram` (one frame per datagram).
+pub trait FrameTransport: std::os::fd::AsRawFd {
+	/// True if each read returns exactly one message (datagrams).
+	const PRESERVES_BOUNDARIES: bool;
+
+	/// Reads bytes (streams) or one message (datagrams).
+	///
+	/// For datagrams, returns the real message length even if it was
+	/// truncated to fit `buf`.
+	fn read_bytes(&self, buf: &mut [u8]) -> std::io::Result<usize>;
+
+	/// Writes bytes (streams) or one message (datagrams).
+	fn write_bytes(&self, buf: &[u8]) -> std::io::Result<usize>;
+}
+
+impl<D: Domain> FrameTransport for ConnectedStream<D> {
+	const PRESERVES_BOUNDARIES: bool = false;
+
+	fn read_bytes(&self, buf: &mut [u8]) -> std::io::Result<usize> {
+		self.read(buf)
+	}
+
+	fn write_bytes(&self, buf: &[u8]) -> std::io::Result<usize> {
+		self.write(buf)
+	}
+}
+
+impl<D: Domain> FrameTransport for ConnectedDatagram<D> {
+	const PRESERVES_BOUNDARIES: bool = true;
+
+	fn read_bytes(&self, buf: &mut [u8]) -> std::io::Result<usize> {
+		self.recv_with_flags(buf, RecvFlags::TRUNC)
+	}
+
+	fn write_bytes(&self, buf: &[u8]) -> std::io::Result<usize> {
+		self.send(buf)
+	}
+}
+
+/// Width of the length prefix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrefixLen {
+	U8,
+	U16,
+	U32,
+	U64,
+}
+
+impl PrefixLen {
+	fn bytes(self) -> usize {
+		match self {
+			PrefixLen::U8 => 1,
+			PrefixLen::U16 => 2,
+			PrefixLen::U32 => 4,
+			PrefixLen::U64 => 8,
+		}
+	}
+
+	fn max_value(self) -> u64 {
+		match self {
+			PrefixLen::U64 => u64::MAX,
+			p => (1u64 << (p.bytes() * 8)) - 1,
+		}
+	}
+}
+
+/// Byte order of the length prefix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Endian {
+	Big,
+	Little,
+}
+
+/// Length-prefixed framing: `[len][payload]`.
+///
+/// The prefix holds the payload length only (not including itself).
+/// Defaults: 4-byte big-endian prefix, 8 MiB maximum frame.
+///
+/// # Example
+/// ```ignore
+/// let mut codec = LengthDelimited::new().max_frame_len(64 * 1024);
+///
+/// codec.write_frame(&conn, b"request")?;
+/// while let Some(frame) = codec.read_frame(&conn)? {
+///     handle(&frame);
+/// }
+/// // None: peer closed cleanly between frames
+/// ```
+#[derive(Debug, Clone)]
+pub struct LengthDelimited {
+	prefix: PrefixLen,
+	endian: Endian,
+	max_frame_len: usize,
+
+	// Read state, kept across WouldBlock
+	buf: Vec<u8>,
+	filled: usize,
+	frame_len: Option<usize>,   // Known once the prefix is complete
+}
+
+impl Default for LengthDelimited {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl LengthDelimited {
+	pub fn new() -> Self {
+		Self {
+			prefix: PrefixLen::U32,
+			endian: Endian::Big,
+			max_frame_len: 8 * 1024 * 1024,
+			buf: Vec::new(),
+			filled: 0,
+			frame_len: None,
+		}
+	}
+
+	/// Set prefix width. Default: `U32`.
+	pub fn prefix_len(mut self, prefix: PrefixLen) -> Self {
+		self.prefix = prefix;
+		self
+	}
+
+	/// Set prefix byte order. Default: `Big`.
+	pub fn endian(mut self, endian: Endian) -> Self {
+		self.endian = endian;
+		self
+	}
+
+	/// Set the largest accepted payload. Default: 8 MiB.
+	///
+	/// Checked before allocating, so a hostile prefix can't force a huge buffer.
+	pub fn max_frame_len(mut self, max: usize) -> Self {
+		self.max_frame_len = max;
+		self
+	}
+
+	/// Returns true if a frame is partially read.
+	pub fn is_mid_frame(&self) -> bool {
+		self.filled > 0 || self.frame_len.is_some()
+	}
+
+	/// Appends the prefix and `payload` to `dst`.
+	///
+	/// Useful for batching several frames into one write.
+	pub fn encode(&self, payload: &[u8], dst: &mut Vec<u8>) -> std::io::Result<()> {
+		let len = payload.len();
+		let max = self.max_frame_len.min(self.prefix.max_value().try_into().unwrap_or(usize::MAX));
+		if len > max {
+			return Err(IoError::FrameTooLarge { len, max }.into());
+		}
+
+		let n = self.prefix.bytes();
+		match self.endian {
+			Endian::Big => dst.extend_from_slice(&(len as u64).to_be_bytes()[8 - n..]),
+			Endian::Little => dst.extend_from_slice(&(len as u64).to_le_bytes()[..n]),
+		}
+		dst.extend_from_slice(payload);
+		Ok(())
+	}
+
+	/// Sends one frame.
+	///
+	/// The whole frame is always written: on a non-blocking stream this
+	/// waits for writability after a partial write rather than leave a
+	/// half-sent frame behind.
+	pub fn write_frame<T: FrameTransport>(&self, transport: &T, payload: &[u8]) -> std::io::Result<()> {
+		let mut frame = Vec::with_capacity(self.prefix.bytes() + payload.len());
+		self.encode(payload, &mut frame)?;
+		write_all(transport, &frame)
+	}
+
+	/// Reads one frame.
+	///
+	/// Returns `Ok(None)` if the peer closed the stream between frames.
+	/// EOF inside a frame is an error. On a non-blocking socket,
+	/// `WouldBlock` keeps the partial frame; call again when readable.
+	pub fn read_frame<T: FrameTransport>(&mut self, transport: &T) -> std::io::Result<Option<Vec<u8>>> {
+		if T::PRESERVES_BOUNDARIES {
+			self.read_datagram(transport).map(Some)
+		} else {
+			self.read_stream(transport)
+		}
+	}
+
+	fn read_stream<T: FrameTransport>(&mut self, transport: &T) -> std::io::Result<Option<Vec<u8>>> {
+		let prefix_len = self.prefix.bytes();
+
+		loop {
+			let target = match self.frame_len {
+				None => prefix_len,
+				Some(len) => len,
+			};
+
+			if self.filled < target {
+				if self.buf.len() < target {
+					self.buf.resize(target, 0);
+				}
+				let n = transport.read_bytes(&mut self.buf[self.filled..target])?;
+				if n == 0 {
+					if !self.is_mid_frame() {
+						return Ok(None);
+					}
+					return Err(IoError::ConnectionClosed.into());
+				}
+				self.filled += n;
+				continue;
+			}
+
+			match self.frame_len {
+				None => {
+					let len = self.decode_len(&self.buf[..prefix_len])?;
+					self.frame_len = Some(len);
+					self.filled = 0;
+				}
+				Some(len) => {
+					let mut frame = std::mem::take(&mut self.buf);
+					frame.truncate(len);
+					self.filled = 0;
+					self.frame_len = None;
+					return Ok(Some(frame));
+				}
+			}
+		}
+	}
+
+	fn read_datagram<T: FrameTransport>(&mut self, transport: &T) -> std::io::Result<Vec<u8>> {
+		let prefix_len = self.prefix.bytes();
+		// No datagram payload exceeds 65535 bytes, whatever the frame limit.
+		let max_payload = self.max_frame_len.min(MAX_DATAGRAM);
+		let capacity = prefix_len + max_payload;
+		if self.buf.len() < capacity {
+			self.buf = vec![0u8; capacity];
+		}
+
+		let n = transport.read_bytes(&mut self.buf[..capacity])?;
+		if n > capacity {
+			return Err(IoError::FrameTooLarge { len: n - prefix_len, max: max_payload }.into());
+		}
+		if n < prefix_len {
+			return Err(IoError::MalformedFrame { reason: "datagram shorter than length prefix" }.into());
+		}
+
+		let len = self.decode_len(&self.buf[..prefix_len])?;
+		if len != n - prefix_len {
+			return Err(IoError::MalformedFrame { reason: "length prefix does not match datagram size" }.into());
+		}
+
+		Ok(self.buf[prefix_len..n].to_vec())
+	}
+
+	fn decode_len(&self, prefix: &[u8]) -> std::io::Result<usize> {
+		let mut bytes = [0u8; 8];
+		let n = prefix.len();
+		let len = match self.endian {
+			Endian::Big => {
+				bytes[8 - n..].copy_from_slice(prefix);
+				u64::from_be_bytes(bytes)
+			}
+			Endian::Little => {
+				bytes[..n].copy_from_slice(prefix);
+				u64::from_le_bytes(bytes)
+			}
+		};
+
+		match usize::try_from(len) {
+			Ok(len) if len <= self.max_frame_len => Ok(len),
+			_ => Err(IoError::FrameTooLarge {
+				len: len.try_into().unwrap_or(usize::MAX),
+				max: self.max_frame_len,
+			}.into()),
+		}
+	}
+}
+
+/// Delimiter-terminated framing: `payload` followed by e.g. `\r\n`.
+///
+/// Bytes read past the end of a frame are kept for the next call, so
+/// pipelined requests aren't lost. Frames are returned without the delimiter.
+/// Defaults to a 64 KiB maximum frame (not counting the delimiter).
+///
+/// # Example
+/// ```ignore
+/// let mut lines = Delimited::new(b"\r\n").max_frame_len(512);
+///
+/// while let Some(line) = lines.read_frame(&conn)? {
+///     let reply = handle_command(&line);
+///     lines.write_frame(&conn, &reply)?;
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Delimited {
+	delimiter: Vec<u8>,
+	max_frame_len: usize,
+	buf: Vec<u8>,       // Received but not yet returned
+	scanned: usize,     // Prefix of buf already searched, no delimiter there
+}
+
+const READ_CHUNK: usize = 4096;
+
+impl Delimited {
+	/// Creates a codec splitting on `delimiter`.
+	///
+	/// # Panics
+	/// If `delimiter` is empty.
+	pub fn new(delimiter: &[u8]) -> Self {
+		assert!(!delimiter.is_empty(), "delimiter must not be empty");
+		Self {
+			delimiter: delimiter.to_vec(),
+			max_frame_len: 64 * 1024,
+			buf: Vec::new(),
+			scanned: 0,
+		}
+	}
+
+	/// Set the longest accepted frame, excluding the delimiter. Default: 64 KiB.
+	pub fn max_frame_len(mut self, max: usize) -> Self {
+		self.max_frame_len = max;
+		self
+	}
+
+	/// Returns bytes received after the last returned frame.
+	pub fn buffered(&self) -> &[u8] {
+		&self.buf
+	}
+
+	/// Sends `payload` followed by the delimiter.
+	///
+	/// The payload is not checked for embedded delimiters.
+	pub fn write_frame<D: Domain>(&self, stream: &ConnectedStream<D>, payload: &[u8]) -> std::io::Result<()> {
+		if payload.len() > self.max_frame_len {
+			return Err(IoError::FrameTooLarge { len: payload.len(), max: self.max_frame_len }.into());
+		}
+		let mut frame = Vec::with_capacity(payload.len() + self.delimiter.len());
+		frame.extend_from_slice(payload);
+		frame.extend_from_slice(&self.delimiter);
+		write_all(stream, &frame)
+	}
+
+	/// Reads one frame.
+	///
+	/// Returns `Ok(None)` if the peer closed the stream with nothing buffered.
+	/// EOF after a partial frame is an error. On a non-blocking socket,
+	/// `WouldBlock` keeps everything buffered; call again when readable.
+	///
+	/// After `FrameTooLarge` the stream is out of sync and should be closed.
+	pub fn read_frame<D: Domain>(&mut self, stream: &ConnectedStream<D>) -> std::io::Result<Option<Vec<u8>>> {
+		loop {
+			if let Some(frame) = self.take_frame()? {
+				return Ok(Some(frame));
+			}
+
+			let len = self.buf.len();
+			self.buf.resize(len + READ_CHUNK, 0);
+			let result = stream.read(&mut self.buf[len..]);
+			let n = *result.as_ref().unwrap_or(&0);
+			self.buf.truncate(len + n);
+
+			match result? {
+				0 if self.buf.is_empty() => return Ok(None),
+				0 => return Err(IoError::ConnectionClosed.into()),
+				_ => {}
+			}
+		}
+	}
+
+	/// Splits a frame off the buffer if a delimiter is present.
+	fn take_frame(&mut self) -> std::io::Result<Option<Vec<u8>>> {
+		let dlen = self.delimiter.len();
+		// The delimiter may straddle the previously scanned region.
+		let start = self.scanned.saturating_sub(dlen - 1);
+
+		let found = self.buf[start..]
+			.windows(dlen)
+			.position(|w| w == self.delimiter.as_slice())
+			.map(|i| start + i);
+
+		match found {
+			Some(end) if end > self.max_frame_len => {
+				Err(IoError::FrameTooLarge { len: end, max: self.max_frame_len }.into())
+			}
+			Some(end) => {
+				let frame = self.buf[..end].to_vec();
+				self.buf.drain(..end + dlen);
+				self.scanned = 0;
+				Ok(Some(frame))
+			}
+			None if self.buf.len() >= self.max_frame_len.saturating_add(dlen) => {
+				Err(IoError::FrameTooLarge { len: self.buf.len(), max: self.max_frame_len }.into())
+			}
+			None => {
+				self.scanned = self.buf.len();
+				Ok(None)
+			}
+		}
+	}
+}
+
+/// Message channel over a sequenced-packet socket.
+///
+/// The recommended way to talk between local processes: the kernel keeps
+/// message boundaries (no prefix to parse), delivery is reliable and
+/// ordered (unlike datagrams), and the peer closing is a clean `None`.
+/// Defaults to a 64 KiB maximum message.
+///
+/// Empty messages are rejected on send: a zero-length read is how the
+/// kernel reports EOF, so they couldn't be told apart.
+///
+/// # Example
+/// ```ignore
+/// let (mut client, mut server) = SeqPacketChannel::pair()?;
+///
+/// client.send(b"ping")?;
+/// while let Some(msg) = server.recv()? {
+///     server.send(&reply_to(&msg))?;
+/// }
+/// // None: client closed
+/// ```
+pub struct SeqPacketChannel<D: Domain> {
+	conn: ConnectedSeqPacket<D>,
+	max_msg_len: usize,
+	buf: Vec<u8>,   // Reused receive buffer, max_msg_len long
+}
+
+impl<D: Domain> SeqPacketChannel<D> {
+	/// Wraps a connected seqpacket socket.
+	pub fn new(conn: ConnectedSeqPacket<D>) -> Self {
+		Self {
+			conn,
+			max_msg_len: 64 * 1024,
+			buf: Vec::new(),
+		}
+	}
+
+	/// Set the largest message sent or accepted. Default: 64 KiB.
+	///
+	/// Also bounded by the socket's send buffer; larger sends fail with EMSGSIZE.
+	pub fn max_msg_len(mut self, max: usize) -> Self {
+		self.max_msg_len = max;
+		self
+	}
+
+	/// Returns the underlying socket.
+	pub fn get_ref(&self) -> &ConnectedSeqPacket<D> {
+		&self.conn
+	}
+
+	/// Unwraps the socket.
+	pub fn into_inner(self) -> ConnectedSeqPacket<D> {
+		self.conn
+	}
+
+	/// Sends one message, whole or not at all.
+	pub fn send(&self, msg: &[u8]) -> std::io::Result<()> {
+		if msg.is_empty() {
+			return Err(IoError::MalformedFrame { reason: "empty seqpacket message" }.into());
+		}
+		if msg.len() > self.max_msg_len {
+			return Err(IoError::FrameTooLarge { len: msg.len(), max: self.max_msg_len }.into());
+		}
+		self.conn.send(msg)?;
+		Ok(())
+	}
+
+	/// Receives one message.
+	///
+	/// Returns `Ok(None)` once the peer has closed the connection. An
+	/// oversized message fails with `FrameTooLarge` and is discarded;
+	/// unlike a stream, the channel stays usable. On a non-blocking
+	/// socket, `WouldBlock` means no message is queued.
+	pub fn recv(&mut self) -> std::io::Result<Option<Vec<u8>>> {
+		if self.buf.len() < self.max_msg_len {
+			self.buf = vec![0u8; self.max_msg_len];
+		}
+
+		let n = self.conn.recv_with_flags(&mut self.buf[..self.max_msg_len], RecvFlags::TRUNC)?;
+		if n == 0 {
+			return Ok(None);
+		}
+		if n > self.max_msg_len {
+			return Err(IoError::FrameTooLarge { len: n, max: self.max_msg_len }.into());
+		}
+		Ok(Some(self.buf[..n].to_vec()))
+	}
+}
+
+impl SeqPacketChannel<Unix> {
+	/// Creates a connected pair of channels, e.g. to hand one to a child process.
+	pub fn pair() -> std::io::Result<(Self, Self)> {
+		let (a, b) = ConnectedSeqPacket::pair()?;
+		Ok((Self::new(a), Self::new(b)))
+	}
+}
+
+impl<D: Domain> std::os::fd::AsRawFd for SeqPacketChannel<D> {
+	fn as_raw_fd(&self) -> std::os::fd::RawFd {
+		self.conn.as_raw_fd()
+	}
+}
+
+/// Writes all of `buf`, waiting out `WouldBlock` between partial writes.
+///
+/// For datagrams a single write sends the whole message or fails.
+pub(crate) fn write_all<T: FrameTransport>(transport: &T, mut buf: &[u8]) -> std::io::Result<()> {
+	while !buf.is_empty() {
+		match transport.write_bytes(buf) {
+			Ok(0) => return Err(IoError::ConnectionClosed.into()),
+			Ok(n) => buf = &buf[n..],
+			Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+			Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+				poll_fd(transport.as_raw_fd(), libc::POLLOUT, None)?;
+			}
+			Err(e) => return Err(e),
+		}
+	}
+	Ok(())
+}
+
+/*
+Why keep read state in the codec?
+On a blocking socket, read_exact(prefix) then read_exact(payload) is enough.
+On a non-blocking one, either read can stop half-way with WouldBlock.
+Throwing the partial bytes away desyncs the stream — the next "prefix" is
+payload garbage. Storing (buf, filled, frame_len) lets the next call pick
+up exactly where the last one stopped.
+*/
@@ -0,0 +1,177 @@
+//! Typed wrappers for the socket ioctls callers most often need.
+//!
+//! Each takes anything `AsRawFd` — wirelane sockets, std sockets, raw
+//! fds wrapped in `BorrowedFd` — so there's no unsafe `ioctl()` block to
+//! write around them. Errors report the ioctl's name, like socket
+//! option errors do.
+//!
+//! # Example
+//! ```ignore
+//! use wirelane::ioctls;
+//!
+//! let mtu = ioctls::interface_mtu(&socket, "eth0")?;
+//! let pending = ioctls::bytes_to_read(&socket)?;
+//! ```
+
+use std::os::fd::AsRawFd;
+use std::time::{Duration, SystemTime};
+use crate::addr::Domain;
+use crate::error::{SocketError, errno};
+use crate::socket::{ConnectedStream, BoundDatagram, ConnectedDatagram};
+
+// From asm-generic/sockios.h. The old (timeval) SIOCGSTAMP, not exported
+// by libc on every target.
+const SIOCGSTAMP: libc::c_ulong = 0x8906;
+// From linux/sockios.h; the same on every architecture. SIOCOUTQ is
+// TIOCOUTQ, whose number varies (mips, powerpc, sparc), so that one
+// comes from libc.
+pub(crate) const SIOCOUTQNSD: libc::Ioctl = 0x894B;
+
+/// Bytes waiting in the receive queue (FIONREAD).
+///
+/// For a stream socket, all queued bytes; for a datagram socket, the size
+/// of the next datagram only (0 for an empty one or an empty queue).
+/// Listening sockets fail with EINVAL.
+pub fn bytes_to_read<S: AsRawFd>(socket: &S) -> std::io::Result<usize> {
+	let mut count: libc::c_int = 0;
+	let result = unsafe { libc::ioctl(socket.as_raw_fd(), libc::FIONREAD, &mut count) };
+	if result == -1 {
+		return Err(SocketError::GetOption { errno: errno(), option: "FIONREAD" }.into());
+	}
+	Ok(count.max(0) as usize)
+}
+
+/// Bytes in the send queue (SIOCOUTQ).
+///
+/// For TCP, payload bytes not yet acknowledged, sent or not. For UDP
+/// and Unix sockets the kernel reports send buffer memory instead:
+/// packets not yet transmitted (UDP) or read by the peer (Unix), each
+/// counted with its few hundred bytes of overhead.
+pub fn bytes_in_send_queue<S: AsRawFd>(socket: &S) -> std::io::Result<usize> {
+	queue_len(socket, libc::TIOCOUTQ, "SIOCOUTQ")
+}
+
+/// Bytes in a TCP send queue that haven't been sent at all (SIOCOUTQNSD).
+///
+/// Unlike `bytes_in_send_queue()`, excludes data in flight waiting for
+/// an ACK: this is the backlog the application itself created.
+pub fn bytes_not_sent<S: AsRawFd>(socket: &S) -> std::io::Result<usize> {
+	queue_len(socket, SIOCOUTQNSD, "SIOCOUTQNSD")
+}
+
+/// Runs an ioctl that reports a queue length in an int (FIONREAD,
+/// TIOCOUTQ, SIOCOUTQNSD).
+pub(crate) fn queue_len<S: AsRawFd>(socket: &S, request: libc::Ioctl, name: &'static str) -> std::io::Result<usize> {
+	let mut count: libc::c_int = 0;
+	let result = unsafe { libc::ioctl(socket.as_raw_fd(), request, &mut count) };
+	if result == -1 {
+		return Err(SocketError::GetOption { errno: errno(), option: name }.into());
+	}
+	Ok(count.max(0) as usize)
+}
+
+/// Sets or clears non-blocking mode (FIONBIO).
+///
+/// Same effect as O_NONBLOCK through fcntl(), in one syscall instead of
+/// two. On a typed `Listener<_, Blocking>` or `Listener<_, NonBlocking>`
+/// this makes the flag disagree with the type; use `into_blocking()` /
+/// `into_nonblocking()` there instead.
+pub fn set_nonblocking<S: AsRawFd>(socket: &S, nonblocking: bool) -> std::io::Result<()> {
+	let mut value = nonblocking as libc::c_int;
+	let result = unsafe { libc::ioctl(socket.as_raw_fd(), libc::FIONBIO, &mut value) };
+	if result == -1 {
+		return Err(SocketError::SetOption { errno: errno(), option: "FIONBIO" }.into());
+	}
+	Ok(())
+}
+
+/// MTU of the network interface called `interface` (SIOCGIFMTU).
+///
+/// Any socket works as the handle; the query is about the interface,
+/// not the socket. Names must be shorter than IFNAMSIZ (16 bytes) and
+/// contain no NUL; an unknown interface fails with ENODEV.
+pub fn interface_mtu<S: AsRawFd>(socket: &S, interface: &str) -> std::io::Result<u32> {
+	let mut req: libc::ifreq = unsafe { std::mem::zeroed() };
+	let name = interface.as_bytes();
+	if name.is_empty() || name.len() >= libc::IFNAMSIZ || name.contains(&0) {
+		return Err(SocketError::GetOption { errno: libc::EINVAL, option: "SIOCGIFMTU (interface name)" }.into());
+	}
+	for (dst, &src) in req.ifr_name.iter_mut().zip(name) {
+		*dst = src as libc::c_char;
+	}
+
+	let result = unsafe { libc::ioctl(socket.as_raw_fd(), libc::SIOCGIFMTU, &mut req) };
+	if result == -1 {
+		return Err(SocketError::GetOption { errno: errno(), option: "SIOCGIFMTU" }.into());
+	}
+	Ok(unsafe { req.ifr_ifru.ifru_mtu } as u32)
+}
+
+/// Arrival time of the last packet the socket received (SIOCGSTAMP).
+///
+/// The fallback for code that can't read SO_TIMESTAMP control messages:
+/// asking turns timestamping on for the socket, so the first call on a
+/// socket that has already received data may fail with ENOENT. Only the
+/// most recent packet is covered, and with microsecond resolution.
+pub fn last_packet_time<S: AsRawFd>(socket: &S) -> std::io::Result<SystemTime> {
+	let mut tv: libc::timeval = unsafe { std::mem::zeroed() };
+	let result = unsafe { libc::ioctl(socket.as_raw_fd(), SIOCGSTAMP as _, &mut tv) };
+	if result == -1 {
+		return Err(SocketError::GetOption { errno: errno(), option: "SIOCGSTAMP" }.into());
+	}
+	Ok(SystemTime::UNIX_EPOCH + Duration::new(tv.tv_sec as u64, tv.tv_usec as u32 * 1000))
+}
+
+impl<D: Domain> ConnectedStream<D> {
+	/// Bytes received and not yet read (FIONREAD).
+	pub fn bytes_readable(&self) -> std::io::Result<usize> {
+		bytes_to_read(self)
+	}
+
+	/// Bytes written and not yet sent: SIOCOUTQNSD for TCP. Unix streams
+	/// have no wire, so there it's the SIOCOUTQ memory the peer hasn't
+	/// read yet, overhead included; 0 still means drained.
+	pub fn bytes_unsent(&self) -> std::io::Result<usize> {
+		if D::raw() == libc::AF_UNIX {
+			bytes_in_send_queue(self)
+		} else {
+			bytes_not_sent(self)
+		}
+	}
+}
+
+impl<D: Domain> BoundDatagram<D> {
+	/// Size of the next queued datagram (FIONREAD); see `bytes_to_read()`.
+	pub fn bytes_readable(&self) -> std::io::Result<usize> {
+		bytes_to_read(self)
+	}
+
+	/// Send buffer memory held by datagrams not yet transmitted
+	/// (SIOCOUTQ, overhead included); 0 means drained.
+	pub fn bytes_unsent(&self) -> std::io::Result<usize> {
+		bytes_in_send_queue(self)
+	}
+}
+
+impl<D: Domain> ConnectedDatagram<D> {
+	/// Size of the next queued datagram (FIONREAD); see `bytes_to_read()`.
+	pub fn bytes_readable(&self) -> std::io::Result<usize> {
+		bytes_to_read(self)
+	}
+
+	/// Send buffer memory held by datagrams not yet transmitted
+	/// (SIOCOUTQ, overhead included); 0 means drained.
+	pub fn bytes_unsent(&self) -> std::io::Result<usize> {
+		bytes_in_send_queue(self)
+	}
+}
+
+/*
+Why a module of free functions instead of methods?
+These ioctls apply to fds wirelane didn't create as much as to its own
+types, and several (SIOCGIFMTU) aren't about the socket at all. Free
+functions over AsRawFd cover every case once, the same way the socket
+option setters do, and keep ConnectedStream and friends from growing a
+method per ioctl. The queue depths are the exception: a backpressure
+loop asks for them on every write, on the socket it's writing to.
+*/
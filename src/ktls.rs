@@ -0,0 +1,322 @@
+//! Kernel TLS: record encryption offloaded to the kernel (TCP_ULP "tls").
+//!
+//! Do the handshake in userspace with any TLS library, export the
+//! negotiated traffic keys, and hand them to `KtlsStream::new()`. From
+//! then on the kernel encrypts every `write()` into TLS records and
+//! decrypts what `read()` returns — including `sendfile()`, which stays
+//! zero-copy. Needs the `tls` kernel module (Linux 4.13+ for TX, 4.17+
+//! for RX, 5.1+ for TLS 1.3).
+//!
+//! # Example
+//! ```ignore
+//! use wirelane::ktls::{KtlsStream, CryptoInfo, TlsVersion, Cipher};
+//!
+//! // Keys, IVs and sequence numbers from the userspace handshake.
+//! let tx = CryptoInfo::new(TlsVersion::Tls13, Cipher::AesGcm128 { key, iv, salt, rec_seq: [0; 8] });
+//! let tls = KtlsStream::new(stream, &tx, Some(&rx))?;
+//! tls.sendfile(&file, Some(&mut offset), len)?;  // encrypted in the kernel
+//! ```
+
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd, RawFd};
+use crate::addr::Domain;
+use crate::error::{SocketError, IoError, errno};
+use crate::socket::ConnectedStream;
+
+// From linux/tls.h; not exported by libc on every target.
+const SOL_TLS: libc::c_int = 282;
+const TLS_TX: libc::c_int = 1;
+const TLS_RX: libc::c_int = 2;
+const TLS_SET_RECORD_TYPE: libc::c_int = 1;
+const TLS_GET_RECORD_TYPE: libc::c_int = 2;
+const TLS_CIPHER_AES_GCM_128: u16 = 51;
+const TLS_CIPHER_AES_GCM_256: u16 = 52;
+const TLS_CIPHER_CHACHA20_POLY1305: u16 = 54;
+// From linux/tcp.h.
+const TCP_ULP: libc::c_int = 31;
+
+/// TLS record content type for application data.
+pub const RECORD_APPLICATION_DATA: u8 = 23;
+/// TLS record content type for alerts (e.g. close_notify).
+pub const RECORD_ALERT: u8 = 21;
+/// TLS record content type for handshake messages (e.g. KeyUpdate).
+pub const RECORD_HANDSHAKE: u8 = 22;
+
+/// Protocol version the keys were negotiated for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TlsVersion {
+	Tls12,
+	Tls13,
+}
+
+impl TlsVersion {
+	fn raw(self) -> u16 {
+		match self {
+			TlsVersion::Tls12 => 0x0303,
+			TlsVersion::Tls13 => 0x0304,
+		}
+	}
+}
+
+/// A cipher suite's key material for one direction.
+///
+/// `salt` plus `iv` make up the nonce (for TLS 1.3, the first 4 and last
+/// 8 bytes of the traffic IV); `rec_seq` is the next record's sequence
+/// number, big-endian — zero right after the handshake.
+#[derive(Clone, PartialEq, Eq)]
+pub enum Cipher {
+	AesGcm128 { key: [u8; 16], iv: [u8; 8], salt: [u8; 4], rec_seq: [u8; 8] },
+	AesGcm256 { key: [u8; 32], iv: [u8; 8], salt: [u8; 4], rec_seq: [u8; 8] },
+	Chacha20Poly1305 { key: [u8; 32], iv: [u8; 12], rec_seq: [u8; 8] },
+}
+
+impl std::fmt::Debug for Cipher {
+	/// Names the cipher only; key material stays out of logs.
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.write_str(match self {
+			Cipher::AesGcm128 { .. } => "AesGcm128",
+			Cipher::AesGcm256 { .. } => "AesGcm256",
+			Cipher::Chacha20Poly1305 { .. } => "Chacha20Poly1305",
+		})
+	}
+}
+
+/// Crypto state for one direction (struct tls12_crypto_info_*).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CryptoInfo {
+	pub version: TlsVersion,
+	pub cipher: Cipher,
+}
+
+impl CryptoInfo {
+	pub fn new(version: TlsVersion, cipher: Cipher) -> Self {
+		Self { version, cipher }
+	}
+
+	/// The struct the kernel expects, as bytes: the tls_crypto_info
+	/// header, then iv, key, salt and rec_seq, with no padding.
+	fn to_raw(&self) -> Vec<u8> {
+		let (cipher, iv, key, salt, rec_seq): (u16, &[u8], &[u8], &[u8], &[u8]) = match &self.cipher {
+			Cipher::AesGcm128 { key, iv, salt, rec_seq } => (TLS_CIPHER_AES_GCM_128, iv, key, salt, rec_seq),
+			Cipher::AesGcm256 { key, iv, salt, rec_seq } => (TLS_CIPHER_AES_GCM_256, iv, key, salt, rec_seq),
+			Cipher::Chacha20Poly1305 { key, iv, rec_seq } => (TLS_CIPHER_CHACHA20_POLY1305, iv, key, &[], rec_seq),
+		};
+		let mut raw = Vec::with_capacity(4 + iv.len() + key.len() + salt.len() + rec_seq.len());
+		raw.extend_from_slice(&self.version.raw().to_ne_bytes());
+		raw.extend_from_slice(&cipher.to_ne_bytes());
+		raw.extend_from_slice(iv);
+		raw.extend_from_slice(key);
+		raw.extend_from_slice(salt);
+		raw.extend_from_slice(rec_seq);
+		raw
+	}
+}
+
+/// Attaches the TLS upper-layer protocol to a connected TCP socket.
+///
+/// Must come before `set_tls_tx()` / `set_tls_rx()`. Fails with ENOENT
+/// if the `tls` module isn't available, EEXIST if already attached.
+pub fn set_tls_ulp<S: AsRawFd>(socket: &S) -> std::io::Result<()> {
+	let name = b"tls";
+	let result = unsafe {
+		libc::setsockopt(
+			socket.as_raw_fd(),
+			libc::IPPROTO_TCP,
+			TCP_ULP,
+			name.as_ptr() as *const libc::c_void,
+			name.len() as libc::socklen_t,
+		)
+	};
+	if result == -1 {
+		return Err(SocketError::SetOption { errno: errno(), option: "TCP_ULP" }.into());
+	}
+	Ok(())
+}
+
+/// Installs the send-side keys (TLS_TX); writes are encrypted from now on.
+pub fn set_tls_tx<S: AsRawFd>(socket: &S, crypto: &CryptoInfo) -> std::io::Result<()> {
+	set_crypto(socket, TLS_TX, crypto, "TLS_TX")
+}
+
+/// Installs the receive-side keys (TLS_RX); reads return plaintext from now on.
+pub fn set_tls_rx<S: AsRawFd>(socket: &S, crypto: &CryptoInfo) -> std::io::Result<()> {
+	set_crypto(socket, TLS_RX, crypto, "TLS_RX")
+}
+
+fn set_crypto<S: AsRawFd>(socket: &S, direction: libc::c_int, crypto: &CryptoInfo, name: &'static str) -> std::io::Result<()> {
+	let mut raw = crypto.to_raw();
+	let result = unsafe {
+		libc::setsockopt(
+			socket.as_raw_fd(),
+			SOL_TLS,
+			direction,
+			raw.as_ptr() as *const libc::c_void,
+			raw.len() as libc::socklen_t,
+		)
+	};
+	// Don't leave a copy of the key behind on the heap.
+	raw.iter_mut().for_each(|b| unsafe { std::ptr::write_volatile(b, 0) });
+	if result == -1 {
+		return Err(SocketError::SetOption { errno: errno(), option: name }.into());
+	}
+	Ok(())
+}
+
+/// A TCP stream whose TLS records are handled by the kernel.
+///
+/// `write()` and `sendfile()` send application-data records; `read()`
+/// returns decrypted application data (if RX keys were installed).
+/// Other record types go through `send_record()` and `recv_record()`.
+pub struct KtlsStream<D: Domain> {
+	stream: ConnectedStream<D>,
+	rx: bool,
+}
+
+impl<D: Domain> KtlsStream<D> {
+	/// Attaches the TLS ULP to `stream` and installs `tx` (and `rx`,
+	/// if given).
+	///
+	/// Call it right after the userspace handshake, before any
+	/// application data has been sent or read. Without `rx` the stream
+	/// still receives raw TLS records for userspace to decrypt.
+	pub fn new(stream: ConnectedStream<D>, tx: &CryptoInfo, rx: Option<&CryptoInfo>) -> std::io::Result<Self> {
+		set_tls_ulp(&stream)?;
+		set_tls_tx(&stream, tx)?;
+		if let Some(rx) = rx {
+			set_tls_rx(&stream, rx)?;
+		}
+		Ok(Self { stream, rx: rx.is_some() })
+	}
+
+	/// The underlying stream, e.g. for socket options.
+	pub fn stream(&self) -> &ConnectedStream<D> {
+		&self.stream
+	}
+
+	/// Whether the kernel decrypts incoming records.
+	pub fn has_rx(&self) -> bool {
+		self.rx
+	}
+
+	/// Sends `buf` as application data, encrypted by the kernel.
+	pub fn write(&self, buf: &[u8]) -> std::io::Result<usize> {
+		self.stream.write(buf)
+	}
+
+	/// Reads decrypted application data.
+	///
+	/// With RX offload, a non-application record (alert, KeyUpdate)
+	/// makes this fail with EIO; read it with `recv_record()`.
+	pub fn read(&self, buf: &mut [u8]) -> std::io::Result<usize> {
+		self.stream.read(buf)
+	}
+
+	/// Zero-copy file transfer, encrypted in the kernel.
+	///
+	/// Same arguments as `ConnectedStream::sendfile()`.
+	pub fn sendfile<F: AsRawFd>(&self, file: &F, offset: Option<&mut i64>, count: usize) -> std::io::Result<usize> {
+		self.stream.sendfile(file, offset, count)
+	}
+
+	/// Sends `buf` as one record of type `record_type` (e.g.
+	/// `RECORD_ALERT` for close_notify).
+	pub fn send_record(&self, record_type: u8, buf: &[u8]) -> std::io::Result<usize> {
+		let cmsg_space = unsafe { libc::CMSG_SPACE(1) } as usize;
+		let mut cmsg_buf = vec![0u64; cmsg_space.div_ceil(8)];
+
+		let mut iov = libc::iovec {
+			iov_base: buf.as_ptr() as *mut libc::c_void,
+			iov_len: buf.len(),
+		};
+		let mut msg: libc::msghdr = unsafe { std::mem::zeroed() };
+		msg.msg_iov = &mut iov;
+		msg.msg_iovlen = 1;
+		msg.msg_control = cmsg_buf.as_mut_ptr() as *mut libc::c_void;
+		msg.msg_controllen = cmsg_space;
+		unsafe {
+			let cmsg = libc::CMSG_FIRSTHDR(&msg);
+			(*cmsg).cmsg_level = SOL_TLS;
+			(*cmsg).cmsg_type = TLS_SET_RECORD_TYPE;
+			(*cmsg).cmsg_len = libc::CMSG_LEN(1) as usize;
+			*libc::CMSG_DATA(cmsg) = record_type;
+		}
+
+		let n = unsafe { libc::sendmsg(self.stream.as_raw_fd(), &msg, libc::MSG_NOSIGNAL) };
+		if n == -1 {
+			Err(IoError::Write { errno: errno() }.into())
+		} else {
+			Ok(n as usize)
+		}
+	}
+
+	/// Reads one decrypted record's worth of data and its content type.
+	///
+	/// Needs RX offload. Returns `(0, RECORD_APPLICATION_DATA)` at EOF.
+	pub fn recv_record(&self, buf: &mut [u8]) -> std::io::Result<(usize, u8)> {
+		let cmsg_space = unsafe { libc::CMSG_SPACE(1) } as usize;
+		let mut cmsg_buf = vec![0u64; cmsg_space.div_ceil(8)];
+
+		let mut iov = libc::iovec {
+			iov_base: buf.as_mut_ptr() as *mut libc::c_void,
+			iov_len: buf.len(),
+		};
+		let mut msg: libc::msghdr = unsafe { std::mem::zeroed() };
+		msg.msg_iov = &mut iov;
+		msg.msg_iovlen = 1;
+		msg.msg_control = cmsg_buf.as_mut_ptr() as *mut libc::c_void;
+		msg.msg_controllen = cmsg_space;
+
+		let n = unsafe { libc::recvmsg(self.stream.as_raw_fd(), &mut msg, 0) };
+		if n == -1 {
+			return Err(IoError::Read { errno: errno() }.into());
+		}
+
+		let mut record_type = RECORD_APPLICATION_DATA;
+		unsafe {
+			let mut cmsg = libc::CMSG_FIRSTHDR(&msg);
+			while !cmsg.is_null() {
+				if (*cmsg).cmsg_level == SOL_TLS && (*cmsg).cmsg_type == TLS_GET_RECORD_TYPE {
+					record_type = *libc::CMSG_DATA(cmsg);
+				}
+				cmsg = libc::CMSG_NXTHDR(&msg, cmsg);
+			}
+		}
+		Ok((n as usize, record_type))
+	}
+}
+
+impl<D: Domain> AsRawFd for KtlsStream<D> {
+	fn as_raw_fd(&self) -> RawFd {
+		self.stream.as_raw_fd()
+	}
+}
+
+impl<D: Domain> AsFd for KtlsStream<D> {
+	fn as_fd(&self) -> BorrowedFd<'_> {
+		self.stream.as_fd()
+	}
+}
+
+impl<D: Domain> std::io::Read for KtlsStream<D> {
+	fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+		KtlsStream::read(self, buf)
+	}
+}
+
+impl<D: Domain> std::io::Write for KtlsStream<D> {
+	fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+		KtlsStream::write(self, buf)
+	}
+
+	fn flush(&mut self) -> std::io::Result<()> {
+		Ok(())
+	}
+}
+
+/*
+Why no handshake here?
+The handshake is where TLS libraries differ — certificate validation,
+session resumption, ALPN, post-handshake auth — and the kernel doesn't
+do it either. Only the record layer is offloaded, so this module starts
+where the libraries end: the exported traffic keys. That keeps any TLS
+stack usable and wirelane free of a crypto dependency.
+*/
@@ -1,20 +1,111 @@
 pub mod socket;
+pub mod framing;
+pub mod route;
+pub mod ioctls;
+pub mod repair;
+pub mod ktls;
+#[cfg(feature = "turnkey")]
+pub mod turnkey;
+#[cfg(feature = "registry")]
+pub mod registry;
+pub mod stun;
+pub mod proxy_protocol;
+pub mod selftest;
+#[cfg(feature = "testing")]
+pub mod testing;
 mod addr;
 mod error;
+mod packet;
 
-pub use self::error::{IoError, SocketError, errno};
-pub use self::addr::{Domain, Ipv4, Ipv6, Unix, SocketAddrV4, SocketAddrV6, UnixAddr};
+pub use self::error::{IoError, SocketError, AddrError, errno};
+pub use self::packet::{Ipv4Header, IcmpEcho, checksum};
+pub use self::framing::{LengthDelimited, Delimited, PrefixLen, Endian, FrameTransport, SeqPacketChannel};
+pub use self::addr::{Domain, PortAddr, Ipv4, Ipv6, Unix, Netlink, SocketAddrV4, SocketAddrV6, UnixAddr, NetlinkAddr,
+					 AddrRedaction, set_addr_redaction, addr_redaction, Port, Ipv4Octets, Ipv6Segments};
 pub use self::socket::{AcceptResult, MsgResult,Shutdown, SockType, Stream, ListenerBuilder,
 					   ConnectorBuilder,
 					   set_recv_buffer_size,
-					   DatagramBuilder, BufferConfig, ReuseConfig, TcpConfig, KeepaliveConfig,
-					   Datagram, RawSocket, BoundSocket,
+					   DatagramBuilder, BufferConfig, ReuseConfig, TcpConfig, KeepaliveConfig, TimeoutConfig, QosConfig, RetryPolicy,
+					   Datagram, Raw, SeqPacket, RawSocket, BoundSocket,
+					   Mode, BlockingMode, NonBlockingMode, Dynamic, Blocking, NonBlocking, CancelHandle,
+					   SeqPacketListener, ConnectedSeqPacket,
 					   ConnectedDatagram, Listener, ConnectedStream, BoundDatagram,
-					   PendingConnect};
-pub use self::socket::{set_reuse_addr, set_reuse_port, set_tcp_nodelay,
+					   PendingConnect, ReadHalf, WriteHalf, ReadResult,
+					   UnixHealthCheck, HealthProbe, PeerHealth, Instrumented, SocketStats,
+					   BufferedStream, ErrorQueue, QueuedError, ErrorEvent, TxtimeError, RecvOutcome,
+					   BufferPool, DatagramLease, UdpServer, TryResult, Pipe, PipeReader, PipeWriter, resolve, resolve_family, ResolveOrder,
+					   MsgHdr, RecvMsg, Cmsg, CmsgIter, send_msg, recv_msg, cmsg_space, DEFAULT_CONTROL_SPACE,
+					   Poller, Waker, Events, Event, Token, Interest, SocketPathGuard,
+					   ListenerStats, somaxconn, sniff_stream, SniffedProtocol,
+					   BatchStats, Socket, RecvInterface,
+					   ShutdownCoordinator, ShutdownReport, DrainReport, DrainEnd, SendFlags, RecvFlags,
+					   KnockListener, MAX_KNOCK_PREFIX, MultiListener, SendBudget, Acceptor, AnyAddr,
+					   ReuseportShard, reuseport_shard, MAX_REUSEPORT_WORKERS,
+					   SocketManifest, ManifestAddr, ManifestFamily, ManifestKind};
+pub use self::socket::{set_reuse_addr, set_reuse_port, set_tcp_nodelay, set_only_v6,
+					   get_reuse_addr, get_reuse_port, get_tcp_nodelay, get_only_v6,
+					   set_tos, set_tclass, get_tos, get_tclass, set_recv_tos, set_recv_tclass,
+					   get_recv_tos, get_recv_tclass,
+					   set_recv_pktinfo, set_recv_pktinfo_v6,
+					   set_ip_transparent, get_ip_transparent, set_ip_transparent_v6, get_ip_transparent_v6,
+					   set_recv_orig_dst, set_recv_orig_dst_v6, set_recv_err, set_recv_err_v6, set_bind_address_no_port,
+					   set_broadcast, get_broadcast, set_multicast_ttl, get_multicast_ttl,
+					   set_multicast_hops_v6, get_multicast_hops_v6,
+					   join_multicast_v4, leave_multicast_v4, join_multicast_v6, leave_multicast_v6,
+					   join_ssm_v4, leave_ssm_v4, join_ssm_v6, leave_ssm_v6,
+					   set_header_included, set_header_included_v6,
+					   get_header_included, get_header_included_v6,
 					   set_tcp_cork, set_tcp_quickack, set_tcp_fastopen,
+					   get_tcp_cork, get_tcp_quickack, get_tcp_fastopen, set_tcp_syn_count, get_tcp_syn_count,
+					   set_tcp_window_clamp, get_tcp_window_clamp, set_tcp_linger2, get_tcp_linger2,
 					   get_tcp_info, TcpInfo,
+					   get_incoming_napi_id, napi_worker, group_by_napi_id,
+					   set_recv_timeout, get_recv_timeout, set_send_timeout, get_send_timeout,
 					   set_keepalive, set_keepalive_idle, set_keepalive_interval, set_keepalive_count,
-					   set_linger, set_send_buffer_size,
+					   get_keepalive, get_keepalive_idle, get_keepalive_interval, get_keepalive_count,
+					   set_linger, get_linger, set_send_buffer_size,
+					   get_recv_buffer_size, get_send_buffer_size,
 					   splice, SPLICE_F_MOVE, SPLICE_F_NONBLOCK, SPLICE_F_MORE,
-					   send_fd, recv_fd, SendMsg};
\ No newline at end of file
+					   send_fd, recv_fd, SendMsg, MappedAddrs,
+					   Credentials, get_peer_credentials, set_passcred, get_passcred, send_credentials, recv_credentials,
+					   add_tcp_md5_key, add_tcp_md5_key_prefix, remove_tcp_md5_key, remove_tcp_md5_key_prefix, MAX_MD5_KEY_LEN,
+					   SockOpt, OptValue, set_opt, get_opt};
+#[cfg(feature = "io_uring")]
+pub use self::socket::{Ring, Completion, FixedRecvPool, MAX_FIXED_RECV_BUFFERS};
+#[cfg(feature = "tokio")]
+pub use self::socket::{AsyncConnectedStream, AsyncListener, AsyncBoundDatagram};
+#[cfg(feature = "xdp")]
+pub use self::socket::{XdpSocket, XdpConfig, XdpDesc, XdpStats};
+
+/// Reports an fd wirelane just created to the leak detector (`testing`
+/// feature) and the socket registry (`registry` feature). Without
+/// either, it compiles to nothing.
+#[inline]
+pub(crate) fn track(fd: std::os::fd::RawFd, what: &'static str) {
+	#[cfg(feature = "testing")]
+	testing::track(fd, what);
+	#[cfg(feature = "registry")]
+	registry::record(fd, what);
+	#[cfg(not(any(feature = "testing", feature = "registry")))]
+	let _ = (fd, what);
+}
+
+/// Fills `buf` from getrandom() without blocking.
+///
+/// Early in boot, before the pool is ready, it falls back to the clock
+/// and pid: callers need values that differ between processes and
+/// calls (jitter, unique names, STUN transaction ids), not secrecy.
+pub(crate) fn fill_random(buf: &mut [u8]) {
+	let n = unsafe { libc::getrandom(buf.as_mut_ptr() as *mut libc::c_void, buf.len(), libc::GRND_NONBLOCK) };
+	if n == buf.len() as isize {
+		return;
+	}
+	let nanos = std::time::SystemTime::now()
+		.duration_since(std::time::UNIX_EPOCH)
+		.unwrap_or_default()
+		.as_nanos();
+	let seed = (nanos ^ ((std::process::id() as u128) << 64)).to_ne_bytes();
+	for (byte, seed) in buf.iter_mut().zip(seed.iter().cycle()) {
+		*byte = *seed;
+	}
+}
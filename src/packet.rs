@@ -0,0 +1,255 @@
+//! Packet headers for raw sockets.
+//!
+//! With IP_HDRINCL the application writes the IP header itself.
+//! These types build and parse headers without unsafe byte casting.
+
+use crate::error::IoError;
+
+/// IPv4 header (RFC 791), without options.
+///
+/// # Example
+/// ```ignore
+/// let socket = RawSocket::<Ipv4, Raw>::with_protocol(libc::IPPROTO_RAW)?.into_datagram();
+///
+/// let packet = Ipv4Header::new(253, [10, 0, 0, 1], [10, 0, 0, 2])  // 253 = experimental
+///     .ttl(16)
+///     .encode(b"payload")?;
+/// socket.send_to(&packet, &SocketAddrV4::new([10, 0, 0, 2], 0))?;
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Ipv4Header {
+	pub tos: u8,              // DSCP (upper 6 bits) + ECN (lower 2)
+	pub id: u16,              // 0 = let the kernel pick
+	pub dont_fragment: bool,
+	pub ttl: u8,
+	pub protocol: u8,         // IPPROTO_* of the payload
+	pub src: [u8; 4],         // 0.0.0.0 = let the kernel pick
+	pub dst: [u8; 4],
+}
+
+impl Ipv4Header {
+	/// Header length without options.
+	pub const LEN: usize = 20;
+
+	/// Creates a header with TTL 64 and everything else zero.
+	pub fn new(protocol: u8, src: [u8; 4], dst: [u8; 4]) -> Self {
+		Self {
+			tos: 0,
+			id: 0,
+			dont_fragment: false,
+			ttl: 64,
+			protocol,
+			src,
+			dst,
+		}
+	}
+
+	pub fn tos(mut self, tos: u8) -> Self {
+		self.tos = tos;
+		self
+	}
+
+	pub fn id(mut self, id: u16) -> Self {
+		self.id = id;
+		self
+	}
+
+	pub fn dont_fragment(mut self, enable: bool) -> Self {
+		self.dont_fragment = enable;
+		self
+	}
+
+	pub fn ttl(mut self, ttl: u8) -> Self {
+		self.ttl = ttl;
+		self
+	}
+
+	/// Serializes the header for a payload of `payload_len` bytes.
+	///
+	/// Total length and checksum are filled in.
+	/// Fails with EMSGSIZE if the packet would exceed 65535 bytes.
+	pub fn to_bytes(&self, payload_len: usize) -> std::io::Result<[u8; Self::LEN]> {
+		let total = u16::try_from(Self::LEN + payload_len)
+			.map_err(|_| IoError::Write { errno: libc::EMSGSIZE })?;
+		let flags_frag: u16 = if self.dont_fragment { 0x4000 } else { 0 };
+
+		let mut h = [0u8; Self::LEN];
+		h[0] = 0x45;  // Version 4, IHL 5 (20 bytes)
+		h[1] = self.tos;
+		h[2..4].copy_from_slice(&total.to_be_bytes());
+		h[4..6].copy_from_slice(&self.id.to_be_bytes());
+		h[6..8].copy_from_slice(&flags_frag.to_be_bytes());
+		h[8] = self.ttl;
+		h[9] = self.protocol;
+		// h[10..12] checksum, computed over the header with this field zeroed
+		h[12..16].copy_from_slice(&self.src);
+		h[16..20].copy_from_slice(&self.dst);
+
+		let sum = checksum(&h);
+		h[10..12].copy_from_slice(&sum.to_be_bytes());
+		Ok(h)
+	}
+
+	/// Builds a complete packet: header followed by `payload`.
+	pub fn encode(&self, payload: &[u8]) -> std::io::Result<Vec<u8>> {
+		let header = self.to_bytes(payload.len())?;
+		let mut packet = Vec::with_capacity(Self::LEN + payload.len());
+		packet.extend_from_slice(&header);
+		packet.extend_from_slice(payload);
+		Ok(packet)
+	}
+
+	/// Parses a received packet into its header and payload.
+	///
+	/// IPv4 raw sockets always deliver the IP header. Options are skipped.
+	/// Returns `None` if `packet` is not a well-formed IPv4 packet.
+	pub fn parse(packet: &[u8]) -> Option<(Self, &[u8])> {
+		if packet.len() < Self::LEN || packet[0] >> 4 != 4 {
+			return None;
+		}
+
+		let header_len = ((packet[0] & 0x0f) as usize) * 4;
+		let total = u16::from_be_bytes([packet[2], packet[3]]) as usize;
+		if header_len < Self::LEN || header_len > total || total > packet.len() {
+			return None;
+		}
+
+		let header = Self {
+			tos: packet[1],
+			id: u16::from_be_bytes([packet[4], packet[5]]),
+			dont_fragment: packet[6] & 0x40 != 0,
+			ttl: packet[8],
+			protocol: packet[9],
+			src: packet[12..16].try_into().ok()?,
+			dst: packet[16..20].try_into().ok()?,
+		};
+		Some((header, &packet[header_len..total]))
+	}
+}
+
+/// ICMP/ICMPv6 echo request or reply.
+///
+/// Ping sockets (`RawSocket::new_icmp()`) deliver the ICMP message without
+/// an IP header; raw IPv4 sockets include one — strip it with
+/// `Ipv4Header::parse()` first.
+///
+/// # Example
+/// ```ignore
+/// let socket = RawSocket::<Ipv4, Datagram>::new_icmp()?.bind_icmp_id(0x1234)?;
+/// socket.send_to(&IcmpEcho::new(0x1234, seq, b"ping").encode_v4(), &target)?;
+///
+/// let (n, from) = socket.recv_from(&mut buf)?;
+/// if let Some(reply) = IcmpEcho::parse_reply(&buf[..n]) && reply.matches(0x1234, seq) {
+///     // our reply
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IcmpEcho<'a> {
+	pub id: u16,
+	pub seq: u16,
+	pub payload: &'a [u8],
+}
+
+const ICMP_ECHO_REPLY: u8 = 0;
+const ICMP_ECHO_REQUEST: u8 = 8;
+const ICMPV6_ECHO_REQUEST: u8 = 128;
+const ICMPV6_ECHO_REPLY: u8 = 129;
+
+impl<'a> IcmpEcho<'a> {
+	/// Echo header length (type, code, checksum, id, seq).
+	pub const HEADER_LEN: usize = 8;
+
+	pub fn new(id: u16, seq: u16, payload: &'a [u8]) -> Self {
+		Self { id, seq, payload }
+	}
+
+	/// Encodes an ICMPv4 echo request, checksum included.
+	pub fn encode_v4(&self) -> Vec<u8> {
+		let mut msg = self.encode(ICMP_ECHO_REQUEST);
+		let sum = checksum(&msg);
+		msg[2..4].copy_from_slice(&sum.to_be_bytes());
+		msg
+	}
+
+	/// Encodes an ICMPv6 echo request.
+	///
+	/// The checksum is left zero — it covers an IPv6 pseudo-header,
+	/// so the kernel computes it on send.
+	pub fn encode_v6(&self) -> Vec<u8> {
+		self.encode(ICMPV6_ECHO_REQUEST)
+	}
+
+	fn encode(&self, icmp_type: u8) -> Vec<u8> {
+		let mut msg = Vec::with_capacity(Self::HEADER_LEN + self.payload.len());
+		msg.extend_from_slice(&[icmp_type, 0, 0, 0]);
+		msg.extend_from_slice(&self.id.to_be_bytes());
+		msg.extend_from_slice(&self.seq.to_be_bytes());
+		msg.extend_from_slice(self.payload);
+		msg
+	}
+
+	/// Parses an ICMPv4 or ICMPv6 echo reply.
+	///
+	/// Returns `None` for anything else (unreachable, time exceeded, ...).
+	pub fn parse_reply(msg: &'a [u8]) -> Option<Self> {
+		if msg.len() < Self::HEADER_LEN || msg[1] != 0 {
+			return None;
+		}
+		if msg[0] != ICMP_ECHO_REPLY && msg[0] != ICMPV6_ECHO_REPLY {
+			return None;
+		}
+
+		Some(Self {
+			id: u16::from_be_bytes([msg[4], msg[5]]),
+			seq: u16::from_be_bytes([msg[6], msg[7]]),
+			payload: &msg[Self::HEADER_LEN..],
+		})
+	}
+
+	/// Returns true if this is the reply to request (`id`, `seq`).
+	///
+	/// On a socket bound with `bind_icmp_id()` the id always matches;
+	/// the sequence number tells outstanding requests apart.
+	pub fn matches(&self, id: u16, seq: u16) -> bool {
+		self.id == id && self.seq == seq
+	}
+}
+
+/// Computes the Internet checksum (RFC 1071) over `data`.
+///
+/// Used by IPv4, ICMP, UDP, and TCP. Store the result big-endian.
+/// Running it over data that already contains a valid checksum yields 0.
+pub fn checksum(data: &[u8]) -> u16 {
+	let mut sum: u32 = 0;
+
+	let mut chunks = data.chunks_exact(2);
+	for pair in &mut chunks {
+		sum += u16::from_be_bytes([pair[0], pair[1]]) as u32;
+	}
+	if let [last] = chunks.remainder() {
+		sum += (*last as u32) << 8;  // Odd length: pad with a zero byte
+	}
+
+	// Fold carries back into the low 16 bits.
+	while sum >> 16 != 0 {
+		sum = (sum & 0xffff) + (sum >> 16);
+	}
+	!(sum as u16)
+}
+
+/*
+IPv4 header layout (20 bytes, no options):
+  0        4        8                16                              31
+  ┌────────┬────────┬────────────────┬────────────────────────────────┐
+  │Version │  IHL   │      TOS       │          Total Length          │
+  ├────────┴────────┴────────────────┼───┬────────────────────────────┤
+  │          Identification          │Flg│      Fragment Offset       │
+  ├─────────────────┬────────────────┼───┴────────────────────────────┤
+  │       TTL       │    Protocol    │        Header Checksum         │
+  ├─────────────────┴────────────────┴────────────────────────────────┤
+  │                         Source Address                            │
+  ├───────────────────────────────────────────────────────────────────┤
+  │                      Destination Address                          │
+  └───────────────────────────────────────────────────────────────────┘
+All multi-byte fields are big-endian (network order).
+*/
@@ -0,0 +1,285 @@
+//! HAProxy PROXY protocol (v1 and v2) on accepted connections.
+//!
+//! Behind a TCP load balancer (HAProxy, AWS NLB, Envoy, ...) every
+//! connection's peer address is the balancer's. With the PROXY protocol
+//! enabled there, each connection starts with a header naming the real
+//! client; `Listener::accept_proxied()` reads it off the stream and
+//! hands it back alongside, so the first byte the application reads is
+//! its own.
+//!
+//! # Example
+//! ```ignore
+//! let listener = ListenerBuilder::<Ipv4>::new().bind(addr)?.listen(1024)?;
+//! loop {
+//!     let (stream, header) = listener.accept_proxied()?;
+//!     let client = header.source.unwrap_or(AnyAddr::Ipv4(stream.peer_addr()?));
+//!     log::info!("connection from {:?}", client);
+//! }
+//! ```
+
+use crate::addr::{Domain, SocketAddrV4, SocketAddrV6, UnixAddr};
+use crate::error::IoError;
+use crate::socket::{AnyAddr, BlockingMode, ConnectedStream, Listener, RecvFlags};
+
+/// The 12 bytes every v2 header starts with.
+const V2_SIGNATURE: [u8; 12] = *b"\r\n\r\n\0\r\nQUIT\n";
+const V2_HEADER_LEN: usize = 16;
+const V1_PREFIX: &[u8] = b"PROXY ";
+/// Longest v1 line, CRLF included ("PROXY TCP6 <ffff:...> <ffff:...> 65535 65535\r\n").
+const V1_MAX_LEN: usize = 107;
+
+const V2_CMD_LOCAL: u8 = 0x0;
+const V2_CMD_PROXY: u8 = 0x1;
+const V2_AF_INET: u8 = 0x1;
+const V2_AF_INET6: u8 = 0x2;
+const V2_AF_UNIX: u8 = 0x3;
+
+/// TLV types from the spec (§2.2.1).
+pub const PP2_TYPE_ALPN: u8 = 0x01;
+pub const PP2_TYPE_AUTHORITY: u8 = 0x02;
+pub const PP2_TYPE_CRC32C: u8 = 0x03;
+pub const PP2_TYPE_UNIQUE_ID: u8 = 0x05;
+pub const PP2_TYPE_SSL: u8 = 0x20;
+pub const PP2_TYPE_NETNS: u8 = 0x30;
+/// AWS NLB: the VPC endpoint ID, behind a one-byte subtype (0x01).
+pub const PP2_TYPE_AWS: u8 = 0xEA;
+
+/// A parsed PROXY protocol header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProxyHeader {
+	/// 1 for the text format, 2 for the binary one.
+	pub version: u8,
+	/// The real client. `None` for LOCAL connections (the balancer's own
+	/// health checks), `UNKNOWN` and unsupported families — fall back
+	/// to the socket's peer address then.
+	pub source: Option<AnyAddr>,
+	/// The address the client connected to, on the balancer.
+	pub destination: Option<AnyAddr>,
+	local: bool,
+	/// Raw v2 TLV area.
+	tlvs: Vec<u8>,
+}
+
+impl ProxyHeader {
+	/// The balancer opened this connection itself (v2 LOCAL command),
+	/// usually for a health check; there's no client behind it.
+	pub fn is_local(&self) -> bool {
+		self.local
+	}
+
+	/// The v2 type-length-value extensions, in order. Empty for v1.
+	pub fn tlvs(&self) -> TlvIter<'_> {
+		TlvIter { rest: &self.tlvs }
+	}
+
+	/// The value of the first TLV of type `ty` (`PP2_TYPE_*`).
+	pub fn tlv(&self, ty: u8) -> Option<&[u8]> {
+		self.tlvs().find(|&(t, _)| t == ty).map(|(_, value)| value)
+	}
+}
+
+/// Iterator over a v2 header's TLVs as `(type, value)`.
+pub struct TlvIter<'a> {
+	rest: &'a [u8],
+}
+
+impl<'a> Iterator for TlvIter<'a> {
+	type Item = (u8, &'a [u8]);
+
+	fn next(&mut self) -> Option<(u8, &'a [u8])> {
+		if self.rest.len() < 3 {
+			return None;
+		}
+		let ty = self.rest[0];
+		let len = u16::from_be_bytes([self.rest[1], self.rest[2]]) as usize;
+		let value = self.rest.get(3..3 + len)?;
+		self.rest = &self.rest[3 + len..];
+		Some((ty, value))
+	}
+}
+
+impl<D: Domain, M: BlockingMode> Listener<D, M> {
+	/// Accepts a connection and reads its PROXY protocol header.
+	///
+	/// Returns the stream positioned right after the header, and the
+	/// header itself. A connection that doesn't start with a valid
+	/// header is closed and the call fails with `InvalidData`; once the
+	/// balancer is set up to send one, a connection without it didn't
+	/// come through the balancer.
+	///
+	/// Blocks until the header arrives. Set a receive timeout on the
+	/// listener (accepted sockets inherit it) so a client that connects
+	/// and says nothing can't stall the accept loop.
+	pub fn accept_proxied(&self) -> std::io::Result<(ConnectedStream<D>, ProxyHeader)> {
+		let stream = self.accept()?;
+		let header = read_header(&stream)?;
+		Ok((stream, header))
+	}
+}
+
+/// Reads a PROXY protocol header (v1 or v2) from the start of `stream`.
+///
+/// Consumes exactly the header: it peeks first, so application data
+/// the client sent in the same segment stays queued. For connections
+/// accepted some other way (`accept_nonblocking()`, an `Acceptor`).
+pub fn read_header<D: Domain>(stream: &ConnectedStream<D>) -> std::io::Result<ProxyHeader> {
+	let mut buf = [0u8; V1_MAX_LEN];
+	let mut have = stream.peek(&mut buf)?;
+	loop {
+		let seen = &buf[..have];
+		if V2_SIGNATURE.starts_with(&seen[..have.min(V2_SIGNATURE.len())]) {
+			if have >= V2_HEADER_LEN {
+				return read_v2(stream);
+			}
+		} else if V1_PREFIX.starts_with(&seen[..have.min(V1_PREFIX.len())]) {
+			if let Some(end) = seen.windows(2).position(|w| w == b"\r\n") {
+				let mut line = [0u8; V1_MAX_LEN];
+				consume(stream, &mut line[..end + 2])?;
+				return parse_v1(&line[..end]);
+			}
+			if have == V1_MAX_LEN {
+				return Err(malformed("PROXY v1 header has no CRLF within 107 bytes"));
+			}
+		} else {
+			return Err(malformed("connection doesn't start with a PROXY protocol header"));
+		}
+		have = peek_more(stream, &mut buf, have)?;
+	}
+}
+
+/// Waits until more than `have` bytes are queued, then peeks all of them.
+fn peek_more<D: Domain>(stream: &ConnectedStream<D>, buf: &mut [u8], have: usize) -> std::io::Result<usize> {
+	let n = stream.recv_with_flags(&mut buf[..have + 1], RecvFlags::PEEK | RecvFlags::WAITALL)?;
+	if n <= have {
+		return Err(IoError::ConnectionClosed.into());
+	}
+	stream.peek(buf)
+}
+
+fn consume<D: Domain>(stream: &ConnectedStream<D>, buf: &mut [u8]) -> std::io::Result<()> {
+	if stream.recv_exact(buf)? < buf.len() {
+		return Err(IoError::ConnectionClosed.into());
+	}
+	Ok(())
+}
+
+fn malformed(reason: &'static str) -> std::io::Error {
+	IoError::MalformedFrame { reason }.into()
+}
+
+/// "PROXY TCP4 192.0.2.1 198.51.100.1 56324 443", CRLF stripped.
+fn parse_v1(line: &[u8]) -> std::io::Result<ProxyHeader> {
+	let line = std::str::from_utf8(line).map_err(|_| malformed("PROXY v1 header isn't ASCII"))?;
+	let mut fields = line.split(' ').skip(1);
+	let mut header = ProxyHeader { version: 1, source: None, destination: None, local: false, tlvs: Vec::new() };
+
+	let proto = fields.next().ok_or(malformed("PROXY v1 header has no protocol"))?;
+	if proto == "UNKNOWN" {
+		// Addresses may follow but must be ignored.
+		return Ok(header);
+	}
+	let (Some(src), Some(dst), Some(sport), Some(dport), None) =
+		(fields.next(), fields.next(), fields.next(), fields.next(), fields.next())
+	else {
+		return Err(malformed("PROXY v1 header needs 4 address fields"));
+	};
+	let sport: u16 = sport.parse().map_err(|_| malformed("invalid port in PROXY v1 header"))?;
+	let dport: u16 = dport.parse().map_err(|_| malformed("invalid port in PROXY v1 header"))?;
+
+	match proto {
+		"TCP4" => {
+			let parse = |ip: &str| ip.parse::<std::net::Ipv4Addr>().map_err(|_| malformed("invalid IPv4 address in PROXY v1 header"));
+			header.source = Some(AnyAddr::Ipv4(SocketAddrV4::new(parse(src)?.octets(), sport)));
+			header.destination = Some(AnyAddr::Ipv4(SocketAddrV4::new(parse(dst)?.octets(), dport)));
+		}
+		"TCP6" => {
+			let parse = |ip: &str| ip.parse::<std::net::Ipv6Addr>().map_err(|_| malformed("invalid IPv6 address in PROXY v1 header"));
+			header.source = Some(AnyAddr::Ipv6(SocketAddrV6::new(parse(src)?.octets(), sport)));
+			header.destination = Some(AnyAddr::Ipv6(SocketAddrV6::new(parse(dst)?.octets(), dport)));
+		}
+		_ => return Err(malformed("unknown protocol in PROXY v1 header")),
+	}
+	Ok(header)
+}
+
+fn read_v2<D: Domain>(stream: &ConnectedStream<D>) -> std::io::Result<ProxyHeader> {
+	let mut fixed = [0u8; V2_HEADER_LEN];
+	consume(stream, &mut fixed)?;
+	let version = fixed[12] >> 4;
+	let command = fixed[12] & 0x0f;
+	let family = fixed[13] >> 4;
+	let len = u16::from_be_bytes([fixed[14], fixed[15]]) as usize;
+	if version != 2 {
+		return Err(malformed("unsupported PROXY protocol version"));
+	}
+
+	let mut body = vec![0u8; len];
+	consume(stream, &mut body)?;
+
+	let mut header = ProxyHeader { version: 2, source: None, destination: None, local: false, tlvs: Vec::new() };
+	match command {
+		V2_CMD_LOCAL => {
+			// Addresses and TLVs, if any, must be ignored.
+			header.local = true;
+			return Ok(header);
+		}
+		V2_CMD_PROXY => {}
+		_ => return Err(malformed("unknown PROXY v2 command")),
+	}
+
+	let addr_len = match family {
+		V2_AF_INET => 12,
+		V2_AF_INET6 => 36,
+		V2_AF_UNIX => 216,
+		// AF_UNSPEC: no addresses, and nothing to skip but TLVs.
+		_ => 0,
+	};
+	let Some(addrs) = body.get(..addr_len) else {
+		return Err(malformed("PROXY v2 address block is truncated"));
+	};
+	match family {
+		V2_AF_INET => {
+			let port = |at: usize| u16::from_be_bytes([addrs[at], addrs[at + 1]]);
+			header.source = Some(AnyAddr::Ipv4(SocketAddrV4::new(addrs[0..4].try_into().unwrap(), port(8))));
+			header.destination = Some(AnyAddr::Ipv4(SocketAddrV4::new(addrs[4..8].try_into().unwrap(), port(10))));
+		}
+		V2_AF_INET6 => {
+			let port = |at: usize| u16::from_be_bytes([addrs[at], addrs[at + 1]]);
+			header.source = Some(AnyAddr::Ipv6(SocketAddrV6::new(addrs[0..16].try_into().unwrap(), port(32))));
+			header.destination = Some(AnyAddr::Ipv6(SocketAddrV6::new(addrs[16..32].try_into().unwrap(), port(34))));
+		}
+		V2_AF_UNIX => {
+			header.source = unix_addr(&addrs[..108]).map(AnyAddr::Unix);
+			header.destination = unix_addr(&addrs[108..]).map(AnyAddr::Unix);
+		}
+		_ => {}
+	}
+	header.tlvs = body[addr_len..].to_vec();
+	Ok(header)
+}
+
+/// A NUL-padded sun_path; a leading NUL marks an abstract name. `None`
+/// for a path with no terminator.
+fn unix_addr(path: &[u8]) -> Option<UnixAddr> {
+	match path.split_first() {
+		Some((0, name)) => {
+			let end = name.iter().rposition(|&b| b != 0).map_or(0, |i| i + 1);
+			Some(UnixAddr::abstract_socket(&name[..end]))
+		}
+		_ => {
+			let end = path.iter().position(|&b| b == 0).unwrap_or(path.len());
+			UnixAddr::try_new(&path[..end]).ok()
+		}
+	}
+}
+
+/*
+Why peek before reading, instead of a buffered reader?
+The header's length isn't known until it's parsed (v1 ends at a CRLF
+anywhere in 107 bytes), and a buffered reader would swallow whatever
+the client sent after it — the TLS ClientHello, the HTTP request — into
+a buffer the returned ConnectedStream doesn't have. Peeking finds the
+end first and then reads exactly that far, so the stream handed back is
+a plain socket positioned at the application's first byte, usable with
+splice(), kTLS or anything else that works on the fd.
+*/
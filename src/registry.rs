@@ -0,0 +1,168 @@
+//! A process-wide list of live wirelane sockets, for diagnostics.
+//!
+//! Every socket wirelane creates — by `socket()`, `accept()`, `dup()`,
+//! `socketpair()` or fd passing — is recorded here with the call that
+//! made it. `snapshot()` reads back the ones still open, with their
+//! addresses, options (as a `SocketManifest`) and TCP stats, ready to
+//! render on a `/debug/sockets` endpoint. Sockets adopted from elsewhere
+//! (`Socket::try_from_fd()`) can be added with `register()`.
+//!
+//! # Example
+//! ```ignore
+//! use wirelane::registry;
+//!
+//! registry::register(&listener, "http");
+//! for socket in registry::snapshot() {
+//!     println!("{socket}");
+//! }
+//! ```
+
+use std::collections::HashMap;
+use std::os::fd::{AsRawFd, BorrowedFd, RawFd};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use crate::socket::{SocketManifest, ManifestAddr, ManifestKind, TcpInfo, get_tcp_info};
+
+/// fd → entry. Closed sockets are pruned lazily, by `snapshot()`.
+static REGISTRY: Mutex<Option<HashMap<RawFd, Entry>>> = Mutex::new(None);
+
+#[derive(Clone)]
+struct Entry {
+	/// Inode at registration; tells our socket apart from a later fd
+	/// that reused the number.
+	inode: u64,
+	created_by: &'static str,
+	created_at: Instant,
+	label: Option<String>,
+}
+
+/// One live socket, as `snapshot()` saw it.
+#[derive(Debug, Clone)]
+pub struct RegisteredSocket {
+	pub fd: RawFd,
+	/// The call that created it, e.g. `"accept()"`.
+	pub created_by: &'static str,
+	/// Time since it was registered.
+	pub age: Duration,
+	/// Set with `register()`.
+	pub label: Option<String>,
+	/// Family, type, addresses and options; `None` for sockets a
+	/// manifest can't describe (netlink, raw, AF_XDP).
+	pub manifest: Option<SocketManifest>,
+	/// TCP connections only.
+	pub tcp_info: Option<TcpInfo>,
+}
+
+/// Adds `socket` to the registry under `label`, or relabels it.
+///
+/// Sockets wirelane created are registered already; this names them,
+/// or adds sockets that came from elsewhere.
+pub fn register<S: AsRawFd>(socket: &S, label: impl Into<String>) {
+	let fd = socket.as_raw_fd();
+	let label = label.into();
+	let mut registry = REGISTRY.lock().unwrap_or_else(|e| e.into_inner());
+	let entries = registry.get_or_insert_with(HashMap::new);
+	match entries.get_mut(&fd) {
+		Some(entry) if inode(fd) == Some(entry.inode) => entry.label = Some(label),
+		_ => {
+			let Some(inode) = inode(fd) else { return };
+			entries.insert(fd, Entry { inode, created_by: "register()", created_at: Instant::now(), label: Some(label) });
+		}
+	}
+}
+
+/// Records an fd wirelane just created. Non-sockets are filtered out
+/// by `snapshot()`.
+pub(crate) fn record(fd: RawFd, created_by: &'static str) {
+	let Some(inode) = inode(fd) else { return };
+	let mut registry = REGISTRY.lock().unwrap_or_else(|e| e.into_inner());
+	registry.get_or_insert_with(HashMap::new)
+		.insert(fd, Entry { inode, created_by, created_at: Instant::now(), label: None });
+}
+
+/// Lists every registered socket that's still open, oldest first.
+///
+/// Queries each socket, so it costs a few syscalls per socket; fine for
+/// a debug endpoint, not for a hot path.
+pub fn snapshot() -> Vec<RegisteredSocket> {
+	// Only copy under the lock: record() runs on every accept.
+	let entries: Vec<(RawFd, Entry)> = {
+		let mut registry = REGISTRY.lock().unwrap_or_else(|e| e.into_inner());
+		let Some(entries) = registry.as_mut() else { return Vec::new() };
+		entries.retain(|&fd, entry| inode(fd) == Some(entry.inode));
+		entries.iter().map(|(&fd, entry)| (fd, entry.clone())).collect()
+	};
+
+	let mut sockets: Vec<RegisteredSocket> = entries.into_iter()
+		.filter_map(|(fd, entry)| {
+			// Closed and reused since the copy was taken.
+			if inode(fd) != Some(entry.inode) || !is_socket(fd) {
+				return None;
+			}
+			let borrowed = unsafe { BorrowedFd::borrow_raw(fd) };
+			let manifest = SocketManifest::capture(&borrowed).ok();
+			let is_tcp_conn = manifest.as_ref().is_some_and(|m| {
+				m.kind == ManifestKind::Stream && !matches!(m.local_addr, Some(ManifestAddr::Unix { .. }))
+			});
+			Some(RegisteredSocket {
+				fd,
+				created_by: entry.created_by,
+				age: entry.created_at.elapsed(),
+				label: entry.label,
+				tcp_info: if is_tcp_conn { get_tcp_info(&borrowed).ok() } else { None },
+				manifest,
+			})
+		})
+		.collect();
+	sockets.sort_by_key(|s| std::cmp::Reverse(s.age));
+	sockets
+}
+
+impl std::fmt::Display for RegisteredSocket {
+	/// One line: fd, label, kind, addresses, origin and age.
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "fd {}", self.fd)?;
+		if let Some(label) = &self.label {
+			write!(f, " [{}]", label)?;
+		}
+		match &self.manifest {
+			Some(m) => {
+				write!(f, " {:?}/{:?}", m.family, m.kind)?;
+				if let Some(local) = &m.local_addr {
+					write!(f, " local={:?}", local)?;
+				}
+				if let Some(peer) = &m.peer_addr {
+					write!(f, " peer={:?}", peer)?;
+				}
+			}
+			None => f.write_str(" (other socket)")?,
+		}
+		if let Some(info) = &self.tcp_info {
+			write!(f, " rtt={}us retrans={}", info.rtt_us, info.total_retrans)?;
+		}
+		write!(f, " from {} {:.1}s ago", self.created_by, self.age.as_secs_f64())
+	}
+}
+
+fn inode(fd: RawFd) -> Option<u64> {
+	let mut st: libc::stat = unsafe { std::mem::zeroed() };
+	if unsafe { libc::fstat(fd, &mut st) } == -1 {
+		return None;
+	}
+	Some(st.st_ino)
+}
+
+fn is_socket(fd: RawFd) -> bool {
+	let mut st: libc::stat = unsafe { std::mem::zeroed() };
+	unsafe { libc::fstat(fd, &mut st) == 0 && st.st_mode & libc::S_IFMT == libc::S_IFSOCK }
+}
+
+/*
+Why fd numbers and inodes instead of a Drop hook on every socket type?
+The typed sockets hand their fd from state to state (RawSocket →
+BoundSocket → Listener, PendingConnect → ConnectedStream) by moving it
+out, which a Drop impl would forbid, and sockets also leave through
+into_raw_fd(). Recording the fd where it's created and checking the
+inode when asked tracks the socket itself, however it's wrapped, at the
+cost of a stale entry until the next snapshot.
+*/
@@ -0,0 +1,357 @@
+//! TCP connection checkpoint/restore with TCP_REPAIR (the CRIU technique).
+//!
+//! `checkpoint()` puts an established `ConnectedStream` into repair mode
+//! and exports everything the kernel needs to recreate it: sequence
+//! numbers, unread and unacknowledged data, negotiated options and the
+//! window. `restore()` rebuilds the connection from that on a fresh
+//! socket — in another process, or after a restart — without the peer
+//! seeing a new handshake. Needs CAP_NET_ADMIN on both ends.
+//!
+//! # Example
+//! ```ignore
+//! use wirelane::repair;
+//!
+//! let state = repair::checkpoint(&stream)?;
+//! drop(stream);  // still in repair mode: closes without FIN or RST
+//! // ... hand `state` to the new owner ...
+//! let stream: ConnectedStream<Ipv4> = repair::restore(&state)?;
+//! ```
+
+use std::os::fd::AsRawFd;
+use crate::addr::{Domain, ToSockAddr, FromSockAddr};
+use crate::error::{SocketError, IoError, errno};
+use crate::ioctls::{queue_len, SIOCOUTQNSD};
+use crate::socket::{
+	RawSocket, Stream, ConnectedStream, SockOpt, OptValue, set_opt, get_opt, set_reuse_addr,
+	get_raw_tcp_info, struct_bytes, struct_from_bytes,
+};
+
+// From linux/tcp.h; not exported by libc on every target.
+const TCP_REPAIR: libc::c_int = 19;
+const TCP_REPAIR_QUEUE: libc::c_int = 20;
+const TCP_QUEUE_SEQ: libc::c_int = 21;
+const TCP_REPAIR_OPTIONS: libc::c_int = 22;
+const TCP_TIMESTAMP: libc::c_int = 24;
+const TCP_REPAIR_WINDOW: libc::c_int = 29;
+const TCP_RECV_QUEUE: libc::c_int = 1;
+const TCP_SEND_QUEUE: libc::c_int = 2;
+const TCPOPT_MSS: u32 = 2;
+const TCPOPT_WINDOW: u32 = 3;
+const TCPOPT_SACK_PERM: u32 = 4;
+const TCPOPT_TIMESTAMP: u32 = 8;
+const TCPI_OPT_TIMESTAMPS: u8 = 1;
+const TCPI_OPT_SACK: u8 = 2;
+const TCPI_OPT_WSCALE: u8 = 4;
+
+/// Largest chunk written into a queue per send() while restoring.
+const RESTORE_CHUNK: usize = 64 * 1024;
+
+/// struct tcp_repair_opt from linux/tcp.h.
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct TcpRepairOpt {
+	opt_code: u32,
+	opt_val: u32,
+}
+
+struct Repair;
+
+impl SockOpt for Repair {
+	const LEVEL: libc::c_int = libc::IPPROTO_TCP;
+	const NAME: libc::c_int = TCP_REPAIR;
+	const LABEL: &'static str = "TCP_REPAIR";
+	type Value = u32;
+}
+
+struct RepairQueue;
+
+impl SockOpt for RepairQueue {
+	const LEVEL: libc::c_int = libc::IPPROTO_TCP;
+	const NAME: libc::c_int = TCP_REPAIR_QUEUE;
+	const LABEL: &'static str = "TCP_REPAIR_QUEUE";
+	type Value = u32;
+}
+
+struct QueueSeq;
+
+impl SockOpt for QueueSeq {
+	const LEVEL: libc::c_int = libc::IPPROTO_TCP;
+	const NAME: libc::c_int = TCP_QUEUE_SEQ;
+	const LABEL: &'static str = "TCP_QUEUE_SEQ";
+	type Value = u32;
+}
+
+struct RepairOptions;
+
+impl SockOpt for RepairOptions {
+	const LEVEL: libc::c_int = libc::IPPROTO_TCP;
+	const NAME: libc::c_int = TCP_REPAIR_OPTIONS;
+	const LABEL: &'static str = "TCP_REPAIR_OPTIONS";
+	type Value = Vec<u8>;
+}
+
+struct Timestamp;
+
+impl SockOpt for Timestamp {
+	const LEVEL: libc::c_int = libc::IPPROTO_TCP;
+	const NAME: libc::c_int = TCP_TIMESTAMP;
+	const LABEL: &'static str = "TCP_TIMESTAMP";
+	type Value = u32;
+}
+
+struct RepairWindowOpt;
+
+impl SockOpt for RepairWindowOpt {
+	const LEVEL: libc::c_int = libc::IPPROTO_TCP;
+	const NAME: libc::c_int = TCP_REPAIR_WINDOW;
+	const LABEL: &'static str = "TCP_REPAIR_WINDOW";
+	type Value = RepairWindow;
+}
+
+struct MaxSeg;
+
+impl SockOpt for MaxSeg {
+	const LEVEL: libc::c_int = libc::IPPROTO_TCP;
+	const NAME: libc::c_int = libc::TCP_MAXSEG;
+	const LABEL: &'static str = "TCP_MAXSEG";
+	type Value = u32;
+}
+
+/// Window state from TCP_REPAIR_WINDOW (struct tcp_repair_window).
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RepairWindow {
+	pub snd_wl1: u32,
+	pub snd_wnd: u32,
+	pub max_window: u32,
+	pub rcv_wnd: u32,
+	pub rcv_wup: u32,
+}
+
+impl OptValue for RepairWindow {
+	const MAX_LEN: usize = std::mem::size_of::<Self>();
+
+	fn encode(&self) -> Vec<u8> {
+		struct_bytes(self)
+	}
+
+	fn decode(bytes: &[u8]) -> Option<Self> {
+		struct_from_bytes(bytes)
+	}
+}
+
+/// Everything needed to recreate an established TCP connection.
+///
+/// Fields are public so the state can be sent to another process in
+/// whatever encoding the caller already uses.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TcpCheckpoint<A> {
+	/// Our end of the connection.
+	pub local: A,
+	/// The peer's end.
+	pub peer: A,
+	/// Sequence number after the last byte queued for sending.
+	pub send_seq: u32,
+	/// Next sequence number expected from the peer.
+	pub recv_seq: u32,
+	/// Sent-but-unacknowledged bytes, then not-yet-sent ones.
+	pub send_queue: Vec<u8>,
+	/// How many bytes at the end of `send_queue` were never sent.
+	pub unsent: usize,
+	/// Received bytes the application hasn't read.
+	pub recv_queue: Vec<u8>,
+	/// Maximum segment size (TCP_MAXSEG).
+	pub mss: u32,
+	/// Negotiated window scale as (send, receive) shifts, if any.
+	pub window_scale: Option<(u8, u8)>,
+	/// Whether SACK was negotiated.
+	pub sack: bool,
+	/// The TCP timestamp clock, if timestamps were negotiated.
+	pub timestamp: Option<u32>,
+	/// Send and receive window state.
+	pub window: RepairWindow,
+}
+
+/// Turns TCP_REPAIR mode on or off.
+///
+/// In repair mode the socket sends nothing: sequence numbers, queues
+/// and options can be read and written directly, and closing it drops
+/// the connection silently. Turning it off resumes normal operation.
+pub fn set_tcp_repair<S: AsRawFd>(socket: &S, enable: bool) -> std::io::Result<()> {
+	set_opt::<_, Repair>(socket, enable as u32)
+}
+
+/// Exports the state of an established connection.
+///
+/// Leaves `stream` in repair mode so it can't send anything that would
+/// invalidate the checkpoint: drop it to release the connection without
+/// a FIN or RST, or call `set_tcp_repair(&stream, false)` to keep using
+/// it. On failure repair mode is turned off again.
+///
+/// Segments arriving after the checkpoint are still acknowledged by
+/// this socket and then lost; block the connection's traffic (e.g. with
+/// a firewall rule) until it's restored.
+pub fn checkpoint<D: Domain>(stream: &ConnectedStream<D>) -> std::io::Result<TcpCheckpoint<D::Addr>>
+where
+	D::Addr: FromSockAddr,
+{
+	set_tcp_repair(stream, true)?;
+	let result = capture(stream);
+	if result.is_err() {
+		let _ = set_tcp_repair(stream, false);
+	}
+	result
+}
+
+fn capture<D: Domain>(stream: &ConnectedStream<D>) -> std::io::Result<TcpCheckpoint<D::Addr>>
+where
+	D::Addr: FromSockAddr,
+{
+	let info = get_raw_tcp_info(stream)?;
+
+	set_opt::<_, RepairQueue>(stream, TCP_SEND_QUEUE as u32)?;
+	let send_seq = get_opt::<_, QueueSeq>(stream)?;
+	let send_queue = peek_queue(stream, queue_len(stream, libc::TIOCOUTQ, "SIOCOUTQ")?)?;
+	let unsent = queue_len(stream, SIOCOUTQNSD, "SIOCOUTQNSD")?.min(send_queue.len());
+
+	set_opt::<_, RepairQueue>(stream, TCP_RECV_QUEUE as u32)?;
+	let recv_seq = get_opt::<_, QueueSeq>(stream)?;
+	let recv_queue = peek_queue(stream, queue_len(stream, libc::FIONREAD, "SIOCINQ")?)?;
+
+	let timestamp = if info.tcpi_options & TCPI_OPT_TIMESTAMPS != 0 {
+		Some(get_opt::<_, Timestamp>(stream)?)
+	} else {
+		None
+	};
+
+	let window = get_opt::<_, RepairWindowOpt>(stream)?;
+
+	Ok(TcpCheckpoint {
+		local: stream.local_addr()?,
+		peer: stream.peer_addr()?,
+		send_seq,
+		recv_seq,
+		send_queue,
+		unsent,
+		recv_queue,
+		mss: get_opt::<_, MaxSeg>(stream)?,
+		window_scale: (info.tcpi_options & TCPI_OPT_WSCALE != 0).then_some((info.tcpi_snd_rcv_wscale & 0xf, info.tcpi_snd_rcv_wscale >> 4)),
+		sack: info.tcpi_options & TCPI_OPT_SACK != 0,
+		timestamp,
+		window,
+	})
+}
+
+/// Recreates a checkpointed connection on a new socket.
+///
+/// The original socket must be closed first (or live on another host
+/// that took over the address), since this binds to `checkpoint.local`.
+/// Data in the receive queue is readable right away; unacknowledged
+/// data is retransmitted and unsent data sent once repair mode ends.
+pub fn restore<D: Domain>(checkpoint: &TcpCheckpoint<D::Addr>) -> std::io::Result<ConnectedStream<D>>
+where
+	D::Addr: ToSockAddr + std::fmt::Debug,
+{
+	let socket = RawSocket::<D, Stream>::new()?;
+	set_tcp_repair(&socket, true)?;
+	set_reuse_addr(&socket, true)?;
+
+	// Rewind both queues by what's about to be written back into them.
+	let sent = checkpoint.send_queue.len() - checkpoint.unsent.min(checkpoint.send_queue.len());
+	set_opt::<_, RepairQueue>(&socket, TCP_SEND_QUEUE as u32)?;
+	set_opt::<_, QueueSeq>(&socket, checkpoint.send_seq.wrapping_sub(checkpoint.send_queue.len() as u32))?;
+	set_opt::<_, RepairQueue>(&socket, TCP_RECV_QUEUE as u32)?;
+	set_opt::<_, QueueSeq>(&socket, checkpoint.recv_seq.wrapping_sub(checkpoint.recv_queue.len() as u32))?;
+
+	let result = checkpoint.local.with_raw(|ptr, len| unsafe { libc::bind(socket.as_raw_fd(), ptr, len) });
+	match result {
+		Ok(-1) => return Err(SocketError::Bind { errno: errno(), addr: format!("{:?}", checkpoint.local) }.into()),
+		Ok(_) => {}
+		Err(e) => return Err(SocketError::from(e).into()),
+	}
+	// In repair mode connect() sends no SYN; it just enters ESTABLISHED.
+	let result = checkpoint.peer.with_raw(|ptr, len| unsafe { libc::connect(socket.as_raw_fd(), ptr, len) });
+	match result {
+		Ok(-1) => return Err(SocketError::Connect { errno: errno(), addr: format!("{:?}", checkpoint.peer) }.into()),
+		Ok(_) => {}
+		Err(e) => return Err(SocketError::from(e).into()),
+	}
+
+	let mut opts = vec![TcpRepairOpt { opt_code: TCPOPT_MSS, opt_val: checkpoint.mss }];
+	if let Some((snd, rcv)) = checkpoint.window_scale {
+		opts.push(TcpRepairOpt { opt_code: TCPOPT_WINDOW, opt_val: snd as u32 | (rcv as u32) << 16 });
+	}
+	if checkpoint.sack {
+		opts.push(TcpRepairOpt { opt_code: TCPOPT_SACK_PERM, opt_val: 0 });
+	}
+	if checkpoint.timestamp.is_some() {
+		opts.push(TcpRepairOpt { opt_code: TCPOPT_TIMESTAMP, opt_val: 0 });
+	}
+	set_opt::<_, RepairOptions>(&socket, opts.iter().flat_map(struct_bytes).collect())?;
+	if let Some(ts) = checkpoint.timestamp {
+		set_opt::<_, Timestamp>(&socket, ts)?;
+	}
+
+	set_opt::<_, RepairQueue>(&socket, TCP_RECV_QUEUE as u32)?;
+	write_queue(&socket, &checkpoint.recv_queue)?;
+	set_opt::<_, RepairQueue>(&socket, TCP_SEND_QUEUE as u32)?;
+	write_queue(&socket, &checkpoint.send_queue[..sent])?;
+
+	set_opt::<_, RepairWindowOpt>(&socket, checkpoint.window)?;
+	set_tcp_repair(&socket, false)?;
+
+	let stream = ConnectedStream::from_fd(socket.into_fd());
+	write_queue(&stream, &checkpoint.send_queue[sent..])?;
+	Ok(stream)
+}
+
+/// Reads the first `len` bytes of the selected queue without consuming them.
+fn peek_queue<S: AsRawFd>(socket: &S, len: usize) -> std::io::Result<Vec<u8>> {
+	let mut data = vec![0u8; len];
+	if len == 0 {
+		return Ok(data);
+	}
+	let n = unsafe {
+		libc::recv(
+			socket.as_raw_fd(),
+			data.as_mut_ptr() as *mut libc::c_void,
+			len,
+			libc::MSG_PEEK | libc::MSG_DONTWAIT,
+		)
+	};
+	if n == -1 {
+		return Err(IoError::Read { errno: errno() }.into());
+	}
+	data.truncate(n as usize);
+	Ok(data)
+}
+
+/// Writes all of `data` into the selected queue (or, out of repair
+/// mode, sends it).
+fn write_queue<S: AsRawFd>(socket: &S, data: &[u8]) -> std::io::Result<()> {
+	let mut written = 0;
+	while written < data.len() {
+		let chunk = &data[written..data.len().min(written + RESTORE_CHUNK)];
+		let n = unsafe {
+			libc::send(socket.as_raw_fd(), chunk.as_ptr() as *const libc::c_void, chunk.len(), libc::MSG_NOSIGNAL)
+		};
+		if n == -1 {
+			if errno() == libc::EINTR {
+				continue;
+			}
+			return Err(IoError::Write { errno: errno() }.into());
+		}
+		written += n as usize;
+	}
+	Ok(())
+}
+
+/*
+Why copy the queues out instead of passing the fd to the new process?
+Passing the fd (send_fd()) works when both processes share a kernel and
+the old one cooperates. A checkpoint is plain data: it survives the old
+process exiting, can be written to disk across a restart, and can move
+to another host that takes over the IP address. The kernel keeps the
+peer unaware either way, because in repair mode nothing is sent: no FIN
+when the old socket closes, no SYN when the new one connects.
+*/
@@ -0,0 +1,352 @@
+//! Routing table and interface queries over NETLINK_ROUTE.
+//!
+//! Answers "which interface and source address would the kernel use to
+//! reach X?" and "what interfaces and addresses does this host have?"
+//! without parsing `ip route` output.
+//!
+//! # Example
+//! ```ignore
+//! use wirelane::route;
+//!
+//! let route = route::lookup("192.0.2.10".parse()?)?;
+//! println!("via {:?} dev {:?} src {:?}", route.gateway, route.interface, route.source);
+//!
+//! for iface in route::interfaces()? {
+//!     println!("{} {:?}", iface.name, iface.addrs);
+//! }
+//! ```
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::sync::atomic::{AtomicU32, Ordering};
+use crate::addr::{Netlink, NetlinkAddr};
+use crate::error::IoError;
+use crate::socket::{RawSocket, BoundDatagram, Datagram};
+
+// From linux/netlink.h and linux/rtnetlink.h. Netlink fields are host byte order.
+const NETLINK_ROUTE: libc::c_int = 0;
+const NLMSG_HDRLEN: usize = 16;
+const NLMSG_ERROR: u16 = 2;
+const NLMSG_DONE: u16 = 3;
+const NLM_F_REQUEST: u16 = 0x1;
+const NLM_F_DUMP: u16 = 0x300;
+const RTM_NEWLINK: u16 = 16;
+const RTM_GETLINK: u16 = 18;
+const RTM_NEWADDR: u16 = 20;
+const RTM_GETADDR: u16 = 22;
+const RTM_NEWROUTE: u16 = 24;
+const RTM_GETROUTE: u16 = 26;
+const IFLA_ADDRESS: u16 = 1;
+const IFLA_IFNAME: u16 = 3;
+const IFLA_MTU: u16 = 4;
+const IFA_ADDRESS: u16 = 1;
+const IFA_LOCAL: u16 = 2;
+const RTA_DST: u16 = 1;
+const RTA_OIF: u16 = 4;
+const RTA_GATEWAY: u16 = 5;
+const RTA_PREFSRC: u16 = 7;
+
+const RECV_BUF: usize = 64 * 1024;
+
+static SEQ: AtomicU32 = AtomicU32::new(1);
+
+/// Result of a route lookup.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Route {
+	/// The destination that was looked up.
+	pub dst: IpAddr,
+	/// Egress interface index. See `interfaces()` for its name.
+	pub interface: Option<u32>,
+	/// Next hop, if the destination is not directly connected.
+	pub gateway: Option<IpAddr>,
+	/// Preferred source address for packets to `dst`.
+	pub source: Option<IpAddr>,
+}
+
+/// A network interface and its addresses.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Interface {
+	pub index: u32,
+	pub name: String,
+	pub flags: u32,                 // IFF_* bits
+	pub mtu: Option<u32>,
+	pub hw_addr: Option<Vec<u8>>,   // MAC for Ethernet; absent for e.g. tun
+	pub addrs: Vec<InterfaceAddr>,
+}
+
+/// An address assigned to an interface.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InterfaceAddr {
+	pub addr: IpAddr,
+	pub prefix_len: u8,
+}
+
+impl Interface {
+	/// Returns true if the interface is administratively up (IFF_UP).
+	pub fn is_up(&self) -> bool {
+		self.flags & libc::IFF_UP as u32 != 0
+	}
+
+	/// Returns true for loopback interfaces (IFF_LOOPBACK).
+	pub fn is_loopback(&self) -> bool {
+		self.flags & libc::IFF_LOOPBACK as u32 != 0
+	}
+}
+
+/// Asks the kernel which route it would use to reach `dst`.
+///
+/// Same as `ip route get <dst>`.
+pub fn lookup(dst: IpAddr) -> std::io::Result<Route> {
+	let socket = open()?;
+	let seq = SEQ.fetch_add(1, Ordering::Relaxed);
+
+	let (family, dst_len, dst_bytes) = match dst {
+		IpAddr::V4(ip) => (libc::AF_INET as u8, 32, ip.octets().to_vec()),
+		IpAddr::V6(ip) => (libc::AF_INET6 as u8, 128, ip.octets().to_vec()),
+	};
+
+	let mut req = Request::new(RTM_GETROUTE, NLM_F_REQUEST, seq);
+	// struct rtmsg: family, dst_len, src_len, tos, table, protocol, scope, type, flags
+	req.push(&[family, dst_len, 0, 0, 0, 0, 0, 0]);
+	req.push(&0u32.to_ne_bytes());
+	req.attr(RTA_DST, &dst_bytes);
+
+	let mut route = Route { dst, interface: None, gateway: None, source: None };
+	exchange(&socket, req, false, |ty, payload| {
+		if ty != RTM_NEWROUTE || payload.len() < 12 {
+			return Ok(());
+		}
+		for (kind, data) in Attrs::new(&payload[12..]) {
+			match kind {
+				RTA_OIF => route.interface = read_u32(data),
+				RTA_GATEWAY => route.gateway = parse_ip(data),
+				RTA_PREFSRC => route.source = parse_ip(data),
+				_ => {}
+			}
+		}
+		Ok(())
+	})?;
+	Ok(route)
+}
+
+/// Lists all interfaces with their IPv4 and IPv6 addresses.
+///
+/// Same as `ip address show`.
+pub fn interfaces() -> std::io::Result<Vec<Interface>> {
+	let socket = open()?;
+	let mut interfaces = Vec::new();
+
+	let seq = SEQ.fetch_add(1, Ordering::Relaxed);
+	let mut req = Request::new(RTM_GETLINK, NLM_F_REQUEST | NLM_F_DUMP, seq);
+	req.push(&[0u8; 16]);  // struct ifinfomsg, AF_UNSPEC
+	exchange(&socket, req, true, |ty, payload| {
+		if ty != RTM_NEWLINK || payload.len() < 16 {
+			return Ok(());
+		}
+		let mut iface = Interface {
+			index: read_u32(&payload[4..8]).unwrap_or(0),
+			name: String::new(),
+			flags: read_u32(&payload[8..12]).unwrap_or(0),
+			mtu: None,
+			hw_addr: None,
+			addrs: Vec::new(),
+		};
+		for (kind, data) in Attrs::new(&payload[16..]) {
+			match kind {
+				IFLA_IFNAME => {
+					let name = data.split(|&b| b == 0).next().unwrap_or(&[]);
+					iface.name = String::from_utf8_lossy(name).into_owned();
+				}
+				IFLA_MTU => iface.mtu = read_u32(data),
+				IFLA_ADDRESS => iface.hw_addr = Some(data.to_vec()),
+				_ => {}
+			}
+		}
+		interfaces.push(iface);
+		Ok(())
+	})?;
+
+	let seq = SEQ.fetch_add(1, Ordering::Relaxed);
+	let mut req = Request::new(RTM_GETADDR, NLM_F_REQUEST | NLM_F_DUMP, seq);
+	req.push(&[0u8; 8]);  // struct ifaddrmsg, AF_UNSPEC
+	exchange(&socket, req, true, |ty, payload| {
+		if ty != RTM_NEWADDR || payload.len() < 8 {
+			return Ok(());
+		}
+		let prefix_len = payload[1];
+		let index = read_u32(&payload[4..8]).unwrap_or(0);
+
+		// IFA_LOCAL is the local end on point-to-point links; prefer it.
+		let mut local = None;
+		let mut address = None;
+		for (kind, data) in Attrs::new(&payload[8..]) {
+			match kind {
+				IFA_LOCAL => local = parse_ip(data),
+				IFA_ADDRESS => address = parse_ip(data),
+				_ => {}
+			}
+		}
+
+		if let Some(addr) = local.or(address)
+			&& let Some(iface) = interfaces.iter_mut().find(|i| i.index == index)
+		{
+			iface.addrs.push(InterfaceAddr { addr, prefix_len });
+		}
+		Ok(())
+	})?;
+
+	Ok(interfaces)
+}
+
+/// Opens a NETLINK_ROUTE socket with a kernel-assigned port id.
+fn open() -> std::io::Result<BoundDatagram<Netlink>> {
+	RawSocket::<Netlink, Datagram>::with_protocol(NETLINK_ROUTE)?
+		.bind_datagram(NetlinkAddr::kernel())
+}
+
+/// Sends `req` and feeds each reply message to `handle` as (type, payload).
+///
+/// Dumps end at NLMSG_DONE; single requests end after the first reply.
+fn exchange<F>(socket: &BoundDatagram<Netlink>, req: Request, dump: bool, mut handle: F) -> std::io::Result<()>
+where
+	F: FnMut(u16, &[u8]) -> std::io::Result<()>,
+{
+	let seq = req.seq;
+	socket.send_to(&req.finish(), &NetlinkAddr::kernel())?;
+
+	let mut buf = vec![0u8; RECV_BUF];
+	loop {
+		let n = socket.recv(&mut buf)?;
+		let mut msgs = &buf[..n];
+
+		while msgs.len() >= NLMSG_HDRLEN {
+			let len = read_u32(&msgs[0..4]).unwrap_or(0) as usize;
+			if len < NLMSG_HDRLEN || len > msgs.len() {
+				return Err(IoError::MalformedFrame { reason: "truncated netlink message" }.into());
+			}
+			let ty = u16::from_ne_bytes([msgs[4], msgs[5]]);
+			let msg_seq = read_u32(&msgs[8..12]).unwrap_or(0);
+			let payload = &msgs[NLMSG_HDRLEN..len];
+			msgs = &msgs[align(len).min(msgs.len())..];
+
+			if msg_seq != seq {
+				continue;  // Stale reply to an earlier request
+			}
+
+			match ty {
+				NLMSG_DONE => return Ok(()),
+				NLMSG_ERROR => {
+					// struct nlmsgerr: negative errno (0 = ack), then the request header
+					let code = payload.get(0..4).map_or(0, |b| i32::from_ne_bytes([b[0], b[1], b[2], b[3]]));
+					if code != 0 {
+						return Err(IoError::Read { errno: -code }.into());
+					}
+					if !dump {
+						return Ok(());
+					}
+				}
+				_ => {
+					handle(ty, payload)?;
+					if !dump {
+						return Ok(());
+					}
+				}
+			}
+		}
+	}
+}
+
+/// A netlink request being assembled.
+struct Request {
+	buf: Vec<u8>,
+	seq: u32,
+}
+
+impl Request {
+	fn new(ty: u16, flags: u16, seq: u32) -> Self {
+		let mut buf = Vec::with_capacity(64);
+		buf.extend_from_slice(&0u32.to_ne_bytes());   // nlmsg_len, patched in finish()
+		buf.extend_from_slice(&ty.to_ne_bytes());
+		buf.extend_from_slice(&flags.to_ne_bytes());
+		buf.extend_from_slice(&seq.to_ne_bytes());
+		buf.extend_from_slice(&0u32.to_ne_bytes());   // nlmsg_pid: 0, the kernel fills it in
+		Self { buf, seq }
+	}
+
+	fn push(&mut self, bytes: &[u8]) {
+		self.buf.extend_from_slice(bytes);
+	}
+
+	/// Appends a route attribute (struct rtattr + data, padded to 4 bytes).
+	fn attr(&mut self, kind: u16, data: &[u8]) {
+		let len = (4 + data.len()) as u16;
+		self.buf.extend_from_slice(&len.to_ne_bytes());
+		self.buf.extend_from_slice(&kind.to_ne_bytes());
+		self.buf.extend_from_slice(data);
+		self.buf.resize(align(self.buf.len()), 0);
+	}
+
+	fn finish(mut self) -> Vec<u8> {
+		let len = self.buf.len() as u32;
+		self.buf[0..4].copy_from_slice(&len.to_ne_bytes());
+		self.buf
+	}
+}
+
+/// Iterator over route attributes: (type, data).
+struct Attrs<'a> {
+	buf: &'a [u8],
+}
+
+impl<'a> Attrs<'a> {
+	fn new(buf: &'a [u8]) -> Self {
+		Self { buf }
+	}
+}
+
+impl<'a> Iterator for Attrs<'a> {
+	type Item = (u16, &'a [u8]);
+
+	fn next(&mut self) -> Option<Self::Item> {
+		if self.buf.len() < 4 {
+			return None;
+		}
+		let len = u16::from_ne_bytes([self.buf[0], self.buf[1]]) as usize;
+		// Top bits are NLA_F_NESTED / NLA_F_NET_BYTEORDER flags.
+		let kind = u16::from_ne_bytes([self.buf[2], self.buf[3]]) & 0x3fff;
+		if len < 4 || len > self.buf.len() {
+			return None;
+		}
+		let data = &self.buf[4..len];
+		self.buf = &self.buf[align(len).min(self.buf.len())..];
+		Some((kind, data))
+	}
+}
+
+/// Rounds up to the 4-byte netlink alignment.
+fn align(len: usize) -> usize {
+	(len + 3) & !3
+}
+
+fn read_u32(data: &[u8]) -> Option<u32> {
+	Some(u32::from_ne_bytes(data.get(..4)?.try_into().ok()?))
+}
+
+/// Addresses in attributes are network byte order; length gives the family.
+fn parse_ip(data: &[u8]) -> Option<IpAddr> {
+	match data.len() {
+		4 => Some(IpAddr::V4(Ipv4Addr::from(<[u8; 4]>::try_from(data).ok()?))),
+		16 => Some(IpAddr::V6(Ipv6Addr::from(<[u8; 16]>::try_from(data).ok()?))),
+		_ => None,
+	}
+}
+
+/*
+Netlink message layout:
+  ┌──────────────┬─────────────────────┬──────────┬──────────┬─────┐
+  │ nlmsghdr     │ family header       │ rtattr   │ rtattr   │ ... │
+  │ (16 bytes)   │ rtmsg / ifinfomsg / │ len,type │ len,type │     │
+  │ len,type,... │ ifaddrmsg           │ + data   │ + data   │     │
+  └──────────────┴─────────────────────┴──────────┴──────────┴─────┘
+Everything is padded to 4 bytes. A dump reply spans many messages
+(possibly many recv() calls) and ends with NLMSG_DONE.
+*/
@@ -0,0 +1,438 @@
+//! Loopback self-tests for validating socket tuning.
+//!
+//! Sets up a socket pair on this host with the options under test, runs
+//! traffic over it for a fixed time and reports what was achieved.
+//! Loopback never touches a NIC, so the numbers measure kernel and
+//! syscall overhead: good for comparing buffer sizes, Nagle or GSO
+//! settings against each other on the target hardware, not as a
+//! prediction of network throughput.
+//!
+//! # Example
+//! ```ignore
+//! use wirelane::selftest::{self, SelftestConfig, Transport};
+//!
+//! let base = SelftestConfig::new(Transport::Tcp).message_size(64 * 1024);
+//! let small = selftest::loopback_throughput(&base.buffers(BufferConfig::new().both(16 * 1024)))?;
+//! let large = selftest::loopback_throughput(&base.buffers(BufferConfig::new().both(1 << 20)))?;
+//! println!("{:.0} vs {:.0} MB/s", small.bytes_per_sec() / 1e6, large.bytes_per_sec() / 1e6);
+//!
+//! let rtt = selftest::latency(&SelftestConfig::new(Transport::Udp).message_size(64))?;
+//! println!("p50 {:?} p99 {:?}", rtt.p50, rtt.p99);
+//! ```
+
+use std::io::ErrorKind;
+use std::os::fd::AsRawFd;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use crate::addr::{Domain, Ipv4, Unix, SocketAddrV4};
+use crate::error::{IoError, SocketError, errno};
+use crate::socket::{
+	ListenerBuilder, ConnectorBuilder, DatagramBuilder, ConnectedStream, ConnectedDatagram,
+	BufferConfig, TcpConfig, Shutdown, set_recv_buffer_size, set_send_buffer_size, set_recv_timeout,
+};
+
+// From linux/udp.h; not exported by libc.
+const UDP_SEGMENT: libc::c_int = 103;
+
+const LOOPBACK: [u8; 4] = [127, 0, 0, 1];
+
+/// How long a receiver waits before checking whether the sender is done.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Socket pair to test.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transport {
+	/// TCP over 127.0.0.1.
+	Tcp,
+	/// Connected UDP sockets on 127.0.0.1.
+	Udp,
+	/// A Unix stream socket pair.
+	Unix,
+}
+
+/// What to test and with which options.
+#[derive(Debug, Clone, Copy)]
+pub struct SelftestConfig {
+	pub transport: Transport,
+	/// Bytes per write (throughput) or per round trip (latency). Default: 1024.
+	pub message_size: usize,
+	/// How long to send for. Default: 1s.
+	pub duration: Duration,
+	/// Buffer sizes for both ends.
+	pub buffers: BufferConfig,
+	/// TCP_NODELAY on both ends (TCP only). Default: true.
+	pub nodelay: bool,
+	/// UDP GSO segment size (UDP_SEGMENT) on the sender. Each write of
+	/// `message_size` bytes then leaves as datagrams of this size.
+	pub gso_segment: Option<u16>,
+}
+
+impl SelftestConfig {
+	pub fn new(transport: Transport) -> Self {
+		Self {
+			transport,
+			message_size: 1024,
+			duration: Duration::from_secs(1),
+			buffers: BufferConfig::default(),
+			nodelay: true,
+			gso_segment: None,
+		}
+	}
+
+	pub fn message_size(mut self, size: usize) -> Self {
+		self.message_size = size;
+		self
+	}
+
+	pub fn duration(mut self, duration: Duration) -> Self {
+		self.duration = duration;
+		self
+	}
+
+	pub fn buffers(mut self, config: BufferConfig) -> Self {
+		self.buffers = config;
+		self
+	}
+
+	pub fn nodelay(mut self, enable: bool) -> Self {
+		self.nodelay = enable;
+		self
+	}
+
+	pub fn gso_segment(mut self, size: u16) -> Self {
+		self.gso_segment = Some(size);
+		self
+	}
+}
+
+/// Result of `loopback_throughput()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ThroughputReport {
+	/// Bytes the sender handed to the kernel.
+	pub sent_bytes: u64,
+	/// Bytes that arrived. Below `sent_bytes` only for UDP, where a full
+	/// receive buffer drops datagrams.
+	pub received_bytes: u64,
+	/// Reads (streams) or datagrams (UDP) it took to receive them.
+	pub received_messages: u64,
+	/// From the first write until the last byte was received.
+	pub elapsed: Duration,
+}
+
+impl ThroughputReport {
+	/// Received bytes per second.
+	pub fn bytes_per_sec(&self) -> f64 {
+		self.received_bytes as f64 / self.elapsed.as_secs_f64()
+	}
+
+	/// Received reads or datagrams per second.
+	pub fn messages_per_sec(&self) -> f64 {
+		self.received_messages as f64 / self.elapsed.as_secs_f64()
+	}
+
+	/// Fraction of sent bytes that never arrived, from 0.0 to 1.0.
+	pub fn loss(&self) -> f64 {
+		if self.sent_bytes == 0 {
+			return 0.0;
+		}
+		1.0 - self.received_bytes as f64 / self.sent_bytes as f64
+	}
+}
+
+/// Result of `latency()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LatencyReport {
+	pub round_trips: u64,
+	pub elapsed: Duration,
+	pub min: Duration,
+	pub p50: Duration,
+	pub p99: Duration,
+	pub max: Duration,
+}
+
+impl LatencyReport {
+	/// Completed round trips per second.
+	pub fn round_trips_per_sec(&self) -> f64 {
+		self.round_trips as f64 / self.elapsed.as_secs_f64()
+	}
+}
+
+/// Streams `message_size` writes one way for `duration` and measures
+/// what arrives.
+///
+/// For UDP, `message_size` must fit in one datagram (or one GSO send).
+pub fn loopback_throughput(config: &SelftestConfig) -> std::io::Result<ThroughputReport> {
+	check(config)?;
+	match config.transport {
+		Transport::Tcp => {
+			let (tx, rx) = tcp_pair(config)?;
+			throughput(tx, rx, config)
+		}
+		Transport::Udp => {
+			let (tx, rx) = udp_pair(config)?;
+			throughput(tx, rx, config)
+		}
+		Transport::Unix => {
+			let (tx, rx) = unix_pair(config)?;
+			throughput(tx, rx, config)
+		}
+	}
+}
+
+/// Sends `message_size` bytes, waits for the echo, and repeats for
+/// `duration`; reports the round-trip time distribution.
+///
+/// UDP round trips that lose a datagram fail with `TimedOut` after a second.
+pub fn latency(config: &SelftestConfig) -> std::io::Result<LatencyReport> {
+	check(config)?;
+	match config.transport {
+		Transport::Tcp => {
+			let (client, server) = tcp_pair(config)?;
+			ping_pong(client, server, config)
+		}
+		Transport::Udp => {
+			let (client, server) = udp_pair(config)?;
+			ping_pong(client, server, config)
+		}
+		Transport::Unix => {
+			let (client, server) = unix_pair(config)?;
+			ping_pong(client, server, config)
+		}
+	}
+}
+
+/// One side of a test pair.
+trait Endpoint: AsRawFd + Send + 'static {
+	fn send(&self, buf: &[u8]) -> std::io::Result<usize>;
+	fn recv(&self, buf: &mut [u8]) -> std::io::Result<usize>;
+	/// Signals the peer that no more data follows: its recv() returns 0.
+	fn finish(&self) -> std::io::Result<()>;
+}
+
+impl<D: Domain + Send + 'static> Endpoint for ConnectedStream<D> {
+	fn send(&self, buf: &[u8]) -> std::io::Result<usize> {
+		self.write(buf)
+	}
+
+	fn recv(&self, buf: &mut [u8]) -> std::io::Result<usize> {
+		self.read(buf)
+	}
+
+	fn finish(&self) -> std::io::Result<()> {
+		self.shutdown(Shutdown::Write)
+	}
+}
+
+impl<D: Domain + Send + 'static> Endpoint for ConnectedDatagram<D> {
+	fn send(&self, buf: &[u8]) -> std::io::Result<usize> {
+		ConnectedDatagram::send(self, buf)
+	}
+
+	fn recv(&self, buf: &mut [u8]) -> std::io::Result<usize> {
+		ConnectedDatagram::recv(self, buf)
+	}
+
+	/// An empty datagram marks the end.
+	fn finish(&self) -> std::io::Result<()> {
+		ConnectedDatagram::send(self, &[]).map(drop)
+	}
+}
+
+fn throughput<E: Endpoint>(tx: E, rx: E, config: &SelftestConfig) -> std::io::Result<ThroughputReport> {
+	// Datagrams lost in a full receive buffer include the end marker, so
+	// the receiver also stops once the sender is done and the queue is idle.
+	let done = Arc::new(AtomicBool::new(false));
+	set_recv_timeout(&rx, Some(POLL_INTERVAL))?;
+
+	let receiver = {
+		let done = Arc::clone(&done);
+		let mut buf = vec![0u8; config.message_size.max(64 * 1024)];
+		std::thread::spawn(move || -> std::io::Result<(u64, u64, Instant)> {
+			let (mut bytes, mut messages, mut last) = (0u64, 0u64, Instant::now());
+			loop {
+				match rx.recv(&mut buf) {
+					Ok(0) => return Ok((bytes, messages, last)),
+					Ok(n) => {
+						bytes += n as u64;
+						messages += 1;
+						last = Instant::now();
+					}
+					Err(e) if e.kind() == ErrorKind::WouldBlock && done.load(Ordering::Acquire) => {
+						return Ok((bytes, messages, last));
+					}
+					Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::Interrupted) => {}
+					Err(e) => return Err(e),
+				}
+			}
+		})
+	};
+
+	let message = vec![0x5a; config.message_size];
+	let start = Instant::now();
+	let mut sent = 0u64;
+	while start.elapsed() < config.duration {
+		match tx.send(&message) {
+			Ok(n) => sent += n as u64,
+			// Loopback UDP reports a full queue instead of blocking.
+			Err(e) if e.raw_os_error() == Some(libc::ENOBUFS) || e.kind() == ErrorKind::Interrupted => {}
+			Err(e) => return Err(e),
+		}
+	}
+	tx.finish()?;
+	done.store(true, Ordering::Release);
+
+	let (received_bytes, received_messages, last) = receiver.join()
+		.map_err(|_| IoError::Read { errno: libc::EIO })??;
+	Ok(ThroughputReport {
+		sent_bytes: sent,
+		received_bytes,
+		received_messages,
+		elapsed: last.saturating_duration_since(start).max(Duration::from_nanos(1)),
+	})
+}
+
+fn ping_pong<E: Endpoint>(client: E, server: E, config: &SelftestConfig) -> std::io::Result<LatencyReport> {
+	let echo = {
+		let mut buf = vec![0u8; config.message_size];
+		std::thread::spawn(move || -> std::io::Result<()> {
+			loop {
+				let n = server.recv(&mut buf)?;
+				if n == 0 {
+					return Ok(());
+				}
+				send_all(&server, &buf[..n])?;
+			}
+		})
+	};
+
+	// A lost UDP datagram would otherwise hang the round trip forever.
+	set_recv_timeout(&client, Some(Duration::from_secs(1)))?;
+	let message = vec![0x5a; config.message_size];
+	let mut reply = vec![0u8; config.message_size];
+	let mut samples = Vec::new();
+	let start = Instant::now();
+	let mut round_trips = || -> std::io::Result<()> {
+		// At least one round trip, so the report always has samples.
+		loop {
+			let sent_at = Instant::now();
+			send_all(&client, &message)?;
+			let mut got = 0;
+			while got < reply.len() {
+				match client.recv(&mut reply[got..]) {
+					Ok(0) => return Err(IoError::ConnectionClosed.into()),
+					Ok(n) => got += n,
+					Err(e) if e.kind() == ErrorKind::WouldBlock => {
+						return Err(IoError::Read { errno: libc::ETIMEDOUT }.into());
+					}
+					Err(e) => return Err(e),
+				}
+			}
+			samples.push(sent_at.elapsed());
+			if start.elapsed() >= config.duration {
+				return Ok(());
+			}
+		}
+	};
+	let result = round_trips();
+	let elapsed = start.elapsed();
+	// The echo thread is stopped on failure too, not left blocked in recv().
+	let finished = client.finish();
+	let joined = echo.join().map_err(|_| IoError::Read { errno: libc::EIO })?;
+	result?;
+	finished?;
+	joined?;
+
+	samples.sort_unstable();
+	let percentile = |p: usize| samples[(samples.len() - 1) * p / 100];
+	Ok(LatencyReport {
+		round_trips: samples.len() as u64,
+		elapsed,
+		min: samples[0],
+		p50: percentile(50),
+		p99: percentile(99),
+		max: samples[samples.len() - 1],
+	})
+}
+
+fn send_all<E: Endpoint>(endpoint: &E, mut buf: &[u8]) -> std::io::Result<()> {
+	while !buf.is_empty() {
+		let n = endpoint.send(buf)?;
+		buf = &buf[n..];
+	}
+	Ok(())
+}
+
+fn check(config: &SelftestConfig) -> std::io::Result<()> {
+	if config.message_size == 0 {
+		return Err(SocketError::InvalidInput { reason: "selftest message_size must be > 0" }.into());
+	}
+	if config.gso_segment.is_some() && config.transport != Transport::Udp {
+		return Err(SocketError::InvalidInput { reason: "UDP_SEGMENT applies to UDP only" }.into());
+	}
+	Ok(())
+}
+
+fn tcp_pair(config: &SelftestConfig) -> std::io::Result<(ConnectedStream<Ipv4>, ConnectedStream<Ipv4>)> {
+	let tcp = TcpConfig::new().nodelay(config.nodelay);
+	let listener = ListenerBuilder::<Ipv4>::new()
+		.buffers(config.buffers)
+		.tcp(tcp)
+		.backlog(1)
+		.bind(SocketAddrV4::new(LOOPBACK, 0))?;
+	let port = listener.local_addr()?.port();
+	let client = ConnectorBuilder::<Ipv4>::new()
+		.buffers(config.buffers)
+		.tcp(tcp)
+		.connect(SocketAddrV4::new(LOOPBACK, port))?;
+	let server = listener.accept()?;
+	Ok((client, server))
+}
+
+fn udp_pair(config: &SelftestConfig) -> std::io::Result<(ConnectedDatagram<Ipv4>, ConnectedDatagram<Ipv4>)> {
+	let builder = || DatagramBuilder::<Ipv4>::new().buffers(config.buffers).bind(SocketAddrV4::new(LOOPBACK, 0));
+	let (a, b) = (builder()?, builder()?);
+	let (port_a, port_b) = (a.local_addr()?.port(), b.local_addr()?.port());
+	let a = a.connect(SocketAddrV4::new(LOOPBACK, port_b))?;
+	let b = b.connect(SocketAddrV4::new(LOOPBACK, port_a))?;
+
+	if let Some(segment) = config.gso_segment {
+		let val = segment as libc::c_int;
+		let result = unsafe {
+			libc::setsockopt(
+				a.as_raw_fd(),
+				libc::IPPROTO_UDP,
+				UDP_SEGMENT,
+				&val as *const _ as *const libc::c_void,
+				std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+			)
+		};
+		if result == -1 {
+			return Err(SocketError::SetOption { errno: errno(), option: "UDP_SEGMENT" }.into());
+		}
+	}
+	Ok((a, b))
+}
+
+fn unix_pair(config: &SelftestConfig) -> std::io::Result<(ConnectedStream<Unix>, ConnectedStream<Unix>)> {
+	let (a, b) = ConnectedStream::<Unix>::pair()?;
+	for socket in [&a, &b] {
+		if let Some(size) = config.buffers.recv {
+			set_recv_buffer_size(socket, size)?;
+		}
+		if let Some(size) = config.buffers.send {
+			set_send_buffer_size(socket, size)?;
+		}
+	}
+	Ok((a, b))
+}
+
+/*
+Why threads and blocking sockets instead of the Poller?
+The point is to measure the options under test, and a blocking
+read/write loop is the least machinery that can sit between them and
+the numbers. An event loop would add its own wakeup costs, and users
+comparing two buffer sizes want the difference to come from the
+buffers. Each run uses fresh sockets on ephemeral ports, so tests can
+run back to back without TIME_WAIT or address conflicts.
+*/
@@ -0,0 +1,90 @@
+use std::os::fd::AsFd;
+use crate::addr::{Ipv4, Ipv6, Unix, SocketAddrV4, SocketAddrV6, UnixAddr};
+use super::{Listener, Socket, BlockingMode};
+
+/// An address of any family an `Acceptor` can report.
+///
+/// New families (SCTP, vsock, ...) get new variants, so match with a
+/// wildcard arm.
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AnyAddr {
+	Ipv4(SocketAddrV4),
+	Ipv6(SocketAddrV6),
+	Unix(UnixAddr),
+}
+
+impl From<SocketAddrV4> for AnyAddr {
+	fn from(addr: SocketAddrV4) -> Self {
+		Self::Ipv4(addr)
+	}
+}
+
+impl From<SocketAddrV6> for AnyAddr {
+	fn from(addr: SocketAddrV6) -> Self {
+		Self::Ipv6(addr)
+	}
+}
+
+impl From<UnixAddr> for AnyAddr {
+	fn from(addr: UnixAddr) -> Self {
+		Self::Unix(addr)
+	}
+}
+
+/// A listener of any protocol, usable as `dyn Acceptor`.
+///
+/// Lets a server hold TCP, IPv6 and Unix listeners (and later SCTP or
+/// vsock ones) in one `Vec<Box<dyn Acceptor>>`. Accepted connections
+/// come back as a `Socket`, the same runtime-typed enum
+/// `Socket::try_from_fd()` returns; match on it for the typed stream.
+pub trait Acceptor: AsFd + Send + Sync {
+	/// Accepts a connection, with the same blocking behavior as the
+	/// listener's own `accept()`.
+	fn accept(&self) -> std::io::Result<(Socket, AnyAddr)>;
+
+	/// Returns the address the listener is bound to.
+	fn local_addr(&self) -> std::io::Result<AnyAddr>;
+}
+
+impl<M: BlockingMode> Acceptor for Listener<Ipv4, M> {
+	fn accept(&self) -> std::io::Result<(Socket, AnyAddr)> {
+		let (stream, addr) = self.accept_with_addr()?;
+		Ok((Socket::Ipv4Stream(stream), addr.into()))
+	}
+
+	fn local_addr(&self) -> std::io::Result<AnyAddr> {
+		Listener::local_addr(self).map(AnyAddr::from)
+	}
+}
+
+impl<M: BlockingMode> Acceptor for Listener<Ipv6, M> {
+	fn accept(&self) -> std::io::Result<(Socket, AnyAddr)> {
+		let (stream, addr) = self.accept_with_addr()?;
+		Ok((Socket::Ipv6Stream(stream), addr.into()))
+	}
+
+	fn local_addr(&self) -> std::io::Result<AnyAddr> {
+		Listener::local_addr(self).map(AnyAddr::from)
+	}
+}
+
+impl<M: BlockingMode> Acceptor for Listener<Unix, M> {
+	fn accept(&self) -> std::io::Result<(Socket, AnyAddr)> {
+		let (stream, addr) = self.accept_with_addr()?;
+		Ok((Socket::UnixStream(stream), addr.into()))
+	}
+
+	fn local_addr(&self) -> std::io::Result<AnyAddr> {
+		Listener::local_addr(self).map(AnyAddr::from)
+	}
+}
+
+/*
+Why return Socket instead of an associated stream type?
+An associated type would make every implementation a different trait,
+and `dyn Acceptor` would need the type spelled out — exactly what a
+heterogeneous set of listeners can't do. Socket already is wirelane's
+"typed at runtime" socket, so a server that only wants bytes matches the
+stream variants it supports and gets the ordinary ConnectedStream back.
+*/
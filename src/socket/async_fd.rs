@@ -0,0 +1,185 @@
+use std::pin::Pin;
+use std::task::{Context, Poll, ready};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::io::unix::AsyncFd;
+use crate::addr::{Domain, ToSockAddr, FromSockAddr};
+use super::{Listener, ConnectedStream, BoundDatagram, AcceptResult, Shutdown};
+
+/// A `ConnectedStream` registered with the tokio reactor.
+///
+/// Implements `AsyncRead`/`AsyncWrite`.
+/// The underlying fd is switched to non-blocking on construction.
+pub struct AsyncConnectedStream<D: Domain> {
+	inner: AsyncFd<ConnectedStream<D>>,
+}
+
+impl<D: Domain> AsyncConnectedStream<D> {
+	/// Registers the stream with the current tokio runtime.
+	///
+	/// Must be called from within a runtime context.
+	pub fn new(stream: ConnectedStream<D>) -> std::io::Result<Self> {
+		stream.set_nonblocking(true)?;
+		Ok(Self { inner: AsyncFd::new(stream)? })
+	}
+
+	/// Returns a reference to the underlying stream.
+	pub fn get_ref(&self) -> &ConnectedStream<D> {
+		self.inner.get_ref()
+	}
+
+	/// Deregisters from the reactor and returns the underlying stream.
+	///
+	/// The stream is left in non-blocking mode.
+	pub fn into_inner(self) -> ConnectedStream<D> {
+		self.inner.into_inner()
+	}
+}
+
+impl<D: Domain> AsyncRead for AsyncConnectedStream<D> {
+	fn poll_read(
+		self: Pin<&mut Self>,
+		cx: &mut Context<'_>,
+		buf: &mut ReadBuf<'_>,
+	) -> Poll<std::io::Result<()>> {
+		loop {
+			let mut guard = ready!(self.inner.poll_read_ready(cx))?;
+
+			let unfilled = buf.initialize_unfilled();
+			match guard.try_io(|inner| inner.get_ref().read(unfilled)) {
+				Ok(Ok(n)) => {
+					buf.advance(n);
+					return Poll::Ready(Ok(()));
+				}
+				Ok(Err(e)) => return Poll::Ready(Err(e)),
+				Err(_would_block) => continue,
+			}
+		}
+	}
+}
+
+impl<D: Domain> AsyncWrite for AsyncConnectedStream<D> {
+	fn poll_write(
+		self: Pin<&mut Self>,
+		cx: &mut Context<'_>,
+		buf: &[u8],
+	) -> Poll<std::io::Result<usize>> {
+		loop {
+			let mut guard = ready!(self.inner.poll_write_ready(cx))?;
+
+			match guard.try_io(|inner| inner.get_ref().write(buf)) {
+				Ok(result) => return Poll::Ready(result),
+				Err(_would_block) => continue,
+			}
+		}
+	}
+
+	fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+		Poll::Ready(Ok(()))  // No userspace buffering
+	}
+
+	fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+		Poll::Ready(self.inner.get_ref().shutdown(Shutdown::Write))
+	}
+}
+
+/// A `Listener` registered with the tokio reactor.
+pub struct AsyncListener<D: Domain> {
+	inner: AsyncFd<Listener<D>>,
+}
+
+impl<D: Domain> AsyncListener<D> {
+	/// Registers the listener with the current tokio runtime.
+	///
+	/// Must be called from within a runtime context.
+	pub fn new(listener: Listener<D>) -> std::io::Result<Self> {
+		listener.set_nonblocking(true)?;
+		Ok(Self { inner: AsyncFd::new(listener)? })
+	}
+
+	/// Returns a reference to the underlying listener.
+	pub fn get_ref(&self) -> &Listener<D> {
+		self.inner.get_ref()
+	}
+
+	/// Deregisters from the reactor and returns the underlying listener.
+	pub fn into_inner(self) -> Listener<D> {
+		self.inner.into_inner()
+	}
+}
+
+impl<D: Domain> AsyncListener<D>
+where
+	D::Addr: FromSockAddr,
+{
+	/// Waits for and accepts the next connection.
+	///
+	/// The returned stream is already registered with the reactor.
+	pub async fn accept(&self) -> std::io::Result<(AsyncConnectedStream<D>, D::Addr)> {
+		loop {
+			let mut guard = self.inner.readable().await?;
+
+			match guard.get_inner().accept_nonblocking()? {
+				AcceptResult::Connection(stream, addr) => {
+					return Ok((AsyncConnectedStream::new(stream)?, addr));
+				}
+				AcceptResult::WouldBlock => guard.clear_ready(),
+				AcceptResult::Interrupted => continue,
+			}
+		}
+	}
+}
+
+/// A `BoundDatagram` registered with the tokio reactor.
+pub struct AsyncBoundDatagram<D: Domain> {
+	inner: AsyncFd<BoundDatagram<D>>,
+}
+
+impl<D: Domain> AsyncBoundDatagram<D>
+where
+	D::Addr: ToSockAddr,
+{
+	/// Registers the socket with the current tokio runtime.
+	///
+	/// Must be called from within a runtime context.
+	pub fn new(socket: BoundDatagram<D>) -> std::io::Result<Self> {
+		socket.set_nonblocking(true)?;
+		Ok(Self { inner: AsyncFd::new(socket)? })
+	}
+
+	/// Returns a reference to the underlying socket.
+	pub fn get_ref(&self) -> &BoundDatagram<D> {
+		self.inner.get_ref()
+	}
+
+	/// Deregisters from the reactor and returns the underlying socket.
+	pub fn into_inner(self) -> BoundDatagram<D> {
+		self.inner.into_inner()
+	}
+
+	/// Waits until the datagram can be sent to `addr`.
+	pub async fn send_to(&self, buf: &[u8], addr: &D::Addr) -> std::io::Result<usize> {
+		loop {
+			let mut guard = self.inner.writable().await?;
+
+			match guard.try_io(|inner| inner.get_ref().send_to(buf, addr)) {
+				Ok(result) => return result,
+				Err(_would_block) => continue,
+			}
+		}
+	}
+
+	/// Waits for a datagram and returns its length and sender.
+	pub async fn recv_from(&self, buf: &mut [u8]) -> std::io::Result<(usize, D::Addr)>
+	where
+		D::Addr: FromSockAddr,
+	{
+		loop {
+			let mut guard = self.inner.readable().await?;
+
+			match guard.try_io(|inner| inner.get_ref().recv_from(buf)) {
+				Ok(result) => return result,
+				Err(_would_block) => continue,
+			}
+		}
+	}
+}
@@ -0,0 +1,87 @@
+use crate::addr::Domain;
+use super::{Listener, Mode};
+use super::options::get_raw_tcp_info;
+
+/// Accept queue state of a TCP listener.
+///
+/// `queued` close to `max_backlog` means the application isn't calling
+/// `accept()` fast enough; once full, new handshakes are dropped and
+/// `listen_overflows` grows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ListenerStats {
+	/// Connections completed and waiting for `accept()`.
+	pub queued: u32,
+	/// Accept queue limit (`sk_max_ack_backlog`): the `listen()` backlog,
+	/// capped by `net.core.somaxconn`.
+	pub max_backlog: u32,
+	/// Times the accept queue was full (TcpExt ListenOverflows).
+	///
+	/// Counted for the whole network namespace, not this listener.
+	/// `None` if /proc/net/netstat is unreadable.
+	pub listen_overflows: Option<u64>,
+	/// SYNs dropped at listeners for any reason (TcpExt ListenDrops),
+	/// namespace-wide like `listen_overflows`.
+	pub listen_drops: Option<u64>,
+}
+
+impl ListenerStats {
+	/// How full the accept queue is, from 0.0 to 1.0.
+	pub fn fill_ratio(&self) -> f64 {
+		if self.max_backlog == 0 {
+			return 0.0;
+		}
+		self.queued as f64 / self.max_backlog as f64
+	}
+}
+
+/// Reads `net.core.somaxconn`, the cap on every `listen()` backlog.
+///
+/// Larger backlogs are silently truncated to this value (4096 by default
+/// since Linux 5.4, 128 before). `None` if /proc is unavailable.
+pub fn somaxconn() -> Option<u32> {
+	std::fs::read_to_string("/proc/sys/net/core/somaxconn").ok()?.trim().parse().ok()
+}
+
+impl<D: Domain, M: Mode> Listener<D, M> {
+	/// Reads accept queue depth and overflow counters (TCP listeners only).
+	///
+	/// On a listening socket TCP_INFO reports the current queue length in
+	/// `tcpi_unacked` and the limit in `tcpi_sacked`. Unix listeners fail
+	/// with EOPNOTSUPP.
+	pub fn stats(&self) -> std::io::Result<ListenerStats> {
+		let info = get_raw_tcp_info(self)?;
+
+		let counters = std::fs::read_to_string("/proc/net/netstat").ok();
+		let counter = |name| counters.as_deref().and_then(|text| tcp_ext_counter(text, name));
+
+		Ok(ListenerStats {
+			queued: info.tcpi_unacked,
+			max_backlog: info.tcpi_sacked,
+			listen_overflows: counter("ListenOverflows"),
+			listen_drops: counter("ListenDrops"),
+		})
+	}
+}
+
+/// Finds `name` in the TcpExt section of /proc/net/netstat.
+///
+/// The file pairs a header line of names with a line of values.
+fn tcp_ext_counter(text: &str, name: &str) -> Option<u64> {
+	let mut lines = text.lines().filter(|line| line.starts_with("TcpExt:"));
+	let names = lines.next()?;
+	let values = lines.next()?;
+
+	let index = names.split_whitespace().position(|n| n == name)?;
+	values.split_whitespace().nth(index)?.parse().ok()
+}
+
+/*
+Why TCP_INFO and not inet_diag?
+Both report the same two numbers for a listener (inet_diag calls them
+idiag_rqueue / idiag_wqueue), but TCP_INFO is one getsockopt on a socket
+we already hold, with no netlink round trip or socket matching.
+
+Neither gives a per-listener overflow count — the kernel only keeps the
+namespace-wide TcpExt counters. Sample them before and after to see
+whether drops line up with this listener's queue filling up.
+*/
@@ -0,0 +1,133 @@
+use std::io::IoSlice;
+use crate::addr::Domain;
+use crate::error::{IoError, errno};
+use super::ConnectedStream;
+
+/// Most iovecs one sendmsg() accepts (UIO_MAXIOV on Linux).
+const MAX_IOV: usize = 1024;
+
+/// How well `send_batch()` coalesced responses into syscalls.
+///
+/// One call returns the numbers for that batch; add them up with `+=`
+/// to track a connection:
+/// ```ignore
+/// let mut total = BatchStats::default();
+/// total += stream.send_batch(&responses, false)?;
+/// println!("{:.1} responses per syscall", total.responses_per_syscall());
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BatchStats {
+	/// `send_batch()` calls.
+	pub batches: u64,
+	/// Non-empty buffers handed in.
+	pub responses: u64,
+	/// Bytes written.
+	pub bytes: u64,
+	/// sendmsg() calls that wrote data.
+	pub syscalls: u64,
+}
+
+impl BatchStats {
+	/// Responses per syscall — 1.0 means no better than one write each.
+	pub fn responses_per_syscall(&self) -> f64 {
+		if self.syscalls == 0 {
+			return 0.0;
+		}
+		self.responses as f64 / self.syscalls as f64
+	}
+
+	/// Average bytes per batch.
+	pub fn bytes_per_batch(&self) -> f64 {
+		if self.batches == 0 {
+			return 0.0;
+		}
+		self.bytes as f64 / self.batches as f64
+	}
+}
+
+impl std::ops::AddAssign for BatchStats {
+	fn add_assign(&mut self, other: Self) {
+		self.batches += other.batches;
+		self.responses += other.responses;
+		self.bytes += other.bytes;
+		self.syscalls += other.syscalls;
+	}
+}
+
+impl<D: Domain> ConnectedStream<D> {
+	/// Writes many small buffers with as few syscalls as possible.
+	///
+	/// Normally one sendmsg() for the whole batch; more only past 1024
+	/// buffers or after a short write. Every call but the last carries
+	/// MSG_MORE so TCP doesn't push a partial segment; pass `more = true`
+	/// to set it on the last one too, when another batch follows right
+	/// away (the kernel sends anyway after ~200ms, or on the next write
+	/// without it). SIGPIPE is raised only if `set_sigpipe()` asked for it.
+	///
+	/// On a non-blocking stream a `WouldBlock` after some bytes went out
+	/// returns `Ok` with `bytes` short of the total — resume from there.
+	pub fn send_batch(&self, bufs: &[IoSlice<'_>], more: bool) -> std::io::Result<BatchStats> {
+		let mut iov: Vec<libc::iovec> = bufs.iter()
+			.filter(|buf| !buf.is_empty())
+			.map(|buf| libc::iovec { iov_base: buf.as_ptr() as *mut libc::c_void, iov_len: buf.len() })
+			.collect();
+
+		let mut stats = BatchStats { batches: 1, responses: iov.len() as u64, ..Default::default() };
+		let mut start = 0;
+
+		while start < iov.len() {
+			let end = iov.len().min(start + MAX_IOV);
+			let last = end == iov.len();
+
+			let mut msg: libc::msghdr = unsafe { std::mem::zeroed() };
+			msg.msg_iov = iov[start..end].as_mut_ptr();
+			msg.msg_iovlen = end - start;
+
+			let mut flags = self.nosignal();
+			if !last || more {
+				flags |= libc::MSG_MORE;
+			}
+
+			let n = unsafe { libc::sendmsg(self.as_raw_fd(), &msg, flags) };
+			if n == -1 {
+				let e = errno();
+				match e {
+					libc::EINTR => continue,
+					libc::EAGAIN if stats.bytes > 0 => return Ok(stats),
+					_ => return Err(IoError::Write { errno: e }.into()),
+				}
+			}
+			stats.syscalls += 1;
+			stats.bytes += n as u64;
+
+			// Skip what was written, trimming a partially written iovec.
+			let mut n = n as usize;
+			while n > 0 && n >= iov[start].iov_len {
+				n -= iov[start].iov_len;
+				start += 1;
+			}
+			if n > 0 {
+				let partial = &mut iov[start];
+				partial.iov_base = unsafe { (partial.iov_base as *mut u8).add(n) } as *mut libc::c_void;
+				partial.iov_len -= n;
+			}
+		}
+
+		Ok(stats)
+	}
+}
+
+/*
+Why sendmsg() and not writev()?
+Same iovec, same single syscall — but writev() has no flags argument,
+so no MSG_MORE and no MSG_NOSIGNAL.
+
+Why not sendmmsg()?
+sendmmsg() sends one message per entry; on a stream socket that's just
+several writes in one syscall, each of which may push its own segment.
+One message with many iovecs lets TCP pack the bytes into full segments.
+
+Why not buffer internally?
+Responses usually already live in their own buffers; an IoSlice list
+avoids copying them into one. Use BufferedStream when they don't.
+*/
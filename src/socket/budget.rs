@@ -0,0 +1,80 @@
+use crate::addr::Domain;
+use crate::ioctls::queue_len;
+use super::ConnectedStream;
+use super::options::get_raw_tcp_info;
+
+/// How much a TCP stream can take right now without piling up in its
+/// send buffer.
+///
+/// Returned by `ConnectedStream::send_budget()`. A scheduler serving many
+/// streams writes at most `bytes` to each per round, so one fast
+/// producer can't fill its socket with data that then sits behind a
+/// small congestion window while other streams starve.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SendBudget {
+	/// Bytes to write now: the congestion window (or one RTT at the
+	/// pacing rate, if smaller) minus what's already queued.
+	pub bytes: usize,
+	/// Congestion window in bytes (`snd_cwnd * snd_mss`).
+	pub cwnd_bytes: usize,
+	/// Bytes sent and not yet acknowledged, as TCP estimates them.
+	pub in_flight_bytes: usize,
+	/// Bytes in the send queue, sent or not (SIOCOUTQ).
+	pub queued_bytes: usize,
+	/// Pacing rate in bytes per second, `None` when unpaced.
+	pub pacing_rate: Option<u64>,
+}
+
+impl<D: Domain> ConnectedStream<D> {
+	/// Estimates how many bytes can be written without queuing excessively.
+	///
+	/// Reads TCP_INFO (cwnd, MSS, packets in flight, pacing rate) and
+	/// SIOCOUTQ. A snapshot: ACKs arriving right after change it. TCP
+	/// streams only; Unix streams fail with EOPNOTSUPP.
+	pub fn send_budget(&self) -> std::io::Result<SendBudget> {
+		let info = get_raw_tcp_info(self)?;
+
+		// SIOCOUTQ shares its number with TIOCOUTQ (linux/sockios.h).
+		let queued_bytes = queue_len(self, libc::TIOCOUTQ, "SIOCOUTQ")?;
+
+		let mss = info.tcpi_snd_mss as usize;
+		let cwnd_bytes = info.tcpi_snd_cwnd as usize * mss;
+		// tcp_packets_in_flight(): sent, minus SACKed or presumed lost, plus retransmitted.
+		let in_flight = (info.tcpi_unacked + info.tcpi_retrans)
+			.saturating_sub(info.tcpi_sacked + info.tcpi_lost);
+		// ~0 means no pacing limit (SO_MAX_PACING_RATE unset, no fq/BBR pacing).
+		let pacing_rate = match info.tcpi_pacing_rate {
+			0 | u64::MAX => None,
+			rate => Some(rate),
+		};
+
+		let mut window = cwnd_bytes;
+		if let Some(rate) = pacing_rate && info.tcpi_rtt > 0 {
+			let per_rtt = rate.saturating_mul(info.tcpi_rtt as u64) / 1_000_000;
+			window = window.min(per_rtt.max(mss as u64) as usize);
+		}
+
+		Ok(SendBudget {
+			bytes: window.saturating_sub(queued_bytes),
+			cwnd_bytes,
+			in_flight_bytes: in_flight as usize * mss,
+			queued_bytes,
+			pacing_rate,
+		})
+	}
+}
+
+/*
+Why subtract SIOCOUTQ rather than the in-flight estimate?
+SIOCOUTQ counts everything the socket holds — in flight and not yet
+sent. Data written past the window just waits in the socket buffer,
+adding latency without going out any sooner; keeping queued bytes under
+one window keeps the backlog in the application, where a scheduler can
+still reorder it. The in-flight figure is reported for callers who want
+to tell "window full of unacked data" apart from "data stuck unsent".
+
+Why cap by one RTT at the pacing rate?
+With fq or BBR pacing, the kernel releases packets at pacing_rate even
+when the window is larger; anything beyond one RTT's worth sits in the
+qdisc instead of the socket, which is the same queuing one level down.
+*/
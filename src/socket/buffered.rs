@@ -0,0 +1,237 @@
+use std::os::fd::AsRawFd;
+use crate::addr::Domain;
+use crate::error::IoError;
+use super::stream::ConnectedStream;
+use super::options::splice;
+
+const DEFAULT_CAPACITY: usize = 8 * 1024;
+
+/// A `ConnectedStream` with an internal read buffer and a coalescing write buffer.
+///
+/// Parsers can peek with `fill_buf()` and `consume()` without one syscall
+/// per tiny read. Small writes are collected and sent in one `write()` on
+/// `flush()` or when the buffer fills up.
+///
+/// Buffered writes are flushed on drop, ignoring errors — call `flush()`
+/// to observe them.
+///
+/// # Example
+/// ```ignore
+/// let mut conn = BufferedStream::new(listener.accept()?.0);
+///
+/// let line_end = loop {
+///     let buf = conn.fill_buf()?;
+///     if let Some(i) = buf.iter().position(|&b| b == b'\n') { break i + 1; }
+/// };
+/// conn.consume(line_end);
+///
+/// conn.write(b"HTTP/1.1 200 OK\r\n")?;
+/// conn.write(b"Content-Length: 0\r\n\r\n")?;
+/// conn.flush()?;  // One syscall for both
+/// ```
+pub struct BufferedStream<D: Domain> {
+	inner: ConnectedStream<D>,
+	read_buf: Box<[u8]>,
+	read_pos: usize,       // Start of unconsumed data
+	read_end: usize,       // End of valid data
+	write_buf: Vec<u8>,
+	write_capacity: usize,
+}
+
+impl<D: Domain> BufferedStream<D> {
+	/// Wraps a stream with 8 KiB read and write buffers.
+	pub fn new(inner: ConnectedStream<D>) -> Self {
+		Self::with_capacity(DEFAULT_CAPACITY, DEFAULT_CAPACITY, inner)
+	}
+
+	/// Wraps a stream with the given buffer sizes.
+	pub fn with_capacity(read_capacity: usize, write_capacity: usize, inner: ConnectedStream<D>) -> Self {
+		Self {
+			inner,
+			read_buf: vec![0; read_capacity].into_boxed_slice(),
+			read_pos: 0,
+			read_end: 0,
+			write_buf: Vec::with_capacity(write_capacity),
+			write_capacity,
+		}
+	}
+
+	/// Returns a reference to the underlying stream.
+	///
+	/// Reading or writing through it directly bypasses the buffers.
+	pub fn get_ref(&self) -> &ConnectedStream<D> {
+		&self.inner
+	}
+
+	/// Returns the unconsumed bytes in the read buffer, without reading more.
+	pub fn read_buffer(&self) -> &[u8] {
+		&self.read_buf[self.read_pos..self.read_end]
+	}
+
+	/// Returns the bytes waiting in the write buffer.
+	pub fn write_buffer(&self) -> &[u8] {
+		&self.write_buf
+	}
+
+	/// Flushes and returns the underlying stream.
+	///
+	/// Unconsumed read data is discarded — check `read_buffer()` first if
+	/// it matters. On flush failure the error is returned and the stream
+	/// is dropped.
+	pub fn into_inner(mut self) -> std::io::Result<ConnectedStream<D>> {
+		self.flush()?;
+		// Buffers are empty/irrelevant now; skip the Drop flush.
+		let mut this = std::mem::ManuallyDrop::new(self);
+		unsafe {
+			std::ptr::drop_in_place(&mut this.read_buf);
+			std::ptr::drop_in_place(&mut this.write_buf);
+			Ok(std::ptr::read(&this.inner))
+		}
+	}
+
+	/// Returns buffered data, reading from the socket if the buffer is empty.
+	///
+	/// An empty slice means end-of-stream. Call `consume()` with the number
+	/// of bytes used.
+	pub fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+		if self.read_pos == self.read_end {
+			let n = self.inner.read(&mut self.read_buf)?;
+			self.read_pos = 0;
+			self.read_end = n;
+		}
+		Ok(&self.read_buf[self.read_pos..self.read_end])
+	}
+
+	/// Marks `amt` bytes from `fill_buf()` as used.
+	///
+	/// Clamped to the amount of buffered data.
+	pub fn consume(&mut self, amt: usize) {
+		self.read_pos = (self.read_pos + amt).min(self.read_end);
+	}
+
+	/// Reads into `buf`, serving buffered data first.
+	///
+	/// Reads at least as large as the buffer skip it entirely when it's empty.
+	pub fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+		if self.read_pos == self.read_end && buf.len() >= self.read_buf.len() {
+			return self.inner.read(buf);
+		}
+
+		let available = self.fill_buf()?;
+		let n = available.len().min(buf.len());
+		buf[..n].copy_from_slice(&available[..n]);
+		self.consume(n);
+		Ok(n)
+	}
+
+	/// Queues `buf` for sending.
+	///
+	/// Flushes first if it doesn't fit. Writes at least as large as the
+	/// buffer go straight to the socket after the flush.
+	pub fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+		if self.write_buf.len() + buf.len() > self.write_capacity {
+			self.flush()?;
+		}
+
+		if buf.len() >= self.write_capacity {
+			self.inner.write(buf)
+		} else {
+			self.write_buf.extend_from_slice(buf);
+			Ok(buf.len())
+		}
+	}
+
+	/// Sends everything in the write buffer.
+	///
+	/// On a non-blocking socket this can fail with `WouldBlock` part-way;
+	/// what was sent is removed and the rest stays buffered for the next call.
+	pub fn flush(&mut self) -> std::io::Result<()> {
+		let mut written = 0;
+		let result = loop {
+			if written == self.write_buf.len() {
+				break Ok(());
+			}
+			match self.inner.write(&self.write_buf[written..]) {
+				Ok(0) => break Err(IoError::ConnectionClosed.into()),
+				Ok(n) => written += n,
+				Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+				Err(e) => break Err(e),
+			}
+		};
+		self.write_buf.drain(..written);
+		result
+	}
+
+	/// Flushes, then sends `count` bytes of `file` with `sendfile()`.
+	///
+	/// See `ConnectedStream::sendfile()`.
+	pub fn sendfile<F: AsRawFd>(
+		&mut self,
+		file: &F,
+		offset: Option<&mut i64>,
+		count: usize,
+	) -> std::io::Result<usize> {
+		self.flush()?;
+		self.inner.sendfile(file, offset, count)
+	}
+
+	/// Flushes, then splices up to `len` bytes from the pipe `fd_in` into the socket.
+	///
+	/// See `splice()`.
+	pub fn splice_from<In: AsRawFd>(&mut self, fd_in: &In, len: usize, flags: u32) -> std::io::Result<usize> {
+		self.flush()?;
+		splice(fd_in, None, &self.inner, None, len, flags)
+	}
+}
+
+impl<D: Domain> Drop for BufferedStream<D> {
+	fn drop(&mut self) {
+		let _ = self.flush();
+	}
+}
+
+impl<D: Domain> std::io::Read for BufferedStream<D> {
+	fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+		BufferedStream::read(self, buf)
+	}
+}
+
+impl<D: Domain> std::io::BufRead for BufferedStream<D> {
+	fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+		BufferedStream::fill_buf(self)
+	}
+
+	fn consume(&mut self, amt: usize) {
+		BufferedStream::consume(self, amt)
+	}
+}
+
+impl<D: Domain> std::io::Write for BufferedStream<D> {
+	fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+		BufferedStream::write(self, buf)
+	}
+
+	fn flush(&mut self) -> std::io::Result<()> {
+		BufferedStream::flush(self)
+	}
+}
+
+impl<D: Domain> std::os::fd::AsRawFd for BufferedStream<D> {
+	fn as_raw_fd(&self) -> std::os::fd::RawFd {
+		self.inner.as_raw_fd()
+	}
+}
+
+impl<D: Domain> std::os::fd::AsFd for BufferedStream<D> {
+	fn as_fd(&self) -> std::os::fd::BorrowedFd<'_> {
+		std::os::fd::AsFd::as_fd(&self.inner)
+	}
+}
+
+/*
+Why not std::io::BufReader/BufWriter?
+They can't be stacked on one socket: BufReader<BufWriter<S>> hides the
+writer's flush behind the reader, and the two can't share the fd for
+sendfile/splice. Keeping both buffers here means zero-copy calls can
+flush pending bytes first so the wire order stays correct.
+*/
@@ -1,13 +1,25 @@
 use std::fmt::Debug;
+use std::time::Duration;
 use std::marker::PhantomData;
-use crate::addr::{Domain, ToSockAddr};
+use crate::addr::{Domain, Ipv4, Ipv6, Unix, SocketAddrV4, SocketAddrV6, ToSockAddr, FromSockAddr, PortAddr, Port};
+use crate::error::SocketError;
+use super::poll_fd;
+use super::unix_path::{SocketPathGuard, unix_path, remove_stale};
+use super::knock::{KnockListener, attach_prefix_filter};
+use super::shard::reuseport_shard;
+use super::resolve::{resolve_family, resolve_passive, first_ok};
+use super::manifest::{SocketManifest, ManifestKind};
 use super::{
-	RawSocket, Listener, ConnectedStream, BoundDatagram,
+	RawSocket, Listener, MultiListener, ConnectedStream, BoundDatagram, PendingConnect,
 	Stream, Datagram,
 	set_reuse_addr, set_reuse_port, set_tcp_nodelay,
 	set_recv_buffer_size, set_send_buffer_size,
 	set_keepalive, set_keepalive_idle, set_keepalive_interval, set_keepalive_count,
-	set_linger,
+	set_linger, set_recv_timeout, set_send_timeout, set_only_v6, set_tcp_syn_count,
+	set_tos, set_tclass, set_recv_tos, set_recv_tclass,
+	set_ip_transparent, set_ip_transparent_v6, set_bind_address_no_port,
+	set_broadcast, set_multicast_ttl, set_multicast_hops_v6,
+	join_multicast_v4, join_multicast_v6, somaxconn, CancelHandle, add_tcp_md5_key,
 };
 
 // ============================================================================
@@ -15,7 +27,8 @@ use super::{
 // ============================================================================
 
 /// Buffer size configuration.
-#[derive(Debug, Clone, Copy, Default)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BufferConfig {
 	pub recv: Option<usize>,
 	pub send: Option<usize>,
@@ -53,8 +66,142 @@ impl BufferConfig {
 	}
 }
 
+/// Blocking I/O timeout configuration (SO_RCVTIMEO / SO_SNDTIMEO).
+///
+/// Timed-out calls fail with `WouldBlock`. On a listener the read timeout
+/// also bounds `accept()`, and accepted connections inherit both values.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TimeoutConfig {
+	pub read: Option<Duration>,
+	pub write: Option<Duration>,
+}
+
+impl TimeoutConfig {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn read(mut self, timeout: Duration) -> Self {
+		self.read = Some(timeout);
+		self
+	}
+
+	pub fn write(mut self, timeout: Duration) -> Self {
+		self.write = Some(timeout);
+		self
+	}
+
+	pub fn both(mut self, timeout: Duration) -> Self {
+		self.read = Some(timeout);
+		self.write = Some(timeout);
+		self
+	}
+
+	fn apply<S: std::os::fd::AsRawFd>(&self, socket: &S) -> std::io::Result<()> {
+		if let Some(timeout) = self.read {
+			set_recv_timeout(socket, Some(timeout))?;
+		}
+		if let Some(timeout) = self.write {
+			set_send_timeout(socket, Some(timeout))?;
+		}
+		Ok(())
+	}
+}
+
+/// Traffic marking configuration (IP_TOS / IPV6_TCLASS).
+///
+/// DSCP picks the forwarding class routers apply (e.g. `QosConfig::EF`
+/// for voice, AF classes for interactive data); ECN marks the packet as
+/// ECN-capable. Ignored for Unix sockets.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct QosConfig {
+	pub dscp: Option<u8>,
+	pub ecn: Option<u8>,
+	pub recv_tos: bool,
+}
+
+impl QosConfig {
+	/// Expedited Forwarding: low loss, low latency (RFC 3246).
+	pub const EF: u8 = 46;
+	/// Assured Forwarding class 4, low drop precedence (RFC 2597).
+	pub const AF41: u8 = 34;
+	/// Assured Forwarding class 3, low drop precedence.
+	pub const AF31: u8 = 26;
+	/// Assured Forwarding class 2, low drop precedence.
+	pub const AF21: u8 = 18;
+	/// Assured Forwarding class 1, low drop precedence.
+	pub const AF11: u8 = 10;
+	/// Class Selector 1: lower-effort / bulk traffic (RFC 8622).
+	pub const CS1: u8 = 8;
+
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Set the DSCP code point (0-63), e.g. `QosConfig::EF`. Larger values
+	/// fail the builder with `InvalidInput`.
+	pub fn dscp(mut self, dscp: u8) -> Self {
+		self.dscp = Some(dscp);
+		self
+	}
+
+	/// Set the ECN field (0-3). 1 or 2 marks packets ECN-capable. Larger
+	/// values fail the builder with `InvalidInput`.
+	pub fn ecn(mut self, ecn: u8) -> Self {
+		self.ecn = Some(ecn);
+		self
+	}
+
+	/// Deliver the TOS of received datagrams (IP_RECVTOS / IPV6_RECVTCLASS),
+	/// read with `recv_from_with_tos()`.
+	pub fn recv_tos(mut self, enable: bool) -> Self {
+		self.recv_tos = enable;
+		self
+	}
+
+	/// The TOS / traffic class byte: DSCP in the upper 6 bits, ECN in the lower 2.
+	pub fn tos(&self) -> Option<u8> {
+		if self.dscp.is_none() && self.ecn.is_none() {
+			return None;
+		}
+		Some((self.dscp.unwrap_or(0) << 2) | (self.ecn.unwrap_or(0) & 0b11))
+	}
+
+	fn apply<S: std::os::fd::AsRawFd>(&self, socket: &S, family: libc::c_int) -> std::io::Result<()> {
+		if let Some(dscp) = self.dscp && dscp > 63 {
+			return Err(SocketError::InvalidInput { reason: "DSCP must be 0-63" }.into());
+		}
+		if let Some(ecn) = self.ecn && ecn > 3 {
+			return Err(SocketError::InvalidInput { reason: "ECN must be 0-3" }.into());
+		}
+		match family {
+			libc::AF_INET => {
+				if let Some(tos) = self.tos() {
+					set_tos(socket, tos)?;
+				}
+				if self.recv_tos {
+					set_recv_tos(socket, true)?;
+				}
+			}
+			libc::AF_INET6 => {
+				if let Some(tos) = self.tos() {
+					set_tclass(socket, tos)?;
+				}
+				if self.recv_tos {
+					set_recv_tclass(socket, true)?;
+				}
+			}
+			_ => {}
+		}
+		Ok(())
+	}
+}
+
 /// Address reuse configuration.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ReuseConfig {
 	pub addr: bool,
 	pub port: bool,
@@ -103,7 +250,8 @@ impl ReuseConfig {
 }
 
 /// TCP-specific configuration.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TcpConfig {
 	pub nodelay: bool,
 	pub keepalive: Option<KeepaliveConfig>,
@@ -160,7 +308,8 @@ impl TcpConfig {
 }
 
 /// Keep-alive timing configuration.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct KeepaliveConfig {
 	pub idle_secs: u32,
 	pub interval_secs: u32,
@@ -198,6 +347,117 @@ impl KeepaliveConfig {
 	}
 }
 
+/// How `ConnectorBuilder::connect()` retries while the server isn't up.
+///
+/// Only failures that mean "not listening yet" are retried: refused
+/// (ECONNREFUSED), timed out (ETIMEDOUT), and for Unix sockets a missing
+/// path (ENOENT) or a full backlog (EAGAIN). Anything else — bad
+/// address, unreachable network, permissions — fails at once.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RetryPolicy {
+	/// Connect attempts in total, the first one included; 1 never retries.
+	pub attempts: u32,
+	/// Wait before the first retry; doubles after each one.
+	pub base_delay: Duration,
+	/// Cap on the wait between attempts.
+	pub max_delay: Duration,
+	/// Wait a random 50-100% of each delay, so clients restarted
+	/// together don't retry in lockstep.
+	pub jitter: bool,
+}
+
+impl Default for RetryPolicy {
+	fn default() -> Self {
+		Self {
+			attempts: 5,
+			base_delay: Duration::from_millis(100),
+			max_delay: Duration::from_secs(5),
+			jitter: true,
+		}
+	}
+}
+
+impl RetryPolicy {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn attempts(mut self, attempts: u32) -> Self {
+		self.attempts = attempts;
+		self
+	}
+
+	pub fn base_delay(mut self, delay: Duration) -> Self {
+		self.base_delay = delay;
+		self
+	}
+
+	pub fn max_delay(mut self, delay: Duration) -> Self {
+		self.max_delay = delay;
+		self
+	}
+
+	pub fn jitter(mut self, enable: bool) -> Self {
+		self.jitter = enable;
+		self
+	}
+
+	/// The wait before retry number `retry` (0 for the first).
+	pub fn delay(&self, retry: u32) -> Duration {
+		let delay = self.base_delay.saturating_mul(1u32.checked_shl(retry).unwrap_or(u32::MAX)).min(self.max_delay);
+		if !self.jitter {
+			return delay;
+		}
+		// Half fixed, half random.
+		let fraction = random_u64() as f64 / u64::MAX as f64;
+		delay / 2 + delay.mul_f64(fraction / 2.0)
+	}
+
+	/// Runs `op` until it succeeds, fails for good, or runs out of attempts.
+	fn run<T>(&self, mut op: impl FnMut() -> std::io::Result<T>) -> std::io::Result<T> {
+		let mut retry = 0;
+		loop {
+			match op() {
+				Err(e) if retry + 1 < self.attempts && is_not_listening(&e) => {
+					std::thread::sleep(self.delay(retry));
+					retry += 1;
+				}
+				result => return result,
+			}
+		}
+	}
+}
+
+/// Whether a connect error means the server isn't accepting yet.
+fn is_not_listening(err: &std::io::Error) -> bool {
+	let errno = match err.get_ref().and_then(|inner| inner.downcast_ref::<SocketError>()) {
+		Some(SocketError::Connect { errno, .. }) => *errno,
+		_ => return false,
+	};
+	matches!(errno, libc::ECONNREFUSED | libc::ETIMEDOUT | libc::ENOENT | libc::EAGAIN)
+}
+
+fn random_u64() -> u64 {
+	let mut bytes = [0u8; 8];
+	crate::fill_random(&mut bytes);
+	u64::from_ne_bytes(bytes)
+}
+
+/// Extra `socket()` type flags for the requested blocking mode.
+fn sock_flags(nonblocking: bool) -> libc::c_int {
+	if nonblocking { libc::SOCK_NONBLOCK } else { 0 }
+}
+
+/// IP_TRANSPARENT or its IPv6 counterpart, picked by family.
+fn set_transparent<S: std::os::fd::AsRawFd>(socket: &S, family: libc::c_int) -> std::io::Result<()> {
+	match family {
+		libc::AF_INET => set_ip_transparent(socket, true),
+		libc::AF_INET6 => set_ip_transparent_v6(socket, true),
+		_ => Err(SocketError::InvalidInput { reason: "IP_TRANSPARENT applies to IP sockets only" }.into()),
+	}
+}
+
 // ============================================================================
 // Listener Builder
 // ============================================================================
@@ -220,8 +480,16 @@ pub struct ListenerBuilder<D: Domain> {
 	reuse: ReuseConfig,
 	tcp: TcpConfig,
 	buffers: BufferConfig,
+	timeouts: TimeoutConfig,
+	qos: QosConfig,
 	backlog: i32,
+	backlog_auto: bool,
 	nonblocking: bool,
+	v6_only: Option<bool>,
+	transparent: bool,
+	unlink_on_drop: bool,
+	unlink_existing: bool,
+	md5_keys: Vec<(D::Addr, Vec<u8>)>,
 	_marker: PhantomData<D>,
 }
 
@@ -237,12 +505,43 @@ impl<D: Domain> ListenerBuilder<D> {
 			reuse: ReuseConfig::default(),
 			tcp: TcpConfig::default(),
 			buffers: BufferConfig::default(),
+			timeouts: TimeoutConfig::default(),
+			qos: QosConfig::default(),
 			backlog: 128,
+			backlog_auto: false,
 			nonblocking: false,
+			v6_only: None,
+			transparent: false,
+			unlink_on_drop: false,
+			unlink_existing: false,
+			md5_keys: Vec::new(),
 			_marker: PhantomData,
 		}
 	}
 
+	/// Starts from the options recorded in a manifest, e.g. to recreate
+	/// a listener captured with `SocketManifest::capture()`.
+	///
+	/// The address isn't applied; pass it to `bind()`. Fails with
+	/// `WrongSocketKind` unless the manifest is a listener of family `D`.
+	pub fn from_manifest(manifest: &SocketManifest) -> std::io::Result<Self> {
+		manifest.expect::<D>(ManifestKind::Listener, "a listener manifest of this family")?;
+		let mut builder = Self::new()
+			.reuse(manifest.reuse)
+			.buffers(manifest.buffers)
+			.timeouts(manifest.timeouts)
+			.qos(manifest.qos)
+			.nonblocking(manifest.nonblocking);
+		if let Some(tcp) = manifest.tcp {
+			builder.tcp = tcp;
+		}
+		if let Some(backlog) = manifest.backlog {
+			builder.backlog = backlog.min(i32::MAX as u32) as i32;
+		}
+		builder.v6_only = manifest.v6_only;
+		Ok(builder)
+	}
+
 	/// Set address reuse options.
 	pub fn reuse(mut self, config: ReuseConfig) -> Self {
 		self.reuse = config;
@@ -261,9 +560,34 @@ impl<D: Domain> ListenerBuilder<D> {
 		self
 	}
 
+	/// Set blocking read/write timeouts.
+	pub fn timeouts(mut self, config: TimeoutConfig) -> Self {
+		self.timeouts = config;
+		self
+	}
+
+	/// Set DSCP/ECN marking (ignored for Unix sockets).
+	pub fn qos(mut self, config: QosConfig) -> Self {
+		self.qos = config;
+		self
+	}
+
 	/// Set listen backlog. Default: 128.
+	///
+	/// Values above `net.core.somaxconn` are capped by the kernel; see
+	/// `Listener::backlog()` for what took effect.
 	pub fn backlog(mut self, backlog: i32) -> Self {
 		self.backlog = backlog;
+		self.backlog_auto = false;
+		self
+	}
+
+	/// Use the largest backlog the system allows (`net.core.somaxconn`).
+	///
+	/// Read at bind time, so a tuned sysctl is picked up without code
+	/// changes. Falls back to the `backlog()` value if /proc is unreadable.
+	pub fn backlog_auto(mut self) -> Self {
+		self.backlog_auto = true;
 		self
 	}
 
@@ -273,6 +597,24 @@ impl<D: Domain> ListenerBuilder<D> {
 		self
 	}
 
+	/// Allow binding to non-local addresses and receiving TPROXY-redirected
+	/// traffic (IP_TRANSPARENT / IPV6_TRANSPARENT). Needs CAP_NET_ADMIN.
+	///
+	/// IP sockets only; Unix builders fail at bind time.
+	pub fn transparent(mut self, enable: bool) -> Self {
+		self.transparent = enable;
+		self
+	}
+
+	/// Require TCP MD5 signatures (RFC 2385) from `peer`, keyed with `key`.
+	///
+	/// Call once per neighbor; see `add_tcp_md5_key()`. Keys for peers
+	/// known later go on the listener with `Listener::add_md5_key()`.
+	pub fn add_md5_key(mut self, peer: D::Addr, key: &[u8]) -> Self {
+		self.md5_keys.push((peer, key.to_vec()));
+		self
+	}
+
 	// Legacy methods for backwards compatibility
 	pub fn reuse_addr(mut self, enable: bool) -> Self {
 		self.reuse.addr = enable;
@@ -294,19 +636,155 @@ impl<D: Domain> ListenerBuilder<D> {
 	where
 		D::Addr: ToSockAddr, <D as Domain>::Addr: Debug
 	{
-		let socket = RawSocket::<D, Stream>::new()?;
+		self.bind_ref(addr)
+	}
+
+	/// Resolves `host` and binds and listens on the first address that
+	/// works. An empty host (`":8080"`) means every interface.
+	pub fn bind_host(self, host: &str) -> std::io::Result<Listener<D>>
+	where
+		D::Addr: ToSockAddr + FromSockAddr + PartialEq + Debug,
+	{
+		first_ok(host, resolve_passive::<D>(host)?, |addr| self.bind_ref(addr))
+	}
+
+	/// Binds to the first free port in `ports`, returning it with the listener.
+	///
+	/// Tries `addr` with each port in turn, moving on only on EADDRINUSE;
+	/// any other failure is returned as-is. If every port is taken, fails
+	/// with `SocketError::NoFreePort` (kind `AddrInUse`).
+	/// For test harnesses and sidecars that need "some nearby free port":
+	/// `bind_with_port_fallback(addr, Port::range(8000, 8099))`.
+	pub fn bind_with_port_fallback<R>(self, addr: D::Addr, ports: R) -> std::io::Result<(Listener<D>, Port)>
+	where
+		D::Addr: ToSockAddr + PortAddr + Clone + Debug,
+		R: IntoIterator<Item = Port>,
+	{
+		port_fallback(addr, ports, |addr| self.bind_ref(addr))
+	}
+
+	/// Binds a listener with these options to each of `addrs`.
+	///
+	/// For servers on several interfaces or ports. Fails on the first
+	/// address that can't be bound, closing the ones already bound.
+	pub fn bind_all(self, addrs: &[D::Addr]) -> std::io::Result<MultiListener<D>>
+	where
+		D::Addr: ToSockAddr + Clone + Debug,
+	{
+		let listeners = addrs.iter()
+			.map(|addr| self.bind_ref(addr.clone()))
+			.collect::<std::io::Result<Vec<_>>>()?;
+		MultiListener::from_listeners(listeners)
+	}
+
+	/// Binds `workers` listeners to `addr` as one SO_REUSEPORT group and
+	/// shards connections between them by flow hash.
+	///
+	/// Listener `i` is worker `i` (see `reuseport_shard()`); hand one to
+	/// each worker thread. SO_REUSEPORT is enabled regardless of
+	/// `reuse_port()`. With port 0, all listeners share the port the
+	/// first one got.
+	pub fn bind_sharded(mut self, addr: D::Addr, workers: u32) -> std::io::Result<Vec<Listener<D>>>
+	where
+		D::Addr: ToSockAddr + FromSockAddr + Clone + Debug,
+	{
+		let shard = reuseport_shard(workers)?;
+		self.reuse.port = true;
+
+		let first = self.bind_ref(addr)?;
+		let addr = first.local_addr()?;
+		let mut listeners = vec![first];
+		for _ in 1..workers {
+			listeners.push(self.bind_ref(addr.clone())?);
+		}
+		shard.attach(&listeners)?;
+		Ok(listeners)
+	}
+
+	fn bind_ref(&self, addr: D::Addr) -> std::io::Result<Listener<D>>
+	where
+		D::Addr: ToSockAddr, <D as Domain>::Addr: Debug
+	{
+		let socket = RawSocket::<D, Stream>::with_flags(sock_flags(self.nonblocking))?;
 		let is_unix = D::raw() == libc::AF_UNIX;
 
 		self.reuse.apply(&socket)?;
 		self.tcp.apply(&socket, is_unix)?;
 		self.buffers.apply(&socket)?;
+		self.timeouts.apply(&socket)?;
+		self.qos.apply(&socket, D::raw())?;
+		if let Some(enable) = self.v6_only {
+			set_only_v6(&socket, enable)?;
+		}
+		if self.transparent {
+			set_transparent(&socket, D::raw())?;
+		}
+		for (peer, key) in &self.md5_keys {
+			add_tcp_md5_key(&socket, peer, key)?;
+		}
 
-		if self.nonblocking {
-			socket.set_nonblocking(true)?;
+		// Only named Unix addresses have a file to manage.
+		let path = if self.unlink_existing || self.unlink_on_drop {
+			unix_path(&addr)
+		} else {
+			None
+		};
+		if self.unlink_existing && let Some(path) = &path {
+			remove_stale(path)?;
 		}
 
 		let bound = socket.bind(addr)?;
-		bound.listen(self.backlog)
+		// Guard right after bind: if listen() fails, the file still goes.
+		let guard = path.filter(|_| self.unlink_on_drop).map(SocketPathGuard::new);
+		let backlog = match self.backlog_auto {
+			true => somaxconn().map_or(self.backlog, |max| max.min(i32::MAX as u32) as i32),
+			false => self.backlog,
+		};
+		let listener = bound.listen(backlog)?;
+		Ok(match guard {
+			Some(guard) => listener.with_unlink_guard(guard),
+			None => listener,
+		})
+	}
+}
+
+impl ListenerBuilder<Ipv6> {
+	/// Restrict the listener to IPv6 clients (IPV6_V6ONLY).
+	///
+	/// Without this, a `[::]` listener also accepts IPv4 clients as
+	/// `::ffff:a.b.c.d`, subject to the system default. Enable it when
+	/// access rules are written for IPv6 peers only.
+	pub fn v6_only(mut self, enable: bool) -> Self {
+		self.v6_only = Some(enable);
+		self
+	}
+
+	/// Accept IPv4 and IPv6 clients on one listener (clears IPV6_V6ONLY).
+	///
+	/// Explicit, so `net.ipv6.bindv6only=1` hosts behave the same. Bind
+	/// to `[::]`; IPv4 clients then show up as `::ffff:a.b.c.d`, see
+	/// `SocketAddrV6::to_v4_mapped()`. Same as `v6_only(!enable)`.
+	pub fn dual_stack(self, enable: bool) -> Self {
+		self.v6_only(!enable)
+	}
+}
+
+impl ListenerBuilder<Unix> {
+	/// Remove the socket file when the listener is dropped.
+	///
+	/// Has no effect for abstract addresses, which leave no file.
+	pub fn unlink_on_drop(mut self, enable: bool) -> Self {
+		self.unlink_on_drop = enable;
+		self
+	}
+
+	/// Remove a stale socket file left by a previous run before binding.
+	///
+	/// Only socket files nobody is listening on are removed; if another
+	/// server is live on the path, bind still fails with EADDRINUSE.
+	pub fn unlink_existing(mut self, enable: bool) -> Self {
+		self.unlink_existing = enable;
+		self
 	}
 }
 
@@ -328,6 +806,13 @@ impl<D: Domain> ListenerBuilder<D> {
 pub struct ConnectorBuilder<D: Domain> {
 	tcp: TcpConfig,
 	buffers: BufferConfig,
+	timeouts: TimeoutConfig,
+	qos: QosConfig,
+	nonblocking: bool,
+	syn_retries: Option<u8>,
+	retry: Option<RetryPolicy>,
+	local_addr: Option<D::Addr>,
+	md5_keys: Vec<(D::Addr, Vec<u8>)>,
 	_marker: PhantomData<D>,
 }
 
@@ -342,10 +827,34 @@ impl<D: Domain> ConnectorBuilder<D> {
 		Self {
 			tcp: TcpConfig::default(),
 			buffers: BufferConfig::default(),
+			timeouts: TimeoutConfig::default(),
+			qos: QosConfig::default(),
+			nonblocking: false,
+			syn_retries: None,
+			retry: None,
+			local_addr: None,
+			md5_keys: Vec::new(),
 			_marker: PhantomData,
 		}
 	}
 
+	/// Starts from the options recorded in a stream manifest.
+	///
+	/// The peer address isn't applied; pass it to `connect()`. Fails with
+	/// `WrongSocketKind` unless the manifest is a stream of family `D`.
+	pub fn from_manifest(manifest: &SocketManifest) -> std::io::Result<Self> {
+		manifest.expect::<D>(ManifestKind::Stream, "a stream manifest of this family")?;
+		let mut builder = Self::new()
+			.buffers(manifest.buffers)
+			.timeouts(manifest.timeouts)
+			.qos(manifest.qos)
+			.nonblocking(manifest.nonblocking);
+		if let Some(tcp) = manifest.tcp {
+			builder.tcp = tcp;
+		}
+		Ok(builder)
+	}
+
 	/// Set TCP options.
 	pub fn tcp(mut self, config: TcpConfig) -> Self {
 		self.tcp = config;
@@ -358,6 +867,70 @@ impl<D: Domain> ConnectorBuilder<D> {
 		self
 	}
 
+	/// Set blocking read/write timeouts.
+	pub fn timeouts(mut self, config: TimeoutConfig) -> Self {
+		self.timeouts = config;
+		self
+	}
+
+	/// Set DSCP/ECN marking (ignored for Unix sockets).
+	pub fn qos(mut self, config: QosConfig) -> Self {
+		self.qos = config;
+		self
+	}
+
+	/// Set non-blocking mode.
+	///
+	/// The socket is created with `SOCK_NONBLOCK`. `connect()` still waits
+	/// for the handshake to finish; the returned stream is non-blocking.
+	pub fn nonblocking(mut self, enable: bool) -> Self {
+		self.nonblocking = enable;
+		self
+	}
+
+	/// Give up after `retries` SYN retransmits (TCP_SYNCNT, 1-127).
+	///
+	/// Fails a connect to a blackholed host in ~3s for 1 retry, ~7s for
+	/// 2, instead of the default ~2 minutes. See `set_tcp_syn_count()`.
+	/// Ignored for Unix sockets.
+	pub fn syn_retries(mut self, retries: u8) -> Self {
+		self.syn_retries = Some(retries);
+		self
+	}
+
+	/// Bind to `addr` before connecting: the connection's source
+	/// address, and port unless it's 0.
+	///
+	/// For multi-homed hosts that must leave through a given address, or
+	/// tests that pin the client port. With port 0 the port is still
+	/// picked at connect time (IP_BIND_ADDRESS_NO_PORT), so many
+	/// connections from one address don't exhaust the ephemeral range.
+	/// A fixed port can only be reused once the previous connection from
+	/// it has left TIME_WAIT.
+	pub fn local_address(mut self, addr: D::Addr) -> Self {
+		self.local_addr = Some(addr);
+		self
+	}
+
+	/// Retry `connect()` and `connect_host()` with backoff while the
+	/// server refuses connections, e.g. because it's still starting.
+	///
+	/// `connect_timeout()`, `connect_cancellable()` and
+	/// `connect_nonblocking()` still make a single attempt.
+	pub fn retry(mut self, policy: RetryPolicy) -> Self {
+		self.retry = Some(policy);
+		self
+	}
+
+	/// Sign segments to `peer` with TCP MD5 (RFC 2385) using `key`.
+	///
+	/// Normally `peer` is the address passed to `connect()`; see
+	/// `add_tcp_md5_key()`.
+	pub fn add_md5_key(mut self, peer: D::Addr, key: &[u8]) -> Self {
+		self.md5_keys.push((peer, key.to_vec()));
+		self
+	}
+
 	// Legacy method
 	pub fn tcp_nodelay(mut self, enable: bool) -> Self {
 		self.tcp.nodelay = enable;
@@ -366,16 +939,159 @@ impl<D: Domain> ConnectorBuilder<D> {
 
 	/// Connects to the remote address.
 	pub fn connect(self, addr: D::Addr) -> std::io::Result<ConnectedStream<D>>
+	where
+		D::Addr: ToSockAddr + Clone, <D as Domain>::Addr: Debug
+	{
+		match self.retry {
+			Some(policy) => policy.run(|| self.connect_ref(addr.clone())),
+			None => self.connect_ref(addr),
+		}
+	}
+
+	/// Resolves `host` (`"example.com:443"`, `"[2001:db8::1]:443"`) to
+	/// this family's addresses and connects to the first that answers.
+	///
+	/// Each address gets a full `connect()` with these options, in the
+	/// resolver's order; if all fail, the last error is returned. An
+	/// unreachable address can take the whole SYN retry budget, so set
+	/// `syn_retries()` when a name has several. See `resolve()` for
+	/// both families at once.
+	pub fn connect_host(self, host: &str) -> std::io::Result<ConnectedStream<D>>
+	where
+		D::Addr: ToSockAddr + FromSockAddr + PartialEq + Clone + Debug,
+	{
+		let addrs = resolve_family::<D>(host)?;
+		let attempt = || first_ok(host, addrs.clone(), |addr| self.connect_ref(addr));
+		match self.retry {
+			Some(policy) => policy.run(attempt),
+			None => attempt(),
+		}
+	}
+
+	fn connect_ref(&self, addr: D::Addr) -> std::io::Result<ConnectedStream<D>>
 	where
 		D::Addr: ToSockAddr, <D as Domain>::Addr: Debug
 	{
-		let socket = RawSocket::<D, Stream>::new()?;
+		let socket = self.configured(self.nonblocking)?;
+
+		if self.nonblocking {
+			let target = format!("{:?}", addr);
+			let pending = socket.connect_nonblocking(addr)?;
+			return finish_connect(pending, target, None);
+		}
+
+		socket.connect(addr)
+	}
+
+	/// Starts a non-blocking connection and returns immediately.
+	///
+	/// The socket is always created with `SOCK_NONBLOCK`, regardless of
+	/// `nonblocking()`. Wait for writability, then call `take_error()`.
+	pub fn connect_nonblocking(self, addr: D::Addr) -> std::io::Result<PendingConnect<D>>
+	where
+		D::Addr: ToSockAddr, <D as Domain>::Addr: Debug
+	{
+		self.configured(true)?.connect_nonblocking(addr)
+	}
+
+	/// Connects, giving up after `timeout`.
+	///
+	/// Starts a non-blocking connect and waits for it with `poll()`.
+	/// Fails with `ErrorKind::TimedOut` if the handshake hasn't finished
+	/// by the deadline — e.g. the peer is silently dropping SYNs.
+	///
+	/// The returned stream is blocking unless `nonblocking(true)` was set.
+	pub fn connect_timeout(self, addr: D::Addr, timeout: Duration) -> std::io::Result<ConnectedStream<D>>
+	where
+		D::Addr: ToSockAddr, <D as Domain>::Addr: Debug
+	{
+		let nonblocking = self.nonblocking;
+		let target = format!("{:?}", addr);
+
+		let pending = self.connect_nonblocking(addr)?;
+		let stream = finish_connect(pending, target, Some(timeout))?;
+
+		if !nonblocking {
+			stream.set_nonblocking(false)?;
+		}
+		Ok(stream)
+	}
+
+	/// Connects, or fails with `IoError::Cancelled` when `cancel` fires
+	/// before the handshake finishes.
+	///
+	/// Like `connect_timeout()`, with the deadline replaced by another
+	/// thread's decision. The returned stream is blocking unless
+	/// `nonblocking(true)` was set.
+	pub fn connect_cancellable(self, addr: D::Addr, cancel: &CancelHandle) -> std::io::Result<ConnectedStream<D>>
+	where
+		D::Addr: ToSockAddr, <D as Domain>::Addr: Debug
+	{
+		let nonblocking = self.nonblocking;
+		let target = format!("{:?}", addr);
+
+		let pending = self.connect_nonblocking(addr)?;
+		cancel.wait(pending.as_raw_fd(), libc::POLLOUT)?;
+		let stream = complete_connect(pending, target)?;
+
+		if !nonblocking {
+			stream.set_nonblocking(false)?;
+		}
+		Ok(stream)
+	}
+
+	/// Creates the socket and applies every option that must precede
+	/// connect, including the local bind.
+	fn configured(&self, nonblocking: bool) -> std::io::Result<RawSocket<D, Stream>>
+	where
+		D::Addr: ToSockAddr, <D as Domain>::Addr: Debug
+	{
+		let socket = RawSocket::<D, Stream>::with_flags(sock_flags(nonblocking))?;
 		let is_unix = D::raw() == libc::AF_UNIX;
 
 		self.tcp.apply(&socket, is_unix)?;
 		self.buffers.apply(&socket)?;
+		self.timeouts.apply(&socket)?;
+		self.qos.apply(&socket, D::raw())?;
+		if let Some(retries) = self.syn_retries && !is_unix {
+			set_tcp_syn_count(&socket, retries)?;
+		}
+		for (peer, key) in &self.md5_keys {
+			add_tcp_md5_key(&socket, peer, key)?;
+		}
+		if let Some(local) = &self.local_addr {
+			if !is_unix {
+				set_bind_address_no_port(&socket, true)?;
+			}
+			socket.bind_source(local)?;
+		}
 
-		socket.connect(addr)
+		Ok(socket)
+	}
+}
+
+/// Waits until a non-blocking connect resolves, then completes it.
+///
+/// `None` waits forever. Fails with ETIMEDOUT if `timeout` elapses first.
+fn finish_connect<D: Domain>(
+	pending: PendingConnect<D>,
+	addr: String,
+	timeout: Option<Duration>,
+) -> std::io::Result<ConnectedStream<D>> {
+	if !poll_fd(pending.as_raw_fd(), libc::POLLOUT, timeout)? {
+		return Err(SocketError::Connect { errno: libc::ETIMEDOUT, addr }.into());
+	}
+	complete_connect(pending, addr)
+}
+
+/// Completes a connect that poll() reported as resolved.
+fn complete_connect<D: Domain>(pending: PendingConnect<D>, addr: String) -> std::io::Result<ConnectedStream<D>> {
+	match pending.take_error()? {
+		None => Ok(pending.finish()),
+		Some(e) => Err(SocketError::Connect {
+			errno: e.raw_os_error().unwrap_or(libc::ECONNREFUSED),
+			addr,
+		}.into()),
 	}
 }
 
@@ -397,6 +1113,12 @@ impl<D: Domain> ConnectorBuilder<D> {
 pub struct DatagramBuilder<D: Domain> {
 	reuse: ReuseConfig,
 	buffers: BufferConfig,
+	timeouts: TimeoutConfig,
+	qos: QosConfig,
+	nonblocking: bool,
+	transparent: bool,
+	broadcast: bool,
+	multicast_ttl: Option<u8>,
 	_marker: PhantomData<D>,
 }
 
@@ -411,10 +1133,30 @@ impl<D: Domain> DatagramBuilder<D> {
 		Self {
 			reuse: ReuseConfig { addr: false, port: false },
 			buffers: BufferConfig::default(),
+			timeouts: TimeoutConfig::default(),
+			qos: QosConfig::default(),
+			nonblocking: false,
+			transparent: false,
+			broadcast: false,
+			multicast_ttl: None,
 			_marker: PhantomData,
 		}
 	}
 
+	/// Starts from the options recorded in a datagram manifest.
+	///
+	/// The address isn't applied; pass it to `bind()`. Fails with
+	/// `WrongSocketKind` unless the manifest is a datagram socket of family `D`.
+	pub fn from_manifest(manifest: &SocketManifest) -> std::io::Result<Self> {
+		manifest.expect::<D>(ManifestKind::Datagram, "a datagram manifest of this family")?;
+		Ok(Self::new()
+			.reuse(manifest.reuse)
+			.buffers(manifest.buffers)
+			.timeouts(manifest.timeouts)
+			.qos(manifest.qos)
+			.nonblocking(manifest.nonblocking))
+	}
+
 	/// Set address reuse options.
 	pub fn reuse(mut self, config: ReuseConfig) -> Self {
 		self.reuse = config;
@@ -427,6 +1169,48 @@ impl<D: Domain> DatagramBuilder<D> {
 		self
 	}
 
+	/// Set blocking read/write timeouts.
+	pub fn timeouts(mut self, config: TimeoutConfig) -> Self {
+		self.timeouts = config;
+		self
+	}
+
+	/// Set DSCP/ECN marking (ignored for Unix sockets).
+	pub fn qos(mut self, config: QosConfig) -> Self {
+		self.qos = config;
+		self
+	}
+
+	/// Set non-blocking mode.
+	pub fn nonblocking(mut self, enable: bool) -> Self {
+		self.nonblocking = enable;
+		self
+	}
+
+	/// Allow binding to non-local addresses and receiving TPROXY-redirected
+	/// traffic (IP_TRANSPARENT / IPV6_TRANSPARENT). Needs CAP_NET_ADMIN.
+	///
+	/// IP sockets only; Unix builders fail at bind time.
+	pub fn transparent(mut self, enable: bool) -> Self {
+		self.transparent = enable;
+		self
+	}
+
+	/// Allow sending to broadcast addresses (SO_BROADCAST).
+	pub fn broadcast(mut self, enable: bool) -> Self {
+		self.broadcast = enable;
+		self
+	}
+
+	/// Hops outgoing multicast may travel (IP_MULTICAST_TTL /
+	/// IPV6_MULTICAST_HOPS). The kernel default is 1.
+	///
+	/// IP sockets only; Unix builders fail at bind time.
+	pub fn multicast_ttl(mut self, ttl: u8) -> Self {
+		self.multicast_ttl = Some(ttl);
+		self
+	}
+
 	// Legacy methods
 	pub fn reuse_addr(mut self, enable: bool) -> Self {
 		self.reuse.addr = enable;
@@ -443,11 +1227,166 @@ impl<D: Domain> DatagramBuilder<D> {
 	where
 		D::Addr: ToSockAddr, <D as Domain>::Addr: Debug
 	{
-		let socket = RawSocket::<D, Datagram>::new()?;
+		self.bind_ref(addr)
+	}
+
+	/// Resolves `host` and binds to the first address that works. An
+	/// empty host (`":5353"`) means every interface.
+	pub fn bind_host(self, host: &str) -> std::io::Result<BoundDatagram<D>>
+	where
+		D::Addr: ToSockAddr + FromSockAddr + PartialEq + Debug,
+	{
+		first_ok(host, resolve_passive::<D>(host)?, |addr| self.bind_ref(addr))
+	}
+
+	/// Binds to the first free port in `ports`, returning it with the socket.
+	///
+	/// Same rules as `ListenerBuilder::bind_with_port_fallback()`.
+	pub fn bind_with_port_fallback<R>(self, addr: D::Addr, ports: R) -> std::io::Result<(BoundDatagram<D>, Port)>
+	where
+		D::Addr: ToSockAddr + PortAddr + Clone + Debug,
+		R: IntoIterator<Item = Port>,
+	{
+		port_fallback(addr, ports, |addr| self.bind_ref(addr))
+	}
+
+	/// Binds `workers` sockets to `addr` as one SO_REUSEPORT group and
+	/// shards flows between them by hash.
+	///
+	/// The datagram counterpart of `ListenerBuilder::bind_sharded()`:
+	/// socket `i` is worker `i`, every datagram of a 4-tuple reaches the
+	/// same socket, and with port 0 all sockets share the first one's port.
+	pub fn bind_sharded(mut self, addr: D::Addr, workers: u32) -> std::io::Result<Vec<BoundDatagram<D>>>
+	where
+		D::Addr: ToSockAddr + FromSockAddr + Clone + Debug,
+	{
+		let shard = reuseport_shard(workers)?;
+		self.reuse.port = true;
+
+		let first = self.bind_ref(addr)?;
+		let addr = first.local_addr()?;
+		let mut sockets = vec![first];
+		for _ in 1..workers {
+			sockets.push(self.bind_ref(addr.clone())?);
+		}
+		shard.attach(&sockets)?;
+		Ok(sockets)
+	}
+
+	/// Binds a UDP socket that only receives datagrams starting with `prefix`.
+	///
+	/// A socket filter matching the 1-64 byte `prefix` is attached and
+	/// locked before bind, so nothing else is ever queued. Read knocks
+	/// with `KnockListener::recv_authorized()`. Fails with EINVAL for
+	/// Unix sockets.
+	pub fn knock_listener(self, addr: D::Addr, prefix: &[u8]) -> std::io::Result<KnockListener<D>>
+	where
+		D::Addr: ToSockAddr, <D as Domain>::Addr: Debug
+	{
+		if D::raw() != libc::AF_INET && D::raw() != libc::AF_INET6 {
+			return Err(SocketError::InvalidAddress { reason: "knock listeners need an IPv4 or IPv6 address" }.into());
+		}
+
+		let socket = self.configured()?;
+		attach_prefix_filter(&socket, prefix)?;
+		Ok(KnockListener::new(socket.bind_datagram(addr)?, prefix))
+	}
+
+	fn bind_ref(&self, addr: D::Addr) -> std::io::Result<BoundDatagram<D>>
+	where
+		D::Addr: ToSockAddr, <D as Domain>::Addr: Debug
+	{
+		self.configured()?.bind_datagram(addr)
+	}
+
+	/// Creates the socket and applies every option that must precede bind.
+	fn configured(&self) -> std::io::Result<RawSocket<D, Datagram>> {
+		let socket = RawSocket::<D, Datagram>::with_flags(sock_flags(self.nonblocking))?;
 
 		self.reuse.apply(&socket)?;
 		self.buffers.apply(&socket)?;
+		self.timeouts.apply(&socket)?;
+		self.qos.apply(&socket, D::raw())?;
+		if self.transparent {
+			set_transparent(&socket, D::raw())?;
+		}
+		if self.broadcast {
+			set_broadcast(&socket, true)?;
+		}
+		if let Some(ttl) = self.multicast_ttl {
+			match D::raw() {
+				libc::AF_INET => set_multicast_ttl(&socket, ttl)?,
+				libc::AF_INET6 => set_multicast_hops_v6(&socket, ttl)?,
+				_ => return Err(SocketError::InvalidInput { reason: "multicast TTL applies to IP sockets only" }.into()),
+			}
+		}
+
+		Ok(socket)
+	}
+}
 
-		socket.bind_datagram(addr)
+impl DatagramBuilder<Ipv4> {
+	/// Binds a socket that receives `group` on `addr.port()`.
+	///
+	/// `addr.ip()` picks the interface to join on (`0.0.0.0` = routing
+	/// default). The combination this gets right:
+	/// - SO_REUSEADDR + SO_REUSEPORT, so other listeners on the same
+	///   group and port (including other processes) each get a copy.
+	/// - Binds to the group, not the wildcard: on Linux a wildcard bind
+	///   also receives every other group joined on that port by anyone,
+	///   plus unicast to the port.
+	/// - Joins the group after bind, on the requested interface.
+	///
+	/// Other builder settings (buffers, timeouts, nonblocking) apply as usual.
+	pub fn multicast_listener(mut self, addr: SocketAddrV4, group: [u8; 4]) -> std::io::Result<BoundDatagram<Ipv4>> {
+		if group[0] & 0xf0 != 0xe0 {
+			return Err(SocketError::InvalidAddress { reason: "not an IPv4 multicast group (224.0.0.0/4)" }.into());
+		}
+
+		self.reuse = ReuseConfig::new().both();
+		let socket = self.bind_ref(SocketAddrV4::new(group, addr.port()))?;
+		join_multicast_v4(&socket, group, addr.ip())?;
+		Ok(socket)
 	}
-}
\ No newline at end of file
+}
+
+impl DatagramBuilder<Ipv6> {
+	/// Binds a socket that receives `group` on `addr.port()`.
+	///
+	/// `addr.scope_id()` is the interface index to join on (0 = routing
+	/// default) and is required for link-local groups (ff02::/16).
+	/// Same reuse and bind rules as the IPv4 version.
+	pub fn multicast_listener(mut self, addr: SocketAddrV6, group: [u8; 16]) -> std::io::Result<BoundDatagram<Ipv6>> {
+		if group[0] != 0xff {
+			return Err(SocketError::InvalidAddress { reason: "not an IPv6 multicast group (ff00::/8)" }.into());
+		}
+
+		self.reuse = ReuseConfig::new().both();
+		let bind_addr = SocketAddrV6::with_scope(group, addr.port(), addr.scope_id());
+		let socket = self.bind_ref(bind_addr)?;
+		join_multicast_v6(&socket, group, addr.scope_id())?;
+		Ok(socket)
+	}
+}
+
+/// Calls `bind` with `addr` on each port until one isn't in use.
+fn port_fallback<A, R, T, F>(addr: A, ports: R, mut bind: F) -> std::io::Result<(T, Port)>
+where
+	A: PortAddr + Clone + Debug,
+	R: IntoIterator<Item = Port>,
+	F: FnMut(A) -> std::io::Result<T>,
+{
+	let mut tried: Option<(u16, u16)> = None;
+	for port in ports {
+		match bind(addr.clone().with_port(port.get())) {
+			Ok(socket) => return Ok((socket, port)),
+			Err(e) if e.kind() == std::io::ErrorKind::AddrInUse => {
+				tried = Some((tried.map_or(port.get(), |(first, _)| first), port.get()));
+			}
+			Err(e) => return Err(e),
+		}
+	}
+
+	let (first, last) = tried.unwrap_or((addr.port(), addr.port()));
+	Err(SocketError::NoFreePort { addr: format!("{:?}", addr), first, last }.into())
+}
@@ -0,0 +1,178 @@
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd, FromRawFd, OwnedFd, RawFd};
+use std::sync::Arc;
+use crate::addr::{Domain, FromSockAddr};
+use crate::error::{SocketError, IoError, errno};
+use super::{Listener, Mode, ConnectedStream};
+
+/// Makes blocking calls on other threads give up.
+///
+/// Pass it to the `*_cancellable()` variants — `Listener::accept_cancellable()`,
+/// `ConnectedStream::read_cancellable()`, `ConnectorBuilder::connect_cancellable()`
+/// — and call `cancel()` from any thread: every call waiting on the handle
+/// (or a clone) fails with `IoError::Cancelled`, and so does every later one.
+///
+/// Cancellation is sticky; make a new handle for the next run.
+///
+/// # Example
+/// ```ignore
+/// let cancel = CancelHandle::new()?;
+/// let worker = {
+///     let cancel = cancel.clone();
+///     std::thread::spawn(move || loop {
+///         let (stream, _) = listener.accept_cancellable(&cancel)?;
+///         // ...
+///     })
+/// };
+/// cancel.cancel()?;
+/// worker.join();  // the thread saw Cancelled and returned
+/// ```
+#[derive(Clone)]
+pub struct CancelHandle {
+	fd: Arc<OwnedFd>,
+}
+
+impl CancelHandle {
+	/// Creates an uncancelled handle (one eventfd).
+	pub fn new() -> std::io::Result<Self> {
+		let efd = unsafe { libc::eventfd(0, libc::EFD_CLOEXEC | libc::EFD_NONBLOCK) };
+		if efd == -1 {
+			return Err(SocketError::Create { errno: errno() }.into());
+		}
+		crate::track(efd, "eventfd()");
+		Ok(Self { fd: Arc::new(unsafe { OwnedFd::from_raw_fd(efd) }) })
+	}
+
+	/// Cancels every current and future call using this handle.
+	///
+	/// Idempotent.
+	pub fn cancel(&self) -> std::io::Result<()> {
+		let one: u64 = 1;
+		let n = unsafe {
+			libc::write(self.fd.as_raw_fd(), &one as *const u64 as *const libc::c_void, 8)
+		};
+		// EAGAIN: counter saturated, so it's long since cancelled.
+		if n == -1 && errno() != libc::EAGAIN {
+			return Err(IoError::Write { errno: errno() }.into());
+		}
+		Ok(())
+	}
+
+	/// Returns true once `cancel()` has been called.
+	pub fn is_cancelled(&self) -> bool {
+		let mut pfd = libc::pollfd { fd: self.fd.as_raw_fd(), events: libc::POLLIN, revents: 0 };
+		unsafe { libc::poll(&mut pfd, 1, 0) == 1 }
+	}
+
+	/// Waits for `events` on `fd`, or fails with `Cancelled` once the
+	/// handle is cancelled. Cancellation wins if both are ready.
+	pub(crate) fn wait(&self, fd: RawFd, events: libc::c_short) -> std::io::Result<()> {
+		let mut pfds = [
+			libc::pollfd { fd, events, revents: 0 },
+			libc::pollfd { fd: self.fd.as_raw_fd(), events: libc::POLLIN, revents: 0 },
+		];
+		loop {
+			let n = unsafe { libc::poll(pfds.as_mut_ptr(), 2, -1) };
+			match n {
+				-1 if errno() == libc::EINTR => continue,
+				-1 => return Err(IoError::Poll { errno: errno() }.into()),
+				_ if pfds[1].revents != 0 => return Err(IoError::Cancelled.into()),
+				_ => return Ok(()),
+			}
+		}
+	}
+}
+
+impl AsFd for CancelHandle {
+	/// Readable once cancelled, so the handle can join an event loop.
+	fn as_fd(&self) -> BorrowedFd<'_> {
+		self.fd.as_fd()
+	}
+}
+
+impl std::fmt::Debug for CancelHandle {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("CancelHandle")
+			.field("cancelled", &self.is_cancelled())
+			.finish()
+	}
+}
+
+impl<D: Domain, M: Mode> Listener<D, M>
+where
+	D::Addr: FromSockAddr,
+{
+	/// Accepts a connection, or fails with `IoError::Cancelled` when
+	/// `cancel` fires first.
+	///
+	/// Waits in `poll()` rather than in `accept()`. The accepted stream is
+	/// blocking, as from `accept()`. With several threads accepting on one
+	/// listener, make it non-blocking: on a blocking listener a thread
+	/// that loses the race for a connection blocks in `accept()` where
+	/// the handle can't reach it.
+	pub fn accept_cancellable(&self, cancel: &CancelHandle) -> std::io::Result<(ConnectedStream<D>, D::Addr)> {
+		let mut storage: libc::sockaddr_storage = unsafe { std::mem::zeroed() };
+		loop {
+			cancel.wait(self.as_raw_fd(), libc::POLLIN)?;
+
+			let mut len = std::mem::size_of::<libc::sockaddr_storage>() as libc::socklen_t;
+			let fd = unsafe {
+				libc::accept4(
+					self.as_raw_fd(),
+					&mut storage as *mut _ as *mut libc::sockaddr,
+					&mut len,
+					libc::SOCK_CLOEXEC,
+				)
+			};
+			if fd == -1 {
+				match errno() {
+					// Another thread took it, or the client gave up first.
+					libc::EAGAIN | libc::EINTR | libc::ECONNABORTED => continue,
+					e => return Err(SocketError::Accept { errno: e }.into()),
+				}
+			}
+
+			crate::track(fd, "accept()");
+			let stream = ConnectedStream::from_fd(unsafe { OwnedFd::from_raw_fd(fd) });
+			let addr = unsafe {
+				D::Addr::from_sockaddr(&storage as *const _ as *const libc::sockaddr, len)
+					.ok_or(SocketError::InvalidAddress { reason: "invalid client address" })?
+			};
+			return Ok((stream, addr));
+		}
+	}
+}
+
+impl<D: Domain> ConnectedStream<D> {
+	/// Reads into `buf`, or fails with `IoError::Cancelled` when `cancel`
+	/// fires before any data (or EOF) arrives.
+	///
+	/// Works the same on blocking and non-blocking streams: the wait is
+	/// in `poll()` and the read itself never blocks.
+	pub fn read_cancellable(&self, buf: &mut [u8], cancel: &CancelHandle) -> std::io::Result<usize> {
+		loop {
+			cancel.wait(self.as_raw_fd(), libc::POLLIN)?;
+
+			let n = unsafe {
+				libc::recv(self.as_raw_fd(), buf.as_mut_ptr() as *mut libc::c_void, buf.len(), libc::MSG_DONTWAIT)
+			};
+			if n == -1 {
+				match errno() {
+					libc::EAGAIN | libc::EINTR => continue,
+					e => return Err(IoError::Read { errno: e }.into()),
+				}
+			}
+			return Ok(n as usize);
+		}
+	}
+}
+
+/*
+Why an eventfd instead of a shared AtomicBool?
+A flag is only seen by code that checks it, and a thread parked in
+accept() or read() checks nothing until the kernel wakes it. Closing or
+shutting down the socket from outside does wake it, but races with fd
+reuse and destroys state the thread may still want. Polling the socket
+and the eventfd together lets the kernel wake the waiter for either
+reason, and the eventfd never being drained makes cancellation stick
+for every waiter at once.
+*/
@@ -1,7 +1,9 @@
+use std::io::IoSlice;
 use std::os::fd::OwnedFd;
 use std::marker::PhantomData;
-use crate::addr::{Domain, ToSockAddr, FromSockAddr};
+use crate::addr::{Domain, Ipv4, Ipv6, Unix, SocketAddrV4, SocketAddrV6, ToSockAddr, FromSockAddr, decode_sockaddr};
 use crate::error::{SocketError, IoError, errno};
+use super::{SendFlags, RecvFlags, AnyAddr, Shutdown};
 
 /// A bound datagram socket ready for send/recv.
 ///
@@ -25,6 +27,25 @@ impl<D: Domain> BoundDatagram<D> {
 		use std::os::fd::AsRawFd;
 		self.fd.as_raw_fd()
 	}
+
+	/// Duplicates the fd (close-on-exec) into a second handle.
+	///
+	/// Both handles share one socket and receive queue — each datagram
+	/// goes to whichever handle reads first. Closes once the last drops.
+	pub fn try_clone(&self) -> std::io::Result<Self> {
+		Ok(Self::from_fd(super::raw::dup_fd(self.as_raw_fd())?))
+	}
+
+	/// Shuts down the socket in one or both directions.
+	///
+	/// `Shutdown::Read` wakes a `recv()` blocked in another thread —
+	/// it returns `Ok(0)` — and every later receive returns 0 at once,
+	/// which is how a receive loop is told to stop during teardown.
+	/// An unconnected IP socket still shuts down; the ENOTCONN the
+	/// kernel reports for it is not an error here.
+	pub fn shutdown(&self, how: Shutdown) -> std::io::Result<()> {
+		shutdown_datagram(self.as_raw_fd(), how)
+	}
 }
 
 impl<D: Domain> BoundDatagram<D>
@@ -40,35 +61,35 @@ where
 				self.as_raw_fd(),
 				buf.as_ptr() as *const libc::c_void,
 				buf.len(),
-				0,
+				libc::MSG_NOSIGNAL,
 				ptr,
 				len,
 			)
 		});
 
 		match result {
-			Some(n) if n >= 0 => Ok(n as usize),
-			Some(_) => Err(IoError::Write { errno: errno() }.into()),
-			None => Err(SocketError::InvalidAddress { reason: "address too long" }.into()),
+			Ok(n) if n >= 0 => Ok(n as usize),
+			Ok(_) => Err(IoError::Write { errno: errno() }.into()),
+			Err(e) => Err(SocketError::from(e).into()),
 		}
 	}
 
-	pub fn send_to_with_flags(&self, buf: &[u8], addr: &D::Addr, flags: i32) -> std::io::Result<usize> {
+	pub fn send_to_with_flags(&self, buf: &[u8], addr: &D::Addr, flags: SendFlags) -> std::io::Result<usize> {
 		let result = addr.with_raw(|ptr, len| unsafe {
 			libc::sendto(
 				self.as_raw_fd(),
 				buf.as_ptr() as *const libc::c_void,
 				buf.len(),
-				flags,
+				flags.bits() | libc::MSG_NOSIGNAL,
 				ptr,
 				len,
 			)
 		});
 
 		match result {
-			Some(n) if n >= 0 => Ok(n as usize),
-			Some(_) => Err(IoError::Write { errno: errno() }.into()),
-			None => Err(SocketError::InvalidAddress { reason: "address too long" }.into()),
+			Ok(n) if n >= 0 => Ok(n as usize),
+			Ok(_) => Err(IoError::Write { errno: errno() }.into()),
+			Err(e) => Err(SocketError::from(e).into()),
 		}
 	}
 	/// Receives data, returning bytes read.
@@ -93,13 +114,13 @@ where
 		}
 	}
 
-	pub fn recv_with_flags(&self, buf: &mut [u8], flags: i32) -> std::io::Result<usize> {
+	pub fn recv_with_flags(&self, buf: &mut [u8], flags: RecvFlags) -> std::io::Result<usize> {
 		let n = unsafe {
 			libc::recvfrom(
 				self.as_raw_fd(),
 				buf.as_mut_ptr() as *mut libc::c_void,
 				buf.len(),
-				flags,
+				flags.bits(),
 				std::ptr::null_mut(),
 				std::ptr::null_mut(),
 			)
@@ -111,6 +132,22 @@ where
 			Ok(n as usize)
 		}
 	}
+
+	/// Size of the next pending datagram, without consuming it.
+	///
+	/// Blocks like `recv()` until one arrives. Allocate exactly that much
+	/// and `recv()` it. An empty datagram reports 0.
+	pub fn peek_size(&self) -> std::io::Result<usize> {
+		self.recv_with_flags(&mut [], RecvFlags::PEEK | RecvFlags::TRUNC)
+	}
+
+	/// Copies the next datagram into `buf` without consuming it (MSG_PEEK).
+	///
+	/// The next `recv()` returns the same datagram. Bytes past `buf.len()`
+	/// aren't lost, only not shown.
+	pub fn peek(&self, buf: &mut [u8]) -> std::io::Result<usize> {
+		self.recv_with_flags(buf, RecvFlags::PEEK)
+	}
 	pub fn recv_from(&self, buf: &mut [u8]) -> std::io::Result<(usize, D::Addr)>
 	where
 		D::Addr: FromSockAddr,
@@ -134,14 +171,13 @@ where
 		}
 
 		let addr = unsafe {
-			D::Addr::from_sockaddr(&storage as *const _ as *const libc::sockaddr, len)
-				.ok_or_else(|| SocketError::InvalidAddress { reason: "invalid sender address" })?
+			decode_sockaddr::<D::Addr>(&storage as *const _ as *const libc::sockaddr, len, "invalid sender address")?
 		};
 
 		Ok((n as usize, addr))
 	}
 
-	pub fn recv_from_with_flags(&self, buf: &mut [u8], flags: i32) -> std::io::Result<(usize, D::Addr)>
+	pub fn recv_from_with_flags(&self, buf: &mut [u8], flags: RecvFlags) -> std::io::Result<(usize, D::Addr)>
 	where
 		D::Addr: FromSockAddr,
 	{
@@ -153,7 +189,7 @@ where
 				self.as_raw_fd(),
 				buf.as_mut_ptr() as *mut libc::c_void,
 				buf.len(),
-				flags,
+				flags.bits(),
 				&mut storage as *mut _ as *mut libc::sockaddr,
 				&mut len,
 			)
@@ -164,28 +200,22 @@ where
 		}
 
 		let addr = unsafe {
-			D::Addr::from_sockaddr(&storage as *const _ as *const libc::sockaddr, len)
-				.ok_or_else(|| SocketError::InvalidAddress { reason: "invalid sender address" })?
+			decode_sockaddr::<D::Addr>(&storage as *const _ as *const libc::sockaddr, len, "invalid sender address")?
 		};
 
 		Ok((n as usize, addr))
 	}
 
+	/// `peek()` that also returns the sender.
+	pub fn peek_from(&self, buf: &mut [u8]) -> std::io::Result<(usize, D::Addr)>
+	where
+		D::Addr: FromSockAddr,
+	{
+		self.recv_from_with_flags(buf, RecvFlags::PEEK)
+	}
+
 	pub fn set_nonblocking(&self, nonblocking: bool) -> std::io::Result<()> {
-		let flags = unsafe { libc::fcntl(self.as_raw_fd(), libc::F_GETFL) };
-		if flags == -1 {
-			return Err(SocketError::GetOption { errno: errno(), option: "F_GETFL" }.into());
-		}
-		let new_flags = if nonblocking {
-			flags | libc::O_NONBLOCK
-		} else {
-			flags & !libc::O_NONBLOCK
-		};
-		let result = unsafe { libc::fcntl(self.as_raw_fd(), libc::F_SETFL, new_flags) };
-		if result == -1 {
-			return Err(SocketError::SetOption { errno: errno(), option: "O_NONBLOCK" }.into());
-		}
-		Ok(())
+		super::mode::set_fd_nonblocking(self.as_raw_fd(), nonblocking)
 	}
 
 	pub fn connect(self, addr: D::Addr) -> std::io::Result<ConnectedDatagram<D>>
@@ -197,9 +227,225 @@ where
 		});
 
 		match result {
-			Some(-1) => Err(SocketError::Connect { errno: errno(), addr: format!("{:?}", addr) }.into()),
-			Some(_) => Ok(ConnectedDatagram::from_fd(self.fd)),
-			None => Err(SocketError::InvalidAddress { reason: "address too long" }.into()),
+			Ok(-1) => Err(SocketError::Connect { errno: errno(), addr: format!("{:?}", addr) }.into()),
+			Ok(_) => Ok(ConnectedDatagram::from_fd(self.fd)),
+			Err(e) => Err(SocketError::from(e).into()),
+		}
+	}
+}
+
+impl BoundDatagram<Ipv4> {
+	/// Sends to `addr` with IP_TOS set for this packet only.
+	///
+	/// Overrides `set_tos()` without touching the socket, so one socket
+	/// can mark packets differently (e.g. probes vs. data).
+	pub fn send_to_with_tos(&self, buf: &[u8], addr: &SocketAddrV4, tos: u8) -> std::io::Result<usize> {
+		self.send_to_with_cmsg(buf, addr, libc::IPPROTO_IP, libc::IP_TOS, tos as libc::c_int)
+	}
+
+	/// Receives a datagram along with its IP_TOS byte.
+	///
+	/// Needs `set_recv_tos(&socket, true)` first; without it the TOS is
+	/// `None`. Useful to check DSCP marks and ECN bits survive the path.
+	pub fn recv_from_with_tos(&self, buf: &mut [u8]) -> std::io::Result<(usize, SocketAddrV4, Option<u8>)> {
+		self.recv_from_with_cmsg::<u8>(buf, libc::IPPROTO_IP, libc::IP_TOS)
+	}
+
+	/// Receives a datagram along with the address it was originally sent to.
+	///
+	/// Needs `set_recv_orig_dst(&socket, true)` first; without it the
+	/// destination is `None`. On a TPROXY socket, reply from a socket
+	/// bound (with `DatagramBuilder::transparent()`) to that address so
+	/// the client sees the server it meant to reach.
+	pub fn recv_from_with_orig_dst(&self, buf: &mut [u8]) -> std::io::Result<(usize, SocketAddrV4, Option<SocketAddrV4>)> {
+		let (n, addr, dst) = self.recv_from_with_cmsg::<libc::sockaddr_in>(buf, libc::IPPROTO_IP, libc::IP_ORIGDSTADDR)?;
+		Ok((n, addr, dst.map(|raw| SocketAddrV4::from_raw(&raw))))
+	}
+}
+
+impl BoundDatagram<Ipv6> {
+	/// Sends to `addr` with IPV6_TCLASS set for this packet only.
+	pub fn send_to_with_tos(&self, buf: &[u8], addr: &SocketAddrV6, tclass: u8) -> std::io::Result<usize> {
+		self.send_to_with_cmsg(buf, addr, libc::IPPROTO_IPV6, libc::IPV6_TCLASS, tclass as libc::c_int)
+	}
+
+	/// Receives a datagram along with its traffic class.
+	///
+	/// Needs `set_recv_tclass(&socket, true)` first; without it the
+	/// traffic class is `None`.
+	pub fn recv_from_with_tos(&self, buf: &mut [u8]) -> std::io::Result<(usize, SocketAddrV6, Option<u8>)> {
+		let (n, addr, tclass) = self.recv_from_with_cmsg::<libc::c_int>(buf, libc::IPPROTO_IPV6, libc::IPV6_TCLASS)?;
+		Ok((n, addr, tclass.map(|t| t as u8)))
+	}
+
+	/// Receives a datagram along with the address it was originally sent to.
+	///
+	/// Needs `set_recv_orig_dst_v6(&socket, true)` first.
+	pub fn recv_from_with_orig_dst(&self, buf: &mut [u8]) -> std::io::Result<(usize, SocketAddrV6, Option<SocketAddrV6>)> {
+		let (n, addr, dst) = self.recv_from_with_cmsg::<libc::sockaddr_in6>(buf, libc::IPPROTO_IPV6, libc::IPV6_ORIGDSTADDR)?;
+		Ok((n, addr, dst.map(|raw| SocketAddrV6::from_raw(&raw))))
+	}
+
+	/// Receives a datagram, handling IPv4-mapped senders per `mapped`.
+	///
+	/// On a dual-stack socket IPv4 peers arrive as `::ffff:a.b.c.d`.
+	/// With `MappedAddrs::Unmap` they come back as `AnyAddr::Ipv4`; with
+	/// `MappedAddrs::Reject` the datagram is consumed and the call fails
+	/// with `WrongFamily`.
+	pub fn recv_from_normalized(&self, buf: &mut [u8], mapped: MappedAddrs) -> std::io::Result<(usize, AnyAddr)> {
+		let (n, addr) = self.recv_from(buf)?;
+		Ok((n, mapped.apply(addr)?))
+	}
+}
+
+/// What to do with IPv4-mapped (`::ffff:a.b.c.d`) addresses on an
+/// IPv6 socket.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MappedAddrs {
+	/// Report them as IPv6 addresses, as the kernel does.
+	#[default]
+	Keep,
+	/// Report them as the IPv4 addresses they stand for.
+	Unmap,
+	/// Fail with `SocketError::WrongFamily`, for IPv6-only protocols.
+	Reject,
+}
+
+impl MappedAddrs {
+	/// Applies the policy to one address.
+	///
+	/// Also works on addresses from `accept()` or `peer_addr()`.
+	pub fn apply(self, addr: SocketAddrV6) -> std::io::Result<AnyAddr> {
+		match (self, addr.to_v4_mapped()) {
+			(MappedAddrs::Unmap, Some(v4)) => Ok(AnyAddr::Ipv4(v4)),
+			(MappedAddrs::Reject, Some(_)) => {
+				Err(SocketError::WrongFamily { expected: libc::AF_INET6, found: libc::AF_INET }.into())
+			}
+			_ => Ok(AnyAddr::Ipv6(addr)),
+		}
+	}
+}
+
+impl<D: Domain> BoundDatagram<D>
+where
+	D::Addr: ToSockAddr,
+{
+	/// sendmsg() with a single control message carrying `value`.
+	pub(crate) fn send_to_with_cmsg<T: Copy>(&self, buf: &[u8], addr: &D::Addr, level: libc::c_int, ty: libc::c_int, value: T) -> std::io::Result<usize> {
+		let cmsg_space = unsafe { libc::CMSG_SPACE(std::mem::size_of::<T>() as u32) } as usize;
+		let mut cmsg_buf = vec![0u8; cmsg_space];
+
+		let mut iov = libc::iovec {
+			iov_base: buf.as_ptr() as *mut libc::c_void,
+			iov_len: buf.len(),
+		};
+
+		let result = addr.with_raw(|ptr, len| {
+			let mut msg: libc::msghdr = unsafe { std::mem::zeroed() };
+			msg.msg_name = ptr as *mut libc::c_void;
+			msg.msg_namelen = len;
+			msg.msg_iov = &mut iov;
+			msg.msg_iovlen = 1;
+			msg.msg_control = cmsg_buf.as_mut_ptr() as *mut libc::c_void;
+			msg.msg_controllen = cmsg_space;
+
+			unsafe {
+				let cmsg = libc::CMSG_FIRSTHDR(&msg);
+				(*cmsg).cmsg_level = level;
+				(*cmsg).cmsg_type = ty;
+				(*cmsg).cmsg_len = libc::CMSG_LEN(std::mem::size_of::<T>() as u32) as usize;
+				std::ptr::write_unaligned(libc::CMSG_DATA(cmsg) as *mut T, value);
+
+				libc::sendmsg(self.as_raw_fd(), &msg, libc::MSG_NOSIGNAL)
+			}
+		});
+
+		match result {
+			Ok(n) if n >= 0 => Ok(n as usize),
+			Ok(_) => Err(IoError::Write { errno: errno() }.into()),
+			Err(e) => Err(SocketError::from(e).into()),
+		}
+	}
+}
+
+impl<D: Domain> BoundDatagram<D>
+where
+	D::Addr: FromSockAddr,
+{
+	/// recvmsg() returning the first control message of `level`/`ty`, if any.
+	pub(crate) fn recv_from_with_cmsg<T: Copy>(
+		&self,
+		buf: &mut [u8],
+		level: libc::c_int,
+		ty: libc::c_int,
+	) -> std::io::Result<(usize, D::Addr, Option<T>)> {
+		let mut storage: libc::sockaddr_storage = unsafe { std::mem::zeroed() };
+		// Room for other enabled cmsgs (timestamps, TOS, ...) next to ours.
+		let mut cmsg_buf = [0u64; 32];
+
+		let mut iov = libc::iovec {
+			iov_base: buf.as_mut_ptr() as *mut libc::c_void,
+			iov_len: buf.len(),
+		};
+		let mut msg: libc::msghdr = unsafe { std::mem::zeroed() };
+		msg.msg_name = &mut storage as *mut _ as *mut libc::c_void;
+		msg.msg_namelen = std::mem::size_of::<libc::sockaddr_storage>() as libc::socklen_t;
+		msg.msg_iov = &mut iov;
+		msg.msg_iovlen = 1;
+		msg.msg_control = cmsg_buf.as_mut_ptr() as *mut libc::c_void;
+		msg.msg_controllen = std::mem::size_of_val(&cmsg_buf);
+
+		let n = unsafe { libc::recvmsg(self.as_raw_fd(), &mut msg, 0) };
+		if n == -1 {
+			return Err(IoError::Read { errno: errno() }.into());
+		}
+
+		let addr = unsafe {
+			decode_sockaddr::<D::Addr>(&storage as *const _ as *const libc::sockaddr, msg.msg_namelen, "invalid sender address")?
+		};
+
+		let mut found = None;
+		unsafe {
+			let mut cmsg = libc::CMSG_FIRSTHDR(&msg);
+			while !cmsg.is_null() {
+				if (*cmsg).cmsg_level == level && (*cmsg).cmsg_type == ty {
+					found = Some(std::ptr::read_unaligned(libc::CMSG_DATA(cmsg) as *const T));
+					break;
+				}
+				cmsg = libc::CMSG_NXTHDR(&msg, cmsg);
+			}
+		}
+
+		Ok((n as usize, addr, found))
+	}
+}
+
+impl<D: Domain> BoundDatagram<D>
+where
+	D::Addr: FromSockAddr,
+{
+	/// Returns the address this socket is bound to.
+	///
+	/// Shows the port the kernel picked after binding to port 0.
+	pub fn local_addr(&self) -> std::io::Result<D::Addr> {
+		let mut storage: libc::sockaddr_storage = unsafe { std::mem::zeroed() };
+		let mut len = std::mem::size_of::<libc::sockaddr_storage>() as libc::socklen_t;
+
+		let result = unsafe {
+			libc::getsockname(
+				self.as_raw_fd(),
+				&mut storage as *mut _ as *mut libc::sockaddr,
+				&mut len,
+			)
+		};
+
+		if result == -1 {
+			return Err(SocketError::GetOption { errno: errno(), option: "SO_SOCKNAME" }.into());
+		}
+
+		unsafe {
+			D::Addr::from_sockaddr(&storage as *const _ as *const libc::sockaddr, len)
+				.ok_or_else(|| SocketError::InvalidAddress { reason: "invalid address" }.into())
 		}
 	}
 }
@@ -254,6 +500,20 @@ impl<D: Domain> ConnectedDatagram<D> {
 		use std::os::fd::AsRawFd;
 		self.fd.as_raw_fd()
 	}
+
+	/// Duplicates the fd (close-on-exec) into a second handle.
+	///
+	/// Both handles share one socket and receive queue — each datagram
+	/// goes to whichever handle reads first. Closes once the last drops.
+	pub fn try_clone(&self) -> std::io::Result<Self> {
+		Ok(Self::from_fd(super::raw::dup_fd(self.as_raw_fd())?))
+	}
+
+	/// Shuts down the socket in one or both directions; see
+	/// `BoundDatagram::shutdown()`.
+	pub fn shutdown(&self, how: Shutdown) -> std::io::Result<()> {
+		shutdown_datagram(self.as_raw_fd(), how)
+	}
 	
 	pub fn send(&self, buf: &[u8]) -> std::io::Result<usize> {
 		let n = unsafe {
@@ -261,7 +521,7 @@ impl<D: Domain> ConnectedDatagram<D> {
 				self.as_raw_fd(),
 				buf.as_ptr() as *const libc::c_void,
 				buf.len(),
-				0,
+				libc::MSG_NOSIGNAL,
 			)
 		};
 
@@ -272,13 +532,13 @@ impl<D: Domain> ConnectedDatagram<D> {
 		}
 	}
 
-	pub fn send_with_flags(&self, buf: &[u8], flags: i32) -> std::io::Result<usize> {
+	pub fn send_with_flags(&self, buf: &[u8], flags: SendFlags) -> std::io::Result<usize> {
 		let n = unsafe {
 			libc::send(
 				self.as_raw_fd(),
 				buf.as_ptr() as *const libc::c_void,
 				buf.len(),
-				flags,
+				flags.bits() | libc::MSG_NOSIGNAL,
 			)
 		};
 
@@ -306,13 +566,13 @@ impl<D: Domain> ConnectedDatagram<D> {
 		}
 	}
 
-	pub fn recv_with_flags(&self, buf: &mut [u8], flags: i32) -> std::io::Result<usize> {
+	pub fn recv_with_flags(&self, buf: &mut [u8], flags: RecvFlags) -> std::io::Result<usize> {
 		let n = unsafe {
 			libc::recv(
 				self.as_raw_fd(),
 				buf.as_mut_ptr() as *mut libc::c_void,
 				buf.len(),
-				flags,
+				flags.bits(),
 			)
 		};
 
@@ -323,21 +583,24 @@ impl<D: Domain> ConnectedDatagram<D> {
 		}
 	}
 
+	/// Size of the next pending datagram, without consuming it.
+	///
+	/// Blocks like `recv()` until one arrives. Allocate exactly that much
+	/// and `recv()` it. An empty datagram reports 0.
+	pub fn peek_size(&self) -> std::io::Result<usize> {
+		self.recv_with_flags(&mut [], RecvFlags::PEEK | RecvFlags::TRUNC)
+	}
+
+	/// Copies the next datagram into `buf` without consuming it (MSG_PEEK).
+	///
+	/// The next `recv()` returns the same datagram. Bytes past `buf.len()`
+	/// aren't lost, only not shown.
+	pub fn peek(&self, buf: &mut [u8]) -> std::io::Result<usize> {
+		self.recv_with_flags(buf, RecvFlags::PEEK)
+	}
+
 	pub fn set_nonblocking(&self, nonblocking: bool) -> std::io::Result<()> {
-		let flags = unsafe { libc::fcntl(self.as_raw_fd(), libc::F_GETFL) };
-		if flags == -1 {
-			return Err(SocketError::GetOption { errno: errno(), option: "F_GETFL" }.into());
-		}
-		let new_flags = if nonblocking {
-			flags | libc::O_NONBLOCK
-		} else {
-			flags & !libc::O_NONBLOCK
-		};
-		let result = unsafe { libc::fcntl(self.as_raw_fd(), libc::F_SETFL, new_flags) };
-		if result == -1 {
-			return Err(SocketError::SetOption { errno: errno(), option: "O_NONBLOCK" }.into());
-		}
-		Ok(())
+		super::mode::set_fd_nonblocking(self.as_raw_fd(), nonblocking)
 	}
 }
 
@@ -394,14 +657,13 @@ where
 		}
 
 		let addr = unsafe {
-			D::Addr::from_sockaddr(&storage as *const _ as *const libc::sockaddr, len)
-				.ok_or_else(|| SocketError::InvalidAddress { reason: "invalid sender address" })?
+			decode_sockaddr::<D::Addr>(&storage as *const _ as *const libc::sockaddr, len, "invalid sender address")?
 		};
 
 		Ok((n as usize, addr))
 	}
 
-	pub fn recv_from_with_flags(&self, buf: &mut [u8], flags: i32) -> std::io::Result<(usize, D::Addr)> {
+	pub fn recv_from_with_flags(&self, buf: &mut [u8], flags: RecvFlags) -> std::io::Result<(usize, D::Addr)> {
 		let mut storage: libc::sockaddr_storage = unsafe { std::mem::zeroed() };
 		let mut len = std::mem::size_of::<libc::sockaddr_storage>() as libc::socklen_t;
 
@@ -410,7 +672,7 @@ where
 				self.as_raw_fd(),
 				buf.as_mut_ptr() as *mut libc::c_void,
 				buf.len(),
-				flags,
+				flags.bits(),
 				&mut storage as *mut _ as *mut libc::sockaddr,
 				&mut len,
 			)
@@ -421,12 +683,16 @@ where
 		}
 
 		let addr = unsafe {
-			D::Addr::from_sockaddr(&storage as *const _ as *const libc::sockaddr, len)
-				.ok_or_else(|| SocketError::InvalidAddress { reason: "invalid sender address" })?
+			decode_sockaddr::<D::Addr>(&storage as *const _ as *const libc::sockaddr, len, "invalid sender address")?
 		};
 
 		Ok((n as usize, addr))
 	}
+
+	/// `peek()` that also returns the sender.
+	pub fn peek_from(&self, buf: &mut [u8]) -> std::io::Result<(usize, D::Addr)> {
+		self.recv_from_with_flags(buf, RecvFlags::PEEK)
+	}
 }
 
 
@@ -444,36 +710,109 @@ where
 				self.as_raw_fd(),
 				buf.as_ptr() as *const libc::c_void,
 				buf.len(),
-				0,
+				libc::MSG_NOSIGNAL,
 				ptr,
 				len,
 			)
 		});
 
 		match result {
-			Some(n) if n >= 0 => Ok(n as usize),
-			Some(_) => Err(IoError::Write { errno: errno() }.into()),
-			None => Err(SocketError::InvalidAddress { reason: "address too long" }.into()),
+			Ok(n) if n >= 0 => Ok(n as usize),
+			Ok(_) => Err(IoError::Write { errno: errno() }.into()),
+			Err(e) => Err(SocketError::from(e).into()),
 		}
 	}
 
-	pub fn send_to_with_flags(&self, buf: &[u8], addr: &D::Addr, flags: i32) -> std::io::Result<usize> {
+	pub fn send_to_with_flags(&self, buf: &[u8], addr: &D::Addr, flags: SendFlags) -> std::io::Result<usize> {
 		let result = addr.with_raw(|ptr, len| unsafe {
 			libc::sendto(
 				self.as_raw_fd(),
 				buf.as_ptr() as *const libc::c_void,
 				buf.len(),
-				flags,
+				flags.bits() | libc::MSG_NOSIGNAL,
 				ptr,
 				len,
 			)
 		});
 
 		match result {
-			Some(n) if n >= 0 => Ok(n as usize),
-			Some(_) => Err(IoError::Write { errno: errno() }.into()),
-			None => Err(SocketError::InvalidAddress { reason: "address too long" }.into()),
+			Ok(n) if n >= 0 => Ok(n as usize),
+			Ok(_) => Err(IoError::Write { errno: errno() }.into()),
+			Err(e) => Err(SocketError::from(e).into()),
+		}
+	}
+}
+
+impl ConnectedDatagram<Ipv4> {
+	/// Sends to `addr` instead of the connected peer, after checking the
+	/// socket's options allow that kind of destination.
+	///
+	/// - Broadcast (255.255.255.255) needs SO_BROADCAST: `set_broadcast()`
+	///   or `DatagramBuilder::broadcast()`.
+	/// - Multicast (224.0.0.0/4) needs an IP_MULTICAST_TTL of at least 1,
+	///   or the packet never leaves this host.
+	///
+	/// Either fails with `InvalidAddress` before anything is sent. A
+	/// subnet broadcast (e.g. 192.0.2.255) can't be recognized without the
+	/// netmask; the kernel's EACCES for it is reported the same way.
+	pub fn send_to_override(&self, buf: &[u8], addr: &SocketAddrV4) -> std::io::Result<usize> {
+		check_v4_destination(self, addr.ip())?;
+		self.send_to(buf, addr).map_err(broadcast_denied)
+	}
+}
+
+impl ConnectedDatagram<Ipv6> {
+	/// Sends to `addr` instead of the connected peer, after checking the
+	/// socket's options allow that kind of destination.
+	///
+	/// Multicast (ff00::/8) needs IPV6_MULTICAST_HOPS of at least 1.
+	/// IPv4-mapped destinations get the IPv4 checks of
+	/// `ConnectedDatagram<Ipv4>::send_to_override()`.
+	pub fn send_to_override(&self, buf: &[u8], addr: &SocketAddrV6) -> std::io::Result<usize> {
+		let ip = addr.ip();
+		if addr.is_v4_mapped() {
+			check_v4_destination(self, [ip[12], ip[13], ip[14], ip[15]])?;
+		} else if ip[0] == 0xff && super::get_multicast_hops_v6(self)? == 0 {
+			return Err(SocketError::InvalidAddress {
+				reason: "multicast destination needs IPV6_MULTICAST_HOPS >= 1 (set_multicast_hops_v6)",
+			}.into());
 		}
+		self.send_to(buf, addr).map_err(broadcast_denied)
+	}
+}
+
+/// Rejects IPv4 broadcast/multicast destinations the socket's options forbid.
+fn check_v4_destination<S: std::os::fd::AsRawFd>(socket: &S, ip: [u8; 4]) -> std::io::Result<()> {
+	if ip == [255, 255, 255, 255] && !super::get_broadcast(socket)? {
+		return Err(SocketError::InvalidAddress {
+			reason: "broadcast destination needs SO_BROADCAST (set_broadcast)",
+		}.into());
+	}
+	if ip[0] & 0xf0 == 224 && super::get_multicast_ttl(socket)? == 0 {
+		return Err(SocketError::InvalidAddress {
+			reason: "multicast destination needs IP_MULTICAST_TTL >= 1 (set_multicast_ttl)",
+		}.into());
+	}
+	Ok(())
+}
+
+/// EACCES from sendto() means a broadcast address without SO_BROADCAST.
+fn broadcast_denied(err: std::io::Error) -> std::io::Error {
+	if err.kind() == std::io::ErrorKind::PermissionDenied {
+		return SocketError::InvalidAddress {
+			reason: "broadcast destination needs SO_BROADCAST (set_broadcast)",
+		}.into();
+	}
+	err
+}
+
+impl ConnectedDatagram<Unix> {
+	/// Creates a pair of connected Unix datagram sockets.
+	///
+	/// Each end sends to the other; message boundaries are kept.
+	pub fn pair() -> std::io::Result<(Self, Self)> {
+		let (a, b) = super::raw::socketpair::<super::Datagram>()?;
+		Ok((Self::from_fd(a), Self::from_fd(b)))
 	}
 }
 
@@ -520,23 +859,16 @@ where
 {
 	/// Sends multiple messages in one syscall.
 	///
-	/// Returns number of messages successfully sent.
-	pub fn sendmmsg(&self, messages: &[SendMsg<D::Addr>]) -> std::io::Result<usize> {
+	/// Each message is gathered from its `bufs`, so headers and payload
+	/// can stay in separate buffers. Returns one `MsgResult` per message
+	/// sent, in order; fewer than `messages.len()` means the rest weren't.
+	pub fn sendmmsg(&self, messages: &[SendMsg<D::Addr>]) -> std::io::Result<Vec<MsgResult>> {
 		if messages.is_empty() {
-			return Ok(0);
+			return Ok(Vec::new());
 		}
 		
 		let len = messages.len();
 		
-		// Build iovecs for each message
-		let mut iovecs: Vec<libc::iovec> = messages
-			.iter()
-			.map(|msg| libc::iovec {
-				iov_base: msg.buf.as_ptr() as *mut libc::c_void,
-				iov_len: msg.buf.len(),
-			})
-			.collect();
-		
 		// Storage for converted addresses
 		let mut sockaddrs: Vec<libc::sockaddr_storage> = vec![unsafe { std::mem::zeroed() }; len];
 		let mut addr_lens: Vec<libc::socklen_t> = vec![0; len];
@@ -544,9 +876,9 @@ where
 		// Convert addresses
 		for (i, msg) in messages.iter().enumerate() {
 			msg.addr.with_raw(|ptr, addr_len| {
-				if addr_len as usize > std::mem::size_of::<libc::sockaddr_storage>() {
-					return None;
-				}
+				// Every sockaddr fits in sockaddr_storage; that's its purpose.
+				debug_assert!(addr_len as usize <= std::mem::size_of::<libc::sockaddr_storage>());
+				let addr_len = addr_len.min(std::mem::size_of::<libc::sockaddr_storage>() as libc::socklen_t);
 				unsafe {
 					std::ptr::copy_nonoverlapping(
 						ptr as *const u8,
@@ -555,18 +887,18 @@ where
 					);
 				}
 				addr_lens[i] = addr_len;
-				Some(())
-			}).ok_or_else(|| SocketError::InvalidAddress { reason: "address too long" })?;
+			})
+				.map_err(SocketError::from)?;
 		}
 
-		// Build mmsghdr array
+		// Build mmsghdr array; IoSlice is ABI-compatible with iovec.
 		let mut hdrs: Vec<libc::mmsghdr> = (0..len)
 			.map(|i| {
 				let mut hdr: libc::mmsghdr = unsafe { std::mem::zeroed() };
 				hdr.msg_hdr.msg_name = &mut sockaddrs[i] as *mut _ as *mut libc::c_void;
 				hdr.msg_hdr.msg_namelen = addr_lens[i];
-				hdr.msg_hdr.msg_iov = &mut iovecs[i];
-				hdr.msg_hdr.msg_iovlen = 1;
+				hdr.msg_hdr.msg_iov = messages[i].bufs.as_ptr() as *mut libc::iovec;
+				hdr.msg_hdr.msg_iovlen = messages[i].bufs.len();
 				hdr
 			})
 			.collect();
@@ -576,7 +908,7 @@ where
 				self.as_raw_fd(),
 				hdrs.as_mut_ptr(),
 				len as libc::c_uint,
-				0,
+				libc::MSG_NOSIGNAL,
 			)
 		};
 
@@ -584,7 +916,7 @@ where
 			return Err(IoError::Write { errno: errno() }.into());
 		}
 
-		Ok(sent as usize)
+		Ok(hdrs[..sent as usize].iter().map(|hdr| MsgResult { bytes: hdr.msg_len as usize }).collect())
 	}
 
 	/// Receives multiple messages in one syscall.
@@ -592,6 +924,11 @@ where
 	/// `bufs` - mutable buffers to receive into
 	/// Returns Vec of (bytes_received, sender_address) per message.
 	pub fn recvmmsg(&self, bufs: &mut [&mut [u8]]) -> std::io::Result<Vec<(usize, D::Addr)>> {
+		self.recvmmsg_with_flags(bufs, RecvFlags::NONE)
+	}
+
+	/// `recvmmsg` with MSG_* flags (e.g. MSG_WAITFORONE).
+	pub(crate) fn recvmmsg_with_flags(&self, bufs: &mut [&mut [u8]], flags: RecvFlags) -> std::io::Result<Vec<(usize, D::Addr)>> {
 		if bufs.is_empty() {
 			return Ok(Vec::new());
 		}
@@ -627,7 +964,7 @@ where
 				self.as_raw_fd(),
 				hdrs.as_mut_ptr(),
 				len as libc::c_uint,
-				0,
+				flags.bits(),
 				std::ptr::null_mut(), // no timeout
 			)
 		};
@@ -641,11 +978,11 @@ where
 		for i in 0..received as usize {
 			let bytes = hdrs[i].msg_len as usize;
 			let addr = unsafe {
-				D::Addr::from_sockaddr(
+				decode_sockaddr::<D::Addr>(
 					&sockaddrs[i] as *const _ as *const libc::sockaddr,
 					hdrs[i].msg_hdr.msg_namelen,
-				)
-					.ok_or_else(|| SocketError::InvalidAddress { reason: "invalid sender address" })?
+					"invalid sender address",
+				)?
 			};
 			results.push((bytes, addr));
 		}
@@ -654,13 +991,27 @@ where
 	}
 }
 
+fn shutdown_datagram(fd: libc::c_int, how: Shutdown) -> std::io::Result<()> {
+	let result = unsafe { libc::shutdown(fd, how.as_raw()) };
+	// UDP without a peer: the shutdown happens, but is reported as ENOTCONN.
+	if result == -1 && errno() != libc::ENOTCONN {
+		Err(SocketError::SetOption { errno: errno(), option: "shutdown" }.into())
+	} else {
+		Ok(())
+	}
+}
+
 /// A single message to send via sendmmsg.
+///
+/// `bufs` are concatenated into one datagram:
+/// `SendMsg { bufs: &[IoSlice::new(&header), IoSlice::new(payload)], addr: &peer }`.
 pub struct SendMsg<'a, A> {
-	pub buf: &'a [u8],
+	pub bufs: &'a [IoSlice<'a>],
 	pub addr: &'a A,
 }
 
 /// Result of a single message in sendmmsg/recvmmsg.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct MsgResult {
 	pub bytes: usize,
 }
\ No newline at end of file
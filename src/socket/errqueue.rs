@@ -0,0 +1,371 @@
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd};
+use std::marker::PhantomData;
+use std::time::{Duration, Instant};
+use crate::addr::{Domain, FromSockAddr, Ipv4, Ipv6};
+use crate::error::{IoError, errno};
+use super::{ConnectedStream, BoundDatagram, ConnectedDatagram, poll_fd, set_recv_err, set_recv_err_v6};
+
+// Not yet exported by libc (linux/errqueue.h).
+const SO_EE_ORIGIN_ZEROCOPY: u8 = 5;
+const SO_EE_ORIGIN_TXTIME: u8 = 6;
+const SO_EE_CODE_ZEROCOPY_COPIED: u8 = 1;
+const SO_EE_CODE_TXTIME_INVALID_PARAM: u8 = 1;
+const SO_EE_CODE_TXTIME_MISSED: u8 = 2;
+
+/// Reader for a socket's error queue (`recvmsg(MSG_ERRQUEUE)`).
+///
+/// Zero-copy completions (SO_ZEROCOPY), dropped timed sends (SO_TXTIME),
+/// and ICMP/local errors (IP_RECVERR) all arrive on the same queue.
+/// Read it from one place and dispatch on `ErrorEvent`.
+///
+/// The error queue never blocks. Readiness is signalled as `POLLERR`.
+///
+/// # Example
+/// ```ignore
+/// for err in socket.error_queue().drain()? {
+///     match err.event {
+///         ErrorEvent::Zerocopy { first, last, .. } => release_buffers(first..=last),
+///         ErrorEvent::Icmp { errno, offender, .. } => log_unreachable(errno, offender),
+///         _ => {}
+///     }
+/// }
+/// ```
+pub struct ErrorQueue<'a, D: Domain> {
+	fd: BorrowedFd<'a>,
+	_marker: PhantomData<D>,
+}
+
+/// One entry read from the error queue.
+#[derive(Debug, Clone)]
+pub struct QueuedError<A> {
+	pub event: ErrorEvent<A>,
+	/// Destination of the packet that triggered the error, if reported.
+	pub destination: Option<A>,
+}
+
+/// A decoded `sock_extended_err`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ErrorEvent<A> {
+	/// `MSG_ZEROCOPY` sends `first..=last` completed; their buffers can be reused.
+	///
+	/// `copied` means the kernel fell back to copying — zero-copy bought nothing.
+	Zerocopy { first: u32, last: u32, copied: bool },
+
+	/// A packet scheduled with SO_TXTIME was dropped.
+	Txtime { txtime: u64, reason: TxtimeError },
+
+	/// An ICMP/ICMPv6 error about a sent packet (unreachable, TTL exceeded, ...).
+	///
+	/// `info` carries the next-hop MTU for "fragmentation needed"/"packet too big".
+	Icmp { errno: i32, icmp_type: u8, icmp_code: u8, info: u32, offender: Option<A> },
+
+	/// A locally generated error, e.g. EMSGSIZE with the path MTU in `info`.
+	Local { errno: i32, info: u32 },
+
+	/// Any other origin (e.g. timestamping), undecoded.
+	Other { origin: u8, errno: i32, code: u8, info: u32, data: u32 },
+}
+
+impl<A> ErrorEvent<A> {
+	/// The path MTU, for "fragmentation needed"/"packet too big" ICMP
+	/// errors and local EMSGSIZE.
+	pub fn path_mtu(&self) -> Option<u32> {
+		match *self {
+			ErrorEvent::Icmp { errno: libc::EMSGSIZE, info, .. }
+			| ErrorEvent::Local { errno: libc::EMSGSIZE, info } if info > 0 => Some(info),
+			_ => None,
+		}
+	}
+}
+
+/// Outcome of `ConnectedDatagram::recv_or_peer_gone()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecvOutcome<A> {
+	/// `n` bytes were received.
+	Data(usize),
+
+	/// Nothing arrived within the timeout.
+	Timeout,
+
+	/// An ICMP error says the peer can't be reached: ECONNREFUSED (port
+	/// unreachable), EHOSTUNREACH, ENETUNREACH or EHOSTDOWN.
+	///
+	/// `offender` is the host that sent the ICMP error, if IP_RECVERR
+	/// was enabled; often a router rather than the peer itself.
+	PeerGone { errno: i32, offender: Option<A> },
+}
+
+/// Why a SO_TXTIME packet was dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TxtimeError {
+	/// The txtime or clock was invalid.
+	InvalidParam,
+	/// The deadline had already passed.
+	Missed,
+	/// Unknown code from a newer kernel.
+	Other(u8),
+}
+
+impl<'a, D: Domain> ErrorQueue<'a, D>
+where
+	D::Addr: FromSockAddr,
+{
+	/// Creates a reader over any socket of domain `D`.
+	pub fn new<S: AsFd>(socket: &'a S) -> Self {
+		Self { fd: socket.as_fd(), _marker: PhantomData }
+	}
+
+	/// Reads one entry. Returns `None` when the queue is empty.
+	pub fn recv(&self) -> std::io::Result<Option<QueuedError<D::Addr>>> {
+		loop {
+			let mut name: libc::sockaddr_storage = unsafe { std::mem::zeroed() };
+			// Room for sock_extended_err + offender address, plus any timestamp cmsg.
+			let mut cmsg_buf = [0u64; 32];
+
+			let mut msg: libc::msghdr = unsafe { std::mem::zeroed() };
+			msg.msg_name = &mut name as *mut _ as *mut libc::c_void;
+			msg.msg_namelen = std::mem::size_of::<libc::sockaddr_storage>() as libc::socklen_t;
+			msg.msg_control = cmsg_buf.as_mut_ptr() as *mut libc::c_void;
+			msg.msg_controllen = std::mem::size_of_val(&cmsg_buf) as _;
+
+			let n = unsafe { libc::recvmsg(self.fd.as_raw_fd(), &mut msg, libc::MSG_ERRQUEUE) };
+			if n == -1 {
+				return match errno() {
+					libc::EAGAIN => Ok(None),
+					e => Err(IoError::Read { errno: e }.into()),
+				};
+			}
+
+			let destination = if msg.msg_namelen > 0 {
+				unsafe { D::Addr::from_sockaddr(&name as *const _ as *const libc::sockaddr, msg.msg_namelen) }
+			} else {
+				None
+			};
+
+			let mut cmsg = unsafe { libc::CMSG_FIRSTHDR(&msg) };
+			while !cmsg.is_null() {
+				let (level, ty) = unsafe { ((*cmsg).cmsg_level, (*cmsg).cmsg_type) };
+				let is_recverr = (level == libc::IPPROTO_IP && ty == libc::IP_RECVERR)
+					|| (level == libc::IPPROTO_IPV6 && ty == libc::IPV6_RECVERR);
+
+				if is_recverr {
+					let event = unsafe { decode::<D::Addr>(cmsg) };
+					return Ok(Some(QueuedError { event, destination }));
+				}
+				cmsg = unsafe { libc::CMSG_NXTHDR(&msg, cmsg) };
+			}
+
+			// Dequeued something without an extended error (shouldn't happen); skip it.
+		}
+	}
+
+	/// Reads entries until the queue is empty.
+	pub fn drain(&self) -> std::io::Result<Vec<QueuedError<D::Addr>>> {
+		let mut out = Vec::new();
+		while let Some(err) = self.recv()? {
+			out.push(err);
+		}
+		Ok(out)
+	}
+}
+
+/// Decodes a sock_extended_err control message.
+///
+/// # Safety
+/// `cmsg` must be an IP_RECVERR/IPV6_RECVERR header within a valid msghdr.
+unsafe fn decode<A: FromSockAddr>(cmsg: *const libc::cmsghdr) -> ErrorEvent<A> {
+	let data = unsafe { libc::CMSG_DATA(cmsg) };
+	let ee: libc::sock_extended_err = unsafe { std::ptr::read_unaligned(data as *const _) };
+	let errno = ee.ee_errno as i32;
+
+	match ee.ee_origin {
+		SO_EE_ORIGIN_ZEROCOPY => ErrorEvent::Zerocopy {
+			first: ee.ee_info,
+			last: ee.ee_data,
+			copied: ee.ee_code & SO_EE_CODE_ZEROCOPY_COPIED != 0,
+		},
+		SO_EE_ORIGIN_TXTIME => ErrorEvent::Txtime {
+			txtime: ((ee.ee_info as u64) << 32) | ee.ee_data as u64,
+			reason: match ee.ee_code {
+				SO_EE_CODE_TXTIME_INVALID_PARAM => TxtimeError::InvalidParam,
+				SO_EE_CODE_TXTIME_MISSED => TxtimeError::Missed,
+				code => TxtimeError::Other(code),
+			},
+		},
+		libc::SO_EE_ORIGIN_ICMP | libc::SO_EE_ORIGIN_ICMP6 => ErrorEvent::Icmp {
+			errno,
+			icmp_type: ee.ee_type,
+			icmp_code: ee.ee_code,
+			info: ee.ee_info,
+			offender: unsafe { offender(cmsg, data) },
+		},
+		libc::SO_EE_ORIGIN_LOCAL => ErrorEvent::Local { errno, info: ee.ee_info },
+		origin => ErrorEvent::Other {
+			origin,
+			errno,
+			code: ee.ee_code,
+			info: ee.ee_info,
+			data: ee.ee_data,
+		},
+	}
+}
+
+/// Reads the offender address that follows sock_extended_err (SO_EE_OFFENDER).
+unsafe fn offender<A: FromSockAddr>(cmsg: *const libc::cmsghdr, data: *const u8) -> Option<A> {
+	let header = unsafe { data.offset_from(cmsg as *const u8) } as usize;
+	let ee_len = std::mem::size_of::<libc::sock_extended_err>();
+	let avail = unsafe { (*cmsg).cmsg_len }.checked_sub(header + ee_len)?;
+
+	let mut storage: libc::sockaddr_storage = unsafe { std::mem::zeroed() };
+	let len = avail.min(std::mem::size_of::<libc::sockaddr_storage>());
+	unsafe {
+		std::ptr::copy_nonoverlapping(data.add(ee_len), &mut storage as *mut _ as *mut u8, len);
+	}
+
+	// AF_UNSPEC means the kernel had no offender to report.
+	if storage.ss_family == libc::AF_UNSPEC as libc::sa_family_t {
+		return None;
+	}
+	unsafe { A::from_sockaddr(&storage as *const _ as *const libc::sockaddr, len as libc::socklen_t) }
+}
+
+/*
+What is the error queue?
+Errors that relate to an earlier send — not to the current call — can't be
+returned from send(). The kernel parks them on a per-socket queue instead,
+each one carrying a sock_extended_err that says where it came from:
+  - ZEROCOPY: not an error at all; a range of MSG_ZEROCOPY send ids is done
+  - TXTIME:   a packet with a launch time was dropped by the qdisc
+  - ICMP:     a router sent back unreachable / time exceeded / too big
+  - LOCAL:    the local stack rejected it (e.g. message larger than path MTU)
+Reading it means recvmsg(MSG_ERRQUEUE) and walking the cmsgs.
+*/
+
+impl<D: Domain> ConnectedStream<D>
+where
+	D::Addr: FromSockAddr,
+{
+	/// Returns a reader for this socket's error queue.
+	pub fn error_queue(&self) -> ErrorQueue<'_, D> {
+		ErrorQueue::new(self)
+	}
+}
+
+impl<D: Domain> BoundDatagram<D>
+where
+	D::Addr: FromSockAddr,
+{
+	/// Returns a reader for this socket's error queue.
+	pub fn error_queue(&self) -> ErrorQueue<'_, D> {
+		ErrorQueue::new(self)
+	}
+
+	/// Drains the error queue: why earlier sends failed, and where to.
+	///
+	/// Enable `set_recv_err(true)` first to get ICMP errors with their
+	/// type, code and offender; without it the queue only holds
+	/// zero-copy and txtime entries.
+	pub fn recv_err(&self) -> std::io::Result<Vec<QueuedError<D::Addr>>> {
+		self.error_queue().drain()
+	}
+}
+
+impl BoundDatagram<Ipv4> {
+	/// Sets IP_RECVERR, queueing ICMP and local errors about sent packets
+	/// for `recv_err()`.
+	pub fn set_recv_err(&self, enable: bool) -> std::io::Result<()> {
+		set_recv_err(self, enable)
+	}
+}
+
+impl BoundDatagram<Ipv6> {
+	/// Sets IPV6_RECVERR, queueing ICMP and local errors about sent packets
+	/// for `recv_err()`.
+	pub fn set_recv_err(&self, enable: bool) -> std::io::Result<()> {
+		set_recv_err_v6(self, enable)
+	}
+}
+
+impl ConnectedDatagram<Ipv4> {
+	/// Sets IP_RECVERR, queueing ICMP and local errors about sent packets
+	/// for `recv_err()`.
+	pub fn set_recv_err(&self, enable: bool) -> std::io::Result<()> {
+		set_recv_err(self, enable)
+	}
+}
+
+impl ConnectedDatagram<Ipv6> {
+	/// Sets IPV6_RECVERR, queueing ICMP and local errors about sent packets
+	/// for `recv_err()`.
+	pub fn set_recv_err(&self, enable: bool) -> std::io::Result<()> {
+		set_recv_err_v6(self, enable)
+	}
+}
+
+impl<D: Domain> ConnectedDatagram<D>
+where
+	D::Addr: FromSockAddr,
+{
+	/// Returns a reader for this socket's error queue.
+	pub fn error_queue(&self) -> ErrorQueue<'_, D> {
+		ErrorQueue::new(self)
+	}
+
+	/// Drains the error queue; see `BoundDatagram::recv_err()`.
+	pub fn recv_err(&self) -> std::io::Result<Vec<QueuedError<D::Addr>>> {
+		self.error_queue().drain()
+	}
+
+	/// Waits up to `timeout` for a datagram, telling an unreachable peer
+	/// apart from a slow one.
+	///
+	/// For request/response clients: `Timeout` means retry or give up,
+	/// `PeerGone` means the ICMP error already answered. `None` waits
+	/// forever. Enable `set_recv_err(true)` first to also catch
+	/// host/network unreachable and learn the offender; without it only
+	/// port unreachable is reported.
+	///
+	/// Other ICMP and local errors (e.g. EMSGSIZE) fail with
+	/// `IoError::Write`, since they concern an earlier send. Zero-copy
+	/// and txtime entries on the error queue are consumed and skipped.
+	pub fn recv_or_peer_gone(&self, buf: &mut [u8], timeout: Option<Duration>) -> std::io::Result<RecvOutcome<D::Addr>> {
+		let deadline = timeout.map(|t| Instant::now() + t);
+		loop {
+			let remaining = deadline.map(|d| d.saturating_duration_since(Instant::now()));
+			// POLLERR wakes this too.
+			if !poll_fd(self.as_raw_fd(), libc::POLLIN, remaining)? {
+				return Ok(RecvOutcome::Timeout);
+			}
+
+			// Read the queued details first; dequeuing also clears the
+			// pending error recv() would otherwise report bare.
+			while let Some(err) = self.error_queue().recv()? {
+				match err.event {
+					ErrorEvent::Icmp { errno, offender, .. } if is_peer_gone(errno) => {
+						return Ok(RecvOutcome::PeerGone { errno, offender });
+					}
+					ErrorEvent::Icmp { errno, .. } | ErrorEvent::Local { errno, .. } => {
+						return Err(IoError::Write { errno }.into());
+					}
+					_ => {}
+				}
+			}
+
+			let n = unsafe {
+				libc::recv(self.as_raw_fd(), buf.as_mut_ptr() as *mut libc::c_void, buf.len(), libc::MSG_DONTWAIT)
+			};
+			if n >= 0 {
+				return Ok(RecvOutcome::Data(n as usize));
+			}
+			match errno() {
+				libc::EAGAIN | libc::EINTR => continue,
+				e if is_peer_gone(e) => return Ok(RecvOutcome::PeerGone { errno: e, offender: None }),
+				e => return Err(IoError::Read { errno: e }.into()),
+			}
+		}
+	}
+}
+
+fn is_peer_gone(errno: i32) -> bool {
+	matches!(errno, libc::ECONNREFUSED | libc::EHOSTUNREACH | libc::ENETUNREACH | libc::EHOSTDOWN)
+}
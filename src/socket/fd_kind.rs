@@ -0,0 +1,41 @@
+use std::os::fd::BorrowedFd;
+use crate::error::SocketError;
+use super::options::get_int;
+
+/// What the kernel says an fd is: family, type, and whether it listens.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct FdKind {
+	pub domain: libc::c_int,
+	pub ty: libc::c_int,
+	pub listening: bool,
+}
+
+impl FdKind {
+	/// Queries SO_DOMAIN, SO_TYPE and SO_ACCEPTCONN.
+	///
+	/// Fails with ENOTSOCK for fds that aren't sockets.
+	pub(crate) fn of(fd: BorrowedFd<'_>) -> std::io::Result<Self> {
+		Ok(Self {
+			domain: get_int(&fd, libc::SOL_SOCKET, libc::SO_DOMAIN, "SO_DOMAIN")?,
+			ty: get_int(&fd, libc::SOL_SOCKET, libc::SO_TYPE, "SO_TYPE")?,
+			listening: get_int(&fd, libc::SOL_SOCKET, libc::SO_ACCEPTCONN, "SO_ACCEPTCONN")? != 0,
+		})
+	}
+
+	/// Checks the fd against an expected family/type/listening state.
+	///
+	/// `expected` names the target type in the error, e.g. "a TCP/IPv4 listener".
+	pub(crate) fn expect(
+		fd: BorrowedFd<'_>,
+		domain: libc::c_int,
+		ty: libc::c_int,
+		listening: bool,
+		expected: &'static str,
+	) -> std::io::Result<()> {
+		let kind = Self::of(fd)?;
+		if kind.domain != domain || kind.ty != ty || kind.listening != listening {
+			return Err(SocketError::WrongSocketKind { expected }.into());
+		}
+		Ok(())
+	}
+}
@@ -0,0 +1,156 @@
+use std::sync::atomic::{AtomicU16, Ordering};
+use io_uring::types::BufRingEntry;
+use crate::error::{SocketError, errno};
+use super::Completion;
+
+/// Most buffers one provided-buffer ring can hold.
+pub const MAX_FIXED_RECV_BUFFERS: u16 = 1 << 15;
+
+/// Receive buffers the kernel picks from itself (io_uring provided-buffer ring).
+///
+/// Register with `Ring::register_recv_pool()` under a group id, then
+/// queue `Ring::recv_fixed()`: the kernel takes a free buffer only when a
+/// datagram arrives and reports its id in `Completion::buffer_id()`.
+/// Read it with `data()` and hand it back with `recycle()` — no
+/// per-receive checkout, and idle sockets pin no memory.
+///
+/// When every buffer is out, `recv_fixed()` completes with ENOBUFS.
+/// Needs Linux 5.19+.
+pub struct FixedRecvPool {
+	/// Page-aligned ring of `count` entries shared with the kernel.
+	ring: *mut BufRingEntry,
+	ring_len: usize,
+	buffers: Box<[u8]>,
+	buf_size: usize,
+	count: u16,
+	/// Our copy of the tail; the kernel reads the shared one.
+	tail: u16,
+	/// Buffers handed to us by a completion and not yet recycled.
+	lent: Vec<bool>,
+}
+
+// The raw ring pointer is owned exclusively by the pool.
+unsafe impl Send for FixedRecvPool {}
+
+impl FixedRecvPool {
+	/// Allocates `count` buffers of `buf_size` bytes.
+	///
+	/// `count` must be a power of two up to `MAX_FIXED_RECV_BUFFERS`.
+	/// Size buffers for the largest datagram; longer ones are truncated.
+	pub fn new(count: u16, buf_size: u32) -> std::io::Result<Self> {
+		if !count.is_power_of_two() || count > MAX_FIXED_RECV_BUFFERS || buf_size == 0 {
+			return Err(SocketError::InvalidInput { reason: "fixed recv pool count must be a power of two up to 32768" }.into());
+		}
+
+		let ring_len = count as usize * std::mem::size_of::<BufRingEntry>();
+		let ptr = unsafe {
+			libc::mmap(
+				std::ptr::null_mut(),
+				ring_len,
+				libc::PROT_READ | libc::PROT_WRITE,
+				libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+				-1,
+				0,
+			)
+		};
+		if ptr == libc::MAP_FAILED {
+			return Err(SocketError::Mmap { errno: errno(), region: "provided buffer ring" }.into());
+		}
+
+		let mut pool = Self {
+			ring: ptr as *mut BufRingEntry,
+			ring_len,
+			buffers: vec![0u8; count as usize * buf_size as usize].into_boxed_slice(),
+			buf_size: buf_size as usize,
+			count,
+			tail: 0,
+			lent: vec![false; count as usize],
+		};
+		for bid in 0..count {
+			pool.push(bid);
+		}
+		pool.publish();
+		Ok(pool)
+	}
+
+	/// Number of buffers.
+	pub fn count(&self) -> u16 {
+		self.count
+	}
+
+	/// Size of each buffer.
+	pub fn buf_size(&self) -> usize {
+		self.buf_size
+	}
+
+	/// The bytes a `recv_fixed()` completion received.
+	///
+	/// `None` if the completion failed or carries no buffer from this
+	/// pool, or the buffer was already recycled.
+	pub fn data(&self, completion: &Completion) -> Option<&[u8]> {
+		let bid = completion.buffer_id()?;
+		let len = completion.result().ok()?;
+		if !*self.lent.get(bid as usize)? {
+			return None;
+		}
+		let start = bid as usize * self.buf_size;
+		Some(&self.buffers[start..start + len.min(self.buf_size)])
+	}
+
+	/// Returns buffer `bid` to the kernel.
+	///
+	/// Returns false (and does nothing) if the buffer isn't currently
+	/// lent out, so a double recycle can't hand the kernel a buffer twice.
+	pub fn recycle(&mut self, bid: u16) -> bool {
+		match self.lent.get_mut(bid as usize) {
+			Some(lent) if *lent => *lent = false,
+			_ => return false,
+		}
+		self.push(bid);
+		self.publish();
+		true
+	}
+
+	/// Base address and entry count, for IORING_REGISTER_PBUF_RING.
+	pub(crate) fn ring_addr(&self) -> (u64, u16) {
+		(self.ring as u64, self.count)
+	}
+
+	/// Marks `bid` as handed to userspace by a completion.
+	pub(crate) fn lend(&mut self, bid: u16) {
+		if let Some(lent) = self.lent.get_mut(bid as usize) {
+			*lent = true;
+		}
+	}
+
+	/// Writes buffer `bid` into the next ring slot (not yet visible to the kernel).
+	fn push(&mut self, bid: u16) {
+		let slot = (self.tail & (self.count - 1)) as usize;
+		let entry = unsafe { &mut *self.ring.add(slot) };
+		entry.set_addr(self.buffers.as_ptr() as u64 + (bid as usize * self.buf_size) as u64);
+		entry.set_len(self.buf_size as u32);
+		entry.set_bid(bid);
+		self.tail = self.tail.wrapping_add(1);
+	}
+
+	/// Makes pushed entries visible to the kernel.
+	fn publish(&self) {
+		let tail = unsafe { BufRingEntry::tail(self.ring) } as *const AtomicU16;
+		unsafe { (*tail).store(self.tail, Ordering::Release) };
+	}
+}
+
+impl Drop for FixedRecvPool {
+	fn drop(&mut self) {
+		unsafe { libc::munmap(self.ring as *mut libc::c_void, self.ring_len) };
+	}
+}
+
+/*
+Why track lent buffers instead of trusting the caller?
+A buffer id recycled twice sits in the ring twice; the kernel can then
+fill it for one receive while userspace still reads it from another.
+That's a data race on memory we hand out as &[u8], so recycle() and
+data() check the lent bit rather than making both unsafe. The bit is
+set when Ring::completions() sees the buffer id, one store per receive.
+*/
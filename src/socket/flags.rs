@@ -0,0 +1,129 @@
+use std::ops::{BitOr, BitOrAssign};
+
+/// MSG_* flags for `send_with_flags()` and `send_to_with_flags()`.
+///
+/// Combine with `|`: `SendFlags::MORE | SendFlags::DONTWAIT`.
+/// Flags not listed here go through `SendFlags::from_raw()`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct SendFlags(i32);
+
+impl SendFlags {
+	/// No flags.
+	pub const NONE: SendFlags = SendFlags(0);
+	/// Don't block; fail with `WouldBlock` instead.
+	pub const DONTWAIT: SendFlags = SendFlags(libc::MSG_DONTWAIT);
+	/// More data follows: hold the segment back (like TCP_CORK, per call).
+	pub const MORE: SendFlags = SendFlags(libc::MSG_MORE);
+	/// Don't raise SIGPIPE on a closed connection; fail with EPIPE.
+	/// Stream and datagram sends add it already, unless
+	/// `ConnectedStream::set_sigpipe(true)`.
+	pub const NOSIGNAL: SendFlags = SendFlags(libc::MSG_NOSIGNAL);
+	/// Send as out-of-band (TCP urgent) data.
+	pub const OOB: SendFlags = SendFlags(libc::MSG_OOB);
+	/// Bypass routing; only send to directly connected hosts.
+	pub const DONTROUTE: SendFlags = SendFlags(libc::MSG_DONTROUTE);
+	/// Ends a record (SOCK_SEQPACKET).
+	pub const EOR: SendFlags = SendFlags(libc::MSG_EOR);
+	/// Tell the link layer the neighbor is reachable (skips ARP probing).
+	pub const CONFIRM: SendFlags = SendFlags(libc::MSG_CONFIRM);
+	/// Zero-copy send; needs SO_ZEROCOPY. Completions arrive on the error queue.
+	pub const ZEROCOPY: SendFlags = SendFlags(libc::MSG_ZEROCOPY);
+
+	/// Wraps raw MSG_* bits, for flags without a constant here.
+	///
+	/// The kernel rejects unknown bits with EINVAL or EOPNOTSUPP.
+	pub const fn from_raw(bits: i32) -> SendFlags {
+		SendFlags(bits)
+	}
+
+	/// Returns the raw MSG_* bits.
+	pub const fn bits(self) -> i32 {
+		self.0
+	}
+
+	/// True if every flag in `other` is set.
+	pub const fn contains(self, other: SendFlags) -> bool {
+		self.0 & other.0 == other.0
+	}
+}
+
+impl BitOr for SendFlags {
+	type Output = SendFlags;
+
+	fn bitor(self, rhs: SendFlags) -> SendFlags {
+		SendFlags(self.0 | rhs.0)
+	}
+}
+
+impl BitOrAssign for SendFlags {
+	fn bitor_assign(&mut self, rhs: SendFlags) {
+		self.0 |= rhs.0;
+	}
+}
+
+/// MSG_* flags for `recv_with_flags()` and `recv_from_with_flags()`.
+///
+/// Combine with `|`: `RecvFlags::PEEK | RecvFlags::TRUNC`.
+/// Flags not listed here go through `RecvFlags::from_raw()`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct RecvFlags(i32);
+
+impl RecvFlags {
+	/// No flags.
+	pub const NONE: RecvFlags = RecvFlags(0);
+	/// Don't block; fail with `WouldBlock` instead.
+	pub const DONTWAIT: RecvFlags = RecvFlags(libc::MSG_DONTWAIT);
+	/// Return data without removing it from the queue.
+	pub const PEEK: RecvFlags = RecvFlags(libc::MSG_PEEK);
+	/// Return the full datagram length even if `buf` was shorter.
+	///
+	/// On streams, discards the bytes instead of copying them.
+	pub const TRUNC: RecvFlags = RecvFlags(libc::MSG_TRUNC);
+	/// Block until `buf` is full (streams), unless a signal or error stops it.
+	pub const WAITALL: RecvFlags = RecvFlags(libc::MSG_WAITALL);
+	/// Receive out-of-band (TCP urgent) data.
+	pub const OOB: RecvFlags = RecvFlags(libc::MSG_OOB);
+	/// Read from the socket error queue (see `ErrorQueue`).
+	pub const ERRQUEUE: RecvFlags = RecvFlags(libc::MSG_ERRQUEUE);
+	/// recvmmsg(): block for the first message only.
+	pub const WAITFORONE: RecvFlags = RecvFlags(libc::MSG_WAITFORONE);
+
+	/// Wraps raw MSG_* bits, for flags without a constant here.
+	///
+	/// The kernel rejects unknown bits with EINVAL or EOPNOTSUPP.
+	pub const fn from_raw(bits: i32) -> RecvFlags {
+		RecvFlags(bits)
+	}
+
+	/// Returns the raw MSG_* bits.
+	pub const fn bits(self) -> i32 {
+		self.0
+	}
+
+	/// True if every flag in `other` is set.
+	pub const fn contains(self, other: RecvFlags) -> bool {
+		self.0 & other.0 == other.0
+	}
+}
+
+impl BitOr for RecvFlags {
+	type Output = RecvFlags;
+
+	fn bitor(self, rhs: RecvFlags) -> RecvFlags {
+		RecvFlags(self.0 | rhs.0)
+	}
+}
+
+impl BitOrAssign for RecvFlags {
+	fn bitor_assign(&mut self, rhs: RecvFlags) {
+		self.0 |= rhs.0;
+	}
+}
+
+/*
+Why two types instead of one `MsgFlags`?
+Several MSG_* values mean different things, or nothing, depending on
+direction: MSG_TRUNC is an input flag only for recv, MSG_MORE only for
+send. Separate types make `send_with_flags(buf, RecvFlags::PEEK)` a
+compile error instead of a silently ignored bit.
+*/
@@ -0,0 +1,129 @@
+use std::time::{Duration, Instant};
+use crate::addr::Unix;
+use crate::error::{SocketError, IoError, errno};
+use super::stream::ConnectedStream;
+
+/// Periodic liveness check for long-lived Unix socket connections.
+///
+/// TCP keep-alive does not exist for AF_UNIX, so a peer that hangs or
+/// dies without closing (e.g. fd inherited by a stuck child) goes unnoticed
+/// until the next write. This runs a cheap probe at most once per interval.
+///
+/// # Example
+/// ```ignore
+/// let mut health = UnixHealthCheck::new(Duration::from_secs(30));
+///
+/// // In the connection's event loop:
+/// if let Some(PeerHealth::Gone) = health.poll(&conn)? {
+///     return Ok(()); // drop the connection
+/// }
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct UnixHealthCheck {
+	interval: Duration,
+	probe: HealthProbe,
+	last_check: Option<Instant>,
+}
+
+/// How `UnixHealthCheck` decides whether the peer is alive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HealthProbe {
+	/// Reads the peer pid (SO_PEERCRED) and checks it with `kill(pid, 0)`.
+	///
+	/// No traffic is generated. The pid is the one recorded at connect time,
+	/// so this can't detect a peer that handed the fd to another process.
+	PeerPid,
+
+	/// Sends a zero-byte message.
+	///
+	/// Fails with EPIPE once the peer has closed its end.
+	/// The peer never sees any data.
+	ZeroByteWrite,
+}
+
+/// Result of a health probe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PeerHealth {
+	/// The peer appears alive.
+	Alive,
+
+	/// The peer process exited or closed its end of the socket.
+	Gone,
+}
+
+impl UnixHealthCheck {
+	/// Creates a check that probes at most once per `interval`.
+	///
+	/// Uses `HealthProbe::PeerPid` by default.
+	pub fn new(interval: Duration) -> Self {
+		Self {
+			interval,
+			probe: HealthProbe::PeerPid,
+			last_check: None,
+		}
+	}
+
+	/// Sets the probe strategy.
+	pub fn probe(mut self, probe: HealthProbe) -> Self {
+		self.probe = probe;
+		self
+	}
+
+	/// Runs the probe if the interval has elapsed since the last one.
+	///
+	/// Returns `None` if it is not yet time to check.
+	pub fn poll(&mut self, stream: &ConnectedStream<Unix>) -> std::io::Result<Option<PeerHealth>> {
+		let now = Instant::now();
+		if let Some(last) = self.last_check
+			&& now.duration_since(last) < self.interval
+		{
+			return Ok(None);
+		}
+		self.last_check = Some(now);
+		self.check(stream).map(Some)
+	}
+
+	/// Runs the probe immediately, ignoring the interval.
+	pub fn check(&self, stream: &ConnectedStream<Unix>) -> std::io::Result<PeerHealth> {
+		match self.probe {
+			HealthProbe::PeerPid => probe_peer_pid(stream),
+			HealthProbe::ZeroByteWrite => probe_zero_write(stream),
+		}
+	}
+}
+
+fn probe_peer_pid(stream: &ConnectedStream<Unix>) -> std::io::Result<PeerHealth> {
+	let cred = stream.peer_credentials()?;
+
+	// Signal 0 performs the existence/permission check without sending anything.
+	if unsafe { libc::kill(cred.pid, 0) } == 0 {
+		return Ok(PeerHealth::Alive);
+	}
+
+	match errno() {
+		libc::ESRCH => Ok(PeerHealth::Gone),
+		libc::EPERM => Ok(PeerHealth::Alive),  // Exists, owned by another user
+		e => Err(SocketError::GetOption { errno: e, option: "kill(pid, 0)" }.into()),
+	}
+}
+
+fn probe_zero_write(stream: &ConnectedStream<Unix>) -> std::io::Result<PeerHealth> {
+	let n = unsafe {
+		libc::send(
+			stream.as_raw_fd(),
+			std::ptr::null(),
+			0,
+			libc::MSG_NOSIGNAL | libc::MSG_DONTWAIT,
+		)
+	};
+
+	if n != -1 {
+		return Ok(PeerHealth::Alive);
+	}
+
+	match errno() {
+		libc::EPIPE | libc::ECONNRESET | libc::ENOTCONN => Ok(PeerHealth::Gone),
+		libc::EAGAIN => Ok(PeerHealth::Alive),  // Send buffer full, peer still attached
+		e => Err(IoError::Write { errno: e }.into()),
+	}
+}
@@ -0,0 +1,90 @@
+use std::os::fd::{AsFd, AsRawFd, OwnedFd};
+use crate::addr::{Ipv4, Ipv6, Unix};
+use crate::error::SocketError;
+use super::fd_kind::FdKind;
+use super::{Listener, ConnectedStream, BoundDatagram, ConnectedDatagram, SeqPacketListener, ConnectedSeqPacket};
+
+/// A socket of a type known only at runtime, e.g. inherited from systemd.
+///
+/// Built by `Socket::try_from_fd()`, which asks the kernel what the fd
+/// is; match on it to get the typed socket.
+pub enum Socket {
+	Ipv4Listener(Listener<Ipv4>),
+	Ipv6Listener(Listener<Ipv6>),
+	UnixListener(Listener<Unix>),
+	Ipv4Stream(ConnectedStream<Ipv4>),
+	Ipv6Stream(ConnectedStream<Ipv6>),
+	UnixStream(ConnectedStream<Unix>),
+	Ipv4Datagram(BoundDatagram<Ipv4>),
+	Ipv6Datagram(BoundDatagram<Ipv6>),
+	UnixDatagram(BoundDatagram<Unix>),
+	Ipv4ConnectedDatagram(ConnectedDatagram<Ipv4>),
+	Ipv6ConnectedDatagram(ConnectedDatagram<Ipv6>),
+	UnixConnectedDatagram(ConnectedDatagram<Unix>),
+	UnixSeqPacketListener(SeqPacketListener<Unix>),
+	UnixSeqPacket(ConnectedSeqPacket<Unix>),
+}
+
+impl Socket {
+	/// Wraps a foreign fd in the typestate the kernel reports for it.
+	///
+	/// Family and type come from SO_DOMAIN/SO_TYPE, listeners from
+	/// SO_ACCEPTCONN, and connectedness from getpeername(). Stream and
+	/// seqpacket sockets must be listening or connected; datagram sockets
+	/// are `*ConnectedDatagram` if they have a peer, `*Datagram` otherwise.
+	///
+	/// Fails with ENOTSOCK for non-sockets and `WrongSocketKind` for
+	/// anything else wirelane has no type for (raw, netlink, ...). The fd
+	/// is closed on error.
+	pub fn try_from_fd(fd: OwnedFd) -> std::io::Result<Self> {
+		let kind = FdKind::of(fd.as_fd())?;
+		let connected = has_peer(&fd);
+
+		let socket = match (kind.domain, kind.ty, kind.listening, connected) {
+			(libc::AF_INET, libc::SOCK_STREAM, true, _) => Self::Ipv4Listener(Listener::from_fd(fd)),
+			(libc::AF_INET6, libc::SOCK_STREAM, true, _) => Self::Ipv6Listener(Listener::from_fd(fd)),
+			(libc::AF_UNIX, libc::SOCK_STREAM, true, _) => Self::UnixListener(Listener::from_fd(fd)),
+			(libc::AF_INET, libc::SOCK_STREAM, false, true) => Self::Ipv4Stream(ConnectedStream::from_fd(fd)),
+			(libc::AF_INET6, libc::SOCK_STREAM, false, true) => Self::Ipv6Stream(ConnectedStream::from_fd(fd)),
+			(libc::AF_UNIX, libc::SOCK_STREAM, false, true) => Self::UnixStream(ConnectedStream::from_fd(fd)),
+			(libc::AF_INET, libc::SOCK_DGRAM, _, false) => Self::Ipv4Datagram(BoundDatagram::from_fd(fd)),
+			(libc::AF_INET6, libc::SOCK_DGRAM, _, false) => Self::Ipv6Datagram(BoundDatagram::from_fd(fd)),
+			(libc::AF_UNIX, libc::SOCK_DGRAM, _, false) => Self::UnixDatagram(BoundDatagram::from_fd(fd)),
+			(libc::AF_INET, libc::SOCK_DGRAM, _, true) => Self::Ipv4ConnectedDatagram(ConnectedDatagram::from_fd(fd)),
+			(libc::AF_INET6, libc::SOCK_DGRAM, _, true) => Self::Ipv6ConnectedDatagram(ConnectedDatagram::from_fd(fd)),
+			(libc::AF_UNIX, libc::SOCK_DGRAM, _, true) => Self::UnixConnectedDatagram(ConnectedDatagram::from_fd(fd)),
+			(libc::AF_UNIX, libc::SOCK_SEQPACKET, true, _) => Self::UnixSeqPacketListener(SeqPacketListener::from_fd(fd)),
+			(libc::AF_UNIX, libc::SOCK_SEQPACKET, false, true) => Self::UnixSeqPacket(ConnectedSeqPacket::from_fd(fd)),
+			(_, libc::SOCK_STREAM | libc::SOCK_SEQPACKET, false, false)
+				if matches!(kind.domain, libc::AF_INET | libc::AF_INET6 | libc::AF_UNIX) =>
+			{
+				return Err(SocketError::WrongSocketKind { expected: "listening or connected" }.into());
+			}
+			_ => {
+				return Err(SocketError::WrongSocketKind {
+					expected: "an IPv4/IPv6/Unix stream, datagram or Unix seqpacket socket",
+				}.into());
+			}
+		};
+		Ok(socket)
+	}
+}
+
+/// True if getpeername() succeeds, i.e. the socket has a peer.
+fn has_peer(fd: &OwnedFd) -> bool {
+	let mut storage: libc::sockaddr_storage = unsafe { std::mem::zeroed() };
+	let mut len = std::mem::size_of::<libc::sockaddr_storage>() as libc::socklen_t;
+	let result = unsafe {
+		libc::getpeername(fd.as_raw_fd(), &mut storage as *mut _ as *mut libc::sockaddr, &mut len)
+	};
+	result == 0
+}
+
+/*
+Why an enum and not `try_from_fd::<T>()`?
+An inherited fd's type is decided by whoever created it — a systemd unit
+file, an inetd config — not by the code receiving it. Returning what the
+kernel says lets the caller match on it and report a useful mismatch.
+When the type is known in advance, TryFrom<TcpListener> and friends
+validate against that one type instead.
+*/
@@ -0,0 +1,155 @@
+use std::mem::ManuallyDrop;
+
+/// Per-socket I/O counters.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SocketStats {
+	pub bytes_read: u64,
+	pub bytes_written: u64,
+	pub reads: u64,           // Successful read/recv calls (including EOF)
+	pub writes: u64,          // Successful write/send calls
+	pub errors: u64,          // Failed calls, excluding WouldBlock/Interrupted
+}
+
+/// Callback receiving the final counters when an `Instrumented` is dropped.
+type DropCallback = Box<dyn FnOnce(&SocketStats) + Send>;
+
+/// Wraps a socket and counts bytes, operations, and errors.
+///
+/// The summary is handed to the `on_drop` callback when the wrapper is
+/// dropped, giving per-connection accounting without a metrics pipeline.
+///
+/// `Read`/`Write` are forwarded and counted automatically. For methods
+/// outside those traits (`recv_from`, `send_to`, ...), use
+/// `track_read()`/`track_write()`.
+///
+/// # Example
+/// ```ignore
+/// let mut conn = Instrumented::new(listener.accept()?)
+///     .on_drop(|stats| println!("closed: {} in, {} out", stats.bytes_read, stats.bytes_written));
+///
+/// let n = conn.track_read(|s| s.recv_with_flags(&mut buf, RecvFlags::NONE))?;
+/// ```
+pub struct Instrumented<T> {
+	inner: T,
+	stats: SocketStats,
+	on_drop: Option<DropCallback>,
+}
+
+impl<T> Instrumented<T> {
+	/// Wraps `inner` with zeroed counters and no callback.
+	pub fn new(inner: T) -> Self {
+		Self {
+			inner,
+			stats: SocketStats::default(),
+			on_drop: None,
+		}
+	}
+
+	/// Sets the callback invoked with the final stats on drop.
+	pub fn on_drop<F>(mut self, callback: F) -> Self
+	where
+		F: FnOnce(&SocketStats) + Send + 'static,
+	{
+		self.on_drop = Some(Box::new(callback));
+		self
+	}
+
+	/// Returns the counters so far.
+	pub fn stats(&self) -> &SocketStats {
+		&self.stats
+	}
+
+	/// Returns a reference to the wrapped socket.
+	///
+	/// I/O done directly through this reference is not counted.
+	pub fn get_ref(&self) -> &T {
+		&self.inner
+	}
+
+	/// Unwraps the socket and returns it with the final stats.
+	///
+	/// The `on_drop` callback is not invoked.
+	pub fn into_inner(self) -> (T, SocketStats) {
+		let mut this = ManuallyDrop::new(self);
+		// Take the fields out without running our Drop impl.
+		let inner = unsafe { std::ptr::read(&this.inner) };
+		unsafe { std::ptr::drop_in_place(&mut this.on_drop) };
+		(inner, this.stats)
+	}
+
+	/// Runs a receive-side operation on the socket and counts its result.
+	pub fn track_read<F>(&mut self, op: F) -> std::io::Result<usize>
+	where
+		F: FnOnce(&mut T) -> std::io::Result<usize>,
+	{
+		let result = op(&mut self.inner);
+		match &result {
+			Ok(n) => {
+				self.stats.reads += 1;
+				self.stats.bytes_read += *n as u64;
+			}
+			Err(e) => self.count_error(e),
+		}
+		result
+	}
+
+	/// Runs a send-side operation on the socket and counts its result.
+	pub fn track_write<F>(&mut self, op: F) -> std::io::Result<usize>
+	where
+		F: FnOnce(&mut T) -> std::io::Result<usize>,
+	{
+		let result = op(&mut self.inner);
+		match &result {
+			Ok(n) => {
+				self.stats.writes += 1;
+				self.stats.bytes_written += *n as u64;
+			}
+			Err(e) => self.count_error(e),
+		}
+		result
+	}
+
+	fn count_error(&mut self, err: &std::io::Error) {
+		// Not failures: the caller is expected to retry.
+		match err.kind() {
+			std::io::ErrorKind::WouldBlock | std::io::ErrorKind::Interrupted => {}
+			_ => self.stats.errors += 1,
+		}
+	}
+}
+
+impl<T> Drop for Instrumented<T> {
+	fn drop(&mut self) {
+		if let Some(callback) = self.on_drop.take() {
+			callback(&self.stats);
+		}
+	}
+}
+
+impl<T: std::io::Read> std::io::Read for Instrumented<T> {
+	fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+		self.track_read(|inner| inner.read(buf))
+	}
+}
+
+impl<T: std::io::Write> std::io::Write for Instrumented<T> {
+	fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+		self.track_write(|inner| inner.write(buf))
+	}
+
+	fn flush(&mut self) -> std::io::Result<()> {
+		self.inner.flush()
+	}
+}
+
+impl<T: std::os::fd::AsRawFd> std::os::fd::AsRawFd for Instrumented<T> {
+	fn as_raw_fd(&self) -> std::os::fd::RawFd {
+		self.inner.as_raw_fd()
+	}
+}
+
+impl<T: std::os::fd::AsFd> std::os::fd::AsFd for Instrumented<T> {
+	fn as_fd(&self) -> std::os::fd::BorrowedFd<'_> {
+		self.inner.as_fd()
+	}
+}
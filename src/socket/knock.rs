@@ -0,0 +1,172 @@
+use crate::addr::{Domain, ToSockAddr, FromSockAddr};
+use crate::error::{SocketError, IoError, errno};
+use super::BoundDatagram;
+
+// From asm-generic/socket.h; not exported by libc on every target.
+const SO_ATTACH_FILTER: libc::c_int = 26;
+const SO_LOCK_FILTER: libc::c_int = 44;
+
+/// UDP header length: socket filters see the datagram from its UDP header.
+const UDP_HDR_LEN: u32 = 8;
+
+/// Longest accepted magic prefix.
+pub const MAX_KNOCK_PREFIX: usize = 64;
+
+/// A UDP socket that only receives datagrams starting with a magic prefix.
+///
+/// Created by `DatagramBuilder::knock_listener()`. A classic BPF filter
+/// drops everything else in the kernel, before it's queued or wakes the
+/// process — scans and floods cost no syscalls. Meant as single-packet
+/// authorization in front of a real service: verify the rest of the
+/// knock (HMAC, timestamp) and then open the door.
+///
+/// The prefix alone is not authentication; anyone who sees one knock
+/// can replay it.
+pub struct KnockListener<D: Domain> {
+	socket: BoundDatagram<D>,
+	prefix: Vec<u8>,
+}
+
+impl<D: Domain> KnockListener<D> {
+	pub(crate) fn new(socket: BoundDatagram<D>, prefix: &[u8]) -> Self {
+		Self { socket, prefix: prefix.to_vec() }
+	}
+
+	/// Receives the next matching datagram, without its prefix.
+	///
+	/// Returns the payload length (the bytes after the prefix, moved to
+	/// the start of `buf`) and the sender. `buf` needs room for the
+	/// prefix too. Anything queued before the filter was attached is
+	/// checked again here and skipped if it doesn't match.
+	pub fn recv_authorized(&self, buf: &mut [u8]) -> std::io::Result<(usize, D::Addr)>
+	where
+		D::Addr: ToSockAddr + FromSockAddr,
+	{
+		if buf.len() < self.prefix.len() {
+			return Err(IoError::FrameTooLarge { len: self.prefix.len(), max: buf.len() }.into());
+		}
+		loop {
+			let (n, addr) = self.socket.recv_from(buf)?;
+			if n >= self.prefix.len() && buf[..self.prefix.len()] == self.prefix[..] {
+				buf.copy_within(self.prefix.len()..n, 0);
+				return Ok((n - self.prefix.len(), addr));
+			}
+		}
+	}
+
+	/// The magic prefix datagrams must start with.
+	pub fn prefix(&self) -> &[u8] {
+		&self.prefix
+	}
+
+	/// The underlying socket, e.g. to reply to a knock.
+	pub fn get_ref(&self) -> &BoundDatagram<D> {
+		&self.socket
+	}
+
+	/// Returns the socket. The filter stays attached.
+	pub fn into_inner(self) -> BoundDatagram<D> {
+		self.socket
+	}
+}
+
+impl<D: Domain> std::os::fd::AsRawFd for KnockListener<D> {
+	fn as_raw_fd(&self) -> std::os::fd::RawFd {
+		self.socket.as_raw_fd()
+	}
+}
+
+impl<D: Domain> std::os::fd::AsFd for KnockListener<D> {
+	fn as_fd(&self) -> std::os::fd::BorrowedFd<'_> {
+		self.socket.as_fd()
+	}
+}
+
+/// Attaches and locks a filter accepting only UDP payloads that start with `prefix`.
+///
+/// Attach before bind(), so no unfiltered datagram is ever queued.
+pub(crate) fn attach_prefix_filter<S: std::os::fd::AsRawFd>(socket: &S, prefix: &[u8]) -> std::io::Result<()> {
+	if prefix.is_empty() || prefix.len() > MAX_KNOCK_PREFIX {
+		return Err(SocketError::InvalidInput { reason: "knock prefix must be 1-64 bytes" }.into());
+	}
+
+	let mut program = prefix_program(prefix);
+	let fprog = libc::sock_fprog {
+		len: program.len() as libc::c_ushort,
+		filter: program.as_mut_ptr(),
+	};
+	let result = unsafe {
+		libc::setsockopt(
+			socket.as_raw_fd(),
+			libc::SOL_SOCKET,
+			SO_ATTACH_FILTER,
+			&fprog as *const _ as *const libc::c_void,
+			std::mem::size_of::<libc::sock_fprog>() as libc::socklen_t,
+		)
+	};
+	if result == -1 {
+		return Err(SocketError::SetOption { errno: errno(), option: "SO_ATTACH_FILTER" }.into());
+	}
+
+	// Nobody holding the fd can swap the filter for a weaker one later.
+	let lock: libc::c_int = 1;
+	let result = unsafe {
+		libc::setsockopt(
+			socket.as_raw_fd(),
+			libc::SOL_SOCKET,
+			SO_LOCK_FILTER,
+			&lock as *const _ as *const libc::c_void,
+			std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+		)
+	};
+	if result == -1 {
+		return Err(SocketError::SetOption { errno: errno(), option: "SO_LOCK_FILTER" }.into());
+	}
+	Ok(())
+}
+
+/// Compares the prefix 4, 2 or 1 bytes at a time; any mismatch, or a
+/// load past the end of a short packet, returns 0 (drop).
+fn prefix_program(prefix: &[u8]) -> Vec<libc::sock_filter> {
+	let stmt = |code: u32, k: u32| libc::sock_filter { code: code as u16, jt: 0, jf: 0, k };
+
+	let mut chunks = Vec::new();
+	let mut offset = 0;
+	while offset < prefix.len() {
+		let (size, width) = match prefix.len() - offset {
+			4.. => (libc::BPF_W, 4),
+			2..=3 => (libc::BPF_H, 2),
+			_ => (libc::BPF_B, 1),
+		};
+		let value = prefix[offset..offset + width].iter().fold(0u32, |acc, &b| (acc << 8) | b as u32);
+		chunks.push((size, offset as u32, value));
+		offset += width;
+	}
+
+	// Two instructions per chunk, then accept, then drop.
+	let drop_at = chunks.len() * 2 + 1;
+	let mut program = Vec::with_capacity(drop_at + 1);
+	for (size, offset, value) in chunks {
+		program.push(stmt(libc::BPF_LD | size | libc::BPF_ABS, UDP_HDR_LEN + offset));
+		let jump_from = program.len() + 1;
+		program.push(libc::sock_filter {
+			code: (libc::BPF_JMP | libc::BPF_JEQ | libc::BPF_K) as u16,
+			jt: 0,
+			jf: (drop_at - jump_from) as u8,
+			k: value,
+		});
+	}
+	program.push(stmt(libc::BPF_RET | libc::BPF_K, u32::MAX));
+	program.push(stmt(libc::BPF_RET | libc::BPF_K, 0));
+	program
+}
+
+/*
+Why classic BPF on the socket instead of checking in userspace?
+A userspace check still pays a wakeup and a recvfrom() per junk
+datagram, and junk fills the receive buffer so real knocks get dropped
+under a flood. The socket filter runs in softirq context before the
+datagram is queued; non-matching packets never reach the socket at all.
+The userspace re-check in recv_authorized() only covers the window
+between socket creation and attach, which builder ordering closes anyway.
+*/
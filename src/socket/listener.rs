@@ -2,6 +2,8 @@ use crate::addr::{Domain, FromSockAddr};
 use crate::socket::{Stream, bound::BoundSocket};
 use crate::error::{SocketError, errno};
 use super::stream::ConnectedStream;
+use super::unix_path::SocketPathGuard;
+use super::mode::{Mode, BlockingMode, NonBlockingMode, Dynamic, Blocking, NonBlocking, set_fd_nonblocking};
 use std::{marker::PhantomData, os::fd::OwnedFd};
 
 
@@ -30,21 +32,49 @@ use std::{marker::PhantomData, os::fd::OwnedFd};
 ///
 /// The methods below expose *different semantic guarantees* on top of
 /// that kernel behavior.
-pub struct Listener<D: Domain> {
+///
+/// With the default mode `M = Dynamic` the flag is a runtime property.
+/// `into_blocking()` / `into_nonblocking()` move it into the type:
+/// `Listener<D, Blocking>` only offers `accept()`, and
+/// `Listener<D, NonBlocking>` only `accept_nonblocking()`.
+pub struct Listener<D: Domain, M: Mode = Dynamic> {
     fd: OwnedFd,
-    _marker: PhantomData<D>,
+    // Declared after `fd`: the socket closes before its file is removed.
+    unlink: Option<SocketPathGuard>,
+    /// The backlog passed to listen(), before the kernel's cap.
+    backlog: Option<u32>,
+    _marker: PhantomData<(D, M)>,
 }
 
-impl<D: Domain> Listener<D> {
+impl<D: Domain, M: Mode> Listener<D, M> {
     /// Creates a Listener from an OwnedFd.
     ///
     /// Internal use only — called by BoundSocket::listen()
     pub(crate) fn from_fd(fd: OwnedFd) -> Self {
         Self {
             fd,
+            unlink: None,
+            backlog: None,
             _marker: PhantomData,
         }
     }
+
+    /// Attaches a guard that removes the socket file when the listener drops.
+    pub(crate) fn with_unlink_guard(mut self, guard: SocketPathGuard) -> Self {
+        self.unlink = Some(guard);
+        self
+    }
+
+    /// The accept queue limit in effect: the `listen()` backlog, capped
+    /// at `net.core.somaxconn` the way the kernel caps it.
+    ///
+    /// `None` for listeners adopted from an fd, or if somaxconn couldn't
+    /// be read. Reads /proc on each call; for TCP the kernel's own value
+    /// is in `stats().max_backlog`.
+    pub fn backlog(&self) -> Option<u32> {
+        let requested = self.backlog?;
+        super::somaxconn().map(|max| requested.min(max))
+    }
     
     /// Returns the raw file descriptor.
     #[inline]
@@ -52,6 +82,64 @@ impl<D: Domain> Listener<D> {
         use std::os::fd::AsRawFd;
         self.fd.as_raw_fd()
     }
+
+    /// Duplicates the fd (close-on-exec) into a second handle.
+    ///
+    /// Both handles accept from the same queue, so several threads can
+    /// each block in `accept()` on their own. An unlink-on-drop guard
+    /// stays with `self`: the file goes when the original drops.
+    pub fn try_clone(&self) -> std::io::Result<Self> {
+        let mut clone = Self::from_fd(super::raw::dup_fd(self.as_raw_fd())?);
+        clone.backlog = self.backlog;
+        Ok(clone)
+    }
+
+    /// Switches the fd to blocking mode and tracks it in the type.
+    pub fn into_blocking(self) -> std::io::Result<Listener<D, Blocking>> {
+        set_fd_nonblocking(self.as_raw_fd(), false)?;
+        Ok(self.with_mode())
+    }
+
+    /// Switches the fd to non-blocking mode and tracks it in the type.
+    pub fn into_nonblocking(self) -> std::io::Result<Listener<D, NonBlocking>> {
+        set_fd_nonblocking(self.as_raw_fd(), true)?;
+        Ok(self.with_mode())
+    }
+
+    /// Forgets the mode; the fd keeps its current flag.
+    pub fn into_dynamic(self) -> Listener<D> {
+        self.with_mode()
+    }
+
+    fn with_mode<N: Mode>(self) -> Listener<D, N> {
+        Listener {
+            fd: self.fd,
+            unlink: self.unlink,
+            backlog: self.backlog,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<D: Domain> Listener<D> {
+    /// Sets or clears the `O_NONBLOCK` flag on the listener socket.
+    ///
+    /// # Important
+    ///
+    /// This affects:
+    /// - Whether `accept()` blocks
+    /// - Whether `accept_nonblocking()` returns `WouldBlock`
+    ///
+    /// This does **not** change typestate.
+    /// Blocking behavior is a runtime property, not a state transition.
+    /// Only `Dynamic` listeners have it; use `into_blocking()` or
+    /// `into_nonblocking()` to make the mode part of the type.
+    pub fn set_nonblocking(&self, nonblocking: bool) -> std::io::Result<()> {
+        set_fd_nonblocking(self.as_raw_fd(), nonblocking)
+    }
+}
+
+impl<D: Domain, M: BlockingMode> Listener<D, M> {
     /// Accepts an incoming connection **using blocking semantics**.
     ///
     /// # Guarantees
@@ -92,38 +180,13 @@ impl<D: Domain> Listener<D> {
             return Err(SocketError::Accept { errno: errno() }.into());
         }
 
+        crate::track(fd, "accept()");
         let fd = unsafe { OwnedFd::from_raw_fd(fd) };
         Ok(ConnectedStream::from_fd(fd))
     }
-    
-    /// Sets or clears the `O_NONBLOCK` flag on the listener socket.
-    ///
-    /// # Important
-    ///
-    /// This affects:
-    /// - Whether `accept()` blocks
-    /// - Whether `accept_nonblocking()` returns `WouldBlock`
-    ///
-    /// This does **not** change typestate.
-    /// Blocking behavior is a runtime property, not a state transition.
-    pub fn set_nonblocking(&self, nonblocking: bool) -> std::io::Result<()> {
-        let flags = unsafe { libc::fcntl(self.as_raw_fd(), libc::F_GETFL) };
-        if flags == -1 {
-            return Err(SocketError::GetOption { errno: errno(), option: "F_GETFL" }.into());
-        }
-        let new_flags = if nonblocking {
-            flags | libc::O_NONBLOCK
-        } else {
-            flags & !libc::O_NONBLOCK
-        };
-        let result = unsafe { libc::fcntl(self.as_raw_fd(), libc::F_SETFL, new_flags) };
-        if result == -1 {
-            return Err(SocketError::SetOption { errno: errno(), option: "O_NONBLOCK" }.into());
-        }
-        Ok(())
-    }
 }
-impl<D: Domain> Listener<D>
+
+impl<D: Domain, M: BlockingMode> Listener<D, M>
 where
     D::Addr: FromSockAddr,
 {
@@ -151,6 +214,7 @@ where
             return Err(SocketError::Accept { errno: errno() }.into());
         }
 
+        crate::track(fd, "accept()");
         let fd = unsafe { OwnedFd::from_raw_fd(fd) };
         let stream = ConnectedStream::from_fd(fd);
 
@@ -163,8 +227,42 @@ where
 
         Ok((stream, addr))
     }
-    
-    
+}
+
+impl<D: Domain, M: Mode> Listener<D, M>
+where
+    D::Addr: FromSockAddr,
+{
+    /// Returns the address this listener is bound to.
+    ///
+    /// Shows the port the kernel picked after binding to port 0.
+    pub fn local_addr(&self) -> std::io::Result<D::Addr> {
+        let mut storage: libc::sockaddr_storage = unsafe { std::mem::zeroed() };
+        let mut len = std::mem::size_of::<libc::sockaddr_storage>() as libc::socklen_t;
+
+        let result = unsafe {
+            libc::getsockname(
+                self.as_raw_fd(),
+                &mut storage as *mut _ as *mut libc::sockaddr,
+                &mut len,
+            )
+        };
+
+        if result == -1 {
+            return Err(SocketError::GetOption { errno: errno(), option: "SO_SOCKNAME" }.into());
+        }
+
+        unsafe {
+            D::Addr::from_sockaddr(&storage as *const _ as *const libc::sockaddr, len)
+                .ok_or_else(|| SocketError::InvalidAddress { reason: "invalid address" }.into())
+        }
+    }
+}
+
+impl<D: Domain, M: NonBlockingMode> Listener<D, M>
+where
+    D::Addr: FromSockAddr,
+{
     /// Attempts to accept a connection **without blocking**.
     ///
     /// # Semantics
@@ -230,6 +328,7 @@ where
             };
         }
         
+        crate::track(fd, "accept()");
         let fd = unsafe { OwnedFd::from_raw_fd(fd) };
         let stream = ConnectedStream::from_fd(fd);
         
@@ -244,13 +343,13 @@ where
     }
 }
 
-impl<D: Domain> std::os::fd::AsRawFd for Listener<D> {
+impl<D: Domain, M: Mode> std::os::fd::AsRawFd for Listener<D, M> {
     fn as_raw_fd(&self) -> std::os::fd::RawFd {
         self.fd.as_raw_fd()
     }
 }
 
-impl<D: Domain> std::os::fd::AsFd for Listener<D> {
+impl<D: Domain, M: Mode> std::os::fd::AsFd for Listener<D, M> {
     fn as_fd(&self) -> std::os::fd::BorrowedFd<'_> {
         self.fd.as_fd()
     }
@@ -261,8 +360,13 @@ impl<D: Domain> std::os::fd::FromRawFd for Listener<D> {
     }
 }
 
-impl<D: Domain> std::os::fd::IntoRawFd for Listener<D> {
+impl<D: Domain, M: Mode> std::os::fd::IntoRawFd for Listener<D, M> {
+    /// The caller now owns the socket, so an unlink-on-drop guard is
+    /// disarmed rather than removing a file still in use.
     fn into_raw_fd(self) -> std::os::fd::RawFd {
+        if let Some(guard) = self.unlink {
+            guard.disarm();
+        }
         self.fd.into_raw_fd()
     }
 }
@@ -295,7 +399,10 @@ impl<D: Domain> BoundSocket<D, Stream> {
         // Extract the fd from self without running Drop
         let fd = self.into_fd();
 
-        Ok(Listener::from_fd(fd))
+        let mut listener = Listener::from_fd(fd);
+        // The kernel compares as unsigned: a negative backlog means "max".
+        listener.backlog = Some(backlog as u32);
+        Ok(listener)
     }
 }
 /*
@@ -0,0 +1,301 @@
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd};
+use crate::addr::{Domain, FromSockAddr, SocketAddrV4, SocketAddrV6, UnixAddr};
+use crate::error::{SocketError, errno};
+use super::fd_kind::FdKind;
+use super::builder::{ReuseConfig, TcpConfig, KeepaliveConfig, BufferConfig, TimeoutConfig, QosConfig};
+use super::{
+	get_reuse_addr, get_reuse_port, get_only_v6, get_tos, get_tclass, get_recv_tos, get_recv_tclass,
+	get_tcp_nodelay, get_keepalive, get_keepalive_idle, get_keepalive_interval, get_keepalive_count,
+	get_linger, get_recv_buffer_size, get_send_buffer_size, get_recv_timeout, get_send_timeout,
+};
+use super::options::get_raw_tcp_info;
+
+/// Address family recorded in a `SocketManifest`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ManifestFamily {
+	Ipv4,
+	Ipv6,
+	Unix,
+}
+
+impl ManifestFamily {
+	fn raw(self) -> libc::c_int {
+		match self {
+			ManifestFamily::Ipv4 => libc::AF_INET,
+			ManifestFamily::Ipv6 => libc::AF_INET6,
+			ManifestFamily::Unix => libc::AF_UNIX,
+		}
+	}
+}
+
+/// Socket type and state recorded in a `SocketManifest`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ManifestKind {
+	Listener,
+	Stream,
+	Datagram,
+	SeqPacketListener,
+	SeqPacket,
+}
+
+/// A socket address in a family-independent, serializable form.
+///
+/// Convert back with `SocketAddrV4::try_from(&addr)` and friends.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ManifestAddr {
+	Ipv4 { ip: [u8; 4], port: u16 },
+	Ipv6 { ip: [u8; 16], port: u16, scope_id: u32 },
+	Unix { path: Vec<u8>, is_abstract: bool },
+}
+
+impl From<&SocketAddrV4> for ManifestAddr {
+	fn from(addr: &SocketAddrV4) -> Self {
+		ManifestAddr::Ipv4 { ip: addr.ip(), port: addr.port() }
+	}
+}
+
+impl From<&SocketAddrV6> for ManifestAddr {
+	fn from(addr: &SocketAddrV6) -> Self {
+		ManifestAddr::Ipv6 { ip: addr.ip(), port: addr.port(), scope_id: addr.scope_id() }
+	}
+}
+
+impl From<&UnixAddr> for ManifestAddr {
+	fn from(addr: &UnixAddr) -> Self {
+		ManifestAddr::Unix { path: addr.path().to_vec(), is_abstract: addr.is_abstract() }
+	}
+}
+
+impl TryFrom<&ManifestAddr> for SocketAddrV4 {
+	type Error = SocketError;
+
+	fn try_from(addr: &ManifestAddr) -> Result<Self, SocketError> {
+		match addr {
+			ManifestAddr::Ipv4 { ip, port } => Ok(SocketAddrV4::new(*ip, *port)),
+			_ => Err(SocketError::InvalidAddress { reason: "manifest address is not IPv4" }),
+		}
+	}
+}
+
+impl TryFrom<&ManifestAddr> for SocketAddrV6 {
+	type Error = SocketError;
+
+	fn try_from(addr: &ManifestAddr) -> Result<Self, SocketError> {
+		match addr {
+			ManifestAddr::Ipv6 { ip, port, scope_id } => Ok(SocketAddrV6::with_scope(*ip, *port, *scope_id)),
+			_ => Err(SocketError::InvalidAddress { reason: "manifest address is not IPv6" }),
+		}
+	}
+}
+
+impl TryFrom<&ManifestAddr> for UnixAddr {
+	type Error = SocketError;
+
+	fn try_from(addr: &ManifestAddr) -> Result<Self, SocketError> {
+		match addr {
+			ManifestAddr::Unix { path, is_abstract: true } => Ok(UnixAddr::abstract_socket(path)),
+			ManifestAddr::Unix { path, is_abstract: false } if path.is_empty() => Ok(UnixAddr::unnamed()),
+			ManifestAddr::Unix { path, is_abstract: false } => Ok(UnixAddr::try_new(path)?),
+			_ => Err(SocketError::InvalidAddress { reason: "manifest address is not a Unix address" }),
+		}
+	}
+}
+
+/// A snapshot of what a socket is and how it's configured.
+///
+/// `capture()` reads it from a live socket; `ListenerBuilder::from_manifest()`
+/// (and the connector and datagram equivalents) turn it back into a
+/// builder with the same options. With the `serde` feature it can be
+/// stored next to a handed-off fd, or kept as the expected state for
+/// `verify()` in config audits.
+///
+/// Option fields use the builders' config types. Buffer sizes are
+/// recorded as requested sizes: half what the kernel reports, so that
+/// applying them reproduces the same effective size.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SocketManifest {
+	pub family: ManifestFamily,
+	pub kind: ManifestKind,
+	pub local_addr: Option<ManifestAddr>,
+	/// `None` if the socket has no peer.
+	pub peer_addr: Option<ManifestAddr>,
+	pub nonblocking: bool,
+	pub reuse: ReuseConfig,
+	/// TCP sockets only.
+	pub tcp: Option<TcpConfig>,
+	pub buffers: BufferConfig,
+	pub timeouts: TimeoutConfig,
+	/// Default (unset) for Unix sockets.
+	pub qos: QosConfig,
+	/// IPv6 sockets only.
+	pub v6_only: Option<bool>,
+	/// Accept queue limit in effect; TCP listeners only.
+	pub backlog: Option<u32>,
+}
+
+impl SocketManifest {
+	/// Reads family, type, addresses and options from a socket.
+	///
+	/// Fails with ENOTSOCK for non-sockets and `WrongSocketKind` for
+	/// sockets wirelane has no type for.
+	pub fn capture<S: AsFd>(socket: &S) -> std::io::Result<Self> {
+		let fd = socket.as_fd();
+		let kind = FdKind::of(fd)?;
+
+		let family = match kind.domain {
+			libc::AF_INET => ManifestFamily::Ipv4,
+			libc::AF_INET6 => ManifestFamily::Ipv6,
+			libc::AF_UNIX => ManifestFamily::Unix,
+			_ => return Err(SocketError::WrongSocketKind { expected: "an IPv4, IPv6 or Unix socket" }.into()),
+		};
+		let manifest_kind = match (kind.ty, kind.listening) {
+			(libc::SOCK_STREAM, true) => ManifestKind::Listener,
+			(libc::SOCK_STREAM, false) => ManifestKind::Stream,
+			(libc::SOCK_DGRAM, _) => ManifestKind::Datagram,
+			(libc::SOCK_SEQPACKET, true) => ManifestKind::SeqPacketListener,
+			(libc::SOCK_SEQPACKET, false) => ManifestKind::SeqPacket,
+			_ => return Err(SocketError::WrongSocketKind { expected: "a stream, datagram or seqpacket socket" }.into()),
+		};
+		let is_ip = family != ManifestFamily::Unix;
+		let is_tcp = is_ip && kind.ty == libc::SOCK_STREAM;
+
+		let tcp = match is_tcp {
+			true => Some(TcpConfig {
+				nodelay: get_tcp_nodelay(&fd)?,
+				keepalive: match get_keepalive(&fd)? {
+					true => Some(KeepaliveConfig {
+						idle_secs: get_keepalive_idle(&fd)?,
+						interval_secs: get_keepalive_interval(&fd)?,
+						count: get_keepalive_count(&fd)?,
+					}),
+					false => None,
+				},
+				// Off is the default, so it's left unset; that also keeps
+				// `Some(None)` out of serialized manifests, where it reads back as `None`.
+				linger: get_linger(&fd)?.map(Some),
+			}),
+			false => None,
+		};
+
+		let (tos, recv_tos) = match family {
+			ManifestFamily::Ipv4 => (get_tos(&fd)?, get_recv_tos(&fd)?),
+			ManifestFamily::Ipv6 => (get_tclass(&fd)?, get_recv_tclass(&fd)?),
+			ManifestFamily::Unix => (0, false),
+		};
+		let qos = QosConfig {
+			dscp: (tos != 0).then_some(tos >> 2),
+			ecn: (tos != 0).then_some(tos & 0b11),
+			recv_tos,
+		};
+
+		Ok(Self {
+			family,
+			kind: manifest_kind,
+			local_addr: socket_addr(fd, family, libc::getsockname),
+			peer_addr: socket_addr(fd, family, libc::getpeername),
+			nonblocking: is_nonblocking(fd)?,
+			reuse: ReuseConfig { addr: get_reuse_addr(&fd)?, port: get_reuse_port(&fd)? },
+			tcp,
+			buffers: BufferConfig {
+				recv: Some(get_recv_buffer_size(&fd)? / 2),
+				send: Some(get_send_buffer_size(&fd)? / 2),
+			},
+			timeouts: TimeoutConfig { read: get_recv_timeout(&fd)?, write: get_send_timeout(&fd)? },
+			qos,
+			v6_only: match family {
+				ManifestFamily::Ipv6 => Some(get_only_v6(&fd)?),
+				_ => None,
+			},
+			backlog: match is_tcp && kind.listening {
+				true => Some(max_backlog(fd)?),
+				false => None,
+			},
+		})
+	}
+
+	/// Names of the fields that differ from `other`, e.g. `["buffers", "tcp"]`.
+	///
+	/// Empty if the manifests match.
+	pub fn differences(&self, other: &SocketManifest) -> Vec<&'static str> {
+		let fields = [
+			("family", self.family == other.family),
+			("kind", self.kind == other.kind),
+			("local_addr", self.local_addr == other.local_addr),
+			("peer_addr", self.peer_addr == other.peer_addr),
+			("nonblocking", self.nonblocking == other.nonblocking),
+			("reuse", self.reuse == other.reuse),
+			("tcp", self.tcp == other.tcp),
+			("buffers", self.buffers == other.buffers),
+			("timeouts", self.timeouts == other.timeouts),
+			("qos", self.qos == other.qos),
+			("v6_only", self.v6_only == other.v6_only),
+			("backlog", self.backlog == other.backlog),
+		];
+		fields.into_iter().filter(|(_, same)| !same).map(|(name, _)| name).collect()
+	}
+
+	/// Captures `socket` and reports which fields drifted from this manifest.
+	pub fn verify<S: AsFd>(&self, socket: &S) -> std::io::Result<Vec<&'static str>> {
+		Ok(self.differences(&Self::capture(socket)?))
+	}
+
+	/// Checks that a builder for domain `D` can recreate this socket.
+	pub(crate) fn expect<D: Domain>(&self, kind: ManifestKind, expected: &'static str) -> std::io::Result<()> {
+		if self.family.raw() != D::raw() || self.kind != kind {
+			return Err(SocketError::WrongSocketKind { expected }.into());
+		}
+		Ok(())
+	}
+}
+
+/// getsockname()/getpeername() decoded for `family`; `None` on failure
+/// (no peer) or for an unnamed Unix socket.
+fn socket_addr(
+	fd: BorrowedFd<'_>,
+	family: ManifestFamily,
+	get: unsafe extern "C" fn(libc::c_int, *mut libc::sockaddr, *mut libc::socklen_t) -> libc::c_int,
+) -> Option<ManifestAddr> {
+	let mut storage: libc::sockaddr_storage = unsafe { std::mem::zeroed() };
+	let mut len = std::mem::size_of::<libc::sockaddr_storage>() as libc::socklen_t;
+	let result = unsafe { get(fd.as_raw_fd(), &mut storage as *mut _ as *mut libc::sockaddr, &mut len) };
+	if result == -1 {
+		return None;
+	}
+
+	let raw = &storage as *const _ as *const libc::sockaddr;
+	unsafe {
+		match family {
+			ManifestFamily::Ipv4 => SocketAddrV4::from_sockaddr(raw, len).map(|a| ManifestAddr::from(&a)),
+			ManifestFamily::Ipv6 => SocketAddrV6::from_sockaddr(raw, len).map(|a| ManifestAddr::from(&a)),
+			ManifestFamily::Unix => UnixAddr::from_sockaddr(raw, len)
+				.filter(|a| !a.is_unnamed())
+				.map(|a| ManifestAddr::from(&a)),
+		}
+	}
+}
+
+fn is_nonblocking(fd: BorrowedFd<'_>) -> std::io::Result<bool> {
+	let flags = unsafe { libc::fcntl(fd.as_raw_fd(), libc::F_GETFL) };
+	if flags == -1 {
+		return Err(SocketError::GetOption { errno: errno(), option: "O_NONBLOCK" }.into());
+	}
+	Ok(flags & libc::O_NONBLOCK != 0)
+}
+
+/// A TCP listener's accept queue limit, from TCP_INFO (see `Listener::stats()`).
+fn max_backlog(fd: BorrowedFd<'_>) -> std::io::Result<u32> {
+	Ok(get_raw_tcp_info(&fd)?.tcpi_sacked)
+}
+
+/*
+Why reuse the builder config structs instead of a flat option list?
+A manifest is only useful if it round-trips: whatever capture() records
+has to mean exactly what the builder would apply. Sharing the types
+makes that true by construction, and a new builder option shows up in
+manifests as soon as its config struct grows a field. The cost is that
+config structs now derive PartialEq and (behind the feature) serde.
+*/
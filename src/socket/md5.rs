@@ -0,0 +1,129 @@
+use std::os::fd::AsRawFd;
+use crate::addr::{Domain, ToSockAddr};
+use crate::error::{SocketError, errno};
+use super::{Listener, Mode};
+
+// From linux/tcp.h; not exported by libc on every target.
+const TCP_MD5SIG: libc::c_int = 14;
+const TCP_MD5SIG_EXT: libc::c_int = 32;
+const TCP_MD5SIG_FLAG_PREFIX: u8 = 0x1;
+
+/// Longest key the kernel accepts (TCP_MD5SIG_MAXKEYLEN).
+pub const MAX_MD5_KEY_LEN: usize = 80;
+
+/// struct tcp_md5sig from linux/tcp.h.
+#[repr(C)]
+struct TcpMd5Sig {
+	addr: libc::sockaddr_storage,
+	flags: u8,
+	prefix_len: u8,
+	key_len: u16,
+	ifindex: libc::c_int,
+	key: [u8; MAX_MD5_KEY_LEN],
+}
+
+/// Adds (or replaces) the TCP MD5 signature key (RFC 2385) for `peer`.
+///
+/// Segments to and from that address are then signed and checked with
+/// `key`; unsigned or wrongly signed ones are dropped silently. Set it
+/// on a listener before peers connect (accepted streams inherit it), or
+/// on a client socket before `connect()`. Only the IP address of `peer`
+/// matters, not the port.
+///
+/// `key` must be 1-80 bytes.
+pub fn add_tcp_md5_key<S: AsRawFd, A: ToSockAddr>(socket: &S, peer: &A, key: &[u8]) -> std::io::Result<()> {
+	if key.is_empty() || key.len() > MAX_MD5_KEY_LEN {
+		return Err(SocketError::InvalidInput { reason: "TCP_MD5SIG key must be 1-80 bytes" }.into());
+	}
+	set_md5sig(socket, peer, None, key)
+}
+
+/// Adds a TCP MD5 key for every peer in `peer`'s `/prefix_len` network
+/// (TCP_MD5SIG_EXT, Linux 4.13+).
+///
+/// For BGP listeners that accept sessions from a whole peering subnet.
+pub fn add_tcp_md5_key_prefix<S: AsRawFd, A: ToSockAddr>(socket: &S, peer: &A, prefix_len: u8, key: &[u8]) -> std::io::Result<()> {
+	if key.is_empty() || key.len() > MAX_MD5_KEY_LEN {
+		return Err(SocketError::InvalidInput { reason: "TCP_MD5SIG_EXT key must be 1-80 bytes" }.into());
+	}
+	set_md5sig(socket, peer, Some(prefix_len), key)
+}
+
+/// Removes the TCP MD5 key for `peer`. Fails with ENOENT if it has none.
+pub fn remove_tcp_md5_key<S: AsRawFd, A: ToSockAddr>(socket: &S, peer: &A) -> std::io::Result<()> {
+	set_md5sig(socket, peer, None, &[])
+}
+
+/// Removes a key added with `add_tcp_md5_key_prefix()`.
+pub fn remove_tcp_md5_key_prefix<S: AsRawFd, A: ToSockAddr>(socket: &S, peer: &A, prefix_len: u8) -> std::io::Result<()> {
+	set_md5sig(socket, peer, Some(prefix_len), &[])
+}
+
+/// An empty `key` deletes the entry.
+fn set_md5sig<S: AsRawFd, A: ToSockAddr>(socket: &S, peer: &A, prefix_len: Option<u8>, key: &[u8]) -> std::io::Result<()> {
+	let mut sig = TcpMd5Sig {
+		addr: unsafe { std::mem::zeroed() },
+		flags: 0,
+		prefix_len: 0,
+		key_len: key.len() as u16,
+		ifindex: 0,
+		key: [0; MAX_MD5_KEY_LEN],
+	};
+	sig.key[..key.len()].copy_from_slice(key);
+
+	peer.with_raw(|ptr, len| {
+		let len = (len as usize).min(std::mem::size_of::<libc::sockaddr_storage>());
+		unsafe { std::ptr::copy_nonoverlapping(ptr as *const u8, &mut sig.addr as *mut _ as *mut u8, len) };
+	}).map_err(SocketError::from)?;
+
+	let (opt, name) = match prefix_len {
+		None => (TCP_MD5SIG, "TCP_MD5SIG"),
+		Some(prefix_len) => {
+			sig.flags = TCP_MD5SIG_FLAG_PREFIX;
+			sig.prefix_len = prefix_len;
+			(TCP_MD5SIG_EXT, "TCP_MD5SIG_EXT")
+		}
+	};
+	let result = unsafe {
+		libc::setsockopt(
+			socket.as_raw_fd(),
+			libc::IPPROTO_TCP,
+			opt,
+			&sig as *const _ as *const libc::c_void,
+			std::mem::size_of::<TcpMd5Sig>() as libc::socklen_t,
+		)
+	};
+	if result == -1 {
+		return Err(SocketError::SetOption { errno: errno(), option: name }.into());
+	}
+	Ok(())
+}
+
+impl<D: Domain, M: Mode> Listener<D, M>
+where
+	D::Addr: ToSockAddr,
+{
+	/// Requires TCP MD5 signatures from `peer`, keyed with `key`.
+	///
+	/// Takes effect for connections accepted from then on; see
+	/// `add_tcp_md5_key()`. Lets a BGP speaker add and drop neighbors
+	/// without rebinding.
+	pub fn add_md5_key(&self, peer: &D::Addr, key: &[u8]) -> std::io::Result<()> {
+		add_tcp_md5_key(self, peer, key)
+	}
+
+	/// Stops requiring signatures from `peer`.
+	pub fn remove_md5_key(&self, peer: &D::Addr) -> std::io::Result<()> {
+		remove_tcp_md5_key(self, peer)
+	}
+}
+
+/*
+Why keys per address instead of one key per socket?
+That's how the kernel models it: a listener serves many neighbors, each
+with its own shared secret, and looks the key up by the SYN's source
+address. Once a peer has a key, its unsigned segments are dropped —
+including the SYN — so a typo shows up as a connection that silently
+never completes, not as an error anywhere. Check both ends' keys first
+when a signed session hangs in SYN_SENT.
+*/
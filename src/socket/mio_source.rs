@@ -0,0 +1,82 @@
+use mio::{Interest, Registry, Token};
+use mio::event::Source;
+use mio::unix::SourceFd;
+use crate::addr::Domain;
+use super::{Listener, Mode, ConnectedStream, BoundDatagram, ConnectedDatagram, PendingConnect};
+
+/*
+Each impl delegates to SourceFd, which registers the raw fd with epoll.
+mio requires sources to be non-blocking — set O_NONBLOCK (or build with
+`.nonblocking(true)`) before registering, otherwise a readiness event
+followed by a blocking call can stall the poll loop.
+*/
+
+impl<D: Domain, M: Mode> Source for Listener<D, M> {
+	fn register(&mut self, registry: &Registry, token: Token, interests: Interest) -> std::io::Result<()> {
+		SourceFd(&self.as_raw_fd()).register(registry, token, interests)
+	}
+
+	fn reregister(&mut self, registry: &Registry, token: Token, interests: Interest) -> std::io::Result<()> {
+		SourceFd(&self.as_raw_fd()).reregister(registry, token, interests)
+	}
+
+	fn deregister(&mut self, registry: &Registry) -> std::io::Result<()> {
+		SourceFd(&self.as_raw_fd()).deregister(registry)
+	}
+}
+
+impl<D: Domain> Source for ConnectedStream<D> {
+	fn register(&mut self, registry: &Registry, token: Token, interests: Interest) -> std::io::Result<()> {
+		SourceFd(&self.as_raw_fd()).register(registry, token, interests)
+	}
+
+	fn reregister(&mut self, registry: &Registry, token: Token, interests: Interest) -> std::io::Result<()> {
+		SourceFd(&self.as_raw_fd()).reregister(registry, token, interests)
+	}
+
+	fn deregister(&mut self, registry: &Registry) -> std::io::Result<()> {
+		SourceFd(&self.as_raw_fd()).deregister(registry)
+	}
+}
+
+impl<D: Domain> Source for BoundDatagram<D> {
+	fn register(&mut self, registry: &Registry, token: Token, interests: Interest) -> std::io::Result<()> {
+		SourceFd(&self.as_raw_fd()).register(registry, token, interests)
+	}
+
+	fn reregister(&mut self, registry: &Registry, token: Token, interests: Interest) -> std::io::Result<()> {
+		SourceFd(&self.as_raw_fd()).reregister(registry, token, interests)
+	}
+
+	fn deregister(&mut self, registry: &Registry) -> std::io::Result<()> {
+		SourceFd(&self.as_raw_fd()).deregister(registry)
+	}
+}
+
+impl<D: Domain> Source for ConnectedDatagram<D> {
+	fn register(&mut self, registry: &Registry, token: Token, interests: Interest) -> std::io::Result<()> {
+		SourceFd(&self.as_raw_fd()).register(registry, token, interests)
+	}
+
+	fn reregister(&mut self, registry: &Registry, token: Token, interests: Interest) -> std::io::Result<()> {
+		SourceFd(&self.as_raw_fd()).reregister(registry, token, interests)
+	}
+
+	fn deregister(&mut self, registry: &Registry) -> std::io::Result<()> {
+		SourceFd(&self.as_raw_fd()).deregister(registry)
+	}
+}
+
+impl<D: Domain> Source for PendingConnect<D> {
+	fn register(&mut self, registry: &Registry, token: Token, interests: Interest) -> std::io::Result<()> {
+		SourceFd(&self.as_raw_fd()).register(registry, token, interests)
+	}
+
+	fn reregister(&mut self, registry: &Registry, token: Token, interests: Interest) -> std::io::Result<()> {
+		SourceFd(&self.as_raw_fd()).reregister(registry, token, interests)
+	}
+
+	fn deregister(&mut self, registry: &Registry) -> std::io::Result<()> {
+		SourceFd(&self.as_raw_fd()).deregister(registry)
+	}
+}
@@ -6,24 +6,129 @@ mod options;
 mod bound;
 mod builder;
 mod pending;
+mod split;
+mod health;
+mod wait;
+mod instrumented;
+mod buffered;
+mod errqueue;
+mod seqpacket;
+mod pool;
+mod poller;
+mod unix_path;
+mod backlog;
+mod fd_kind;
+mod std_conv;
+mod sniff;
+mod batch;
+mod inherit;
+mod sockopt;
+mod pktinfo;
+mod shutdown;
+mod flags;
+mod knock;
+mod multi;
+mod manifest;
+mod budget;
+mod acceptor;
+mod shard;
+mod mode;
+mod cancel;
+mod md5;
+mod udp_server;
+mod try_io;
+mod msg;
+mod pipe;
+mod resolve;
+#[cfg(feature = "tokio")]
+mod async_fd;
+#[cfg(feature = "mio")]
+mod mio_source;
+#[cfg(feature = "io_uring")]
+mod ring;
+#[cfg(feature = "io_uring")]
+mod fixed_recv;
+#[cfg(feature = "xdp")]
+mod xdp;
 
 pub use self::listener::{Listener,AcceptResult};
 pub use self::raw::RawSocket;
 pub use self::stream::{ConnectedStream,Shutdown};
+pub use self::split::{ReadHalf, WriteHalf, ReadResult};
+pub use self::health::{UnixHealthCheck, HealthProbe, PeerHealth};
+pub use self::instrumented::{Instrumented, SocketStats};
+pub use self::buffered::BufferedStream;
+pub use self::errqueue::{ErrorQueue, QueuedError, ErrorEvent, TxtimeError, RecvOutcome};
+pub use self::seqpacket::{SeqPacketListener, ConnectedSeqPacket};
+pub use self::pool::{BufferPool, DatagramLease};
+pub use self::poller::{Poller, Waker, Events, Event, Token, Interest};
+pub use self::unix_path::SocketPathGuard;
+pub use self::backlog::{ListenerStats, somaxconn};
+pub use self::sniff::{sniff_stream, SniffedProtocol};
+pub use self::batch::BatchStats;
+pub use self::inherit::Socket;
+pub use self::sockopt::{SockOpt, OptValue, set_opt, get_opt};
+pub(crate) use self::sockopt::{struct_bytes, struct_from_bytes};
+pub(crate) use self::options::get_raw_tcp_info;
+pub use self::pktinfo::RecvInterface;
+pub use self::shutdown::{ShutdownCoordinator, ShutdownReport, DrainReport, DrainEnd};
+pub use self::flags::{SendFlags, RecvFlags};
+pub use self::knock::{KnockListener, MAX_KNOCK_PREFIX};
+pub use self::multi::MultiListener;
+pub use self::budget::SendBudget;
+pub use self::acceptor::{Acceptor, AnyAddr};
+pub use self::shard::{ReuseportShard, reuseport_shard, MAX_REUSEPORT_WORKERS};
+pub use self::cancel::CancelHandle;
+pub use self::md5::{add_tcp_md5_key, add_tcp_md5_key_prefix, remove_tcp_md5_key, remove_tcp_md5_key_prefix, MAX_MD5_KEY_LEN};
+pub use self::udp_server::UdpServer;
+pub use self::try_io::TryResult;
+pub use self::pipe::{Pipe, PipeReader, PipeWriter};
+pub use self::resolve::{resolve, resolve_family, ResolveOrder};
+pub use self::msg::{MsgHdr, RecvMsg, Cmsg, CmsgIter, send_msg, recv_msg, cmsg_space, DEFAULT_CONTROL_SPACE};
+pub use self::mode::{Mode, BlockingMode, NonBlockingMode, Dynamic, Blocking, NonBlocking};
+pub use self::manifest::{SocketManifest, ManifestAddr, ManifestFamily, ManifestKind};
 pub use self::bound::BoundSocket;
-pub use self::datagram::{BoundDatagram, ConnectedDatagram, SendMsg, MsgResult};
-pub use self::options::{set_reuse_addr, set_reuse_port, set_tcp_nodelay, set_linger,
+pub use self::datagram::{BoundDatagram, ConnectedDatagram, SendMsg, MsgResult, MappedAddrs};
+pub use self::options::{set_reuse_addr, set_reuse_port, set_tcp_nodelay, set_linger, set_only_v6,
+						get_reuse_addr, get_reuse_port, get_tcp_nodelay, get_linger, get_only_v6,
+						set_tos, set_tclass, get_tos, get_tclass, set_recv_tos, set_recv_tclass,
+						get_recv_tos, get_recv_tclass,
+						set_recv_pktinfo, set_recv_pktinfo_v6,
+						set_ip_transparent, get_ip_transparent, set_ip_transparent_v6, get_ip_transparent_v6,
+						set_recv_orig_dst, set_recv_orig_dst_v6, set_recv_err, set_recv_err_v6, set_bind_address_no_port,
+						set_broadcast, get_broadcast, set_multicast_ttl, get_multicast_ttl,
+						set_multicast_hops_v6, get_multicast_hops_v6,
+						join_multicast_v4, leave_multicast_v4, join_multicast_v6, leave_multicast_v6,
+						join_ssm_v4, leave_ssm_v4, join_ssm_v6, leave_ssm_v6,
+						set_header_included, set_header_included_v6,
+						get_header_included, get_header_included_v6,
 						set_recv_buffer_size, set_keepalive, set_keepalive_count,
 						set_keepalive_idle, set_keepalive_interval, set_send_buffer_size,
+						get_recv_buffer_size, get_keepalive, get_keepalive_count,
+						get_keepalive_idle, get_keepalive_interval, get_send_buffer_size,
 						set_tcp_cork, set_tcp_quickack, set_tcp_fastopen,
+						get_tcp_cork, get_tcp_quickack, get_tcp_fastopen, set_tcp_syn_count, get_tcp_syn_count,
+						set_tcp_window_clamp, get_tcp_window_clamp, set_tcp_linger2, get_tcp_linger2,
 						get_tcp_info, TcpInfo, 
+						get_incoming_napi_id, napi_worker, group_by_napi_id,
+						set_recv_timeout, get_recv_timeout, set_send_timeout, get_send_timeout,
 						splice, SPLICE_F_MOVE, SPLICE_F_NONBLOCK, SPLICE_F_MORE,
-						send_fd, recv_fd};
+						send_fd, recv_fd,
+						Credentials, get_peer_credentials, set_passcred, get_passcred, send_credentials, recv_credentials};
 pub use self::pending::PendingConnect;
+pub(crate) use self::wait::poll_fd;
+#[cfg(feature = "io_uring")]
+pub use self::ring::{Ring, Completion};
+#[cfg(feature = "io_uring")]
+pub use self::fixed_recv::{FixedRecvPool, MAX_FIXED_RECV_BUFFERS};
+#[cfg(feature = "tokio")]
+pub use self::async_fd::{AsyncConnectedStream, AsyncListener, AsyncBoundDatagram};
+#[cfg(feature = "xdp")]
+pub use self::xdp::{XdpSocket, XdpConfig, XdpDesc, XdpStats};
 
 
 pub use self::builder::{ListenerBuilder, ConnectorBuilder, DatagramBuilder,
-						BufferConfig, ReuseConfig, TcpConfig, KeepaliveConfig};
+						BufferConfig, ReuseConfig, TcpConfig, KeepaliveConfig, TimeoutConfig, QosConfig, RetryPolicy};
 
 /// Trait for socket type markers.
 ///
@@ -32,6 +137,8 @@ pub use self::builder::{ListenerBuilder, ConnectorBuilder, DatagramBuilder,
 ///
 /// - `Stream` — reliable, ordered byte stream (TCP-like)
 /// - `Datagram` — unreliable, unordered packets (UDP-like)
+/// - `SeqPacket` — reliable, ordered messages over a connection
+/// - `Raw` — packets at the IP layer, below TCP/UDP
 pub trait SockType {
 	/// Returns the libc constant for this socket type.
 	fn raw() -> libc::c_int;
@@ -50,6 +157,18 @@ pub struct Stream;
 /// Used for UDP (with Ipv4/Ipv6) or Unix datagram sockets.
 pub struct Datagram;
 
+/// Sequenced-packet socket marker.
+///
+/// Connection-oriented and reliable like `Stream`, but keeps message
+/// boundaries like `Datagram`. Used with Unix sockets (and SCTP).
+pub struct SeqPacket;
+
+/// Raw socket marker.
+///
+/// Sends and receives IP packets directly, bypassing TCP/UDP.
+/// Needs CAP_NET_RAW. Create with `RawSocket::with_protocol()`.
+pub struct Raw;
+
 /*
  ---
   Key difference:
@@ -77,6 +196,20 @@ impl SockType for Datagram {
 	}
 }
 
+impl SockType for SeqPacket {
+	#[inline]
+	fn raw() -> libc::c_int {
+		libc::SOCK_SEQPACKET
+	}
+}
+
+impl SockType for Raw {
+	#[inline]
+	fn raw() -> libc::c_int {
+		libc::SOCK_RAW
+	}
+}
+
 /*
 ---
   The mapping:
@@ -86,6 +219,10 @@ impl SockType for Datagram {
   │ Stream   │ SOCK_STREAM │ 1             │
   ├──────────┼─────────────┼───────────────┤
   │ Datagram │ SOCK_DGRAM  │ 2             │
+  ├──────────┼─────────────┼───────────────┤
+  │ Raw      │ SOCK_RAW    │ 3             │
+  ├──────────┼─────────────┼───────────────┤
+  │SeqPacket │SOCK_SEQPACKET│ 5            │
   └──────────┴─────────────┴───────────────┘
   ---
 */
\ No newline at end of file
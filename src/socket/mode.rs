@@ -0,0 +1,68 @@
+use crate::error::{SocketError, errno};
+
+/// Trait for blocking-mode markers on `Listener<D, M>`.
+///
+/// - `Dynamic` — the default: the fd's O_NONBLOCK flag decides at
+///   runtime, and every method is available
+/// - `Blocking` — O_NONBLOCK is known to be clear
+/// - `NonBlocking` — O_NONBLOCK is known to be set
+///
+/// Switch with `into_blocking()` / `into_nonblocking()`, which set the
+/// flag to match. Typed modes have no `set_nonblocking()`, so the flag
+/// can't drift from the type.
+pub trait Mode: Send + Sync {}
+
+/// Modes where blocking calls with strong postconditions (`accept()`)
+/// are allowed: `Blocking` and `Dynamic`.
+pub trait BlockingMode: Mode {}
+
+/// Modes where never-blocking calls (`accept_nonblocking()`) are
+/// allowed: `NonBlocking` and `Dynamic`.
+pub trait NonBlockingMode: Mode {}
+
+/// Mode marker: blocking behavior is decided by the fd flag at runtime.
+pub struct Dynamic;
+
+/// Mode marker: the fd is in blocking mode.
+pub struct Blocking;
+
+/// Mode marker: the fd is in non-blocking mode.
+pub struct NonBlocking;
+
+impl Mode for Dynamic {}
+impl Mode for Blocking {}
+impl Mode for NonBlocking {}
+
+impl BlockingMode for Dynamic {}
+impl BlockingMode for Blocking {}
+
+impl NonBlockingMode for Dynamic {}
+impl NonBlockingMode for NonBlocking {}
+
+/// Sets or clears O_NONBLOCK on `fd`.
+pub(crate) fn set_fd_nonblocking(fd: libc::c_int, nonblocking: bool) -> std::io::Result<()> {
+	let flags = unsafe { libc::fcntl(fd, libc::F_GETFL) };
+	if flags == -1 {
+		return Err(SocketError::GetOption { errno: errno(), option: "F_GETFL" }.into());
+	}
+	let new_flags = if nonblocking {
+		flags | libc::O_NONBLOCK
+	} else {
+		flags & !libc::O_NONBLOCK
+	};
+	let result = unsafe { libc::fcntl(fd, libc::F_SETFL, new_flags) };
+	if result == -1 {
+		return Err(SocketError::SetOption { errno: errno(), option: "O_NONBLOCK" }.into());
+	}
+	Ok(())
+}
+
+/*
+Why a Dynamic default instead of making Listener<D> mean Blocking?
+Builders, fd adoption and MultiListener all hand out listeners whose
+flag is set at runtime (`nonblocking(true)`, an inherited fd of unknown
+mode). Calling those Blocking would be exactly the lie the markers exist
+to prevent. Dynamic keeps existing code compiling with its old runtime
+semantics; code that wants the compile-time guarantee opts in with one
+conversion.
+*/
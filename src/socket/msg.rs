@@ -0,0 +1,369 @@
+use std::io::{IoSlice, IoSliceMut};
+use std::os::fd::{AsRawFd, BorrowedFd, FromRawFd, OwnedFd, RawFd};
+use std::time::{Duration, SystemTime};
+use crate::addr::{ToSockAddr, FromSockAddr, decode_sockaddr};
+use crate::error::{SocketError, IoError, errno};
+use super::{Credentials, RecvInterface, SendFlags, RecvFlags};
+
+// From linux/udp.h; libc doesn't export them on every target.
+const SOL_UDP: libc::c_int = 17;
+const UDP_SEGMENT: libc::c_int = 103;
+const UDP_GRO: libc::c_int = 104;
+
+/// Room for the control messages a typical receive asks for: a few fds,
+/// credentials, a timestamp, pktinfo and TOS.
+pub const DEFAULT_CONTROL_SPACE: usize = 256;
+
+/// Bytes a control message with `data_len` bytes of payload takes in a
+/// control buffer (CMSG_SPACE), for sizing `recv_msg()`'s.
+pub fn cmsg_space(data_len: usize) -> usize {
+	unsafe { libc::CMSG_SPACE(data_len as u32) as usize }
+}
+
+/// A message to send with `send_msg()`: data, destination and control
+/// messages.
+///
+/// # Example
+/// ```ignore
+/// let msg = MsgHdr::new(&[IoSlice::new(&header), IoSlice::new(payload)])
+///     .to(&client)
+///     .pktinfo_v4(&via)
+///     .tos(0x2e << 2);
+/// send_msg(&socket, &msg, SendFlags::NONE)?;
+/// ```
+pub struct MsgHdr<'a, A> {
+	bufs: &'a [IoSlice<'a>],
+	addr: Option<&'a A>,
+	/// u64 storage keeps every cmsghdr aligned.
+	control: Vec<u64>,
+	control_len: usize,
+}
+
+impl<'a, A> MsgHdr<'a, A> {
+	/// A message gathered from `bufs`, with no destination or control
+	/// messages yet.
+	pub fn new(bufs: &'a [IoSlice<'a>]) -> Self {
+		Self { bufs, addr: None, control: Vec::new(), control_len: 0 }
+	}
+
+	/// Sends to `addr` (unconnected datagram sockets).
+	pub fn to(mut self, addr: &'a A) -> Self {
+		self.addr = Some(addr);
+		self
+	}
+
+	/// Passes file descriptors (SCM_RIGHTS, Unix sockets only).
+	pub fn rights(self, fds: &[BorrowedFd<'_>]) -> Self {
+		let raw: Vec<u8> = fds.iter().flat_map(|fd| fd.as_raw_fd().to_ne_bytes()).collect();
+		self.raw(libc::SOL_SOCKET, libc::SCM_RIGHTS, &raw)
+	}
+
+	/// Sends credentials (SCM_CREDENTIALS); see `send_credentials()`.
+	pub fn credentials(self, cred: &Credentials) -> Self {
+		self.value(libc::SOL_SOCKET, libc::SCM_CREDENTIALS, cred.to_raw())
+	}
+
+	/// Pins the source address and interface (IP_PKTINFO); see
+	/// `BoundDatagram::send_to_if()`.
+	pub fn pktinfo_v4(self, via: &RecvInterface<[u8; 4]>) -> Self {
+		let info = libc::in_pktinfo {
+			ipi_ifindex: via.ifindex as libc::c_int,
+			ipi_spec_dst: libc::in_addr { s_addr: u32::from_ne_bytes(via.local_ip) },
+			ipi_addr: libc::in_addr { s_addr: 0 },
+		};
+		self.value(libc::IPPROTO_IP, libc::IP_PKTINFO, info)
+	}
+
+	/// The IPv6 counterpart of `pktinfo_v4()` (IPV6_PKTINFO).
+	pub fn pktinfo_v6(self, via: &RecvInterface<[u8; 16]>) -> Self {
+		let info = libc::in6_pktinfo {
+			ipi6_addr: libc::in6_addr { s6_addr: via.local_ip },
+			ipi6_ifindex: via.ifindex,
+		};
+		self.value(libc::IPPROTO_IPV6, libc::IPV6_PKTINFO, info)
+	}
+
+	/// IP_TOS for this message only.
+	pub fn tos(self, tos: u8) -> Self {
+		self.value(libc::IPPROTO_IP, libc::IP_TOS, tos as libc::c_int)
+	}
+
+	/// IPV6_TCLASS for this message only.
+	pub fn tclass(self, tclass: u8) -> Self {
+		self.value(libc::IPPROTO_IPV6, libc::IPV6_TCLASS, tclass as libc::c_int)
+	}
+
+	/// UDP GSO: the kernel splits the data into datagrams of `size`
+	/// bytes (UDP_SEGMENT).
+	pub fn segment_size(self, size: u16) -> Self {
+		self.value(SOL_UDP, UDP_SEGMENT, size)
+	}
+
+	/// Any other control message, `data` being its payload.
+	pub fn raw(mut self, level: libc::c_int, ty: libc::c_int, data: &[u8]) -> Self {
+		let start = self.control_len;
+		let space = cmsg_space(data.len());
+		self.control_len += space;
+		self.control.resize(self.control_len.div_ceil(8), 0);
+
+		let header = libc::cmsghdr {
+			cmsg_len: unsafe { libc::CMSG_LEN(data.len() as u32) } as _,
+			cmsg_level: level,
+			cmsg_type: ty,
+		};
+		let bytes = self.control.as_mut_ptr() as *mut u8;
+		unsafe {
+			std::ptr::write_unaligned(bytes.add(start) as *mut libc::cmsghdr, header);
+			let data_at = bytes.add(start + libc::CMSG_LEN(0) as usize);
+			std::ptr::copy_nonoverlapping(data.as_ptr(), data_at, data.len());
+		}
+		self
+	}
+
+	fn value<T: Copy>(self, level: libc::c_int, ty: libc::c_int, value: T) -> Self {
+		let bytes = unsafe {
+			std::slice::from_raw_parts(&value as *const T as *const u8, std::mem::size_of::<T>())
+		};
+		self.raw(level, ty, bytes)
+	}
+}
+
+/// Sends `msg` with sendmsg(). Returns bytes sent.
+///
+/// MSG_NOSIGNAL is always added, as for the socket types' own sends.
+pub fn send_msg<S: AsRawFd, A: ToSockAddr>(socket: &S, msg: &MsgHdr<'_, A>, flags: SendFlags) -> std::io::Result<usize> {
+	let mut hdr: libc::msghdr = unsafe { std::mem::zeroed() };
+	hdr.msg_iov = msg.bufs.as_ptr() as *mut libc::iovec;
+	hdr.msg_iovlen = msg.bufs.len();
+	if msg.control_len > 0 {
+		hdr.msg_control = msg.control.as_ptr() as *mut libc::c_void;
+		hdr.msg_controllen = msg.control_len;
+	}
+
+	let send = |hdr: &libc::msghdr| -> std::io::Result<usize> {
+		let n = unsafe { libc::sendmsg(socket.as_raw_fd(), hdr, flags.bits() | libc::MSG_NOSIGNAL) };
+		if n == -1 {
+			Err(IoError::Write { errno: errno() }.into())
+		} else {
+			Ok(n as usize)
+		}
+	};
+
+	match msg.addr {
+		None => send(&hdr),
+		Some(addr) => addr.with_raw(|ptr, len| {
+			hdr.msg_name = ptr as *mut libc::c_void;
+			hdr.msg_namelen = len;
+			send(&hdr)
+		}).map_err(SocketError::from)?,
+	}
+}
+
+/// Receives one message with recvmsg(), with room for `control_space`
+/// bytes of control messages (`DEFAULT_CONTROL_SPACE` or `cmsg_space()`).
+///
+/// Received fds are opened close-on-exec and owned by the result; take
+/// them with `RecvMsg::take_fds()` or they're closed with it.
+pub fn recv_msg<S: AsRawFd, A: FromSockAddr>(
+	socket: &S,
+	bufs: &mut [IoSliceMut<'_>],
+	control_space: usize,
+	flags: RecvFlags,
+) -> std::io::Result<RecvMsg<A>> {
+	let mut name: libc::sockaddr_storage = unsafe { std::mem::zeroed() };
+	let mut control = vec![0u64; control_space.div_ceil(8)];
+
+	let mut hdr: libc::msghdr = unsafe { std::mem::zeroed() };
+	hdr.msg_name = &mut name as *mut _ as *mut libc::c_void;
+	hdr.msg_namelen = std::mem::size_of::<libc::sockaddr_storage>() as libc::socklen_t;
+	hdr.msg_iov = bufs.as_mut_ptr() as *mut libc::iovec;
+	hdr.msg_iovlen = bufs.len();
+	if control_space > 0 {
+		hdr.msg_control = control.as_mut_ptr() as *mut libc::c_void;
+		hdr.msg_controllen = control.len() * 8;
+	}
+
+	let n = unsafe { libc::recvmsg(socket.as_raw_fd(), &mut hdr, flags.bits() | libc::MSG_CMSG_CLOEXEC) };
+	if n == -1 {
+		return Err(IoError::Read { errno: errno() }.into());
+	}
+
+	let mut msg = RecvMsg {
+		bytes: n as usize,
+		addr: None,
+		msg_flags: hdr.msg_flags,
+		control,
+		control_len: hdr.msg_controllen,
+		fds: Vec::new(),
+	};
+
+	// Own the fds first, so they're closed even if the address is bad.
+	let received: Vec<RawFd> = msg.cmsgs()
+		.filter_map(|cmsg| match cmsg {
+			Cmsg::Rights(fds) => Some(fds),
+			_ => None,
+		})
+		.flatten()
+		.collect();
+	for fd in received {
+		crate::track(fd, "recv_msg()");
+		msg.fds.push(unsafe { OwnedFd::from_raw_fd(fd) });
+	}
+
+	// Stream sockets leave the name empty.
+	if hdr.msg_namelen > 0 {
+		msg.addr = Some(unsafe {
+			decode_sockaddr::<A>(&name as *const _ as *const libc::sockaddr, hdr.msg_namelen, "invalid sender address")?
+		});
+	}
+	Ok(msg)
+}
+
+/// A message received with `recv_msg()`.
+pub struct RecvMsg<A> {
+	/// Bytes written into the buffers.
+	pub bytes: usize,
+	/// The sender, for datagram sockets.
+	pub addr: Option<A>,
+	msg_flags: libc::c_int,
+	control: Vec<u64>,
+	control_len: usize,
+	fds: Vec<OwnedFd>,
+}
+
+impl<A> RecvMsg<A> {
+	/// Walks the received control messages.
+	pub fn cmsgs(&self) -> CmsgIter<'_> {
+		let bytes = unsafe {
+			std::slice::from_raw_parts(self.control.as_ptr() as *const u8, self.control_len.min(self.control.len() * 8))
+		};
+		CmsgIter { control: bytes, offset: 0 }
+	}
+
+	/// Takes ownership of the fds passed with SCM_RIGHTS.
+	pub fn take_fds(&mut self) -> Vec<OwnedFd> {
+		std::mem::take(&mut self.fds)
+	}
+
+	/// The datagram was larger than the buffers; the rest is lost (MSG_TRUNC).
+	pub fn is_truncated(&self) -> bool {
+		self.msg_flags & libc::MSG_TRUNC != 0
+	}
+
+	/// Control messages didn't fit in `control_space`; some are missing
+	/// (MSG_CTRUNC). Fds that didn't fit were closed by the kernel.
+	pub fn is_control_truncated(&self) -> bool {
+		self.msg_flags & libc::MSG_CTRUNC != 0
+	}
+}
+
+/// A decoded control message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Cmsg<'a> {
+	/// SCM_RIGHTS. Owned by the `RecvMsg`; see `take_fds()`.
+	Rights(Vec<RawFd>),
+	/// SCM_CREDENTIALS (needs `set_passcred()`).
+	Credentials(Credentials),
+	/// SO_TIMESTAMP or SO_TIMESTAMPNS: when the packet was received.
+	Timestamp(SystemTime),
+	/// SO_TIMESTAMPING: software and raw hardware timestamps, where set.
+	Timestamping { software: Option<SystemTime>, hardware: Option<SystemTime> },
+	/// IP_PKTINFO.
+	PktInfoV4(RecvInterface<[u8; 4]>),
+	/// IPV6_PKTINFO. IPv6 reports only the destination, so `local_ip`
+	/// equals `dst_ip`.
+	PktInfoV6(RecvInterface<[u8; 16]>),
+	/// IP_TOS or IPV6_TCLASS.
+	Tos(u8),
+	/// UDP_GRO: size of the datagrams coalesced into this one.
+	GroSegmentSize(u16),
+	/// Anything else, or a known type with an unexpected length.
+	Raw { level: libc::c_int, ty: libc::c_int, data: &'a [u8] },
+}
+
+/// Iterator over the control messages of a `RecvMsg`.
+pub struct CmsgIter<'a> {
+	control: &'a [u8],
+	offset: usize,
+}
+
+impl<'a> Iterator for CmsgIter<'a> {
+	type Item = Cmsg<'a>;
+
+	fn next(&mut self) -> Option<Cmsg<'a>> {
+		let header_len = unsafe { libc::CMSG_LEN(0) } as usize;
+		let rest = self.control.get(self.offset..)?;
+		if rest.len() < header_len {
+			return None;
+		}
+		let header: libc::cmsghdr = unsafe { std::ptr::read_unaligned(rest.as_ptr() as *const libc::cmsghdr) };
+		let len = header.cmsg_len as usize;
+		if len < header_len {
+			return None;
+		}
+		let data = &rest[header_len..len.min(rest.len())];
+		self.offset += cmsg_space(len - header_len);
+		Some(decode(header.cmsg_level, header.cmsg_type, data))
+	}
+}
+
+fn decode(level: libc::c_int, ty: libc::c_int, data: &[u8]) -> Cmsg<'_> {
+	fn read<T: Copy>(data: &[u8]) -> Option<T> {
+		(data.len() >= std::mem::size_of::<T>()).then(|| unsafe { std::ptr::read_unaligned(data.as_ptr() as *const T) })
+	}
+	fn time(ts: libc::timespec) -> Option<SystemTime> {
+		(ts.tv_sec != 0 || ts.tv_nsec != 0)
+			.then(|| SystemTime::UNIX_EPOCH + Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32))
+	}
+
+	let decoded = match (level, ty) {
+		(libc::SOL_SOCKET, libc::SCM_RIGHTS) => Some(Cmsg::Rights(
+			data.chunks_exact(4).map(|c| RawFd::from_ne_bytes([c[0], c[1], c[2], c[3]])).collect(),
+		)),
+		(libc::SOL_SOCKET, libc::SCM_CREDENTIALS) => {
+			read::<libc::ucred>(data).map(|cred| Cmsg::Credentials(Credentials::from_raw(&cred)))
+		}
+		(libc::SOL_SOCKET, libc::SO_TIMESTAMP) => read::<libc::timeval>(data).map(|tv| {
+			Cmsg::Timestamp(SystemTime::UNIX_EPOCH + Duration::new(tv.tv_sec as u64, tv.tv_usec as u32 * 1000))
+		}),
+		(libc::SOL_SOCKET, libc::SO_TIMESTAMPNS) => read::<libc::timespec>(data).map(|ts| {
+			Cmsg::Timestamp(SystemTime::UNIX_EPOCH + Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32))
+		}),
+		// struct scm_timestamping: [software, deprecated, raw hardware].
+		(libc::SOL_SOCKET, libc::SO_TIMESTAMPING) => read::<[libc::timespec; 3]>(data).map(|ts| {
+			Cmsg::Timestamping { software: time(ts[0]), hardware: time(ts[2]) }
+		}),
+		(libc::IPPROTO_IP, libc::IP_PKTINFO) => read::<libc::in_pktinfo>(data).map(|info| {
+			Cmsg::PktInfoV4(RecvInterface {
+				ifindex: info.ipi_ifindex as u32,
+				local_ip: info.ipi_spec_dst.s_addr.to_ne_bytes(),
+				dst_ip: info.ipi_addr.s_addr.to_ne_bytes(),
+			})
+		}),
+		(libc::IPPROTO_IPV6, libc::IPV6_PKTINFO) => read::<libc::in6_pktinfo>(data).map(|info| {
+			Cmsg::PktInfoV6(RecvInterface {
+				ifindex: info.ipi6_ifindex,
+				local_ip: info.ipi6_addr.s6_addr,
+				dst_ip: info.ipi6_addr.s6_addr,
+			})
+		}),
+		// IP_TOS arrives as one byte, IPV6_TCLASS as an int.
+		(libc::IPPROTO_IP, libc::IP_TOS) => data.first().map(|&tos| Cmsg::Tos(tos)),
+		(libc::IPPROTO_IPV6, libc::IPV6_TCLASS) => read::<libc::c_int>(data).map(|tclass| Cmsg::Tos(tclass as u8)),
+		(SOL_UDP, UDP_GRO) => read::<libc::c_int>(data).map(|size| Cmsg::GroSegmentSize(size as u16)),
+		_ => None,
+	};
+	decoded.unwrap_or(Cmsg::Raw { level, ty, data })
+}
+
+/*
+Why keep the one-purpose helpers (send_fd, recv_credentials,
+recv_from_with_tos, ...) next to this?
+They're what most callers need and can't be misused: one control
+message in, one typed value out. send_msg()/recv_msg() are for the
+combinations those don't cover — pktinfo plus TOS on one reply, fds
+together with credentials, GSO segments with a pinned source — where
+each feature used to mean another hand-built msghdr. recv_msg() takes
+the received fds into OwnedFds before returning, so a caller that only
+wanted a timestamp can't leak the fds a peer slipped in.
+*/
@@ -0,0 +1,122 @@
+use std::io::ErrorKind;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+use crate::addr::{Domain, FromSockAddr};
+use crate::error::{IoError, SocketError, errno};
+use super::wait::to_poll_ms;
+use super::{Listener, ConnectedStream};
+
+/// Stream, client address, and index of the listener it arrived on.
+type Accepted<D> = (ConnectedStream<D>, <D as Domain>::Addr, usize);
+
+/// Several listeners of one family, accepted from as one.
+///
+/// Created by `ListenerBuilder::bind_all()` (every address gets the same
+/// options) or `MultiListener::from_listeners()`. `accept()` polls all
+/// of them and takes turns between ready ones, so a busy address can't
+/// starve the others.
+///
+/// For IPv4 and IPv6 clients on the same port, prefer a single `[::]`
+/// listener with `ListenerBuilder::dual_stack(true)`.
+pub struct MultiListener<D: Domain> {
+	listeners: Vec<Listener<D>>,
+	next: AtomicUsize,
+}
+
+impl<D: Domain> MultiListener<D> {
+	/// Combines listeners. Each is switched to non-blocking mode; `accept()`
+	/// waits in `poll()` instead.
+	pub fn from_listeners(listeners: Vec<Listener<D>>) -> std::io::Result<Self> {
+		if listeners.is_empty() {
+			return Err(SocketError::InvalidInput { reason: "MultiListener needs at least one listener" }.into());
+		}
+		for listener in &listeners {
+			listener.set_nonblocking(true)?;
+		}
+		Ok(Self { listeners, next: AtomicUsize::new(0) })
+	}
+
+	/// The underlying listeners, in the order they were bound.
+	pub fn listeners(&self) -> &[Listener<D>] {
+		&self.listeners
+	}
+
+	/// Returns the listeners. They stay non-blocking.
+	pub fn into_listeners(self) -> Vec<Listener<D>> {
+		self.listeners
+	}
+
+	/// Number of listeners.
+	pub fn len(&self) -> usize {
+		self.listeners.len()
+	}
+
+	/// Always false: a `MultiListener` holds at least one listener.
+	pub fn is_empty(&self) -> bool {
+		self.listeners.is_empty()
+	}
+}
+
+impl<D: Domain> MultiListener<D>
+where
+	D::Addr: FromSockAddr,
+{
+	/// Blocks until any listener has a connection and accepts it.
+	///
+	/// Returns the stream (in blocking mode, as from `Listener::accept()`),
+	/// the client address, and the index of the listener it arrived on.
+	pub fn accept(&self) -> std::io::Result<Accepted<D>> {
+		loop {
+			if let Some(accepted) = self.accept_timeout(None)? {
+				return Ok(accepted);
+			}
+		}
+	}
+
+	/// Like `accept()`, but gives up after `timeout` with `Ok(None)`.
+	///
+	/// `None` waits forever. A connection another thread accepted first
+	/// doesn't end the wait early.
+	pub fn accept_timeout(&self, timeout: Option<Duration>) -> std::io::Result<Option<Accepted<D>>> {
+		let deadline = timeout.map(|t| Instant::now() + t);
+		let mut fds: Vec<libc::pollfd> = self.listeners.iter()
+			.map(|l| libc::pollfd { fd: l.as_raw_fd(), events: libc::POLLIN, revents: 0 })
+			.collect();
+
+		loop {
+			let timeout_ms = match deadline {
+				None => -1,
+				Some(deadline) => to_poll_ms(deadline.saturating_duration_since(Instant::now())),
+			};
+			let n = unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, timeout_ms) };
+			match n {
+				-1 if errno() == libc::EINTR => continue,
+				-1 => return Err(IoError::Poll { errno: errno() }.into()),
+				0 => return Ok(None),
+				_ => {}
+			}
+
+			let start = self.next.fetch_add(1, Ordering::Relaxed) % fds.len();
+			for i in (start..fds.len()).chain(0..start) {
+				if fds[i].revents == 0 {
+					continue;
+				}
+				match self.listeners[i].accept_with_addr() {
+					Ok((stream, addr)) => return Ok(Some((stream, addr, i))),
+					// Taken by another thread, or reset before we got to it.
+					Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::Interrupted | ErrorKind::ConnectionAborted) => {}
+					Err(e) => return Err(e),
+				}
+			}
+		}
+	}
+}
+
+/*
+Why poll() over the listeners instead of a thread per address?
+A thread per listener means a channel to merge connections and a way to
+stop the threads on shutdown. With a handful of fds poll() costs nothing,
+and the listeners are non-blocking so a connection stolen between poll()
+and accept() just sends us back to waiting instead of blocking on one
+address while the others queue up.
+*/
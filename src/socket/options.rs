@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+use std::time::Duration;
 use std::os::fd::FromRawFd;
 use std::os::fd::OwnedFd;
 use std::os::fd::RawFd;
@@ -26,6 +28,11 @@ pub fn set_reuse_addr<S: AsRawFd>(socket: &S, enable: bool) -> std::io::Result<(
 	}
 }
 
+/// Gets SO_REUSEADDR.
+pub fn get_reuse_addr<S: AsRawFd>(socket: &S) -> std::io::Result<bool> {
+	Ok(get_int(socket, libc::SOL_SOCKET, libc::SO_REUSEADDR, "SO_REUSEADDR")? != 0)
+}
+
 /// Sets SO_REUSEPORT on a socket.
 ///
 /// Allows multiple sockets to bind the same port.
@@ -48,6 +55,651 @@ pub fn set_reuse_port<S: AsRawFd>(socket: &S, enable: bool) -> std::io::Result<(
 	}
 }
 
+/// Gets SO_REUSEPORT.
+pub fn get_reuse_port<S: AsRawFd>(socket: &S) -> std::io::Result<bool> {
+	Ok(get_int(socket, libc::SOL_SOCKET, libc::SO_REUSEPORT, "SO_REUSEPORT")? != 0)
+}
+
+/// Sets IPV6_V6ONLY on an IPv6 socket.
+///
+/// When enabled, the socket only handles IPv6 traffic — IPv4 clients
+/// can't reach it as `::ffff:a.b.c.d` mapped addresses.
+/// Must be set before `bind()`. The default comes from
+/// `/proc/sys/net/ipv6/bindv6only` (usually off).
+pub fn set_only_v6<S: AsRawFd>(socket: &S, enable: bool) -> std::io::Result<()> {
+	let val: libc::c_int = if enable { 1 } else { 0 };
+	let result = unsafe {
+		libc::setsockopt(
+			socket.as_raw_fd(),
+			libc::IPPROTO_IPV6,
+			libc::IPV6_V6ONLY,
+			&val as *const _ as *const libc::c_void,
+			std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+		)
+	};
+	if result == -1 {
+		Err(SocketError::SetOption { errno: errno(), option: "IPV6_V6ONLY" }.into())
+	} else {
+		Ok(())
+	}
+}
+
+/// Gets IPV6_V6ONLY — the effective value, including the sysctl default.
+pub fn get_only_v6<S: AsRawFd>(socket: &S) -> std::io::Result<bool> {
+	Ok(get_int(socket, libc::IPPROTO_IPV6, libc::IPV6_V6ONLY, "IPV6_V6ONLY")? != 0)
+}
+
+/// Sets IP_TOS on an IPv4 socket.
+///
+/// `tos` is DSCP (upper 6 bits) + ECN (lower 2) and applies to every
+/// packet sent. For per-packet marking see `send_to_with_tos()`.
+pub fn set_tos<S: AsRawFd>(socket: &S, tos: u8) -> std::io::Result<()> {
+	let val = tos as libc::c_int;
+	let result = unsafe {
+		libc::setsockopt(
+			socket.as_raw_fd(),
+			libc::IPPROTO_IP,
+			libc::IP_TOS,
+			&val as *const _ as *const libc::c_void,
+			std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+		)
+	};
+	if result == -1 {
+		Err(SocketError::SetOption { errno: errno(), option: "IP_TOS" }.into())
+	} else {
+		Ok(())
+	}
+}
+
+/// Gets IP_TOS.
+pub fn get_tos<S: AsRawFd>(socket: &S) -> std::io::Result<u8> {
+	Ok(get_int(socket, libc::IPPROTO_IP, libc::IP_TOS, "IP_TOS")? as u8)
+}
+
+/// Sets IPV6_TCLASS on an IPv6 socket.
+///
+/// The IPv6 counterpart of `set_tos()`: same DSCP + ECN layout.
+pub fn set_tclass<S: AsRawFd>(socket: &S, tclass: u8) -> std::io::Result<()> {
+	let val = tclass as libc::c_int;
+	let result = unsafe {
+		libc::setsockopt(
+			socket.as_raw_fd(),
+			libc::IPPROTO_IPV6,
+			libc::IPV6_TCLASS,
+			&val as *const _ as *const libc::c_void,
+			std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+		)
+	};
+	if result == -1 {
+		Err(SocketError::SetOption { errno: errno(), option: "IPV6_TCLASS" }.into())
+	} else {
+		Ok(())
+	}
+}
+
+/// Gets IPV6_TCLASS.
+pub fn get_tclass<S: AsRawFd>(socket: &S) -> std::io::Result<u8> {
+	Ok(get_int(socket, libc::IPPROTO_IPV6, libc::IPV6_TCLASS, "IPV6_TCLASS")? as u8)
+}
+
+/// Sets IP_RECVTOS on an IPv4 datagram socket.
+///
+/// Each received packet then carries its TOS byte, read with
+/// `recv_from_with_tos()`.
+pub fn set_recv_tos<S: AsRawFd>(socket: &S, enable: bool) -> std::io::Result<()> {
+	let val: libc::c_int = if enable { 1 } else { 0 };
+	let result = unsafe {
+		libc::setsockopt(
+			socket.as_raw_fd(),
+			libc::IPPROTO_IP,
+			libc::IP_RECVTOS,
+			&val as *const _ as *const libc::c_void,
+			std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+		)
+	};
+	if result == -1 {
+		Err(SocketError::SetOption { errno: errno(), option: "IP_RECVTOS" }.into())
+	} else {
+		Ok(())
+	}
+}
+
+/// Gets IP_RECVTOS.
+pub fn get_recv_tos<S: AsRawFd>(socket: &S) -> std::io::Result<bool> {
+	Ok(get_int(socket, libc::IPPROTO_IP, libc::IP_RECVTOS, "IP_RECVTOS")? != 0)
+}
+
+/// Sets IPV6_RECVTCLASS on an IPv6 datagram socket.
+///
+/// The IPv6 counterpart of `set_recv_tos()`.
+pub fn set_recv_tclass<S: AsRawFd>(socket: &S, enable: bool) -> std::io::Result<()> {
+	let val: libc::c_int = if enable { 1 } else { 0 };
+	let result = unsafe {
+		libc::setsockopt(
+			socket.as_raw_fd(),
+			libc::IPPROTO_IPV6,
+			libc::IPV6_RECVTCLASS,
+			&val as *const _ as *const libc::c_void,
+			std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+		)
+	};
+	if result == -1 {
+		Err(SocketError::SetOption { errno: errno(), option: "IPV6_RECVTCLASS" }.into())
+	} else {
+		Ok(())
+	}
+}
+
+/// Gets IPV6_RECVTCLASS.
+pub fn get_recv_tclass<S: AsRawFd>(socket: &S) -> std::io::Result<bool> {
+	Ok(get_int(socket, libc::IPPROTO_IPV6, libc::IPV6_RECVTCLASS, "IPV6_RECVTCLASS")? != 0)
+}
+
+/// Sets IP_TRANSPARENT on an IPv4 socket (needs CAP_NET_ADMIN).
+///
+/// Lets the socket bind to non-local addresses and accept traffic
+/// redirected by an iptables/nftables TPROXY rule. Set before `bind()`.
+pub fn set_ip_transparent<S: AsRawFd>(socket: &S, enable: bool) -> std::io::Result<()> {
+	let val: libc::c_int = if enable { 1 } else { 0 };
+	let result = unsafe {
+		libc::setsockopt(
+			socket.as_raw_fd(),
+			libc::IPPROTO_IP,
+			libc::IP_TRANSPARENT,
+			&val as *const _ as *const libc::c_void,
+			std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+		)
+	};
+	if result == -1 {
+		Err(SocketError::SetOption { errno: errno(), option: "IP_TRANSPARENT" }.into())
+	} else {
+		Ok(())
+	}
+}
+
+/// Gets IP_TRANSPARENT.
+pub fn get_ip_transparent<S: AsRawFd>(socket: &S) -> std::io::Result<bool> {
+	Ok(get_int(socket, libc::IPPROTO_IP, libc::IP_TRANSPARENT, "IP_TRANSPARENT")? != 0)
+}
+
+/// Sets IPV6_TRANSPARENT on an IPv6 socket (needs CAP_NET_ADMIN).
+///
+/// The IPv6 counterpart of `set_ip_transparent()`.
+pub fn set_ip_transparent_v6<S: AsRawFd>(socket: &S, enable: bool) -> std::io::Result<()> {
+	let val: libc::c_int = if enable { 1 } else { 0 };
+	let result = unsafe {
+		libc::setsockopt(
+			socket.as_raw_fd(),
+			libc::IPPROTO_IPV6,
+			libc::IPV6_TRANSPARENT,
+			&val as *const _ as *const libc::c_void,
+			std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+		)
+	};
+	if result == -1 {
+		Err(SocketError::SetOption { errno: errno(), option: "IPV6_TRANSPARENT" }.into())
+	} else {
+		Ok(())
+	}
+}
+
+/// Gets IPV6_TRANSPARENT.
+pub fn get_ip_transparent_v6<S: AsRawFd>(socket: &S) -> std::io::Result<bool> {
+	Ok(get_int(socket, libc::IPPROTO_IPV6, libc::IPV6_TRANSPARENT, "IPV6_TRANSPARENT")? != 0)
+}
+
+/// Sets IP_RECVORIGDSTADDR on an IPv4 datagram socket.
+///
+/// Each received datagram then carries the destination it was originally
+/// sent to, read with `recv_from_with_orig_dst()`. Behind TPROXY that's
+/// the real server address, not the proxy's.
+pub fn set_recv_orig_dst<S: AsRawFd>(socket: &S, enable: bool) -> std::io::Result<()> {
+	let val: libc::c_int = if enable { 1 } else { 0 };
+	let result = unsafe {
+		libc::setsockopt(
+			socket.as_raw_fd(),
+			libc::IPPROTO_IP,
+			libc::IP_RECVORIGDSTADDR,
+			&val as *const _ as *const libc::c_void,
+			std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+		)
+	};
+	if result == -1 {
+		Err(SocketError::SetOption { errno: errno(), option: "IP_RECVORIGDSTADDR" }.into())
+	} else {
+		Ok(())
+	}
+}
+
+/// Sets IPV6_RECVORIGDSTADDR on an IPv6 datagram socket.
+///
+/// The IPv6 counterpart of `set_recv_orig_dst()`.
+pub fn set_recv_orig_dst_v6<S: AsRawFd>(socket: &S, enable: bool) -> std::io::Result<()> {
+	let val: libc::c_int = if enable { 1 } else { 0 };
+	let result = unsafe {
+		libc::setsockopt(
+			socket.as_raw_fd(),
+			libc::IPPROTO_IPV6,
+			libc::IPV6_RECVORIGDSTADDR,
+			&val as *const _ as *const libc::c_void,
+			std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+		)
+	};
+	if result == -1 {
+		Err(SocketError::SetOption { errno: errno(), option: "IPV6_RECVORIGDSTADDR" }.into())
+	} else {
+		Ok(())
+	}
+}
+
+/// Sets IP_BIND_ADDRESS_NO_PORT: a `bind()` to port 0 picks only the
+/// source address, and the port is chosen at `connect()`.
+///
+/// Ports are then allocated per destination, as for an unbound client,
+/// instead of reserving one out of the ephemeral range for good. Set
+/// before `bind()`; IPv4 and IPv6 sockets alike. No effect when binding
+/// a specific port.
+pub fn set_bind_address_no_port<S: AsRawFd>(socket: &S, enable: bool) -> std::io::Result<()> {
+	let val: libc::c_int = if enable { 1 } else { 0 };
+	let result = unsafe {
+		libc::setsockopt(
+			socket.as_raw_fd(),
+			libc::IPPROTO_IP,
+			libc::IP_BIND_ADDRESS_NO_PORT,
+			&val as *const _ as *const libc::c_void,
+			std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+		)
+	};
+	if result == -1 {
+		Err(SocketError::SetOption { errno: errno(), option: "IP_BIND_ADDRESS_NO_PORT" }.into())
+	} else {
+		Ok(())
+	}
+}
+
+/// Sets IP_RECVERR on an IPv4 socket.
+///
+/// ICMP errors about sent packets are then queued with their details
+/// (type, code, offending router) for `error_queue()`, and soft errors
+/// like host unreachable are reported too, not just port unreachable.
+pub fn set_recv_err<S: AsRawFd>(socket: &S, enable: bool) -> std::io::Result<()> {
+	let val: libc::c_int = if enable { 1 } else { 0 };
+	let result = unsafe {
+		libc::setsockopt(
+			socket.as_raw_fd(),
+			libc::IPPROTO_IP,
+			libc::IP_RECVERR,
+			&val as *const _ as *const libc::c_void,
+			std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+		)
+	};
+	if result == -1 {
+		Err(SocketError::SetOption { errno: errno(), option: "IP_RECVERR" }.into())
+	} else {
+		Ok(())
+	}
+}
+
+/// Sets IPV6_RECVERR on an IPv6 socket.
+///
+/// The IPv6 counterpart of `set_recv_err()`.
+pub fn set_recv_err_v6<S: AsRawFd>(socket: &S, enable: bool) -> std::io::Result<()> {
+	let val: libc::c_int = if enable { 1 } else { 0 };
+	let result = unsafe {
+		libc::setsockopt(
+			socket.as_raw_fd(),
+			libc::IPPROTO_IPV6,
+			libc::IPV6_RECVERR,
+			&val as *const _ as *const libc::c_void,
+			std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+		)
+	};
+	if result == -1 {
+		Err(SocketError::SetOption { errno: errno(), option: "IPV6_RECVERR" }.into())
+	} else {
+		Ok(())
+	}
+}
+
+/// Sets SO_BROADCAST, allowing sends to broadcast addresses.
+///
+/// Without it the kernel rejects them with EACCES.
+pub fn set_broadcast<S: AsRawFd>(socket: &S, enable: bool) -> std::io::Result<()> {
+	let val: libc::c_int = if enable { 1 } else { 0 };
+	let result = unsafe {
+		libc::setsockopt(
+			socket.as_raw_fd(),
+			libc::SOL_SOCKET,
+			libc::SO_BROADCAST,
+			&val as *const _ as *const libc::c_void,
+			std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+		)
+	};
+	if result == -1 {
+		Err(SocketError::SetOption { errno: errno(), option: "SO_BROADCAST" }.into())
+	} else {
+		Ok(())
+	}
+}
+
+/// Gets SO_BROADCAST.
+pub fn get_broadcast<S: AsRawFd>(socket: &S) -> std::io::Result<bool> {
+	Ok(get_int(socket, libc::SOL_SOCKET, libc::SO_BROADCAST, "SO_BROADCAST")? != 0)
+}
+
+/// Sets IP_MULTICAST_TTL, how many hops outgoing IPv4 multicast travels.
+///
+/// Defaults to 1 (local network only); 0 keeps it on this host.
+pub fn set_multicast_ttl<S: AsRawFd>(socket: &S, ttl: u8) -> std::io::Result<()> {
+	let val: libc::c_int = ttl as libc::c_int;
+	let result = unsafe {
+		libc::setsockopt(
+			socket.as_raw_fd(),
+			libc::IPPROTO_IP,
+			libc::IP_MULTICAST_TTL,
+			&val as *const _ as *const libc::c_void,
+			std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+		)
+	};
+	if result == -1 {
+		Err(SocketError::SetOption { errno: errno(), option: "IP_MULTICAST_TTL" }.into())
+	} else {
+		Ok(())
+	}
+}
+
+/// Gets IP_MULTICAST_TTL.
+pub fn get_multicast_ttl<S: AsRawFd>(socket: &S) -> std::io::Result<u8> {
+	Ok(get_int(socket, libc::IPPROTO_IP, libc::IP_MULTICAST_TTL, "IP_MULTICAST_TTL")? as u8)
+}
+
+/// Sets IPV6_MULTICAST_HOPS, the IPv6 counterpart of `set_multicast_ttl()`.
+pub fn set_multicast_hops_v6<S: AsRawFd>(socket: &S, hops: u8) -> std::io::Result<()> {
+	let val: libc::c_int = hops as libc::c_int;
+	let result = unsafe {
+		libc::setsockopt(
+			socket.as_raw_fd(),
+			libc::IPPROTO_IPV6,
+			libc::IPV6_MULTICAST_HOPS,
+			&val as *const _ as *const libc::c_void,
+			std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+		)
+	};
+	if result == -1 {
+		Err(SocketError::SetOption { errno: errno(), option: "IPV6_MULTICAST_HOPS" }.into())
+	} else {
+		Ok(())
+	}
+}
+
+/// Gets IPV6_MULTICAST_HOPS.
+pub fn get_multicast_hops_v6<S: AsRawFd>(socket: &S) -> std::io::Result<u8> {
+	Ok(get_int(socket, libc::IPPROTO_IPV6, libc::IPV6_MULTICAST_HOPS, "IPV6_MULTICAST_HOPS")? as u8)
+}
+
+/// Sets IP_PKTINFO on an IPv4 datagram socket.
+///
+/// Each received packet then carries its arrival interface and local
+/// address, read with `recv_from_if()`.
+pub fn set_recv_pktinfo<S: AsRawFd>(socket: &S, enable: bool) -> std::io::Result<()> {
+	let val: libc::c_int = if enable { 1 } else { 0 };
+	let result = unsafe {
+		libc::setsockopt(
+			socket.as_raw_fd(),
+			libc::IPPROTO_IP,
+			libc::IP_PKTINFO,
+			&val as *const _ as *const libc::c_void,
+			std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+		)
+	};
+	if result == -1 {
+		Err(SocketError::SetOption { errno: errno(), option: "IP_PKTINFO" }.into())
+	} else {
+		Ok(())
+	}
+}
+
+/// Sets IPV6_RECVPKTINFO on an IPv6 datagram socket.
+///
+/// The IPv6 counterpart of `set_recv_pktinfo()`.
+pub fn set_recv_pktinfo_v6<S: AsRawFd>(socket: &S, enable: bool) -> std::io::Result<()> {
+	let val: libc::c_int = if enable { 1 } else { 0 };
+	let result = unsafe {
+		libc::setsockopt(
+			socket.as_raw_fd(),
+			libc::IPPROTO_IPV6,
+			libc::IPV6_RECVPKTINFO,
+			&val as *const _ as *const libc::c_void,
+			std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+		)
+	};
+	if result == -1 {
+		Err(SocketError::SetOption { errno: errno(), option: "IPV6_RECVPKTINFO" }.into())
+	} else {
+		Ok(())
+	}
+}
+
+/// Joins an IPv4 multicast group (IP_ADD_MEMBERSHIP).
+///
+/// `interface` is the local address of the interface to join on;
+/// `[0, 0, 0, 0]` lets the kernel pick by routing table.
+pub fn join_multicast_v4<S: AsRawFd>(socket: &S, group: [u8; 4], interface: [u8; 4]) -> std::io::Result<()> {
+	set_membership_v4(socket, libc::IP_ADD_MEMBERSHIP, group, interface, "IP_ADD_MEMBERSHIP")
+}
+
+/// Leaves an IPv4 multicast group (IP_DROP_MEMBERSHIP).
+pub fn leave_multicast_v4<S: AsRawFd>(socket: &S, group: [u8; 4], interface: [u8; 4]) -> std::io::Result<()> {
+	set_membership_v4(socket, libc::IP_DROP_MEMBERSHIP, group, interface, "IP_DROP_MEMBERSHIP")
+}
+
+fn set_membership_v4<S: AsRawFd>(socket: &S, opt: libc::c_int, group: [u8; 4], interface: [u8; 4], name: &'static str) -> std::io::Result<()> {
+	let mreq = libc::ip_mreq {
+		imr_multiaddr: libc::in_addr { s_addr: u32::from_ne_bytes(group) },
+		imr_interface: libc::in_addr { s_addr: u32::from_ne_bytes(interface) },
+	};
+	let result = unsafe {
+		libc::setsockopt(
+			socket.as_raw_fd(),
+			libc::IPPROTO_IP,
+			opt,
+			&mreq as *const _ as *const libc::c_void,
+			std::mem::size_of::<libc::ip_mreq>() as libc::socklen_t,
+		)
+	};
+	if result == -1 {
+		Err(SocketError::SetOption { errno: errno(), option: name }.into())
+	} else {
+		Ok(())
+	}
+}
+
+/// Joins an IPv6 multicast group (IPV6_ADD_MEMBERSHIP).
+///
+/// `interface` is an interface index; 0 lets the kernel pick.
+pub fn join_multicast_v6<S: AsRawFd>(socket: &S, group: [u8; 16], interface: u32) -> std::io::Result<()> {
+	set_membership_v6(socket, libc::IPV6_ADD_MEMBERSHIP, group, interface, "IPV6_ADD_MEMBERSHIP")
+}
+
+/// Leaves an IPv6 multicast group (IPV6_DROP_MEMBERSHIP).
+pub fn leave_multicast_v6<S: AsRawFd>(socket: &S, group: [u8; 16], interface: u32) -> std::io::Result<()> {
+	set_membership_v6(socket, libc::IPV6_DROP_MEMBERSHIP, group, interface, "IPV6_DROP_MEMBERSHIP")
+}
+
+fn set_membership_v6<S: AsRawFd>(socket: &S, opt: libc::c_int, group: [u8; 16], interface: u32, name: &'static str) -> std::io::Result<()> {
+	let mreq = libc::ipv6_mreq {
+		ipv6mr_multiaddr: libc::in6_addr { s6_addr: group },
+		ipv6mr_interface: interface as _,
+	};
+	let result = unsafe {
+		libc::setsockopt(
+			socket.as_raw_fd(),
+			libc::IPPROTO_IPV6,
+			opt,
+			&mreq as *const _ as *const libc::c_void,
+			std::mem::size_of::<libc::ipv6_mreq>() as libc::socklen_t,
+		)
+	};
+	if result == -1 {
+		Err(SocketError::SetOption { errno: errno(), option: name }.into())
+	} else {
+		Ok(())
+	}
+}
+
+/// Joins an IPv4 source-specific multicast channel (IP_ADD_SOURCE_MEMBERSHIP).
+///
+/// Only datagrams from `source` to `group` are delivered (IGMPv3).
+/// `interface` is the local address of the interface to join on;
+/// `[0, 0, 0, 0]` lets the kernel pick by routing table.
+pub fn join_ssm_v4<S: AsRawFd>(socket: &S, group: [u8; 4], source: [u8; 4], interface: [u8; 4]) -> std::io::Result<()> {
+	set_source_membership_v4(socket, libc::IP_ADD_SOURCE_MEMBERSHIP, group, source, interface, "IP_ADD_SOURCE_MEMBERSHIP")
+}
+
+/// Leaves an IPv4 source-specific multicast channel (IP_DROP_SOURCE_MEMBERSHIP).
+pub fn leave_ssm_v4<S: AsRawFd>(socket: &S, group: [u8; 4], source: [u8; 4], interface: [u8; 4]) -> std::io::Result<()> {
+	set_source_membership_v4(socket, libc::IP_DROP_SOURCE_MEMBERSHIP, group, source, interface, "IP_DROP_SOURCE_MEMBERSHIP")
+}
+
+fn set_source_membership_v4<S: AsRawFd>(
+	socket: &S,
+	opt: libc::c_int,
+	group: [u8; 4],
+	source: [u8; 4],
+	interface: [u8; 4],
+	name: &'static str,
+) -> std::io::Result<()> {
+	let mreq = libc::ip_mreq_source {
+		imr_multiaddr: libc::in_addr { s_addr: u32::from_ne_bytes(group) },
+		imr_interface: libc::in_addr { s_addr: u32::from_ne_bytes(interface) },
+		imr_sourceaddr: libc::in_addr { s_addr: u32::from_ne_bytes(source) },
+	};
+	let result = unsafe {
+		libc::setsockopt(
+			socket.as_raw_fd(),
+			libc::IPPROTO_IP,
+			opt,
+			&mreq as *const _ as *const libc::c_void,
+			std::mem::size_of::<libc::ip_mreq_source>() as libc::socklen_t,
+		)
+	};
+	if result == -1 {
+		Err(SocketError::SetOption { errno: errno(), option: name }.into())
+	} else {
+		Ok(())
+	}
+}
+
+/// Joins an IPv6 source-specific multicast channel (MCAST_JOIN_SOURCE_GROUP).
+///
+/// Only datagrams from `source` to `group` are delivered (MLDv2).
+/// `interface` is an interface index; 0 lets the kernel pick.
+pub fn join_ssm_v6<S: AsRawFd>(socket: &S, group: [u8; 16], source: [u8; 16], interface: u32) -> std::io::Result<()> {
+	set_source_membership_v6(socket, libc::MCAST_JOIN_SOURCE_GROUP, group, source, interface, "MCAST_JOIN_SOURCE_GROUP")
+}
+
+/// Leaves an IPv6 source-specific multicast channel (MCAST_LEAVE_SOURCE_GROUP).
+pub fn leave_ssm_v6<S: AsRawFd>(socket: &S, group: [u8; 16], source: [u8; 16], interface: u32) -> std::io::Result<()> {
+	set_source_membership_v6(socket, libc::MCAST_LEAVE_SOURCE_GROUP, group, source, interface, "MCAST_LEAVE_SOURCE_GROUP")
+}
+
+fn set_source_membership_v6<S: AsRawFd>(
+	socket: &S,
+	opt: libc::c_int,
+	group: [u8; 16],
+	source: [u8; 16],
+	interface: u32,
+	name: &'static str,
+) -> std::io::Result<()> {
+	fn storage(ip: [u8; 16]) -> libc::sockaddr_storage {
+		let mut storage: libc::sockaddr_storage = unsafe { std::mem::zeroed() };
+		let sin6 = &mut storage as *mut _ as *mut libc::sockaddr_in6;
+		unsafe {
+			(*sin6).sin6_family = libc::AF_INET6 as libc::sa_family_t;
+			(*sin6).sin6_addr = libc::in6_addr { s6_addr: ip };
+		}
+		storage
+	}
+
+	let req = libc::group_source_req {
+		gsr_interface: interface,
+		gsr_group: storage(group),
+		gsr_source: storage(source),
+	};
+	// The generic MCAST_* options are not IPv6-specific; the level picks
+	// the family of the socket they apply to.
+	let result = unsafe {
+		libc::setsockopt(
+			socket.as_raw_fd(),
+			libc::IPPROTO_IPV6,
+			opt,
+			&req as *const _ as *const libc::c_void,
+			std::mem::size_of::<libc::group_source_req>() as libc::socklen_t,
+		)
+	};
+	if result == -1 {
+		Err(SocketError::SetOption { errno: errno(), option: name }.into())
+	} else {
+		Ok(())
+	}
+}
+
+/// Sets IP_HDRINCL on an IPv4 raw socket.
+///
+/// When enabled, each send must start with a complete IPv4 header
+/// (see `Ipv4Header`). The kernel still fills in the checksum and total
+/// length, plus the id and source address if left as zero.
+/// Sockets created with IPPROTO_RAW have this on implicitly.
+pub fn set_header_included<S: AsRawFd>(socket: &S, enable: bool) -> std::io::Result<()> {
+	let val: libc::c_int = if enable { 1 } else { 0 };
+	let result = unsafe {
+		libc::setsockopt(
+			socket.as_raw_fd(),
+			libc::IPPROTO_IP,
+			libc::IP_HDRINCL,
+			&val as *const _ as *const libc::c_void,
+			std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+		)
+	};
+	if result == -1 {
+		Err(SocketError::SetOption { errno: errno(), option: "IP_HDRINCL" }.into())
+	} else {
+		Ok(())
+	}
+}
+
+/// Gets IP_HDRINCL. True for IPPROTO_RAW sockets even if never set.
+pub fn get_header_included<S: AsRawFd>(socket: &S) -> std::io::Result<bool> {
+	Ok(get_int(socket, libc::IPPROTO_IP, libc::IP_HDRINCL, "IP_HDRINCL")? != 0)
+}
+
+/// Sets IPV6_HDRINCL on an IPv6 raw socket.
+///
+/// IPv6 counterpart of `set_header_included()`: sends must start with a
+/// full 40-byte IPv6 header. Unlike IPv4, nothing is filled in for you.
+pub fn set_header_included_v6<S: AsRawFd>(socket: &S, enable: bool) -> std::io::Result<()> {
+	let val: libc::c_int = if enable { 1 } else { 0 };
+	let result = unsafe {
+		libc::setsockopt(
+			socket.as_raw_fd(),
+			libc::IPPROTO_IPV6,
+			libc::IPV6_HDRINCL,
+			&val as *const _ as *const libc::c_void,
+			std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+		)
+	};
+	if result == -1 {
+		Err(SocketError::SetOption { errno: errno(), option: "IPV6_HDRINCL" }.into())
+	} else {
+		Ok(())
+	}
+}
+
+/// Gets IPV6_HDRINCL.
+pub fn get_header_included_v6<S: AsRawFd>(socket: &S) -> std::io::Result<bool> {
+	Ok(get_int(socket, libc::IPPROTO_IPV6, libc::IPV6_HDRINCL, "IPV6_HDRINCL")? != 0)
+}
+
 /// Sets TCP_NODELAY on a socket.
 ///
 /// Disables Nagle's algorithm — sends data immediately.
@@ -70,6 +722,11 @@ pub fn set_tcp_nodelay<S: AsRawFd>(socket: &S, enable: bool) -> std::io::Result<
 	}
 }
 
+/// Gets TCP_NODELAY.
+pub fn get_tcp_nodelay<S: AsRawFd>(socket: &S) -> std::io::Result<bool> {
+	Ok(get_int(socket, libc::IPPROTO_TCP, libc::TCP_NODELAY, "TCP_NODELAY")? != 0)
+}
+
 /// Sets receive buffer size (SO_RCVBUF).
 ///
 /// Controls how much data the kernel buffers for incoming packets.
@@ -96,6 +753,15 @@ pub fn set_recv_buffer_size<S: AsRawFd>(socket: &S, size: usize) -> std::io::Res
 	}
 }
 
+/// Gets the effective receive buffer size (SO_RCVBUF).
+///
+/// This is what the kernel actually uses: twice the requested size
+/// (the extra half covers bookkeeping), clamped to
+/// `net.core.rmem_max` × 2 and a minimum of a few KiB.
+pub fn get_recv_buffer_size<S: AsRawFd>(socket: &S) -> std::io::Result<usize> {
+	Ok(get_int(socket, libc::SOL_SOCKET, libc::SO_RCVBUF, "SO_RCVBUF")? as usize)
+}
+
 /// Sets send buffer size (SO_SNDBUF).
 ///
 /// Controls how much outgoing data the kernel buffers before blocking/returning EAGAIN.
@@ -121,6 +787,13 @@ pub fn set_send_buffer_size<S: AsRawFd>(socket: &S, size: usize) -> std::io::Res
 	}
 }
 
+/// Gets the effective send buffer size (SO_SNDBUF).
+///
+/// Doubled and clamped like `get_recv_buffer_size()`, against `net.core.wmem_max`.
+pub fn get_send_buffer_size<S: AsRawFd>(socket: &S) -> std::io::Result<usize> {
+	Ok(get_int(socket, libc::SOL_SOCKET, libc::SO_SNDBUF, "SO_SNDBUF")? as usize)
+}
+
 
 /// Enables TCP keep-alive (SO_KEEPALIVE).
 ///
@@ -145,6 +818,11 @@ pub fn set_keepalive<S: AsRawFd>(socket: &S, enable: bool) -> std::io::Result<()
 	}
 }
 
+/// Gets SO_KEEPALIVE.
+pub fn get_keepalive<S: AsRawFd>(socket: &S) -> std::io::Result<bool> {
+	Ok(get_int(socket, libc::SOL_SOCKET, libc::SO_KEEPALIVE, "SO_KEEPALIVE")? != 0)
+}
+
 /// Sets TCP keep-alive idle time (TCP_KEEPIDLE).
 ///
 /// Seconds of idle time before the first keep-alive probe is sent.
@@ -168,6 +846,11 @@ pub fn set_keepalive_idle<S: AsRawFd>(socket: &S, seconds: u32) -> std::io::Resu
 	}
 }
 
+/// Gets TCP_KEEPIDLE in seconds — the sysctl default if never set.
+pub fn get_keepalive_idle<S: AsRawFd>(socket: &S) -> std::io::Result<u32> {
+	Ok(get_int(socket, libc::IPPROTO_TCP, libc::TCP_KEEPIDLE, "TCP_KEEPIDLE")? as u32)
+}
+
 /// Sets TCP keep-alive probe interval (TCP_KEEPINTVL).
 ///
 /// Seconds between successive keep-alive probes if no response.
@@ -178,74 +861,221 @@ pub fn set_keepalive_interval<S: AsRawFd>(socket: &S, seconds: u32) -> std::io::
 	let result = unsafe {
 		libc::setsockopt(
 			socket.as_raw_fd(),
-			libc::IPPROTO_TCP,
-			libc::TCP_KEEPINTVL,
+			libc::IPPROTO_TCP,
+			libc::TCP_KEEPINTVL,
+			&val as *const _ as *const libc::c_void,
+			std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+		)
+	};
+	if result == -1 {
+		Err(SocketError::SetOption { errno: errno(), option: "socket option" }.into())
+	} else {
+		Ok(())
+	}
+}
+
+/// Gets TCP_KEEPINTVL in seconds — the sysctl default if never set.
+pub fn get_keepalive_interval<S: AsRawFd>(socket: &S) -> std::io::Result<u32> {
+	Ok(get_int(socket, libc::IPPROTO_TCP, libc::TCP_KEEPINTVL, "TCP_KEEPINTVL")? as u32)
+}
+
+/// Sets TCP keep-alive probe count (TCP_KEEPCNT).
+///
+/// Number of unacknowledged probes before connection is considered dead.
+/// Default is typically 9. For faster detection, use 3-5.
+/// Total detection time = KEEPIDLE + (KEEPINTVL × KEEPCNT).
+/// Requires SO_KEEPALIVE to be enabled.
+pub fn set_keepalive_count<S: AsRawFd>(socket: &S, count: u32) -> std::io::Result<()> {
+	let val = count as libc::c_int;
+	let result = unsafe {
+		libc::setsockopt(
+			socket.as_raw_fd(),
+			libc::IPPROTO_TCP,
+			libc::TCP_KEEPCNT,
+			&val as *const _ as *const libc::c_void,
+			std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+		)
+	};
+	if result == -1 {
+		Err(SocketError::SetOption { errno: errno(), option: "socket option" }.into())
+	} else {
+		Ok(())
+	}
+}
+
+/// Gets TCP_KEEPCNT — the sysctl default if never set.
+pub fn get_keepalive_count<S: AsRawFd>(socket: &S) -> std::io::Result<u32> {
+	Ok(get_int(socket, libc::IPPROTO_TCP, libc::TCP_KEEPCNT, "TCP_KEEPCNT")? as u32)
+}
+/// Sets socket linger behavior (SO_LINGER).
+///
+/// Controls what happens when close() is called with unsent data:
+/// - `None` — default behavior, close returns immediately, kernel sends data in background
+/// - `Some(0)` — hard reset (RST), discards unsent data, no TIME_WAIT
+/// - `Some(n)` — close blocks up to n seconds waiting for data to send
+///
+/// For high-connection servers: `Some(0)` avoids TIME_WAIT buildup but is aggressive.
+/// For graceful shutdown: `None` or `Some(30)`.
+pub fn set_linger<S: AsRawFd>(socket: &S, linger: Option<u32>) -> std::io::Result<()> {
+	let val = match linger {
+		None => libc::linger { l_onoff: 0, l_linger: 0 },
+		Some(seconds) => libc::linger {
+			l_onoff: 1,
+			l_linger: seconds as libc::c_int
+		},
+	};
+	let result = unsafe {
+		libc::setsockopt(
+			socket.as_raw_fd(),
+			libc::SOL_SOCKET,
+			libc::SO_LINGER,
+			&val as *const _ as *const libc::c_void,
+			std::mem::size_of::<libc::linger>() as libc::socklen_t,
+		)
+	};
+	if result == -1 {
+		Err(SocketError::SetOption { errno: errno(), option: "socket option" }.into())
+	} else {
+		Ok(())
+	}
+}
+
+/// Gets SO_LINGER, in the same form `set_linger()` takes.
+pub fn get_linger<S: AsRawFd>(socket: &S) -> std::io::Result<Option<u32>> {
+	let mut val: libc::linger = unsafe { std::mem::zeroed() };
+	let mut len = std::mem::size_of::<libc::linger>() as libc::socklen_t;
+	let result = unsafe {
+		libc::getsockopt(
+			socket.as_raw_fd(),
+			libc::SOL_SOCKET,
+			libc::SO_LINGER,
+			&mut val as *mut _ as *mut libc::c_void,
+			&mut len,
+		)
+	};
+	if result == -1 {
+		return Err(SocketError::GetOption { errno: errno(), option: "SO_LINGER" }.into());
+	}
+	if val.l_onoff == 0 {
+		Ok(None)
+	} else {
+		Ok(Some(val.l_linger as u32))
+	}
+}
+/// Sets receive timeout (SO_RCVTIMEO).
+///
+/// Blocking reads (and `accept()` on a listener) fail with `WouldBlock`
+/// after `timeout` instead of hanging forever. `None` disables the timeout.
+/// A zero duration is rejected — the kernel would treat it as "no timeout".
+pub fn set_recv_timeout<S: AsRawFd>(socket: &S, timeout: Option<Duration>) -> std::io::Result<()> {
+	set_timeout(socket, libc::SO_RCVTIMEO, "SO_RCVTIMEO", timeout)
+}
+
+/// Gets receive timeout (SO_RCVTIMEO). `None` means no timeout.
+pub fn get_recv_timeout<S: AsRawFd>(socket: &S) -> std::io::Result<Option<Duration>> {
+	get_timeout(socket, libc::SO_RCVTIMEO, "SO_RCVTIMEO")
+}
+
+/// Sets send timeout (SO_SNDTIMEO).
+///
+/// Blocking writes fail with `WouldBlock` after `timeout` if the send buffer
+/// stays full. `None` disables the timeout. A zero duration is rejected.
+pub fn set_send_timeout<S: AsRawFd>(socket: &S, timeout: Option<Duration>) -> std::io::Result<()> {
+	set_timeout(socket, libc::SO_SNDTIMEO, "SO_SNDTIMEO", timeout)
+}
+
+/// Gets send timeout (SO_SNDTIMEO). `None` means no timeout.
+pub fn get_send_timeout<S: AsRawFd>(socket: &S) -> std::io::Result<Option<Duration>> {
+	get_timeout(socket, libc::SO_SNDTIMEO, "SO_SNDTIMEO")
+}
+
+fn set_timeout<S: AsRawFd>(
+	socket: &S,
+	opt: libc::c_int,
+	name: &'static str,
+	timeout: Option<Duration>,
+) -> std::io::Result<()> {
+	let val = match timeout {
+		None => libc::timeval { tv_sec: 0, tv_usec: 0 },
+		Some(d) if d.is_zero() => {
+			return Err(SocketError::SetOption { errno: libc::EINVAL, option: name }.into());
+		}
+		Some(d) => {
+			// Round sub-microsecond timeouts up so they don't become "disabled".
+			let usec = d.subsec_micros().max(if d.as_secs() == 0 { 1 } else { 0 });
+			libc::timeval {
+				tv_sec: d.as_secs().min(libc::time_t::MAX as u64) as libc::time_t,
+				tv_usec: usec as libc::suseconds_t,
+			}
+		}
+	};
+	let result = unsafe {
+		libc::setsockopt(
+			socket.as_raw_fd(),
+			libc::SOL_SOCKET,
+			opt,
 			&val as *const _ as *const libc::c_void,
-			std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+			std::mem::size_of::<libc::timeval>() as libc::socklen_t,
 		)
 	};
 	if result == -1 {
-		Err(SocketError::SetOption { errno: errno(), option: "socket option" }.into())
+		Err(SocketError::SetOption { errno: errno(), option: name }.into())
 	} else {
 		Ok(())
 	}
 }
 
-/// Sets TCP keep-alive probe count (TCP_KEEPCNT).
-///
-/// Number of unacknowledged probes before connection is considered dead.
-/// Default is typically 9. For faster detection, use 3-5.
-/// Total detection time = KEEPIDLE + (KEEPINTVL × KEEPCNT).
-/// Requires SO_KEEPALIVE to be enabled.
-pub fn set_keepalive_count<S: AsRawFd>(socket: &S, count: u32) -> std::io::Result<()> {
-	let val = count as libc::c_int;
+fn get_timeout<S: AsRawFd>(
+	socket: &S,
+	opt: libc::c_int,
+	name: &'static str,
+) -> std::io::Result<Option<Duration>> {
+	let mut val: libc::timeval = unsafe { std::mem::zeroed() };
+	let mut len = std::mem::size_of::<libc::timeval>() as libc::socklen_t;
 	let result = unsafe {
-		libc::setsockopt(
+		libc::getsockopt(
 			socket.as_raw_fd(),
-			libc::IPPROTO_TCP,
-			libc::TCP_KEEPCNT,
-			&val as *const _ as *const libc::c_void,
-			std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+			libc::SOL_SOCKET,
+			opt,
+			&mut val as *mut _ as *mut libc::c_void,
+			&mut len,
 		)
 	};
 	if result == -1 {
-		Err(SocketError::SetOption { errno: errno(), option: "socket option" }.into())
+		return Err(SocketError::GetOption { errno: errno(), option: name }.into());
+	}
+	if val.tv_sec == 0 && val.tv_usec == 0 {
+		Ok(None)
 	} else {
-		Ok(())
+		Ok(Some(Duration::new(val.tv_sec as u64, val.tv_usec as u32 * 1000)))
 	}
 }
-/// Sets socket linger behavior (SO_LINGER).
-///
-/// Controls what happens when close() is called with unsent data:
-/// - `None` — default behavior, close returns immediately, kernel sends data in background
-/// - `Some(0)` — hard reset (RST), discards unsent data, no TIME_WAIT
-/// - `Some(n)` — close blocks up to n seconds waiting for data to send
-///
-/// For high-connection servers: `Some(0)` avoids TIME_WAIT buildup but is aggressive.
-/// For graceful shutdown: `None` or `Some(30)`.
-pub fn set_linger<S: AsRawFd>(socket: &S, linger: Option<u32>) -> std::io::Result<()> {
-	let val = match linger {
-		None => libc::linger { l_onoff: 0, l_linger: 0 },
-		Some(seconds) => libc::linger {
-			l_onoff: 1,
-			l_linger: seconds as libc::c_int
-		},
-	};
+
+/// Reads an int-valued option; backs the `get_*` functions above and
+/// `FdKind::of()`.
+pub(crate) fn get_int<S: AsRawFd>(
+	socket: &S,
+	level: libc::c_int,
+	opt: libc::c_int,
+	name: &'static str,
+) -> std::io::Result<libc::c_int> {
+	let mut val: libc::c_int = 0;
+	let mut len = std::mem::size_of::<libc::c_int>() as libc::socklen_t;
 	let result = unsafe {
-		libc::setsockopt(
+		libc::getsockopt(
 			socket.as_raw_fd(),
-			libc::SOL_SOCKET,
-			libc::SO_LINGER,
-			&val as *const _ as *const libc::c_void,
-			std::mem::size_of::<libc::linger>() as libc::socklen_t,
+			level,
+			opt,
+			&mut val as *mut _ as *mut libc::c_void,
+			&mut len,
 		)
 	};
 	if result == -1 {
-		Err(SocketError::SetOption { errno: errno(), option: "socket option" }.into())
-	} else {
-		Ok(())
+		return Err(SocketError::GetOption { errno: errno(), option: name }.into());
 	}
+	Ok(val)
 }
+
 pub const SPLICE_F_MOVE: u32 = libc::SPLICE_F_MOVE as u32;
 pub const SPLICE_F_NONBLOCK: u32 = libc::SPLICE_F_NONBLOCK as u32;
 pub const SPLICE_F_MORE: u32 = libc::SPLICE_F_MORE as u32;
@@ -314,6 +1144,11 @@ pub fn set_tcp_cork<S: AsRawFd>(socket: &S, enable: bool) -> std::io::Result<()>
 	}
 }
 
+/// Gets TCP_CORK.
+pub fn get_tcp_cork<S: AsRawFd>(socket: &S) -> std::io::Result<bool> {
+	Ok(get_int(socket, libc::IPPROTO_TCP, libc::TCP_CORK, "TCP_CORK")? != 0)
+}
+
 /// Sets TCP_QUICKACK — disables delayed ACKs.
 ///
 /// Normally TCP waits ~40ms hoping to piggyback ACK on response data.
@@ -339,6 +1174,12 @@ pub fn set_tcp_quickack<S: AsRawFd>(socket: &S, enable: bool) -> std::io::Result
 	}
 }
 
+/// Gets TCP_QUICKACK — reflects the current mode, which the kernel
+/// may already have switched back since it was set.
+pub fn get_tcp_quickack<S: AsRawFd>(socket: &S) -> std::io::Result<bool> {
+	Ok(get_int(socket, libc::IPPROTO_TCP, libc::TCP_QUICKACK, "TCP_QUICKACK")? != 0)
+}
+
 /// Enables TCP Fast Open on a listening socket.
 ///
 /// `queue_len` is max pending TFO connections (typically 5-10).
@@ -360,6 +1201,102 @@ pub fn set_tcp_fastopen<S: AsRawFd>(socket: &S, queue_len: i32) -> std::io::Resu
 	}
 }
 
+/// Gets the TCP Fast Open queue length (0 if disabled).
+pub fn get_tcp_fastopen<S: AsRawFd>(socket: &S) -> std::io::Result<i32> {
+	get_int(socket, libc::IPPROTO_TCP, libc::TCP_FASTOPEN, "TCP_FASTOPEN")
+}
+
+/// Sets TCP_SYNCNT — SYN retransmits before connect() gives up.
+///
+/// With the 1s initial RTO doubling each time, `count` retries fail a
+/// blackholed connect after about 2^(count+1) - 1 seconds: 1 → ~3s,
+/// 2 → ~7s, 3 → ~15s. The default (net.ipv4.tcp_syn_retries = 6) is
+/// ~127s. Range 1-127; set before connecting.
+pub fn set_tcp_syn_count<S: AsRawFd>(socket: &S, count: u8) -> std::io::Result<()> {
+	let val = count as libc::c_int;
+	let result = unsafe {
+		libc::setsockopt(
+			socket.as_raw_fd(),
+			libc::IPPROTO_TCP,
+			libc::TCP_SYNCNT,
+			&val as *const _ as *const libc::c_void,
+			std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+		)
+	};
+	if result == -1 {
+		Err(SocketError::SetOption { errno: errno(), option: "TCP_SYNCNT" }.into())
+	} else {
+		Ok(())
+	}
+}
+
+/// Gets TCP_SYNCNT (the system default if never set).
+pub fn get_tcp_syn_count<S: AsRawFd>(socket: &S) -> std::io::Result<u8> {
+	Ok(get_int(socket, libc::IPPROTO_TCP, libc::TCP_SYNCNT, "TCP_SYNCNT")? as u8)
+}
+
+/// Sets TCP_WINDOW_CLAMP — caps the receive window this socket advertises.
+///
+/// Unlike shrinking SO_RCVBUF, this leaves buffer autotuning alone and
+/// only limits how much the peer may have in flight toward us, so
+/// receive memory per socket stays bounded. For 2M connections:
+/// 2M × 64KB clamp = 128GB worst case instead of the autotuned maximum.
+///
+/// Values under half the minimum receive buffer (about 1KB) are raised
+/// to it; 0 removes the clamp, but only before connecting (else EINVAL).
+pub fn set_tcp_window_clamp<S: AsRawFd>(socket: &S, bytes: u32) -> std::io::Result<()> {
+	let val = bytes.min(libc::c_int::MAX as u32) as libc::c_int;
+	let result = unsafe {
+		libc::setsockopt(
+			socket.as_raw_fd(),
+			libc::IPPROTO_TCP,
+			libc::TCP_WINDOW_CLAMP,
+			&val as *const _ as *const libc::c_void,
+			std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+		)
+	};
+	if result == -1 {
+		Err(SocketError::SetOption { errno: errno(), option: "TCP_WINDOW_CLAMP" }.into())
+	} else {
+		Ok(())
+	}
+}
+
+/// Gets TCP_WINDOW_CLAMP (0 if unclamped and not yet connected).
+pub fn get_tcp_window_clamp<S: AsRawFd>(socket: &S) -> std::io::Result<u32> {
+	Ok(get_int(socket, libc::IPPROTO_TCP, libc::TCP_WINDOW_CLAMP, "TCP_WINDOW_CLAMP")?.max(0) as u32)
+}
+
+/// Sets TCP_LINGER2 — how long an orphaned socket stays in FIN_WAIT2.
+///
+/// After we close and the peer ACKs our FIN, the socket waits for the
+/// peer's FIN for `secs` seconds (capped at 120). 0 restores the
+/// system default (net.ipv4.tcp_fin_timeout, 60s); negative skips
+/// FIN_WAIT2 entirely. For 2M connections with peers that never close,
+/// a short value frees kernel memory sooner.
+pub fn set_tcp_linger2<S: AsRawFd>(socket: &S, secs: i32) -> std::io::Result<()> {
+	let result = unsafe {
+		libc::setsockopt(
+			socket.as_raw_fd(),
+			libc::IPPROTO_TCP,
+			libc::TCP_LINGER2,
+			&secs as *const _ as *const libc::c_void,
+			std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+		)
+	};
+	if result == -1 {
+		Err(SocketError::SetOption { errno: errno(), option: "TCP_LINGER2" }.into())
+	} else {
+		Ok(())
+	}
+}
+
+/// Gets TCP_LINGER2 in seconds (the system default if unset, -1 if
+/// FIN_WAIT2 is skipped).
+pub fn get_tcp_linger2<S: AsRawFd>(socket: &S) -> std::io::Result<i32> {
+	get_int(socket, libc::IPPROTO_TCP, libc::TCP_LINGER2, "TCP_LINGER2")
+}
+
 /// TCP connection statistics.
 #[derive(Debug, Clone, Copy, Default)]
 pub struct TcpInfo {
@@ -379,9 +1316,25 @@ pub struct TcpInfo {
 /// Returns RTT, congestion window, retransmit count, etc.
 /// Useful for monitoring and adaptive protocols.
 pub fn get_tcp_info<S: AsRawFd>(socket: &S) -> std::io::Result<TcpInfo> {
+	let info = get_raw_tcp_info(socket)?;
+	Ok(TcpInfo {
+		state: info.tcpi_state,
+		retransmits: info.tcpi_retransmits,
+		probes: info.tcpi_probes,
+		backoff: info.tcpi_backoff,
+		rtt_us: info.tcpi_rtt,
+		rtt_var_us: info.tcpi_rttvar,
+		snd_cwnd: info.tcpi_snd_cwnd,
+		rcv_rtt_us: info.tcpi_rcv_rtt,
+		total_retrans: info.tcpi_total_retrans,
+	})
+}
+
+/// The whole `tcp_info`, for readers of fields `TcpInfo` leaves out.
+pub(crate) fn get_raw_tcp_info<S: AsRawFd>(socket: &S) -> std::io::Result<libc::tcp_info> {
 	let mut info: libc::tcp_info = unsafe { std::mem::zeroed() };
 	let mut len = std::mem::size_of::<libc::tcp_info>() as libc::socklen_t;
-	
+
 	let result = unsafe {
 		libc::getsockopt(
 			socket.as_raw_fd(),
@@ -391,27 +1344,65 @@ pub fn get_tcp_info<S: AsRawFd>(socket: &S) -> std::io::Result<TcpInfo> {
 			&mut len,
 		)
 	};
-	
 	if result == -1 {
 		return Err(SocketError::GetOption { errno: errno(), option: "TCP_INFO" }.into());
 	}
+	Ok(info)
+}
 
-	Ok(TcpInfo {
-		state: info.tcpi_state,
-		retransmits: info.tcpi_retransmits,
-		probes: info.tcpi_probes,
-		backoff: info.tcpi_backoff,
-		rtt_us: info.tcpi_rtt,
-		rtt_var_us: info.tcpi_rttvar,
-		snd_cwnd: info.tcpi_snd_cwnd,
-		rcv_rtt_us: info.tcpi_rcv_rtt,
-		total_retrans: info.tcpi_total_retrans,
-	})
+/// Gets the NAPI ID of the NIC receive queue that last delivered data (SO_INCOMING_NAPI_ID).
+///
+/// Sockets with the same NAPI ID are fed by the same hardware queue.
+/// Returns 0 until data has arrived, and always 0 for loopback or
+/// drivers without NAPI support.
+pub fn get_incoming_napi_id<S: AsRawFd>(socket: &S) -> std::io::Result<u32> {
+	let mut val: libc::c_uint = 0;
+	let mut len = std::mem::size_of::<libc::c_uint>() as libc::socklen_t;
+
+	let result = unsafe {
+		libc::getsockopt(
+			socket.as_raw_fd(),
+			libc::SOL_SOCKET,
+			libc::SO_INCOMING_NAPI_ID,
+			&mut val as *mut _ as *mut libc::c_void,
+			&mut len,
+		)
+	};
+
+	if result == -1 {
+		return Err(SocketError::GetOption { errno: errno(), option: "SO_INCOMING_NAPI_ID" }.into());
+	}
+
+	Ok(val)
+}
+
+/// Picks a worker index for a socket from its NAPI ID.
+///
+/// All sockets fed by the same NIC queue map to the same worker, so a
+/// busy-polling worker keeps its queue's data hot in cache.
+/// Sockets with NAPI ID 0 (unknown) map to worker 0.
+pub fn napi_worker<S: AsRawFd>(socket: &S, workers: usize) -> std::io::Result<usize> {
+	let id = get_incoming_napi_id(socket)?;
+	Ok(id as usize % workers.max(1))
+}
+
+/// Groups sockets by NAPI ID.
+///
+/// Use to hand each worker the set of sockets served by one NIC queue.
+/// Sockets whose NAPI ID is not yet known are grouped under 0.
+pub fn group_by_napi_id<S: AsRawFd>(sockets: Vec<S>) -> std::io::Result<HashMap<u32, Vec<S>>> {
+	let mut groups: HashMap<u32, Vec<S>> = HashMap::new();
+	for socket in sockets {
+		let id = get_incoming_napi_id(&socket)?;
+		groups.entry(id).or_default().push(socket);
+	}
+	Ok(groups)
 }
 
 /// Sends a file descriptor over a Unix socket.
 ///
 /// The receiving process gets a new fd pointing to the same resource.
+/// A closed peer fails with `EPIPE` instead of raising SIGPIPE.
 pub fn send_fd<S: AsRawFd, F: AsRawFd>(socket: &S, fd: &F) -> std::io::Result<()> {
 	let fd_to_send = fd.as_raw_fd();
 	
@@ -445,7 +1436,7 @@ pub fn send_fd<S: AsRawFd, F: AsRawFd>(socket: &S, fd: &F) -> std::io::Result<()
 		);
 	}
 	
-	let result = unsafe { libc::sendmsg(socket.as_raw_fd(), &msg, 0) };
+	let result = unsafe { libc::sendmsg(socket.as_raw_fd(), &msg, libc::MSG_NOSIGNAL) };
 
 	if result == -1 {
 		Err(IoError::Write { errno: errno() }.into())
@@ -491,11 +1482,175 @@ pub fn recv_fd<S: AsRawFd>(socket: &S) -> std::io::Result<OwnedFd> {
 		}
 
 		let fd = *(libc::CMSG_DATA(cmsg) as *const RawFd);
+		crate::track(fd, "recv_fd()");
 		Ok(OwnedFd::from_raw_fd(fd))
 	}
 }
 
 
+/// Process credentials of a Unix socket peer.
+///
+/// Returned by `get_peer_credentials()` (SO_PEERCRED) and carried in
+/// SCM_CREDENTIALS control messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Credentials {
+	pub pid: libc::pid_t,
+	pub uid: libc::uid_t,
+	pub gid: libc::gid_t,
+}
+
+impl Credentials {
+	/// Credentials of the calling process.
+	///
+	/// Without CAP_SYS_ADMIN / CAP_SETUID / CAP_SETGID these are the only
+	/// values `send_credentials()` is allowed to send.
+	pub fn current() -> Self {
+		unsafe {
+			Self {
+				pid: libc::getpid(),
+				uid: libc::geteuid(),
+				gid: libc::getegid(),
+			}
+		}
+	}
+
+	pub(crate) fn from_raw(cred: &libc::ucred) -> Self {
+		Self { pid: cred.pid, uid: cred.uid, gid: cred.gid }
+	}
+
+	pub(crate) fn to_raw(self) -> libc::ucred {
+		libc::ucred { pid: self.pid, uid: self.uid, gid: self.gid }
+	}
+}
+
+/// Gets the credentials of the peer process (SO_PEERCRED).
+///
+/// Recorded by the kernel at connect()/socketpair() time, so the peer
+/// can't forge them. For connected Unix stream and seqpacket sockets.
+pub fn get_peer_credentials<S: AsRawFd>(socket: &S) -> std::io::Result<Credentials> {
+	let mut cred: libc::ucred = unsafe { std::mem::zeroed() };
+	let mut len = std::mem::size_of::<libc::ucred>() as libc::socklen_t;
+
+	let result = unsafe {
+		libc::getsockopt(
+			socket.as_raw_fd(),
+			libc::SOL_SOCKET,
+			libc::SO_PEERCRED,
+			&mut cred as *mut _ as *mut libc::c_void,
+			&mut len,
+		)
+	};
+
+	if result == -1 {
+		return Err(SocketError::GetOption { errno: errno(), option: "SO_PEERCRED" }.into());
+	}
+	Ok(Credentials::from_raw(&cred))
+}
+
+/// Sets SO_PASSCRED on a Unix socket.
+///
+/// The receiver must enable this to get SCM_CREDENTIALS messages.
+/// If the sender doesn't attach credentials, the kernel fills in its own.
+pub fn set_passcred<S: AsRawFd>(socket: &S, enable: bool) -> std::io::Result<()> {
+	let val: libc::c_int = if enable { 1 } else { 0 };
+	let result = unsafe {
+		libc::setsockopt(
+			socket.as_raw_fd(),
+			libc::SOL_SOCKET,
+			libc::SO_PASSCRED,
+			&val as *const _ as *const libc::c_void,
+			std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+		)
+	};
+	if result == -1 {
+		Err(SocketError::SetOption { errno: errno(), option: "SO_PASSCRED" }.into())
+	} else {
+		Ok(())
+	}
+}
+
+/// Gets SO_PASSCRED.
+pub fn get_passcred<S: AsRawFd>(socket: &S) -> std::io::Result<bool> {
+	Ok(get_int(socket, libc::SOL_SOCKET, libc::SO_PASSCRED, "SO_PASSCRED")? != 0)
+}
+
+/// Sends `buf` with an SCM_CREDENTIALS control message.
+///
+/// The kernel checks `cred` — unprivileged processes can only send
+/// their own (see `Credentials::current()`), otherwise EPERM.
+/// Returns bytes sent.
+pub fn send_credentials<S: AsRawFd>(socket: &S, buf: &[u8], cred: &Credentials) -> std::io::Result<usize> {
+	let raw = cred.to_raw();
+	let cmsg_space = unsafe { libc::CMSG_SPACE(std::mem::size_of::<libc::ucred>() as u32) } as usize;
+	let mut cmsg_buf = vec![0u8; cmsg_space];
+
+	let mut iov = libc::iovec {
+		iov_base: buf.as_ptr() as *mut libc::c_void,
+		iov_len: buf.len(),
+	};
+
+	let mut msg: libc::msghdr = unsafe { std::mem::zeroed() };
+	msg.msg_iov = &mut iov;
+	msg.msg_iovlen = 1;
+	msg.msg_control = cmsg_buf.as_mut_ptr() as *mut libc::c_void;
+	msg.msg_controllen = cmsg_space;
+
+	let cmsg = unsafe { libc::CMSG_FIRSTHDR(&msg) };
+	unsafe {
+		(*cmsg).cmsg_level = libc::SOL_SOCKET;
+		(*cmsg).cmsg_type = libc::SCM_CREDENTIALS;
+		(*cmsg).cmsg_len = libc::CMSG_LEN(std::mem::size_of::<libc::ucred>() as u32) as usize;
+		std::ptr::write_unaligned(libc::CMSG_DATA(cmsg) as *mut libc::ucred, raw);
+	}
+
+	let n = unsafe { libc::sendmsg(socket.as_raw_fd(), &msg, libc::MSG_NOSIGNAL) };
+	if n == -1 {
+		Err(IoError::Write { errno: errno() }.into())
+	} else {
+		Ok(n as usize)
+	}
+}
+
+/// Receives into `buf` along with any SCM_CREDENTIALS control message.
+///
+/// Returns bytes received and the sender's credentials. Credentials are
+/// only delivered once `set_passcred(socket, true)` has been called.
+pub fn recv_credentials<S: AsRawFd>(socket: &S, buf: &mut [u8]) -> std::io::Result<(usize, Option<Credentials>)> {
+	let cmsg_space = unsafe { libc::CMSG_SPACE(std::mem::size_of::<libc::ucred>() as u32) } as usize;
+	let mut cmsg_buf = vec![0u8; cmsg_space];
+
+	let mut iov = libc::iovec {
+		iov_base: buf.as_mut_ptr() as *mut libc::c_void,
+		iov_len: buf.len(),
+	};
+
+	let mut msg: libc::msghdr = unsafe { std::mem::zeroed() };
+	msg.msg_iov = &mut iov;
+	msg.msg_iovlen = 1;
+	msg.msg_control = cmsg_buf.as_mut_ptr() as *mut libc::c_void;
+	msg.msg_controllen = cmsg_space;
+
+	let n = unsafe { libc::recvmsg(socket.as_raw_fd(), &mut msg, libc::MSG_CMSG_CLOEXEC) };
+	if n == -1 {
+		return Err(IoError::Read { errno: errno() }.into());
+	}
+
+	let mut cred = None;
+	let mut cmsg = unsafe { libc::CMSG_FIRSTHDR(&msg) };
+	while !cmsg.is_null() {
+		unsafe {
+			if (*cmsg).cmsg_level == libc::SOL_SOCKET && (*cmsg).cmsg_type == libc::SCM_CREDENTIALS {
+				let raw = std::ptr::read_unaligned(libc::CMSG_DATA(cmsg) as *const libc::ucred);
+				cred = Some(Credentials::from_raw(&raw));
+			}
+			cmsg = libc::CMSG_NXTHDR(&msg, cmsg);
+		}
+	}
+
+	Ok((n as usize, cred))
+}
+
+
 /// A single message to send via sendmmsg.
 pub struct SendMsg<'a, A> {
 	pub buf: &'a [u8],
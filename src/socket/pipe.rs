@@ -0,0 +1,194 @@
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd, FromRawFd, OwnedFd, RawFd};
+use crate::error::{SocketError, IoError, errno};
+use super::splice;
+
+/// A pipe, the buffer in the middle of a splice() relay.
+///
+/// splice() only moves data to or from a pipe, so a zero-copy
+/// socket → socket relay goes socket → pipe → socket. `Pipe` owns both
+/// ends (close-on-exec) and splices through them.
+///
+/// # Example
+/// ```ignore
+/// let pipe = Pipe::new()?;
+/// pipe.set_size(1 << 20)?;
+/// loop {
+///     let n = pipe.splice_from(&client, 1 << 20, SPLICE_F_MOVE)?;
+///     if n == 0 { break; }
+///     let mut left = n;
+///     while left > 0 {
+///         left -= pipe.splice_to(&upstream, left, SPLICE_F_MOVE | SPLICE_F_MORE)?;
+///     }
+/// }
+/// ```
+pub struct Pipe {
+	reader: PipeReader,
+	writer: PipeWriter,
+}
+
+/// The read end of a `Pipe`.
+pub struct PipeReader {
+	fd: OwnedFd,
+}
+
+/// The write end of a `Pipe`.
+pub struct PipeWriter {
+	fd: OwnedFd,
+}
+
+impl Pipe {
+	/// Creates a blocking pipe.
+	pub fn new() -> std::io::Result<Self> {
+		Self::with_flags(libc::O_CLOEXEC)
+	}
+
+	/// Creates a pipe with both ends non-blocking, for use with a poller.
+	pub fn nonblocking() -> std::io::Result<Self> {
+		Self::with_flags(libc::O_CLOEXEC | libc::O_NONBLOCK)
+	}
+
+	fn with_flags(flags: libc::c_int) -> std::io::Result<Self> {
+		let mut fds = [0 as RawFd; 2];
+		if unsafe { libc::pipe2(fds.as_mut_ptr(), flags) } == -1 {
+			return Err(SocketError::Create { errno: errno() }.into());
+		}
+		crate::track(fds[0], "pipe2()");
+		crate::track(fds[1], "pipe2()");
+		unsafe {
+			Ok(Self {
+				reader: PipeReader { fd: OwnedFd::from_raw_fd(fds[0]) },
+				writer: PipeWriter { fd: OwnedFd::from_raw_fd(fds[1]) },
+			})
+		}
+	}
+
+	/// Sets the pipe's capacity (F_SETPIPE_SZ) and returns what the
+	/// kernel actually chose: `bytes` rounded up to a power-of-two
+	/// number of pages.
+	///
+	/// The default is 64 KiB. One splice() moves at most this much, so a
+	/// larger pipe means fewer syscalls per byte relayed. Unprivileged
+	/// processes are capped at /proc/sys/fs/pipe-max-size (EPERM above).
+	pub fn set_size(&self, bytes: usize) -> std::io::Result<usize> {
+		let size = unsafe { libc::fcntl(self.writer.as_raw_fd(), libc::F_SETPIPE_SZ, bytes as libc::c_int) };
+		if size == -1 {
+			return Err(SocketError::SetOption { errno: errno(), option: "F_SETPIPE_SZ" }.into());
+		}
+		Ok(size as usize)
+	}
+
+	/// The pipe's capacity in bytes (F_GETPIPE_SZ).
+	pub fn size(&self) -> std::io::Result<usize> {
+		let size = unsafe { libc::fcntl(self.writer.as_raw_fd(), libc::F_GETPIPE_SZ) };
+		if size == -1 {
+			return Err(SocketError::GetOption { errno: errno(), option: "F_GETPIPE_SZ" }.into());
+		}
+		Ok(size as usize)
+	}
+
+	/// Moves up to `len` bytes from `from` (a socket or file) into the
+	/// pipe. Returns 0 at end of input.
+	pub fn splice_from<In: AsRawFd>(&self, from: &In, len: usize, flags: u32) -> std::io::Result<usize> {
+		splice(from, None, &self.writer, None, len, flags)
+	}
+
+	/// Moves up to `len` bytes from the pipe into `to`.
+	pub fn splice_to<Out: AsRawFd>(&self, to: &Out, len: usize, flags: u32) -> std::io::Result<usize> {
+		splice(&self.reader, None, to, None, len, flags)
+	}
+
+	pub fn reader(&self) -> &PipeReader {
+		&self.reader
+	}
+
+	pub fn writer(&self) -> &PipeWriter {
+		&self.writer
+	}
+
+	/// Separates the ends, e.g. to hand one to another thread or process.
+	pub fn into_split(self) -> (PipeReader, PipeWriter) {
+		(self.reader, self.writer)
+	}
+}
+
+impl PipeReader {
+	pub fn read(&self, buf: &mut [u8]) -> std::io::Result<usize> {
+		let n = unsafe { libc::read(self.fd.as_raw_fd(), buf.as_mut_ptr() as *mut libc::c_void, buf.len()) };
+		if n == -1 {
+			Err(IoError::Read { errno: errno() }.into())
+		} else {
+			Ok(n as usize)
+		}
+	}
+}
+
+impl PipeWriter {
+	pub fn write(&self, buf: &[u8]) -> std::io::Result<usize> {
+		let n = unsafe { libc::write(self.fd.as_raw_fd(), buf.as_ptr() as *const libc::c_void, buf.len()) };
+		if n == -1 {
+			Err(IoError::Write { errno: errno() }.into())
+		} else {
+			Ok(n as usize)
+		}
+	}
+}
+
+impl std::io::Read for PipeReader {
+	fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+		PipeReader::read(self, buf)
+	}
+}
+
+impl std::io::Write for PipeWriter {
+	fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+		PipeWriter::write(self, buf)
+	}
+
+	fn flush(&mut self) -> std::io::Result<()> {
+		Ok(())
+	}
+}
+
+impl AsRawFd for PipeReader {
+	fn as_raw_fd(&self) -> RawFd {
+		self.fd.as_raw_fd()
+	}
+}
+
+impl AsFd for PipeReader {
+	fn as_fd(&self) -> BorrowedFd<'_> {
+		self.fd.as_fd()
+	}
+}
+
+impl From<PipeReader> for OwnedFd {
+	fn from(reader: PipeReader) -> OwnedFd {
+		reader.fd
+	}
+}
+
+impl AsRawFd for PipeWriter {
+	fn as_raw_fd(&self) -> RawFd {
+		self.fd.as_raw_fd()
+	}
+}
+
+impl AsFd for PipeWriter {
+	fn as_fd(&self) -> BorrowedFd<'_> {
+		self.fd.as_fd()
+	}
+}
+
+impl From<PipeWriter> for OwnedFd {
+	fn from(writer: PipeWriter) -> OwnedFd {
+		writer.fd
+	}
+}
+
+/*
+Why not std::io::pipe()?
+It gives blocking ends only and no F_SETPIPE_SZ, and splice() relays
+want both: a non-blocking pipe so a poller can drive it, and a pipe
+big enough that one splice() moves a whole socket buffer. The ends
+convert into OwnedFd for anything that takes std types.
+*/
@@ -0,0 +1,130 @@
+use crate::addr::{Ipv4, Ipv6, SocketAddrV4, SocketAddrV6};
+use crate::error::IoError;
+use super::{BoundDatagram, set_recv_pktinfo, set_recv_pktinfo_v6};
+
+/// Where a datagram arrived: interface and local addresses.
+///
+/// `Ip` is `[u8; 4]` or `[u8; 16]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecvInterface<Ip> {
+	/// Index of the receiving interface (see `if_nametoindex`).
+	pub ifindex: u32,
+	/// Local address to reply from. For IPv4 this is the address of the
+	/// receiving interface even when `dst_ip` is broadcast or multicast.
+	pub local_ip: Ip,
+	/// Destination address in the packet header.
+	pub dst_ip: Ip,
+}
+
+impl BoundDatagram<Ipv4> {
+	/// Enables IP_PKTINFO, for `recv_from_if()`.
+	pub fn set_pktinfo(&self, enable: bool) -> std::io::Result<()> {
+		set_recv_pktinfo(self, enable)
+	}
+
+	/// Receives a datagram along with the interface it arrived on and
+	/// the address it was sent to.
+	///
+	/// Needs `set_pktinfo(true)` first; without it every call fails with
+	/// `IoError::MissingControlMessage` (kind `InvalidInput`).
+	pub fn recv_from_if(&self, buf: &mut [u8]) -> std::io::Result<(usize, SocketAddrV4, RecvInterface<[u8; 4]>)> {
+		let (n, addr, info) = self.recv_from_with_cmsg::<libc::in_pktinfo>(
+			buf, libc::IPPROTO_IP, libc::IP_PKTINFO,
+		)?;
+		let info = info.ok_or(IoError::MissingControlMessage { cmsg: "IP_PKTINFO" })?;
+
+		Ok((n, addr, RecvInterface {
+			ifindex: info.ipi_ifindex as u32,
+			local_ip: info.ipi_spec_dst.s_addr.to_ne_bytes(),
+			dst_ip: info.ipi_addr.s_addr.to_ne_bytes(),
+		}))
+	}
+
+	/// Same as `recv_from_if()`, named like `recv_from_with_tos()` and
+	/// `recv_from_with_orig_dst()`.
+	pub fn recv_from_with_info(&self, buf: &mut [u8]) -> std::io::Result<(usize, SocketAddrV4, RecvInterface<[u8; 4]>)> {
+		self.recv_from_if(buf)
+	}
+
+	/// Sends to `addr` out of the interface in `via`, from `via.local_ip`.
+	///
+	/// Pass the `RecvInterface` of a request to make the reply leave the
+	/// way the request came in, regardless of the routing table.
+	pub fn send_to_if(&self, buf: &[u8], addr: &SocketAddrV4, via: &RecvInterface<[u8; 4]>) -> std::io::Result<usize> {
+		let info = libc::in_pktinfo {
+			ipi_ifindex: via.ifindex as libc::c_int,
+			ipi_spec_dst: libc::in_addr { s_addr: u32::from_ne_bytes(via.local_ip) },
+			ipi_addr: libc::in_addr { s_addr: 0 },
+		};
+		self.send_to_with_cmsg(buf, addr, libc::IPPROTO_IP, libc::IP_PKTINFO, info)
+	}
+
+	/// Sends to `addr` from the local address `src`, letting the routing
+	/// table pick the interface.
+	///
+	/// For a server bound to 0.0.0.0: reply from the `local_ip` the
+	/// request arrived on, so the client sees the address it asked.
+	pub fn send_to_from(&self, buf: &[u8], addr: &SocketAddrV4, src: [u8; 4]) -> std::io::Result<usize> {
+		self.send_to_if(buf, addr, &RecvInterface { ifindex: 0, local_ip: src, dst_ip: [0; 4] })
+	}
+}
+
+impl BoundDatagram<Ipv6> {
+	/// Enables IPV6_RECVPKTINFO, for `recv_from_if()`.
+	pub fn set_pktinfo(&self, enable: bool) -> std::io::Result<()> {
+		set_recv_pktinfo_v6(self, enable)
+	}
+
+	/// Receives a datagram along with the interface it arrived on and
+	/// the address it was sent to.
+	///
+	/// Needs `set_pktinfo(true)` first, else `IoError::MissingControlMessage`.
+	/// IPv6 reports only the destination address, so `local_ip` equals
+	/// `dst_ip`.
+	pub fn recv_from_if(&self, buf: &mut [u8]) -> std::io::Result<(usize, SocketAddrV6, RecvInterface<[u8; 16]>)> {
+		let (n, addr, info) = self.recv_from_with_cmsg::<libc::in6_pktinfo>(
+			buf, libc::IPPROTO_IPV6, libc::IPV6_PKTINFO,
+		)?;
+		let info = info.ok_or(IoError::MissingControlMessage { cmsg: "IPV6_PKTINFO" })?;
+
+		Ok((n, addr, RecvInterface {
+			ifindex: info.ipi6_ifindex,
+			local_ip: info.ipi6_addr.s6_addr,
+			dst_ip: info.ipi6_addr.s6_addr,
+		}))
+	}
+
+	/// Same as `recv_from_if()`, named like `recv_from_with_tos()` and
+	/// `recv_from_with_orig_dst()`.
+	pub fn recv_from_with_info(&self, buf: &mut [u8]) -> std::io::Result<(usize, SocketAddrV6, RecvInterface<[u8; 16]>)> {
+		self.recv_from_if(buf)
+	}
+
+	/// Sends to `addr` out of the interface in `via`, from `via.local_ip`.
+	///
+	/// A multicast `local_ip` can't be a source address — the kernel
+	/// rejects it with EINVAL. Reply to multicast requests with a copy of
+	/// `via` whose `local_ip` is `[0; 16]` to let the kernel pick one.
+	pub fn send_to_if(&self, buf: &[u8], addr: &SocketAddrV6, via: &RecvInterface<[u8; 16]>) -> std::io::Result<usize> {
+		let info = libc::in6_pktinfo {
+			ipi6_addr: libc::in6_addr { s6_addr: via.local_ip },
+			ipi6_ifindex: via.ifindex,
+		};
+		self.send_to_with_cmsg(buf, addr, libc::IPPROTO_IPV6, libc::IPV6_PKTINFO, info)
+	}
+
+	/// Sends to `addr` from the local address `src`, letting the routing
+	/// table pick the interface. `src` must be a unicast address.
+	pub fn send_to_from(&self, buf: &[u8], addr: &SocketAddrV6, src: [u8; 16]) -> std::io::Result<usize> {
+		self.send_to_if(buf, addr, &RecvInterface { ifindex: 0, local_ip: src, dst_ip: [0; 16] })
+	}
+}
+
+/*
+Why not just bind one socket per interface address?
+That works for a fixed set of unicast addresses, but not for broadcast,
+multicast, or addresses that come and go. One wildcard socket plus
+PKTINFO sees every interface and can answer on the right one — which
+matters with asymmetric routing, where the routing table would send
+the reply out a different interface than the request used.
+*/
@@ -0,0 +1,312 @@
+use std::os::fd::{AsFd, AsRawFd, FromRawFd, OwnedFd};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use crate::error::{SocketError, IoError, errno};
+use super::wait::to_poll_ms;
+
+/// Identifies a registered fd in returned events.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Token(pub usize);
+
+/// Readiness a registration is interested in.
+///
+/// Combine with `|`: `Interest::READABLE | Interest::WRITABLE`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Interest(u32);
+
+impl Interest {
+	/// Data (or EOF, or a pending connection) to read.
+	pub const READABLE: Interest = Interest((libc::EPOLLIN | libc::EPOLLRDHUP) as u32);
+	/// Send buffer space (or connect completion).
+	pub const WRITABLE: Interest = Interest(libc::EPOLLOUT as u32);
+
+	/// Edge-triggered: report each readiness change once.
+	///
+	/// The fd must then be drained until `WouldBlock`.
+	pub fn edge(self) -> Interest {
+		Interest(self.0 | libc::EPOLLET as u32)
+	}
+
+	/// Report once, then disable until re-armed with `modify()`.
+	///
+	/// Lets several threads wait on one poller without two of them
+	/// handling the same fd.
+	pub fn oneshot(self) -> Interest {
+		Interest(self.0 | libc::EPOLLONESHOT as u32)
+	}
+}
+
+impl std::ops::BitOr for Interest {
+	type Output = Interest;
+
+	fn bitor(self, rhs: Interest) -> Interest {
+		Interest(self.0 | rhs.0)
+	}
+}
+
+/// A readiness event returned by `Poller::wait()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Event {
+	token: Token,
+	flags: u32,
+}
+
+impl Event {
+	pub fn token(&self) -> Token {
+		self.token
+	}
+
+	pub fn is_readable(&self) -> bool {
+		self.flags & (libc::EPOLLIN | libc::EPOLLPRI) as u32 != 0
+	}
+
+	pub fn is_writable(&self) -> bool {
+		self.flags & libc::EPOLLOUT as u32 != 0
+	}
+
+	/// The socket has a pending error (read it with `take_error()`).
+	pub fn is_error(&self) -> bool {
+		self.flags & libc::EPOLLERR as u32 != 0
+	}
+
+	/// The peer closed its write side, or the connection is gone.
+	pub fn is_hangup(&self) -> bool {
+		self.flags & (libc::EPOLLHUP | libc::EPOLLRDHUP) as u32 != 0
+	}
+}
+
+/// Buffer of events filled by `Poller::wait()`.
+pub struct Events {
+	buf: Vec<libc::epoll_event>,
+	len: usize,
+}
+
+impl Events {
+	/// Room for up to `capacity` events per `wait()`.
+	pub fn with_capacity(capacity: usize) -> Self {
+		Self {
+			buf: vec![libc::epoll_event { events: 0, u64: 0 }; capacity.max(1)],
+			len: 0,
+		}
+	}
+
+	pub fn len(&self) -> usize {
+		self.len
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.len == 0
+	}
+
+	pub fn iter(&self) -> impl Iterator<Item = Event> + '_ {
+		self.buf[..self.len].iter().map(|ev| {
+			// epoll_event is packed on x86_64: copy fields out, never borrow.
+			let (flags, data) = (ev.events, ev.u64);
+			Event { token: Token(data as usize), flags }
+		})
+	}
+}
+
+/// Wakes a thread blocked in `Poller::wait()`.
+///
+/// Cheap to clone and `Send + Sync` — hand one to each thread that needs
+/// to interrupt the event loop (new work queued, shutdown, ...).
+#[derive(Clone)]
+pub struct Waker {
+	fd: Arc<OwnedFd>,
+}
+
+impl Waker {
+	/// Makes the current or next `wait()` return.
+	///
+	/// Wakes coalesce: several calls before `wait()` returns wake it once.
+	pub fn wake(&self) -> std::io::Result<()> {
+		let one: u64 = 1;
+		let n = unsafe {
+			libc::write(self.fd.as_raw_fd(), &one as *const u64 as *const libc::c_void, 8)
+		};
+		// EAGAIN: counter saturated, so a wake is already pending.
+		if n == -1 && errno() != libc::EAGAIN {
+			return Err(IoError::Write { errno: errno() }.into());
+		}
+		Ok(())
+	}
+}
+
+/// Reserved for the internal waker; never handed out as a user token.
+const WAKER_TOKEN: u64 = u64::MAX;
+
+/// An epoll instance.
+///
+/// `Send + Sync`: share it as `Arc<Poller>`. `register`, `modify` and
+/// `deregister` may be called from any thread, including while another
+/// thread is blocked in `wait()` — the kernel serializes them.
+///
+/// # Example
+/// ```ignore
+/// let poller = Arc::new(Poller::new()?);
+/// listener.set_nonblocking(true)?;
+/// poller.register(&listener, Token(0), Interest::READABLE)?;
+///
+/// let waker = poller.waker();  // give to other threads
+/// let mut events = Events::with_capacity(256);
+/// loop {
+///     poller.wait(&mut events, None)?;
+///     for event in events.iter() {
+///         // dispatch on event.token()
+///     }
+/// }
+/// ```
+pub struct Poller {
+	epfd: OwnedFd,
+	waker: Arc<OwnedFd>,
+}
+
+impl Poller {
+	/// Creates an epoll instance with its waker registered.
+	pub fn new() -> std::io::Result<Self> {
+		let epfd = unsafe { libc::epoll_create1(libc::EPOLL_CLOEXEC) };
+		if epfd == -1 {
+			return Err(SocketError::Create { errno: errno() }.into());
+		}
+		crate::track(epfd, "epoll_create1()");
+		let epfd = unsafe { OwnedFd::from_raw_fd(epfd) };
+
+		let efd = unsafe { libc::eventfd(0, libc::EFD_CLOEXEC | libc::EFD_NONBLOCK) };
+		if efd == -1 {
+			return Err(SocketError::Create { errno: errno() }.into());
+		}
+		crate::track(efd, "eventfd()");
+		let waker = Arc::new(unsafe { OwnedFd::from_raw_fd(efd) });
+
+		let poller = Self { epfd, waker };
+		poller.ctl(libc::EPOLL_CTL_ADD, poller.waker.as_raw_fd(), libc::EPOLLIN as u32, WAKER_TOKEN)?;
+		Ok(poller)
+	}
+
+	/// Returns a handle that interrupts `wait()`.
+	pub fn waker(&self) -> Waker {
+		Waker { fd: Arc::clone(&self.waker) }
+	}
+
+	/// Starts watching `source` for `interest`.
+	///
+	/// `source` should be non-blocking. `Token(usize::MAX)` is reserved.
+	pub fn register<S: AsFd>(&self, source: &S, token: Token, interest: Interest) -> std::io::Result<()> {
+		self.ctl(libc::EPOLL_CTL_ADD, source.as_fd().as_raw_fd(), interest.0, user_token(token)?)
+	}
+
+	/// Changes the token or interest of a registered source.
+	///
+	/// Also re-arms a `oneshot()` registration.
+	pub fn modify<S: AsFd>(&self, source: &S, token: Token, interest: Interest) -> std::io::Result<()> {
+		self.ctl(libc::EPOLL_CTL_MOD, source.as_fd().as_raw_fd(), interest.0, user_token(token)?)
+	}
+
+	/// Stops watching `source`.
+	///
+	/// A `wait()` already returning on another thread may still hand out
+	/// one last event for it — treat unknown tokens as stale.
+	pub fn deregister<S: AsFd>(&self, source: &S) -> std::io::Result<()> {
+		let result = unsafe {
+			libc::epoll_ctl(self.epfd.as_raw_fd(), libc::EPOLL_CTL_DEL, source.as_fd().as_raw_fd(), std::ptr::null_mut())
+		};
+		if result == -1 {
+			return Err(IoError::Poll { errno: errno() }.into());
+		}
+		Ok(())
+	}
+
+	/// Waits for events or until `timeout` elapses.
+	///
+	/// `None` waits forever. Returns the number of events stored in
+	/// `events`; 0 on timeout or when woken by a `Waker` with nothing
+	/// else ready. Retries on EINTR with the remaining time.
+	pub fn wait(&self, events: &mut Events, timeout: Option<Duration>) -> std::io::Result<usize> {
+		let deadline = timeout.map(|t| Instant::now() + t);
+		events.len = 0;
+
+		let n = loop {
+			let timeout_ms = match deadline {
+				None => -1,
+				Some(deadline) => to_poll_ms(deadline.saturating_duration_since(Instant::now())),
+			};
+
+			let n = unsafe {
+				libc::epoll_wait(
+					self.epfd.as_raw_fd(),
+					events.buf.as_mut_ptr(),
+					events.buf.len() as libc::c_int,
+					timeout_ms,
+				)
+			};
+			match n {
+				-1 if errno() == libc::EINTR => continue,
+				-1 => return Err(IoError::Poll { errno: errno() }.into()),
+				n => break n as usize,
+			}
+		};
+
+		// Drop the waker's event, compacting the rest in place.
+		let mut kept = 0;
+		for i in 0..n {
+			let data = events.buf[i].u64;
+			if data == WAKER_TOKEN {
+				self.reset_waker();
+			} else {
+				events.buf[kept] = events.buf[i];
+				kept += 1;
+			}
+		}
+		events.len = kept;
+		Ok(kept)
+	}
+
+	fn reset_waker(&self) {
+		let mut count: u64 = 0;
+		// Nonblocking; EAGAIN just means another thread already reset it.
+		unsafe {
+			libc::read(self.waker.as_raw_fd(), &mut count as *mut u64 as *mut libc::c_void, 8);
+		}
+	}
+
+	fn ctl(&self, op: libc::c_int, fd: libc::c_int, flags: u32, data: u64) -> std::io::Result<()> {
+		let mut event = libc::epoll_event { events: flags, u64: data };
+		let result = unsafe { libc::epoll_ctl(self.epfd.as_raw_fd(), op, fd, &mut event) };
+		if result == -1 {
+			return Err(IoError::Poll { errno: errno() }.into());
+		}
+		Ok(())
+	}
+}
+
+fn user_token(token: Token) -> std::io::Result<u64> {
+	let data = token.0 as u64;
+	if data == WAKER_TOKEN {
+		return Err(SocketError::InvalidInput { reason: "Token(usize::MAX) is reserved for the waker" }.into());
+	}
+	Ok(data)
+}
+
+impl AsRawFd for Poller {
+	fn as_raw_fd(&self) -> std::os::fd::RawFd {
+		self.epfd.as_raw_fd()
+	}
+}
+
+impl AsFd for Poller {
+	fn as_fd(&self) -> std::os::fd::BorrowedFd<'_> {
+		self.epfd.as_fd()
+	}
+}
+
+/*
+Why is sharing safe without a lock?
+The epoll instance lives in the kernel, which already serializes
+epoll_ctl against epoll_wait. Poller holds only fds, so &Poller is all
+any thread needs; there is no userspace registration table to guard.
+
+The waker is an eventfd registered like any other fd: write() bumps the
+counter and makes it readable, which ends the wait; wait() reads it back
+to zero. Several wakes before that collapse into one.
+*/
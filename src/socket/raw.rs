@@ -1,12 +1,13 @@
 use crate::socket::pending::PendingConnect;
 use crate::Datagram;
+use crate::socket::Raw;
 use crate::socket::datagram::BoundDatagram;
 use crate::socket::Stream;
 use crate::socket::stream::ConnectedStream;
 use crate::addr::ToSockAddr;
 use std::os::fd::{OwnedFd, FromRawFd};
 use std::marker::PhantomData;
-use crate::addr::Domain;
+use crate::addr::{Domain, Ipv4, Ipv6, Unix, SocketAddrV4, SocketAddrV6};
 use crate::error::{SocketError, errno};
 use super::SockType;
 use super::bound::BoundSocket;
@@ -37,12 +38,39 @@ impl <D: Domain, T: SockType> RawSocket<D, T> {
 	/// Calls the `socket()` syscall with the appropriate domain and type.
 	/// The socket is created with `SOCK_CLOEXEC` (close on exec).
 	pub fn new() -> std::io::Result<Self> {
+		Self::with_flags(0)
+	}
+
+	/// Creates a new raw socket in non-blocking mode.
+	///
+	/// Same as `new()`, but `SOCK_NONBLOCK` is set atomically at creation,
+	/// so there is no window where the fd is blocking.
+	pub fn new_nonblocking() -> std::io::Result<Self> {
+		Self::with_flags(libc::SOCK_NONBLOCK)
+	}
+
+	/// Creates a socket for a specific protocol.
+	///
+	/// The default (`new()`) lets the kernel pick — TCP for streams,
+	/// UDP for datagrams. Raw sockets always need one, e.g.
+	/// `RawSocket::<Ipv4, Raw>::with_protocol(libc::IPPROTO_RAW)`.
+	pub fn with_protocol(protocol: libc::c_int) -> std::io::Result<Self> {
+		Self::with_flags_and_protocol(0, protocol)
+	}
+
+	/// Creates the socket with extra `SOCK_*` type flags.
+	pub(crate) fn with_flags(flags: libc::c_int) -> std::io::Result<Self> {
+		Self::with_flags_and_protocol(flags, 0)
+	}
+
+	pub(crate) fn with_flags_and_protocol(flags: libc::c_int, protocol: libc::c_int) -> std::io::Result<Self> {
 		let fd = unsafe {
-			libc::socket(D::raw(), T::raw() | libc::SOCK_CLOEXEC, 0)
+			libc::socket(D::raw(), T::raw() | libc::SOCK_CLOEXEC | flags, protocol)
 		};
 		if fd == -1 {
 			return Err(SocketError::Create { errno: errno() }.into());
 		}
+		crate::track(fd, "socket()");
 		let fd = unsafe { OwnedFd::from_raw_fd(fd) };
 
 		Ok(Self {
@@ -63,27 +91,7 @@ impl <D: Domain, T: SockType> RawSocket<D, T> {
 	///
 	/// Required for use with epoll/io_uring.
 	pub fn set_nonblocking(&self, nonblocking: bool) -> std::io::Result<()> {
-		let flags = unsafe { libc::fcntl(self.as_raw_fd(), libc::F_GETFL) };
-
-		if flags == -1 {
-			return Err(SocketError::GetOption { errno: errno(), option: "F_GETFL" }.into());
-		}
-
-		let new_flags = if nonblocking {
-			flags | libc::O_NONBLOCK
-		} else {
-			flags & !libc::O_NONBLOCK
-		};
-
-		let result = unsafe {
-			libc::fcntl(self.as_raw_fd(), libc::F_SETFL, new_flags)
-		};
-
-		if result == -1 {
-			return Err(SocketError::SetOption { errno: errno(), option: "O_NONBLOCK" }.into());
-		}
-
-		Ok(())
+		super::mode::set_fd_nonblocking(self.as_raw_fd(), nonblocking)
 	}
 	
 	/// Binds the socket to an address.
@@ -102,14 +110,12 @@ impl <D: Domain, T: SockType> RawSocket<D, T> {
 		});
 
 		match result {
-			Some(-1) => Err(SocketError::Bind {
+			Ok(-1) => Err(SocketError::Bind {
 				errno: errno(),
 				addr: format!("{:?}", addr),
 			}.into()),
-			Some(_) => Ok(BoundSocket::from_fd(self.into_fd())),
-			None => Err(SocketError::InvalidAddress {
-				reason: "address too long",
-			}.into()),
+			Ok(_) => Ok(BoundSocket::from_fd(self.into_fd())),
+			Err(e) => Err(SocketError::from(e).into()),
 		}
 	}
 	
@@ -139,6 +145,29 @@ what bind() needs to do:
 5. Return the next state type
 */
 impl<D: Domain> RawSocket<D, Stream> {
+	/// Binds the local end of a connection that's still to be made.
+	///
+	/// Unlike `bind()`, the socket stays a `RawSocket`: it's going to
+	/// `connect()`, not `listen()`. Picks the source address (and port,
+	/// unless 0) the connection uses.
+	pub fn bind_source(&self, addr: &D::Addr) -> std::io::Result<()>
+	where
+		D::Addr: ToSockAddr + std::fmt::Debug,
+	{
+		let result = addr.with_raw(|ptr, len| unsafe {
+			libc::bind(self.as_raw_fd(), ptr, len)
+		});
+
+		match result {
+			Ok(-1) => Err(SocketError::Bind {
+				errno: errno(),
+				addr: format!("{:?}", addr),
+			}.into()),
+			Ok(_) => Ok(()),
+			Err(e) => Err(SocketError::from(e).into()),
+		}
+	}
+
 	/// Connects to a remote address.
 	///
 	/// For clients — establishes connection to a server.
@@ -152,14 +181,12 @@ impl<D: Domain> RawSocket<D, Stream> {
 		});
 
 		match result {
-			Some(-1) => Err(SocketError::Connect {
+			Ok(-1) => Err(SocketError::Connect {
 				errno: errno(),
 				addr: format!("{:?}", addr),
 			}.into()),
-			Some(_) => Ok(ConnectedStream::from_fd(self.into_fd())),
-			None => Err(SocketError::InvalidAddress {
-				reason: "address too long",
-			}.into()),
+			Ok(_) => Ok(ConnectedStream::from_fd(self.into_fd())),
+			Err(e) => Err(SocketError::from(e).into()),
 		}
 	}
 
@@ -169,7 +196,7 @@ impl<D: Domain> RawSocket<D, Stream> {
 	/// Use epoll/io_uring to wait for writability, then check `take_error()`.
 	pub fn connect_nonblocking(self, addr: D::Addr) -> std::io::Result<PendingConnect<D>>
 	where
-		D::Addr: ToSockAddr + std::fmt::Debug + Clone,
+		D::Addr: ToSockAddr + std::fmt::Debug,
 	{
 		// Ensure non-blocking
 		self.set_nonblocking(true)?;
@@ -179,11 +206,11 @@ impl<D: Domain> RawSocket<D, Stream> {
 		});
 
 		match result {
-			Some(0) => {
+			Ok(0) => {
 				// Immediate success (rare, but possible on localhost)
 				Ok(PendingConnect::from_fd(self.into_fd()))
 			}
-			Some(-1) => {
+			Ok(-1) => {
 				let e = errno();
 				if e == libc::EINPROGRESS {
 					// Expected: connection in progress
@@ -195,10 +222,8 @@ impl<D: Domain> RawSocket<D, Stream> {
 					}.into())
 				}
 			}
-			Some(_) => unreachable!("connect() returned unexpected value"),
-			None => Err(SocketError::InvalidAddress {
-				reason: "address too long",
-			}.into()),
+			Ok(_) => unreachable!("connect() returned unexpected value"),
+			Err(e) => Err(SocketError::from(e).into()),
 		}
 	}
 }
@@ -216,17 +241,63 @@ impl<D: Domain> RawSocket<D, Datagram> {
 		});
 
 		match result {
-			Some(-1) => Err(SocketError::Bind {
+			Ok(-1) => Err(SocketError::Bind {
 				errno: errno(),
 				addr: format!("{:?}", addr),
 			}.into()),
-			Some(_) => Ok(BoundDatagram::from_fd(self.into_fd())),
-			None => Err(SocketError::InvalidAddress {
-				reason: "address too long",
-			}.into()),
+			Ok(_) => Ok(BoundDatagram::from_fd(self.into_fd())),
+			Err(e) => Err(SocketError::from(e).into()),
 		}
 	}
 }
+impl RawSocket<Ipv4, Datagram> {
+	/// Creates an unprivileged ICMP ("ping") socket.
+	///
+	/// No CAP_NET_RAW needed, but the caller's group must be within
+	/// `net.ipv4.ping_group_range`. Only echo requests can be sent,
+	/// and the kernel builds the IP header.
+	pub fn new_icmp() -> std::io::Result<Self> {
+		Self::with_protocol(libc::IPPROTO_ICMP)
+	}
+
+	/// Binds an ICMP socket to echo identifier `id`.
+	///
+	/// The kernel overwrites the identifier of every request sent with `id`,
+	/// and only delivers echo replies carrying it — so concurrent pingers
+	/// in one process each see just their own replies. `0` lets the kernel
+	/// pick a free identifier.
+	pub fn bind_icmp_id(self, id: u16) -> std::io::Result<BoundDatagram<Ipv4>> {
+		// For ping sockets the port field carries the echo identifier.
+		self.bind_datagram(SocketAddrV4::new([0, 0, 0, 0], id))
+	}
+}
+
+impl RawSocket<Ipv6, Datagram> {
+	/// Creates an unprivileged ICMPv6 ("ping") socket.
+	///
+	/// See `RawSocket::<Ipv4, Datagram>::new_icmp()`.
+	pub fn new_icmp() -> std::io::Result<Self> {
+		Self::with_protocol(libc::IPPROTO_ICMPV6)
+	}
+
+	/// Binds an ICMPv6 socket to echo identifier `id`.
+	///
+	/// See `RawSocket::<Ipv4, Datagram>::bind_icmp_id()`.
+	pub fn bind_icmp_id(self, id: u16) -> std::io::Result<BoundDatagram<Ipv6>> {
+		self.bind_datagram(SocketAddrV6::new([0; 16], id))
+	}
+}
+
+impl<D: Domain> RawSocket<D, Raw> {
+	/// Turns the raw socket into a datagram-style socket for send_to/recv_from.
+	///
+	/// Raw sockets need no bind — they see every packet of their protocol.
+	/// Port numbers in addresses are ignored.
+	pub fn into_datagram(self) -> BoundDatagram<D> {
+		BoundDatagram::from_fd(self.into_fd())
+	}
+}
+
 impl<D: Domain, T: SockType> std::os::fd::AsRawFd for RawSocket<D, T> {
 	fn as_raw_fd(&self) -> std::os::fd::RawFd {
 		self.fd.as_raw_fd()
@@ -249,4 +320,30 @@ impl<D: Domain, T: SockType> std::os::fd::IntoRawFd for RawSocket<D, T> {
 	fn into_raw_fd(self) -> std::os::fd::RawFd {
 		self.fd.into_raw_fd()
 	}
-}
\ No newline at end of file
+}
+
+/// Creates a pair of connected Unix sockets of type `T`.
+///
+/// Both ends get `SOCK_CLOEXEC`. Backs the `pair()` constructors.
+pub(crate) fn socketpair<T: SockType>() -> std::io::Result<(OwnedFd, OwnedFd)> {
+	let mut fds = [0 as libc::c_int; 2];
+	let result = unsafe {
+		libc::socketpair(Unix::raw(), T::raw() | libc::SOCK_CLOEXEC, 0, fds.as_mut_ptr())
+	};
+	if result == -1 {
+		return Err(SocketError::Create { errno: errno() }.into());
+	}
+	crate::track(fds[0], "socketpair()");
+	crate::track(fds[1], "socketpair()");
+	unsafe { Ok((OwnedFd::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1]))) }
+}
+
+/// Duplicates `fd` with close-on-exec set, backing every `try_clone()`.
+pub(crate) fn dup_fd(fd: libc::c_int) -> std::io::Result<OwnedFd> {
+	let new_fd = unsafe { libc::fcntl(fd, libc::F_DUPFD_CLOEXEC, 0) };
+	if new_fd == -1 {
+		return Err(SocketError::Dup { errno: errno() }.into());
+	}
+	crate::track(new_fd, "dup()");
+	unsafe { Ok(OwnedFd::from_raw_fd(new_fd)) }
+}
@@ -0,0 +1,178 @@
+use std::ffi::CString;
+use crate::addr::{Domain, FromSockAddr, decode_sockaddr};
+use crate::error::{SocketError, errno};
+use super::AnyAddr;
+
+/// How `resolve()` orders IPv4 and IPv6 results.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ResolveOrder {
+	/// As getaddrinfo() sorted them: RFC 6724, tuned by /etc/gai.conf.
+	#[default]
+	System,
+	/// IPv4 addresses first, each family otherwise in system order.
+	Ipv4First,
+	/// IPv6 addresses first, each family otherwise in system order.
+	Ipv6First,
+	Ipv4Only,
+	Ipv6Only,
+}
+
+/// Resolves `"host:port"` with getaddrinfo() into the crate's address
+/// types.
+///
+/// `host` is a name or a literal address, IPv6 in brackets
+/// (`"example.com:443"`, `"10.0.0.1:80"`, `"[2001:db8::1]:443"`). The
+/// port is numeric. Only families the machine has addresses for are
+/// returned (AI_ADDRCONFIG), without duplicates. Blocks for as long as
+/// the lookup takes; the system resolver has its own timeouts
+/// (/etc/resolv.conf).
+///
+/// # Example
+/// ```ignore
+/// for addr in resolve("example.com:443", ResolveOrder::Ipv6First)? {
+///     println!("{:?}", addr);
+/// }
+/// ```
+pub fn resolve(host: &str, order: ResolveOrder) -> std::io::Result<Vec<AnyAddr>> {
+	let family = match order {
+		ResolveOrder::Ipv4Only => libc::AF_INET,
+		ResolveOrder::Ipv6Only => libc::AF_INET6,
+		_ => libc::AF_UNSPEC,
+	};
+	let mut addrs = Vec::new();
+	lookup(host, family, libc::AI_ADDRCONFIG, |sa, len| unsafe {
+		let addr = match (*sa).sa_family as libc::c_int {
+			libc::AF_INET => AnyAddr::Ipv4(decode_sockaddr(sa, len, "invalid resolved address")?),
+			libc::AF_INET6 => AnyAddr::Ipv6(decode_sockaddr(sa, len, "invalid resolved address")?),
+			_ => return Ok(()),
+		};
+		if !addrs.contains(&addr) {
+			addrs.push(addr);
+		}
+		Ok(())
+	})?;
+
+	// Stable, so each family keeps the system's order.
+	match order {
+		ResolveOrder::Ipv4First => addrs.sort_by_key(|addr| !matches!(addr, AnyAddr::Ipv4(_))),
+		ResolveOrder::Ipv6First => addrs.sort_by_key(|addr| !matches!(addr, AnyAddr::Ipv6(_))),
+		_ => {}
+	}
+	Ok(addrs)
+}
+
+/// Resolves `"host:port"` to addresses of family `D` only, in system
+/// order: `resolve_family::<Ipv4>("db.internal:5432")`.
+pub fn resolve_family<D: Domain>(host: &str) -> std::io::Result<Vec<D::Addr>>
+where
+	D::Addr: FromSockAddr + PartialEq,
+{
+	resolve_for::<D>(host, libc::AI_ADDRCONFIG)
+}
+
+/// Addresses to bind for `host`: an empty host (`":8080"`) means every
+/// interface (AI_PASSIVE).
+pub(crate) fn resolve_passive<D: Domain>(host: &str) -> std::io::Result<Vec<D::Addr>>
+where
+	D::Addr: FromSockAddr + PartialEq,
+{
+	resolve_for::<D>(host, libc::AI_PASSIVE)
+}
+
+fn resolve_for<D: Domain>(host: &str, flags: libc::c_int) -> std::io::Result<Vec<D::Addr>>
+where
+	D::Addr: FromSockAddr + PartialEq,
+{
+	let mut addrs = Vec::new();
+	lookup(host, D::raw(), flags, |sa, len| {
+		let addr = unsafe { decode_sockaddr::<D::Addr>(sa, len, "invalid resolved address")? };
+		if !addrs.contains(&addr) {
+			addrs.push(addr);
+		}
+		Ok(())
+	})?;
+	Ok(addrs)
+}
+
+/// Tries `op` on each address in turn; the first success wins, else the
+/// last error.
+pub(crate) fn first_ok<A, T>(host: &str, addrs: Vec<A>, mut op: impl FnMut(A) -> std::io::Result<T>) -> std::io::Result<T> {
+	let mut last = None;
+	for addr in addrs {
+		match op(addr) {
+			Ok(value) => return Ok(value),
+			Err(e) => last = Some(e),
+		}
+	}
+	Err(last.unwrap_or_else(|| SocketError::Resolve { host: host.to_string(), code: libc::EAI_NONAME, errno: 0 }.into()))
+}
+
+/// Runs getaddrinfo() and hands each result's sockaddr to `each`.
+fn lookup(
+	host: &str,
+	family: libc::c_int,
+	flags: libc::c_int,
+	mut each: impl FnMut(*const libc::sockaddr, libc::socklen_t) -> Result<(), SocketError>,
+) -> std::io::Result<()> {
+	let (name, port) = split_host_port(host)?;
+	let name = match name {
+		"" => None,
+		name => Some(CString::new(name).map_err(|_| SocketError::InvalidAddress { reason: "host name contains NUL" })?),
+	};
+	let service = CString::new(port.to_string()).unwrap();
+
+	let mut hints: libc::addrinfo = unsafe { std::mem::zeroed() };
+	hints.ai_family = family;
+	// One entry per address instead of one per socket type.
+	hints.ai_socktype = libc::SOCK_STREAM;
+	hints.ai_flags = flags | libc::AI_NUMERICSERV;
+
+	let mut list: *mut libc::addrinfo = std::ptr::null_mut();
+	let code = unsafe {
+		libc::getaddrinfo(
+			name.as_ref().map_or(std::ptr::null(), |n| n.as_ptr()),
+			service.as_ptr(),
+			&hints,
+			&mut list,
+		)
+	};
+	if code != 0 {
+		return Err(SocketError::Resolve { host: host.to_string(), code, errno: errno() }.into());
+	}
+
+	let mut result = Ok(());
+	let mut entry = list;
+	while !entry.is_null() && result.is_ok() {
+		let info = unsafe { &*entry };
+		result = each(info.ai_addr, info.ai_addrlen);
+		entry = info.ai_next;
+	}
+	unsafe { libc::freeaddrinfo(list) };
+	result.map_err(Into::into)
+}
+
+/// Splits `"host:port"`, unbracketing an IPv6 literal.
+fn split_host_port(host: &str) -> Result<(&str, u16), SocketError> {
+	let (name, port) = host.rsplit_once(':')
+		.ok_or(SocketError::InvalidAddress { reason: "expected host:port" })?;
+	let name = match name.strip_prefix('[') {
+		Some(inner) => inner.strip_suffix(']')
+			.ok_or(SocketError::InvalidAddress { reason: "unclosed '[' in host" })?,
+		None if name.contains(':') => {
+			return Err(SocketError::InvalidAddress { reason: "IPv6 literal must be in brackets, e.g. [::1]:80" });
+		}
+		None => name,
+	};
+	let port = port.parse().map_err(|_| SocketError::InvalidAddress { reason: "invalid port number" })?;
+	Ok((name, port))
+}
+
+/*
+Why getaddrinfo() instead of std::net::ToSocketAddrs?
+ToSocketAddrs calls the same function and returns std's SocketAddr,
+which then has to be converted family by family into the crate's
+types — the step everyone was writing by hand. Calling it directly
+decodes straight into D::Addr, lets the family filter run in the
+resolver (an Ipv4 builder never waits on an AAAA query), and keeps the
+EAI_* code in the error instead of a generic "failed to lookup".
+*/
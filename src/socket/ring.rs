@@ -0,0 +1,367 @@
+use std::collections::HashMap;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use io_uring::{IoUring, opcode, squeue, types};
+use crate::addr::{Domain, ToSockAddr};
+use crate::error::{SocketError, IoError};
+use super::{Stream, RawSocket, Listener, ConnectedStream, PendingConnect, FixedRecvPool};
+
+/// An io_uring submission/completion ring for socket I/O.
+///
+/// Operations are queued with a caller-chosen `token` and complete later.
+/// Each `Completion` carries the token back so the caller can map it
+/// to its own connection state.
+///
+/// Nothing is sent to the kernel until `submit()` or `submit_and_wait()`.
+///
+/// # Safety Contract
+///
+/// Submission methods are `unsafe`: the kernel holds raw pointers to the
+/// buffers and file descriptors until the matching completion is reaped.
+/// The caller must keep them alive and unmoved until then, and tokens must
+/// be unique among in-flight operations on this ring.
+pub struct Ring {
+	ring: IoUring,
+	/// Destination addresses for in-flight connect/send_to, keyed by token.
+	/// The kernel may read these after submission, so they live until completion.
+	addrs: HashMap<u64, Box<libc::sockaddr_storage>>,
+	/// Provided-buffer pools by group id. Declared after `ring`, so the
+	/// kernel lets go of them before they are freed.
+	pools: HashMap<u16, FixedRecvPool>,
+	/// Group of each in-flight `recv_fixed()`, keyed by token.
+	fixed_recvs: HashMap<u64, u16>,
+}
+
+impl Ring {
+	/// Creates a ring with room for `entries` submission queue entries.
+	///
+	/// `entries` is rounded up to a power of two by the kernel.
+	pub fn new(entries: u32) -> std::io::Result<Self> {
+		Ok(Self {
+			ring: IoUring::new(entries)?,
+			addrs: HashMap::new(),
+			pools: HashMap::new(),
+			fixed_recvs: HashMap::new(),
+		})
+	}
+
+	/// Queues an accept on the listener.
+	///
+	/// The completion result is the new connection's fd.
+	/// Use `Completion::into_stream()` to take ownership of it.
+	///
+	/// # Safety
+	/// `listener` must outlive the operation.
+	pub unsafe fn accept<D: Domain>(&mut self, listener: &Listener<D>, token: u64) -> std::io::Result<()> {
+		let entry = opcode::Accept::new(
+			types::Fd(listener.as_raw_fd()),
+			std::ptr::null_mut(),
+			std::ptr::null_mut(),
+		)
+			.flags(libc::SOCK_CLOEXEC)
+			.build()
+			.user_data(token);
+
+		unsafe { self.push(&entry) }
+	}
+
+	/// Queues a connect and returns the socket as `PendingConnect`.
+	///
+	/// When the completion arrives, check `PendingConnect::take_error()`
+	/// (or the completion result) and call `finish()`.
+	///
+	/// # Safety
+	/// The returned `PendingConnect` must outlive the operation.
+	pub unsafe fn connect<D: Domain>(
+		&mut self,
+		socket: RawSocket<D, Stream>,
+		addr: D::Addr,
+		token: u64,
+	) -> std::io::Result<PendingConnect<D>>
+	where
+		D::Addr: ToSockAddr,
+	{
+		let (storage, len) = box_addr(&addr)?;
+
+		let entry = opcode::Connect::new(types::Fd(socket.as_raw_fd()), addr_ptr(&storage), len)
+			.build()
+			.user_data(token);
+
+		unsafe { self.push(&entry)? };
+		self.addrs.insert(token, storage);
+		Ok(PendingConnect::from_fd(socket.into_fd()))
+	}
+
+	/// Queues a receive into `buf`.
+	///
+	/// Works on any connected or bound socket
+	/// (`ConnectedStream`, `BoundDatagram`, `ConnectedDatagram`).
+	/// The completion result is the number of bytes received.
+	///
+	/// # Safety
+	/// `socket` and `buf` must outlive the operation, and `buf` must not be
+	/// accessed until the completion is reaped.
+	pub unsafe fn recv<S: AsRawFd>(&mut self, socket: &S, buf: &mut [u8], token: u64) -> std::io::Result<()> {
+		let entry = opcode::Recv::new(
+			types::Fd(socket.as_raw_fd()),
+			buf.as_mut_ptr(),
+			buf.len() as u32,
+		)
+			.build()
+			.user_data(token);
+
+		unsafe { self.push(&entry) }
+	}
+
+	/// Registers `pool` as provided-buffer group `group` (IORING_REGISTER_PBUF_RING).
+	///
+	/// The ring owns the pool from now on; reach it with `recv_pool()`.
+	/// Fails with EEXIST if the group id is taken.
+	pub fn register_recv_pool(&mut self, group: u16, pool: FixedRecvPool) -> std::io::Result<()> {
+		if self.pools.contains_key(&group) {
+			return Err(SocketError::SetOption { errno: libc::EEXIST, option: "provided buffer group" }.into());
+		}
+		let (addr, count) = pool.ring_addr();
+		// The pool's ring stays mapped until it drops, after the ring itself.
+		unsafe { self.ring.submitter().register_buf_ring_with_flags(addr, count, group, 0) }
+			.map_err(|e| SocketError::SetOption {
+				errno: e.raw_os_error().unwrap_or(libc::EINVAL),
+				option: "IORING_REGISTER_PBUF_RING",
+			})?;
+		self.pools.insert(group, pool);
+		Ok(())
+	}
+
+	/// The pool registered as `group`.
+	pub fn recv_pool(&self, group: u16) -> Option<&FixedRecvPool> {
+		self.pools.get(&group)
+	}
+
+	/// The pool registered as `group`, e.g. to `recycle()` buffers.
+	pub fn recv_pool_mut(&mut self, group: u16) -> Option<&mut FixedRecvPool> {
+		self.pools.get_mut(&group)
+	}
+
+	/// Queues a receive into a buffer the kernel picks from pool `group`.
+	///
+	/// The completion's `buffer_id()` names the buffer; read it with
+	/// `FixedRecvPool::data()` and `recycle()` it when done. Completes
+	/// with ENOBUFS if the pool has no free buffer.
+	///
+	/// # Safety
+	/// `socket` must outlive the operation.
+	pub unsafe fn recv_fixed<S: AsRawFd>(&mut self, socket: &S, group: u16, token: u64) -> std::io::Result<()> {
+		if !self.pools.contains_key(&group) {
+			return Err(SocketError::SetOption { errno: libc::ENOENT, option: "provided buffer group" }.into());
+		}
+		let entry = opcode::Recv::new(types::Fd(socket.as_raw_fd()), std::ptr::null_mut(), 0)
+			.buf_group(group)
+			.build()
+			.flags(squeue::Flags::BUFFER_SELECT)
+			.user_data(token);
+
+		unsafe { self.push(&entry)? };
+		self.fixed_recvs.insert(token, group);
+		Ok(())
+	}
+
+	/// Queues a send of `buf` on a connected socket.
+	///
+	/// The completion result is the number of bytes sent; a closed peer
+	/// completes with `-EPIPE` rather than raising SIGPIPE.
+	///
+	/// # Safety
+	/// `socket` and `buf` must outlive the operation.
+	pub unsafe fn send<S: AsRawFd>(&mut self, socket: &S, buf: &[u8], token: u64) -> std::io::Result<()> {
+		let entry = opcode::Send::new(
+			types::Fd(socket.as_raw_fd()),
+			buf.as_ptr(),
+			buf.len() as u32,
+		)
+			.flags(libc::MSG_NOSIGNAL)
+			.build()
+			.user_data(token);
+
+		unsafe { self.push(&entry) }
+	}
+
+	/// Queues a send of `buf` to `addr` on an unconnected datagram socket.
+	///
+	/// Requires Linux 6.0+ (IORING_OP_SEND with a destination address).
+	///
+	/// # Safety
+	/// `socket` and `buf` must outlive the operation.
+	pub unsafe fn send_to<S: AsRawFd, A: ToSockAddr>(
+		&mut self,
+		socket: &S,
+		buf: &[u8],
+		addr: &A,
+		token: u64,
+	) -> std::io::Result<()> {
+		let (storage, len) = box_addr(addr)?;
+
+		let entry = opcode::Send::new(
+			types::Fd(socket.as_raw_fd()),
+			buf.as_ptr(),
+			buf.len() as u32,
+		)
+			.flags(libc::MSG_NOSIGNAL)
+			.dest_addr(addr_ptr(&storage))
+			.dest_addr_len(len)
+			.build()
+			.user_data(token);
+
+		unsafe { self.push(&entry)? };
+		self.addrs.insert(token, storage);
+		Ok(())
+	}
+
+	/// Queues a splice between two fds (one must be a pipe).
+	///
+	/// Both sides use their current position, as sockets and pipes
+	/// have no offsets. The completion result is the number of bytes moved.
+	///
+	/// # Safety
+	/// Both fds must outlive the operation.
+	pub unsafe fn splice<In: AsRawFd, Out: AsRawFd>(
+		&mut self,
+		fd_in: &In,
+		fd_out: &Out,
+		len: u32,
+		flags: u32,
+		token: u64,
+	) -> std::io::Result<()> {
+		let entry = opcode::Splice::new(
+			types::Fd(fd_in.as_raw_fd()),
+			-1,
+			types::Fd(fd_out.as_raw_fd()),
+			-1,
+			len,
+		)
+			.flags(flags)
+			.build()
+			.user_data(token);
+
+		unsafe { self.push(&entry) }
+	}
+
+	/// Submits queued operations without waiting.
+	///
+	/// Returns the number of entries submitted.
+	pub fn submit(&mut self) -> std::io::Result<usize> {
+		self.ring.submit()
+	}
+
+	/// Submits queued operations and blocks until `want` completions are ready.
+	pub fn submit_and_wait(&mut self, want: usize) -> std::io::Result<usize> {
+		self.ring.submit_and_wait(want)
+	}
+
+	/// Drains all ready completions.
+	pub fn completions(&mut self) -> Vec<Completion> {
+		let mut results = Vec::new();
+		for cqe in self.ring.completion() {
+			results.push(Completion {
+				token: cqe.user_data(),
+				result: cqe.result(),
+				flags: cqe.flags(),
+			});
+		}
+		for completion in &results {
+			self.addrs.remove(&completion.token);
+			if let Some(group) = self.fixed_recvs.remove(&completion.token)
+				&& let Some(bid) = completion.buffer_id()
+				&& let Some(pool) = self.pools.get_mut(&group)
+			{
+				pool.lend(bid);
+			}
+		}
+		results
+	}
+
+	/// Pushes an entry, flushing the queue to the kernel once if it is full.
+	unsafe fn push(&mut self, entry: &squeue::Entry) -> std::io::Result<()> {
+		if self.ring.submission().is_full() {
+			self.ring.submit()?;
+		}
+		unsafe { self.ring.submission().push(entry) }
+			.map_err(|_| IoError::WouldBlock.into())
+	}
+}
+
+/// Copies `addr` into heap storage that stays put while the ring owns it;
+/// callers keep it in `Ring::addrs` once the entry is queued.
+fn box_addr<A: ToSockAddr>(addr: &A) -> std::io::Result<(Box<libc::sockaddr_storage>, libc::socklen_t)> {
+	let mut storage: Box<libc::sockaddr_storage> = Box::new(unsafe { std::mem::zeroed() });
+
+	let len = addr.with_raw(|ptr, len| {
+		// sockaddr_storage is sized to hold any family's address.
+		debug_assert!(len as usize <= std::mem::size_of::<libc::sockaddr_storage>());
+		let len = len.min(std::mem::size_of::<libc::sockaddr_storage>() as libc::socklen_t);
+		unsafe {
+			std::ptr::copy_nonoverlapping(
+				ptr as *const u8,
+				&mut *storage as *mut _ as *mut u8,
+				len as usize,
+			);
+		}
+		len
+	})
+		.map_err(SocketError::from)?;
+
+	Ok((storage, len))
+}
+
+fn addr_ptr(storage: &libc::sockaddr_storage) -> *const libc::sockaddr {
+	storage as *const _ as *const libc::sockaddr
+}
+
+impl AsRawFd for Ring {
+	fn as_raw_fd(&self) -> std::os::fd::RawFd {
+		self.ring.as_raw_fd()
+	}
+}
+
+/// A completed ring operation.
+#[derive(Debug, Clone, Copy)]
+pub struct Completion {
+	token: u64,
+	result: i32,
+	flags: u32,
+}
+
+impl Completion {
+	/// Returns the token passed at submission.
+	pub fn token(&self) -> u64 {
+		self.token
+	}
+
+	/// Returns the operation result.
+	///
+	/// Byte count for recv/send/splice, the new fd for accept, 0 for connect.
+	pub fn result(&self) -> std::io::Result<usize> {
+		if self.result < 0 {
+			Err(std::io::Error::from_raw_os_error(-self.result))
+		} else {
+			Ok(self.result as usize)
+		}
+	}
+
+	/// The provided buffer a `recv_fixed()` filled, if any.
+	pub fn buffer_id(&self) -> Option<u16> {
+		io_uring::cqueue::buffer_select(self.flags)
+	}
+
+	/// Takes ownership of the fd produced by an accept.
+	///
+	/// # Safety
+	/// This completion must come from `Ring::accept()` on a `Listener<D>`,
+	/// and must be converted at most once.
+	pub unsafe fn into_stream<D: Domain>(self) -> std::io::Result<ConnectedStream<D>> {
+		if self.result < 0 {
+			return Err(SocketError::Accept { errno: -self.result }.into());
+		}
+		crate::track(self.result, "io_uring accept");
+		let fd = unsafe { OwnedFd::from_raw_fd(self.result) };
+		Ok(ConnectedStream::from_fd(fd))
+	}
+}
@@ -0,0 +1,250 @@
+use std::os::fd::{OwnedFd, FromRawFd};
+use std::marker::PhantomData;
+use crate::addr::{Domain, Unix, ToSockAddr, FromSockAddr};
+use crate::error::{SocketError, IoError, errno};
+use super::{RawSocket, BoundSocket, SeqPacket, SendFlags, RecvFlags};
+use super::mode::set_fd_nonblocking;
+
+/// A listening sequenced-packet socket.
+///
+/// Created by `BoundSocket<D, SeqPacket>::listen()`.
+/// Accepted connections are `ConnectedSeqPacket<D>`.
+pub struct SeqPacketListener<D: Domain> {
+	fd: OwnedFd,
+	_marker: PhantomData<D>,
+}
+
+/// A connected sequenced-packet socket.
+///
+/// Like a stream, it is connection-oriented and reliable. Like a datagram,
+/// each `send()` is delivered as one message: a `recv()` never returns
+/// part of two messages. If the buffer is too small, the rest of that
+/// message is discarded.
+pub struct ConnectedSeqPacket<D: Domain> {
+	fd: OwnedFd,
+	_marker: PhantomData<D>,
+}
+
+impl<D: Domain> BoundSocket<D, SeqPacket> {
+	/// Starts listening for connections.
+	pub fn listen(self, backlog: i32) -> std::io::Result<SeqPacketListener<D>> {
+		let result = unsafe { libc::listen(self.as_raw_fd(), backlog) };
+		if result == -1 {
+			return Err(SocketError::Listen { errno: errno(), backlog }.into());
+		}
+		Ok(SeqPacketListener::from_fd(self.into_fd()))
+	}
+}
+
+impl<D: Domain> RawSocket<D, SeqPacket> {
+	/// Connects to a listening sequenced-packet socket.
+	pub fn connect(self, addr: D::Addr) -> std::io::Result<ConnectedSeqPacket<D>>
+	where
+		D::Addr: ToSockAddr + std::fmt::Debug,
+	{
+		let result = addr.with_raw(|ptr, len| unsafe {
+			libc::connect(self.as_raw_fd(), ptr, len)
+		});
+
+		match result {
+			Ok(-1) => Err(SocketError::Connect {
+				errno: errno(),
+				addr: format!("{:?}", addr),
+			}.into()),
+			Ok(_) => Ok(ConnectedSeqPacket::from_fd(self.into_fd())),
+			Err(e) => Err(SocketError::from(e).into()),
+		}
+	}
+}
+
+impl<D: Domain> SeqPacketListener<D> {
+	pub(crate) fn from_fd(fd: OwnedFd) -> Self {
+		Self { fd, _marker: PhantomData }
+	}
+
+	/// Returns the raw file descriptor.
+	#[inline]
+	pub fn as_raw_fd(&self) -> libc::c_int {
+		use std::os::fd::AsRawFd;
+		self.fd.as_raw_fd()
+	}
+
+	/// Duplicates the fd (close-on-exec) into a second handle.
+	///
+	/// Both handles refer to the same socket, which closes once the last drops.
+	pub fn try_clone(&self) -> std::io::Result<Self> {
+		Ok(Self::from_fd(super::raw::dup_fd(self.as_raw_fd())?))
+	}
+
+	/// Accepts a connection.
+	///
+	/// Blocks unless the listener is non-blocking, in which case it fails
+	/// with `WouldBlock` when no connection is pending.
+	pub fn accept(&self) -> std::io::Result<ConnectedSeqPacket<D>> {
+		let fd = unsafe {
+			libc::accept4(self.as_raw_fd(), std::ptr::null_mut(), std::ptr::null_mut(), libc::SOCK_CLOEXEC)
+		};
+		if fd == -1 {
+			return Err(SocketError::Accept { errno: errno() }.into());
+		}
+		crate::track(fd, "accept()");
+		Ok(ConnectedSeqPacket::from_fd(unsafe { OwnedFd::from_raw_fd(fd) }))
+	}
+
+	/// Accepts a connection and returns the peer address.
+	pub fn accept_with_addr(&self) -> std::io::Result<(ConnectedSeqPacket<D>, D::Addr)>
+	where
+		D::Addr: FromSockAddr,
+	{
+		let mut storage: libc::sockaddr_storage = unsafe { std::mem::zeroed() };
+		let mut len = std::mem::size_of::<libc::sockaddr_storage>() as libc::socklen_t;
+
+		let fd = unsafe {
+			libc::accept4(
+				self.as_raw_fd(),
+				&mut storage as *mut _ as *mut libc::sockaddr,
+				&mut len,
+				libc::SOCK_CLOEXEC,
+			)
+		};
+		if fd == -1 {
+			return Err(SocketError::Accept { errno: errno() }.into());
+		}
+		crate::track(fd, "accept()");
+		let conn = ConnectedSeqPacket::from_fd(unsafe { OwnedFd::from_raw_fd(fd) });
+
+		let addr = unsafe {
+			D::Addr::from_sockaddr(&storage as *const _ as *const libc::sockaddr, len)
+				.ok_or(SocketError::InvalidAddress { reason: "invalid client address" })?
+		};
+		Ok((conn, addr))
+	}
+
+	pub fn set_nonblocking(&self, nonblocking: bool) -> std::io::Result<()> {
+		set_fd_nonblocking(self.as_raw_fd(), nonblocking)
+	}
+}
+
+impl<D: Domain> ConnectedSeqPacket<D> {
+	pub(crate) fn from_fd(fd: OwnedFd) -> Self {
+		Self { fd, _marker: PhantomData }
+	}
+
+	/// Returns the raw file descriptor.
+	#[inline]
+	pub fn as_raw_fd(&self) -> libc::c_int {
+		use std::os::fd::AsRawFd;
+		self.fd.as_raw_fd()
+	}
+
+	/// Duplicates the fd (close-on-exec) into a second handle.
+	///
+	/// Both handles refer to the same socket, which closes once the last drops.
+	pub fn try_clone(&self) -> std::io::Result<Self> {
+		Ok(Self::from_fd(super::raw::dup_fd(self.as_raw_fd())?))
+	}
+
+	/// Sends `buf` as one message.
+	pub fn send(&self, buf: &[u8]) -> std::io::Result<usize> {
+		self.send_with_flags(buf, SendFlags::NONE)
+	}
+
+	pub fn send_with_flags(&self, buf: &[u8], flags: SendFlags) -> std::io::Result<usize> {
+		let n = unsafe {
+			libc::send(self.as_raw_fd(), buf.as_ptr() as *const libc::c_void, buf.len(), flags.bits() | libc::MSG_NOSIGNAL)
+		};
+		if n == -1 {
+			Err(IoError::Write { errno: errno() }.into())
+		} else {
+			Ok(n as usize)
+		}
+	}
+
+	/// Receives one message.
+	///
+	/// Returns 0 once the peer has closed the connection (or sent an
+	/// empty message). Excess bytes of an oversized message are dropped;
+	/// use `recv_with_flags(buf, RecvFlags::TRUNC)` to learn the full size.
+	pub fn recv(&self, buf: &mut [u8]) -> std::io::Result<usize> {
+		self.recv_with_flags(buf, RecvFlags::NONE)
+	}
+
+	pub fn recv_with_flags(&self, buf: &mut [u8], flags: RecvFlags) -> std::io::Result<usize> {
+		let n = unsafe {
+			libc::recv(self.as_raw_fd(), buf.as_mut_ptr() as *mut libc::c_void, buf.len(), flags.bits())
+		};
+		if n == -1 {
+			Err(IoError::Read { errno: errno() }.into())
+		} else {
+			Ok(n as usize)
+		}
+	}
+
+	pub fn set_nonblocking(&self, nonblocking: bool) -> std::io::Result<()> {
+		set_fd_nonblocking(self.as_raw_fd(), nonblocking)
+	}
+}
+
+impl ConnectedSeqPacket<Unix> {
+	/// Creates a pair of connected Unix sequenced-packet sockets.
+	pub fn pair() -> std::io::Result<(Self, Self)> {
+		let (a, b) = super::raw::socketpair::<SeqPacket>()?;
+		Ok((Self::from_fd(a), Self::from_fd(b)))
+	}
+}
+
+impl<D: Domain> std::os::fd::AsRawFd for SeqPacketListener<D> {
+	fn as_raw_fd(&self) -> std::os::fd::RawFd {
+		self.fd.as_raw_fd()
+	}
+}
+
+impl<D: Domain> std::os::fd::AsFd for SeqPacketListener<D> {
+	fn as_fd(&self) -> std::os::fd::BorrowedFd<'_> {
+		self.fd.as_fd()
+	}
+}
+
+impl<D: Domain> std::os::fd::FromRawFd for SeqPacketListener<D> {
+	unsafe fn from_raw_fd(fd: std::os::fd::RawFd) -> Self {
+		unsafe { Self::from_fd(OwnedFd::from_raw_fd(fd)) }
+	}
+}
+
+impl<D: Domain> std::os::fd::IntoRawFd for SeqPacketListener<D> {
+	fn into_raw_fd(self) -> std::os::fd::RawFd {
+		self.fd.into_raw_fd()
+	}
+}
+
+impl<D: Domain> std::os::fd::AsRawFd for ConnectedSeqPacket<D> {
+	fn as_raw_fd(&self) -> std::os::fd::RawFd {
+		self.fd.as_raw_fd()
+	}
+}
+
+impl<D: Domain> std::os::fd::AsFd for ConnectedSeqPacket<D> {
+	fn as_fd(&self) -> std::os::fd::BorrowedFd<'_> {
+		self.fd.as_fd()
+	}
+}
+
+impl<D: Domain> std::os::fd::FromRawFd for ConnectedSeqPacket<D> {
+	unsafe fn from_raw_fd(fd: std::os::fd::RawFd) -> Self {
+		unsafe { Self::from_fd(OwnedFd::from_raw_fd(fd)) }
+	}
+}
+
+impl<D: Domain> std::os::fd::IntoRawFd for ConnectedSeqPacket<D> {
+	fn into_raw_fd(self) -> std::os::fd::RawFd {
+		self.fd.into_raw_fd()
+	}
+}
+
+/*
+Why separate types instead of Listener<D> / ConnectedStream<D>?
+ConnectedStream promises byte-stream semantics: reads may return any
+slice of the data, and Read/Write adapters rely on that. A seqpacket
+read returns exactly one message and drops the excess — code written
+for streams would silently lose data. Distinct types keep the two apart.
+*/
@@ -0,0 +1,134 @@
+use std::os::fd::AsRawFd;
+use crate::error::{SocketError, errno};
+use super::get_reuse_port;
+
+// From asm-generic/socket.h; not exported by libc on every target.
+const SO_ATTACH_REUSEPORT_CBPF: libc::c_int = 51;
+
+/// Largest SO_REUSEPORT group the kernel allows (REUSEPORT_MAX_SOCKS).
+pub const MAX_REUSEPORT_WORKERS: u32 = 1 << 16;
+
+/// A classic BPF program that spreads an SO_REUSEPORT group's flows over
+/// `workers` sockets by flow hash.
+///
+/// Created by `reuseport_shard()`. Every packet of a flow (TCP
+/// connection, or UDP 4-tuple) goes to the same socket; socket `i` of
+/// the group — the `i`-th to bind — is worker `i`. Attach with
+/// `attach()`, or let `ListenerBuilder::bind_sharded()` do it all.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReuseportShard {
+	workers: u32,
+}
+
+/// Builds the sharding program for a group of `workers` sockets.
+///
+/// Fails with EINVAL for 0 workers or more than `MAX_REUSEPORT_WORKERS`.
+pub fn reuseport_shard(workers: u32) -> std::io::Result<ReuseportShard> {
+	if workers == 0 || workers > MAX_REUSEPORT_WORKERS {
+		return Err(SocketError::InvalidInput { reason: "reuseport shard needs 1-65536 workers" }.into());
+	}
+	Ok(ReuseportShard { workers })
+}
+
+impl ReuseportShard {
+	/// Number of sockets the program distributes over.
+	pub fn workers(&self) -> u32 {
+		self.workers
+	}
+
+	/// The worker a packet with this flow hash (skb->hash) goes to.
+	///
+	/// `None` for hash 0, which the program leaves to the kernel's
+	/// default reuseport hash.
+	pub fn worker_for_hash(&self, hash: u32) -> Option<u32> {
+		(hash != 0).then(|| hash % self.workers)
+	}
+
+	/// Attaches the program to a reuseport group (SO_ATTACH_REUSEPORT_CBPF).
+	///
+	/// `group` must be every socket of the group, in the order they were
+	/// bound, all with SO_REUSEPORT and the same local address. A group
+	/// of the wrong size would silently leave workers idle (too many) or
+	/// fall back to the kernel's own hash for some flows (too few), so
+	/// it's rejected with EINVAL.
+	pub fn attach<S: AsRawFd>(&self, group: &[S]) -> std::io::Result<()> {
+		if group.len() != self.workers as usize {
+			return Err(SocketError::InvalidInput { reason: "reuseport shard group size differs from its worker count" }.into());
+		}
+		let first = local_sockaddr(&group[0])?;
+		for socket in group {
+			if !get_reuse_port(socket)? {
+				return Err(SocketError::InvalidInput { reason: "reuseport shard socket lacks SO_REUSEPORT" }.into());
+			}
+			if local_sockaddr(socket)? != first {
+				return Err(SocketError::InvalidInput { reason: "reuseport shard sockets are bound to different addresses" }.into());
+			}
+		}
+
+		let mut program = self.program();
+		let fprog = libc::sock_fprog {
+			len: program.len() as libc::c_ushort,
+			filter: program.as_mut_ptr(),
+		};
+		// The program belongs to the group, whichever member it's set on.
+		let result = unsafe {
+			libc::setsockopt(
+				group[0].as_raw_fd(),
+				libc::SOL_SOCKET,
+				SO_ATTACH_REUSEPORT_CBPF,
+				&fprog as *const _ as *const libc::c_void,
+				std::mem::size_of::<libc::sock_fprog>() as libc::socklen_t,
+			)
+		};
+		if result == -1 {
+			return Err(SocketError::SetOption { errno: errno(), option: "SO_ATTACH_REUSEPORT_CBPF" }.into());
+		}
+		Ok(())
+	}
+
+	/// `ld rxhash; mod #workers; ret a` — the return value indexes the group.
+	///
+	/// A zero hash (not computed yet, e.g. unconnected UDP over loopback)
+	/// returns an out-of-range index, which makes the kernel fall back to
+	/// its own 4-tuple hash instead of sending every such flow to worker 0.
+	fn program(&self) -> Vec<libc::sock_filter> {
+		let stmt = |code: u32, k: u32| libc::sock_filter { code: code as u16, jt: 0, jf: 0, k };
+		vec![
+			stmt(libc::BPF_LD | libc::BPF_W | libc::BPF_ABS, (libc::SKF_AD_OFF + libc::SKF_AD_RXHASH) as u32),
+			libc::sock_filter { code: (libc::BPF_JMP | libc::BPF_JEQ | libc::BPF_K) as u16, jt: 2, jf: 0, k: 0 },
+			stmt(libc::BPF_ALU | libc::BPF_MOD | libc::BPF_K, self.workers),
+			stmt(libc::BPF_RET | libc::BPF_A, 0),
+			stmt(libc::BPF_RET | libc::BPF_K, self.workers),
+		]
+	}
+}
+
+/// The socket's bound address as raw bytes, for comparing group members.
+fn local_sockaddr<S: AsRawFd>(socket: &S) -> std::io::Result<Vec<u8>> {
+	let mut storage: libc::sockaddr_storage = unsafe { std::mem::zeroed() };
+	let mut len = std::mem::size_of::<libc::sockaddr_storage>() as libc::socklen_t;
+	let result = unsafe {
+		libc::getsockname(socket.as_raw_fd(), &mut storage as *mut _ as *mut libc::sockaddr, &mut len)
+	};
+	if result == -1 {
+		return Err(SocketError::GetOption { errno: errno(), option: "SO_SOCKNAME" }.into());
+	}
+	let bytes = unsafe { std::slice::from_raw_parts(&storage as *const _ as *const u8, len as usize) };
+	Ok(bytes.to_vec())
+}
+
+/*
+Why the kernel's flow hash instead of hashing the 4-tuple in BPF?
+By the time a reuseport program runs, the packet data starts after the
+TCP/UDP header, so reaching ports and addresses means negative offsets
+and per-family IP header parsing. skb->hash is already the 4-tuple hash
+(from the NIC's RSS or the stack), so a handful of instructions do the
+job and a flow's worker matches the RX queue it arrived on when RSS is
+in use. Where no hash was computed, the kernel's fallback still picks by
+4-tuple, so flows stay pinned either way.
+
+Why validate the group in userspace?
+The kernel accepts the program on any group size: an index past the end
+quietly falls back to its default hash, and a short count leaves sockets
+that never get traffic. Both look like "sharding works, mostly".
+*/
@@ -0,0 +1,217 @@
+use std::time::{Duration, Instant};
+use crate::addr::Domain;
+use crate::error::{IoError, errno};
+use super::{Listener, ConnectedStream, Shutdown, RecvFlags, set_linger};
+use super::wait::to_poll_ms;
+
+/// What `ShutdownCoordinator::run()` did with each connection.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ShutdownReport {
+	/// Peers that closed their side before the deadline.
+	pub drained: usize,
+	/// Connections reset after the deadline (or that failed mid-drain).
+	pub reset: usize,
+	/// One entry per stream, in the order they were added.
+	pub drains: Vec<DrainReport>,
+}
+
+impl ShutdownReport {
+	/// Total bytes read and thrown away across all streams.
+	pub fn bytes_discarded(&self) -> u64 {
+		self.drains.iter().map(|d| d.bytes_discarded).sum()
+	}
+
+	/// Streams that had unread or still-arriving data at shutdown.
+	pub fn with_data_in_flight(&self) -> usize {
+		self.drains.iter().filter(|d| d.bytes_discarded > 0).count()
+	}
+}
+
+/// How draining one connection went.
+///
+/// Many reports with `bytes_discarded > 0` mean peers were mid-request
+/// at shutdown; many `DrainEnd::Deadline` mean the drain timeout is
+/// shorter than peers take to close.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DrainReport {
+	/// Bytes unread at the half-close or sent after it, all discarded.
+	pub bytes_discarded: u64,
+	/// From the half-close until the drain ended.
+	pub duration: Duration,
+	/// Why it ended.
+	pub ended_by: DrainEnd,
+}
+
+/// Why a connection's drain ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DrainEnd {
+	/// The peer closed its side (EOF).
+	PeerClosed,
+	/// The deadline passed; the connection was reset.
+	Deadline,
+	/// Shutdown or a read failed, usually because the peer reset it.
+	Error,
+}
+
+/// Tears a server down in the right order.
+///
+/// 1. Stop accepting: the listener is shut down and closed, so threads
+///    blocked in `accept()` on a clone wake up with an error.
+/// 2. Half-close every stream (FIN): peers see EOF after the data
+///    already written.
+/// 3. Read and discard until each peer closes too, up to the deadline.
+/// 4. Reset whatever is left (SO_LINGER 0, so no TIME_WAIT).
+///
+/// Closing with unread input would make the kernel send RST right away
+/// and possibly discard responses the peer hasn't read yet — step 3 is
+/// what avoids that.
+///
+/// # Example
+/// ```ignore
+/// let report = ShutdownCoordinator::new(listener)
+///     .drain_timeout(Duration::from_secs(5))
+///     .streams(connections)
+///     .run()?;
+/// log::info!("{} drained, {} reset", report.drained, report.reset);
+/// ```
+pub struct ShutdownCoordinator<D: Domain> {
+	listener: Option<Listener<D>>,
+	streams: Vec<ConnectedStream<D>>,
+	drain_timeout: Duration,
+}
+
+impl<D: Domain> ShutdownCoordinator<D> {
+	/// Starts a shutdown plan for `listener`. Default drain timeout: 10s.
+	pub fn new(listener: Listener<D>) -> Self {
+		Self {
+			listener: Some(listener),
+			streams: Vec::new(),
+			drain_timeout: Duration::from_secs(10),
+		}
+	}
+
+	/// Shuts down connections only, e.g. when the listener is owned elsewhere.
+	pub fn without_listener() -> Self {
+		Self {
+			listener: None,
+			streams: Vec::new(),
+			drain_timeout: Duration::from_secs(10),
+		}
+	}
+
+	/// Set how long to wait for peers to close. Default: 10s.
+	pub fn drain_timeout(mut self, timeout: Duration) -> Self {
+		self.drain_timeout = timeout;
+		self
+	}
+
+	/// Adds a connection to close.
+	pub fn stream(mut self, stream: ConnectedStream<D>) -> Self {
+		self.streams.push(stream);
+		self
+	}
+
+	/// Adds connections to close.
+	pub fn streams<I: IntoIterator<Item = ConnectedStream<D>>>(mut self, streams: I) -> Self {
+		self.streams.extend(streams);
+		self
+	}
+
+	/// Runs all four steps. Blocks for at most the drain timeout.
+	///
+	/// Only `poll()` failing is an error; a stream that errors while
+	/// draining just counts as reset.
+	pub fn run(self) -> std::io::Result<ShutdownReport> {
+		let deadline = Instant::now() + self.drain_timeout;
+
+		if let Some(listener) = self.listener {
+			// Wakes accept() blocked on other fds for this socket; then close.
+			unsafe { libc::shutdown(listener.as_raw_fd(), libc::SHUT_RDWR) };
+			drop(listener);
+		}
+
+		let mut drains = vec![DrainReport { bytes_discarded: 0, duration: Duration::ZERO, ended_by: DrainEnd::Error }; self.streams.len()];
+		// (index into drains, stream, half-closed at) for streams still draining.
+		let mut open = Vec::with_capacity(self.streams.len());
+		for (i, stream) in self.streams.into_iter().enumerate() {
+			// ENOTCONN: the peer already reset it; nothing to drain.
+			if stream.shutdown(Shutdown::Write).is_ok() {
+				open.push((i, stream, Instant::now()));
+			}
+		}
+
+		let mut buf = [0u8; 4096];
+		while !open.is_empty() {
+			let remaining = deadline.saturating_duration_since(Instant::now());
+			if remaining.is_zero() {
+				break;
+			}
+
+			let mut fds: Vec<libc::pollfd> = open.iter()
+				.map(|(_, s, _)| libc::pollfd { fd: s.as_raw_fd(), events: libc::POLLIN, revents: 0 })
+				.collect();
+			let n = unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, to_poll_ms(remaining)) };
+			if n == -1 {
+				if errno() == libc::EINTR {
+					continue;
+				}
+				return Err(IoError::Poll { errno: errno() }.into());
+			}
+
+			// Walk backwards so swap_remove keeps indices of unvisited fds valid.
+			for i in (0..fds.len()).rev() {
+				if fds[i].revents == 0 {
+					continue;
+				}
+				let (index, stream, closed_at) = &open[i];
+				let drain = &mut drains[*index];
+				let ended_by = match stream.recv_with_flags(&mut buf, RecvFlags::DONTWAIT) {
+					Ok(0) => DrainEnd::PeerClosed,
+					Ok(n) => {
+						drain.bytes_discarded += n as u64;
+						continue;
+					}
+					Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => continue,
+					Err(_) => DrainEnd::Error,
+				};
+				drain.ended_by = ended_by;
+				drain.duration = closed_at.elapsed();
+				open.swap_remove(i);
+			}
+		}
+
+		for (index, stream, closed_at) in open {
+			let _ = set_linger(&stream, Some(0));
+			drains[index].ended_by = DrainEnd::Deadline;
+			drains[index].duration = closed_at.elapsed();
+		}
+
+		let drained = drains.iter().filter(|d| d.ended_by == DrainEnd::PeerClosed).count();
+		Ok(ShutdownReport { drained, reset: drains.len() - drained, drains })
+	}
+}
+
+impl<D: Domain> ConnectedStream<D> {
+	/// Closes the stream gracefully and reports how the drain went.
+	///
+	/// Half-closes (FIN), discards input until the peer closes or
+	/// `timeout` passes, then closes — resetting if the peer never
+	/// finished. The single-stream form of `ShutdownCoordinator`.
+	pub fn close_graceful(self, timeout: Duration) -> std::io::Result<DrainReport> {
+		let report = ShutdownCoordinator::without_listener()
+			.drain_timeout(timeout)
+			.stream(self)
+			.run()?;
+		Ok(report.drains[0])
+	}
+}
+
+/*
+Why half-close first instead of just closing?
+close() with unread data in the receive queue sends RST instead of FIN,
+and an RST can make the peer drop response bytes it hasn't read yet.
+shutdown(SHUT_WR) sends FIN after everything already queued; waiting for
+the peer's own FIN means both sides saw all the data before the socket
+goes away. The deadline bounds how long a slow or hostile peer can
+delay the whole shutdown.
+*/
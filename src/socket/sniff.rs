@@ -0,0 +1,100 @@
+use std::time::{Duration, Instant};
+use crate::addr::Domain;
+use super::{ConnectedStream, RecvFlags};
+use super::wait::poll_fd;
+
+/// Longest pause between peeks while waiting for the rest of a prefix.
+const PEEK_BACKOFF: Duration = Duration::from_millis(1);
+
+/// Peeks up to `n` bytes from `stream` without consuming them.
+///
+/// Waits until `n` bytes are queued, the peer closes, or `timeout`
+/// elapses, and returns whatever is queued at that point — possibly
+/// fewer than `n`, possibly empty. The bytes stay in the receive queue,
+/// so the handler the stream is dispatched to reads them again.
+///
+/// Works on blocking and non-blocking streams alike.
+pub fn sniff_stream<D: Domain>(stream: &ConnectedStream<D>, n: usize, timeout: Duration) -> std::io::Result<Vec<u8>> {
+	let deadline = Instant::now() + timeout;
+	let mut buf = vec![0u8; n];
+	let mut seen = 0;
+
+	while seen < n {
+		let remaining = deadline.saturating_duration_since(Instant::now());
+		if !poll_fd(stream.as_raw_fd(), libc::POLLIN, Some(remaining))? {
+			break;
+		}
+
+		let got = stream.recv_with_flags(&mut buf, RecvFlags::PEEK | RecvFlags::DONTWAIT)?;
+		if got == 0 || got == n {
+			seen = got;
+			break;
+		}
+
+		// Partial prefix: poll() keeps reporting readable for bytes already
+		// seen, so back off instead of spinning until more arrive.
+		if got == seen {
+			let remaining = deadline.saturating_duration_since(Instant::now());
+			if remaining.is_zero() {
+				break;
+			}
+			std::thread::sleep(remaining.min(PEEK_BACKOFF));
+		}
+		seen = got;
+	}
+
+	buf.truncate(seen);
+	Ok(buf)
+}
+
+/// Protocol guessed from the first bytes a client sends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SniffedProtocol {
+	/// TLS handshake record (0x16 0x03 ..).
+	Tls,
+	/// HTTP/1.x request line or the HTTP/2 connection preface.
+	Http,
+	/// SSH identification string ("SSH-").
+	Ssh,
+	/// Not enough bytes to tell, or none of the above.
+	Unknown,
+}
+
+/// HTTP methods followed by the space of the request line.
+const HTTP_METHODS: &[&[u8]] = &[
+	b"GET ", b"HEAD ", b"POST ", b"PUT ", b"DELETE ", b"OPTIONS ",
+	b"PATCH ", b"CONNECT ", b"TRACE ", b"PRI ",
+];
+
+impl SniffedProtocol {
+	/// Classifies a prefix returned by `sniff_stream()`.
+	///
+	/// 8 bytes are enough for every protocol here.
+	pub fn detect(prefix: &[u8]) -> Self {
+		if prefix.len() >= 2 && prefix[0] == 0x16 && prefix[1] == 0x03 {
+			return Self::Tls;
+		}
+		if prefix.starts_with(b"SSH-") {
+			return Self::Ssh;
+		}
+		if HTTP_METHODS.iter().any(|method| prefix.starts_with(method)) {
+			return Self::Http;
+		}
+		Self::Unknown
+	}
+}
+
+/*
+Why peek instead of read-and-replay?
+A consumed prefix would have to travel with the stream to the handler,
+and every handler (TLS library, HTTP parser, SSH server) would need a
+"here are some bytes I already read" entry point. MSG_PEEK leaves them
+in the kernel, so the handler gets a plain ConnectedStream.
+
+Why the backoff?
+Once some bytes are queued, poll() returns immediately until they're
+read — which a peek never does. Waiting for the remainder needs either
+SO_RCVLOWAT (honored by TCP's poll, not by Unix streams) or a short
+sleep between peeks. Client hellos normally arrive in one segment, so
+the sleep path is rare.
+*/
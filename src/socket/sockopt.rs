@@ -0,0 +1,200 @@
+use std::os::fd::AsRawFd;
+use crate::error::{SocketError, errno};
+
+/// A socket option wirelane has no dedicated setter for.
+///
+/// Implement it on a marker type and use `set_opt()`/`get_opt()`:
+/// ```ignore
+/// struct BindToDevice;
+///
+/// impl SockOpt for BindToDevice {
+///     const LEVEL: libc::c_int = libc::SOL_SOCKET;
+///     const NAME: libc::c_int = libc::SO_BINDTODEVICE;
+///     const LABEL: &'static str = "SO_BINDTODEVICE";
+///     type Value = Vec<u8>;
+/// }
+///
+/// set_opt::<_, BindToDevice>(&socket, b"eth0".to_vec())?;
+/// ```
+pub trait SockOpt {
+	/// Protocol level, e.g. `SOL_SOCKET`, `IPPROTO_TCP`.
+	const LEVEL: libc::c_int;
+	/// Option name, e.g. `SO_MARK`.
+	const NAME: libc::c_int;
+	/// Name shown in errors.
+	const LABEL: &'static str = "socket option";
+	/// Payload type.
+	type Value: OptValue;
+}
+
+/// How an option payload is laid out in memory.
+///
+/// Implemented for `c_int`, `u32`, `bool` (as a `c_int` 0/1), `Vec<u8>`
+/// (byte strings such as interface or congestion control names) and the
+/// libc structs options commonly take. For another `#[repr(C)]` struct,
+/// copy its bytes in `encode()` and check the length in `decode()`.
+pub trait OptValue: Sized {
+	/// Buffer size `get_opt()` hands to getsockopt().
+	const MAX_LEN: usize;
+
+	/// Bytes passed to setsockopt().
+	fn encode(&self) -> Vec<u8>;
+
+	/// Parses what getsockopt() returned; `None` if the length is wrong.
+	fn decode(bytes: &[u8]) -> Option<Self>;
+}
+
+impl OptValue for libc::c_int {
+	const MAX_LEN: usize = std::mem::size_of::<libc::c_int>();
+
+	fn encode(&self) -> Vec<u8> {
+		self.to_ne_bytes().to_vec()
+	}
+
+	fn decode(bytes: &[u8]) -> Option<Self> {
+		Some(Self::from_ne_bytes(bytes.try_into().ok()?))
+	}
+}
+
+impl OptValue for u32 {
+	const MAX_LEN: usize = std::mem::size_of::<u32>();
+
+	fn encode(&self) -> Vec<u8> {
+		self.to_ne_bytes().to_vec()
+	}
+
+	fn decode(bytes: &[u8]) -> Option<Self> {
+		Some(Self::from_ne_bytes(bytes.try_into().ok()?))
+	}
+}
+
+impl OptValue for bool {
+	const MAX_LEN: usize = std::mem::size_of::<libc::c_int>();
+
+	fn encode(&self) -> Vec<u8> {
+		(*self as libc::c_int).encode()
+	}
+
+	/// A few options report a single byte; anything non-zero is true.
+	fn decode(bytes: &[u8]) -> Option<Self> {
+		match bytes.len() {
+			1 => Some(bytes[0] != 0),
+			_ => libc::c_int::decode(bytes).map(|v| v != 0),
+		}
+	}
+}
+
+impl OptValue for Vec<u8> {
+	/// Enough for interface names, congestion control and ULP names.
+	const MAX_LEN: usize = 256;
+
+	fn encode(&self) -> Vec<u8> {
+		self.clone()
+	}
+
+	/// Trailing NULs are stripped.
+	fn decode(bytes: &[u8]) -> Option<Self> {
+		let end = bytes.iter().rposition(|&b| b != 0).map_or(0, |i| i + 1);
+		Some(bytes[..end].to_vec())
+	}
+}
+
+impl OptValue for libc::linger {
+	const MAX_LEN: usize = std::mem::size_of::<Self>();
+
+	fn encode(&self) -> Vec<u8> {
+		struct_bytes(self)
+	}
+
+	fn decode(bytes: &[u8]) -> Option<Self> {
+		struct_from_bytes(bytes)
+	}
+}
+
+impl OptValue for libc::timeval {
+	const MAX_LEN: usize = std::mem::size_of::<Self>();
+
+	fn encode(&self) -> Vec<u8> {
+		struct_bytes(self)
+	}
+
+	fn decode(bytes: &[u8]) -> Option<Self> {
+		struct_from_bytes(bytes)
+	}
+}
+
+impl OptValue for libc::ucred {
+	const MAX_LEN: usize = std::mem::size_of::<Self>();
+
+	fn encode(&self) -> Vec<u8> {
+		struct_bytes(self)
+	}
+
+	fn decode(bytes: &[u8]) -> Option<Self> {
+		struct_from_bytes(bytes)
+	}
+}
+
+/// Copies a plain C struct (integers only, no padding) into bytes.
+pub(crate) fn struct_bytes<T: Copy>(value: &T) -> Vec<u8> {
+	let ptr = value as *const T as *const u8;
+	unsafe { std::slice::from_raw_parts(ptr, std::mem::size_of::<T>()) }.to_vec()
+}
+
+/// Reads a plain C struct back; every bit pattern must be valid for `T`.
+pub(crate) fn struct_from_bytes<T: Copy>(bytes: &[u8]) -> Option<T> {
+	if bytes.len() != std::mem::size_of::<T>() {
+		return None;
+	}
+	Some(unsafe { std::ptr::read_unaligned(bytes.as_ptr() as *const T) })
+}
+
+/// Sets any option described by a `SockOpt`.
+pub fn set_opt<S: AsRawFd, O: SockOpt>(socket: &S, value: O::Value) -> std::io::Result<()> {
+	let bytes = value.encode();
+	let result = unsafe {
+		libc::setsockopt(
+			socket.as_raw_fd(),
+			O::LEVEL,
+			O::NAME,
+			bytes.as_ptr() as *const libc::c_void,
+			bytes.len() as libc::socklen_t,
+		)
+	};
+	if result == -1 {
+		return Err(SocketError::SetOption { errno: errno(), option: O::LABEL }.into());
+	}
+	Ok(())
+}
+
+/// Reads any option described by a `SockOpt`.
+///
+/// A reply that doesn't decode as `O::Value` fails with EINVAL.
+pub fn get_opt<S: AsRawFd, O: SockOpt>(socket: &S) -> std::io::Result<O::Value> {
+	let mut buf = vec![0u8; <O::Value as OptValue>::MAX_LEN];
+	let mut len = buf.len() as libc::socklen_t;
+	let result = unsafe {
+		libc::getsockopt(
+			socket.as_raw_fd(),
+			O::LEVEL,
+			O::NAME,
+			buf.as_mut_ptr() as *mut libc::c_void,
+			&mut len,
+		)
+	};
+	if result == -1 {
+		return Err(SocketError::GetOption { errno: errno(), option: O::LABEL }.into());
+	}
+
+	let len = (len as usize).min(buf.len());
+	O::Value::decode(&buf[..len])
+		.ok_or_else(|| SocketError::GetOption { errno: libc::EINVAL, option: O::LABEL }.into())
+}
+
+/*
+Why a trait per option instead of set_opt(socket, level, name, value)?
+The level/name/payload triple is where raw setsockopt goes wrong: an
+option that takes a c_int handed a u8, or a struct for the wrong level.
+Naming the option once as a type pins all three together, and every
+call site after that is as checked as the dedicated setters.
+*/
@@ -0,0 +1,151 @@
+use std::os::fd::{AsRawFd, OwnedFd};
+use std::marker::PhantomData;
+use std::sync::Arc;
+use crate::addr::Domain;
+use crate::error::{SocketError, IoError, errno};
+use super::stream::ConnectedStream;
+
+impl<D: Domain> ConnectedStream<D> {
+	/// Splits the stream into independently owned read and write halves.
+	///
+	/// Both halves share the same fd. The fd is closed when both are dropped.
+	/// Each half can be moved to its own thread.
+	pub fn into_split(self) -> (ReadHalf<D>, WriteHalf<D>) {
+		let sigpipe = self.sigpipe();
+		let fd = Arc::new(self.into_fd());
+		(
+			ReadHalf { fd: Arc::clone(&fd), _marker: PhantomData },
+			WriteHalf { fd, sigpipe, _marker: PhantomData },
+		)
+	}
+}
+
+/// The receiving half of a split `ConnectedStream`.
+pub struct ReadHalf<D: Domain> {
+	fd: Arc<OwnedFd>,
+	_marker: PhantomData<D>,
+}
+
+impl<D: Domain> ReadHalf<D> {
+	/// Reads data from the stream.
+	///
+	/// Unlike `ConnectedStream::read()`, end-of-stream is reported as
+	/// `ReadResult::Eof` instead of `Ok(0)`, so an orderly shutdown by the
+	/// peer can't be confused with an empty buffer.
+	pub fn read(&self, buf: &mut [u8]) -> std::io::Result<ReadResult> {
+		let n = unsafe {
+			libc::read(
+				self.fd.as_raw_fd(),
+				buf.as_mut_ptr() as *mut libc::c_void,
+				buf.len(),
+			)
+		};
+
+		match n {
+			-1 => Err(IoError::Read { errno: errno() }.into()),
+			0 if !buf.is_empty() => Ok(ReadResult::Eof),
+			n => Ok(ReadResult::Data(n as usize)),
+		}
+	}
+}
+
+/// The sending half of a split `ConnectedStream`.
+pub struct WriteHalf<D: Domain> {
+	fd: Arc<OwnedFd>,
+	/// Carried over from `ConnectedStream::set_sigpipe()`.
+	sigpipe: bool,
+	_marker: PhantomData<D>,
+}
+
+impl<D: Domain> WriteHalf<D> {
+	/// Writes data to the stream.
+	pub fn write(&self, buf: &[u8]) -> std::io::Result<usize> {
+		let flags = if self.sigpipe { 0 } else { libc::MSG_NOSIGNAL };
+		let n = unsafe {
+			libc::send(
+				self.fd.as_raw_fd(),
+				buf.as_ptr() as *const libc::c_void,
+				buf.len(),
+				flags,
+			)
+		};
+
+		if n == -1 {
+			Err(IoError::Write { errno: errno() }.into())
+		} else {
+			Ok(n as usize)
+		}
+	}
+
+	/// Closes the write direction (SHUT_WR) and consumes the half.
+	///
+	/// The peer sees end-of-stream after draining what was already sent.
+	/// The read half stays usable until the peer closes its side.
+	///
+	/// Dropping a `WriteHalf` without calling this sends nothing —
+	/// the fd stays open while the `ReadHalf` is alive.
+	pub fn finish(self) -> std::io::Result<()> {
+		let result = unsafe { libc::shutdown(self.fd.as_raw_fd(), libc::SHUT_WR) };
+
+		if result == -1 {
+			Err(SocketError::SetOption { errno: errno(), option: "shutdown" }.into())
+		} else {
+			Ok(())
+		}
+	}
+}
+
+/// Outcome of a read on a `ReadHalf`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReadResult {
+	/// `n` bytes were read into the buffer.
+	Data(usize),
+
+	/// The peer shut down its write direction.
+	///
+	/// No more data will arrive. Further reads keep returning `Eof`.
+	Eof,
+}
+
+impl<D: Domain> std::io::Read for ReadHalf<D> {
+	fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+		match ReadHalf::read(self, buf)? {
+			ReadResult::Data(n) => Ok(n),
+			ReadResult::Eof => Ok(0),
+		}
+	}
+}
+
+impl<D: Domain> std::io::Write for WriteHalf<D> {
+	fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+		WriteHalf::write(self, buf)
+	}
+
+	fn flush(&mut self) -> std::io::Result<()> {
+		Ok(())
+	}
+}
+
+impl<D: Domain> std::os::fd::AsRawFd for ReadHalf<D> {
+	fn as_raw_fd(&self) -> std::os::fd::RawFd {
+		self.fd.as_raw_fd()
+	}
+}
+
+impl<D: Domain> std::os::fd::AsFd for ReadHalf<D> {
+	fn as_fd(&self) -> std::os::fd::BorrowedFd<'_> {
+		self.fd.as_fd()
+	}
+}
+
+impl<D: Domain> std::os::fd::AsRawFd for WriteHalf<D> {
+	fn as_raw_fd(&self) -> std::os::fd::RawFd {
+		self.fd.as_raw_fd()
+	}
+}
+
+impl<D: Domain> std::os::fd::AsFd for WriteHalf<D> {
+	fn as_fd(&self) -> std::os::fd::BorrowedFd<'_> {
+		self.fd.as_fd()
+	}
+}
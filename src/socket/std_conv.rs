@@ -0,0 +1,160 @@
+//! Conversions between wirelane sockets and `std::net` / `std::os::unix::net`.
+//!
+//! wirelane → std always succeeds. std → wirelane checks family, type
+//! and listening state first, since e.g. a `TcpStream` may be IPv4 or
+//! IPv6; on mismatch the std socket is closed and `WrongSocketKind`
+//! returned — check `local_addr()` beforehand if it should be kept.
+
+use std::net::{TcpListener, TcpStream, UdpSocket};
+use std::os::fd::{AsFd, FromRawFd, IntoRawFd, OwnedFd};
+use std::os::unix::net::{UnixDatagram, UnixListener, UnixStream};
+use crate::addr::{Ipv4, Ipv6, Unix};
+use super::fd_kind::FdKind;
+use super::{ConnectedStream, Listener, BoundDatagram};
+
+/// Moves ownership of `fd` between socket wrappers.
+fn rewrap<S: IntoRawFd, T: FromRawFd>(socket: S) -> T {
+	// SAFETY: `socket` gives up the fd, so `T` is its only owner.
+	unsafe { T::from_raw_fd(socket.into_raw_fd()) }
+}
+
+/// Validates, then rewraps — the std side as an `OwnedFd`.
+fn checked<T: FromRawFd>(fd: OwnedFd, domain: libc::c_int, ty: libc::c_int, listening: bool, expected: &'static str) -> std::io::Result<T> {
+	FdKind::expect(fd.as_fd(), domain, ty, listening, expected)?;
+	Ok(rewrap(fd))
+}
+
+// TCP streams
+
+impl From<ConnectedStream<Ipv4>> for TcpStream {
+	fn from(stream: ConnectedStream<Ipv4>) -> Self {
+		rewrap(stream)
+	}
+}
+
+impl From<ConnectedStream<Ipv6>> for TcpStream {
+	fn from(stream: ConnectedStream<Ipv6>) -> Self {
+		rewrap(stream)
+	}
+}
+
+impl TryFrom<TcpStream> for ConnectedStream<Ipv4> {
+	type Error = std::io::Error;
+
+	fn try_from(stream: TcpStream) -> std::io::Result<Self> {
+		checked(stream.into(), libc::AF_INET, libc::SOCK_STREAM, false, "a connected TCP/IPv4 stream")
+	}
+}
+
+impl TryFrom<TcpStream> for ConnectedStream<Ipv6> {
+	type Error = std::io::Error;
+
+	fn try_from(stream: TcpStream) -> std::io::Result<Self> {
+		checked(stream.into(), libc::AF_INET6, libc::SOCK_STREAM, false, "a connected TCP/IPv6 stream")
+	}
+}
+
+// TCP listeners
+
+impl From<Listener<Ipv4>> for TcpListener {
+	fn from(listener: Listener<Ipv4>) -> Self {
+		rewrap(listener)
+	}
+}
+
+impl From<Listener<Ipv6>> for TcpListener {
+	fn from(listener: Listener<Ipv6>) -> Self {
+		rewrap(listener)
+	}
+}
+
+impl TryFrom<TcpListener> for Listener<Ipv4> {
+	type Error = std::io::Error;
+
+	fn try_from(listener: TcpListener) -> std::io::Result<Self> {
+		checked(listener.into(), libc::AF_INET, libc::SOCK_STREAM, true, "a TCP/IPv4 listener")
+	}
+}
+
+impl TryFrom<TcpListener> for Listener<Ipv6> {
+	type Error = std::io::Error;
+
+	fn try_from(listener: TcpListener) -> std::io::Result<Self> {
+		checked(listener.into(), libc::AF_INET6, libc::SOCK_STREAM, true, "a TCP/IPv6 listener")
+	}
+}
+
+// UDP
+
+impl From<BoundDatagram<Ipv4>> for UdpSocket {
+	fn from(socket: BoundDatagram<Ipv4>) -> Self {
+		rewrap(socket)
+	}
+}
+
+impl From<BoundDatagram<Ipv6>> for UdpSocket {
+	fn from(socket: BoundDatagram<Ipv6>) -> Self {
+		rewrap(socket)
+	}
+}
+
+impl TryFrom<UdpSocket> for BoundDatagram<Ipv4> {
+	type Error = std::io::Error;
+
+	fn try_from(socket: UdpSocket) -> std::io::Result<Self> {
+		checked(socket.into(), libc::AF_INET, libc::SOCK_DGRAM, false, "a UDP/IPv4 socket")
+	}
+}
+
+impl TryFrom<UdpSocket> for BoundDatagram<Ipv6> {
+	type Error = std::io::Error;
+
+	fn try_from(socket: UdpSocket) -> std::io::Result<Self> {
+		checked(socket.into(), libc::AF_INET6, libc::SOCK_DGRAM, false, "a UDP/IPv6 socket")
+	}
+}
+
+// Unix
+
+impl From<ConnectedStream<Unix>> for UnixStream {
+	fn from(stream: ConnectedStream<Unix>) -> Self {
+		rewrap(stream)
+	}
+}
+
+impl TryFrom<UnixStream> for ConnectedStream<Unix> {
+	type Error = std::io::Error;
+
+	fn try_from(stream: UnixStream) -> std::io::Result<Self> {
+		checked(stream.into(), libc::AF_UNIX, libc::SOCK_STREAM, false, "a connected Unix stream")
+	}
+}
+
+impl From<Listener<Unix>> for UnixListener {
+	/// An unlink-on-drop guard is disarmed; the file stays.
+	fn from(listener: Listener<Unix>) -> Self {
+		rewrap(listener)
+	}
+}
+
+impl TryFrom<UnixListener> for Listener<Unix> {
+	type Error = std::io::Error;
+
+	fn try_from(listener: UnixListener) -> std::io::Result<Self> {
+		checked(listener.into(), libc::AF_UNIX, libc::SOCK_STREAM, true, "a Unix stream listener")
+	}
+}
+
+impl From<BoundDatagram<Unix>> for UnixDatagram {
+	fn from(socket: BoundDatagram<Unix>) -> Self {
+		rewrap(socket)
+	}
+}
+
+impl TryFrom<UnixDatagram> for BoundDatagram<Unix> {
+	type Error = std::io::Error;
+
+	fn try_from(socket: UnixDatagram) -> std::io::Result<Self> {
+		checked(socket.into(), libc::AF_UNIX, libc::SOCK_DGRAM, false, "a Unix datagram socket")
+	}
+}
@@ -1,13 +1,15 @@
 use std::io::IoSlice;
 use std::io::IoSliceMut;
 use std::os::fd::IntoRawFd;
-use std::os::fd::RawFd;
+use std::os::fd::{RawFd, BorrowedFd};
 use std::os::fd::FromRawFd;
 use crate::addr::FromSockAddr;
 use std::os::fd::OwnedFd;
 use std::marker::PhantomData;
-use crate::addr::Domain;
+use std::time::{Duration, Instant};
+use crate::addr::{Domain, Unix, Ipv4, Ipv6, SocketAddrV4, SocketAddrV6};
 use crate::error::{SocketError, IoError, errno};
+use super::{SendFlags, RecvFlags, TryResult};
 
 /// A connected stream socket.
 ///
@@ -15,6 +17,9 @@ use crate::error::{SocketError, IoError, errno};
 /// Created by Listener::accept() (server) or RawSocket::connect() (client).
 pub struct ConnectedStream<D: Domain> {
 	fd: OwnedFd,
+	established: Instant,
+	max_lifetime: Option<Duration>,
+	sigpipe: bool,
 	_marker: PhantomData<D>,
 }
 
@@ -23,15 +28,99 @@ impl<D: Domain> ConnectedStream<D> {
 	pub(crate) fn from_fd(fd: OwnedFd) -> Self {
 		Self {
 			fd,
+			established: Instant::now(),
+			max_lifetime: None,
+			sigpipe: false,
 			_marker: PhantomData,
 		}
 	}
+
+	/// When the connection was established: accepted, connected, or
+	/// (for `from_raw_fd()` and std conversions) wrapped.
+	pub fn established_at(&self) -> Instant {
+		self.established
+	}
+
+	/// Time since `established_at()`.
+	pub fn age(&self) -> Duration {
+		self.established.elapsed()
+	}
+
+	/// Sets how long this connection should live, or `None` for no limit.
+	///
+	/// Nothing is closed automatically: pools and proxies check
+	/// `is_expired()` between requests and rotate the connection, so a
+	/// request in progress is never cut off.
+	pub fn set_max_lifetime(&mut self, lifetime: Option<Duration>) {
+		self.max_lifetime = lifetime;
+	}
+
+	/// The limit set with `set_max_lifetime()`.
+	pub fn max_lifetime(&self) -> Option<Duration> {
+		self.max_lifetime
+	}
+
+	/// Time left before `is_expired()`; `None` without a limit.
+	pub fn remaining_lifetime(&self) -> Option<Duration> {
+		self.max_lifetime.map(|max| max.saturating_sub(self.age()))
+	}
+
+	/// True once the connection is older than its max lifetime.
+	pub fn is_expired(&self) -> bool {
+		self.remaining_lifetime() == Some(Duration::ZERO)
+	}
+
+	/// Lets writes to a closed connection raise SIGPIPE again.
+	///
+	/// By default every write passes MSG_NOSIGNAL, so a peer reset fails
+	/// the write with EPIPE (`BrokenPipe`) instead of killing the process.
+	/// Turn that off only for tools that rely on SIGPIPE to exit quietly
+	/// when their output goes away.
+	///
+	/// `sendfile()` and `splice()` take no flags and can still raise it;
+	/// ignore SIGPIPE process-wide if you use them on connections that
+	/// may reset.
+	pub fn set_sigpipe(&mut self, raise: bool) {
+		self.sigpipe = raise;
+	}
+
+	/// Whether writes may raise SIGPIPE; see `set_sigpipe()`.
+	pub fn sigpipe(&self) -> bool {
+		self.sigpipe
+	}
+
+	/// MSG_NOSIGNAL unless SIGPIPE was asked for.
+	pub(super) fn nosignal(&self) -> libc::c_int {
+		if self.sigpipe { 0 } else { libc::MSG_NOSIGNAL }
+	}
 	/// Returns the raw file descriptor.
 	#[inline]
 	pub fn as_raw_fd(&self) -> libc::c_int {
 		use std::os::fd::AsRawFd;
 		self.fd.as_raw_fd()
 	}
+
+	/// Duplicates the fd (close-on-exec) into a second handle.
+	///
+	/// Both handles refer to the same connection: reads, writes and
+	/// `shutdown()` through either affect both, and it closes once the
+	/// last handle drops. Lets e.g. a reader and a writer thread each own one.
+	///
+	/// The clone keeps this handle's establishment time, max lifetime and
+	/// SIGPIPE setting.
+	pub fn try_clone(&self) -> std::io::Result<Self> {
+		Ok(Self {
+			fd: super::raw::dup_fd(self.as_raw_fd())?,
+			established: self.established,
+			max_lifetime: self.max_lifetime,
+			sigpipe: self.sigpipe,
+			_marker: PhantomData,
+		})
+	}
+	/// Extracts the owned file descriptor, consuming self.
+	pub(crate) fn into_fd(self) -> OwnedFd {
+		self.fd
+	}
 	pub fn read(&self, buf: &mut [u8]) -> std::io::Result<usize> {
 		let n = unsafe {
 			libc::read(
@@ -50,10 +139,11 @@ impl<D: Domain> ConnectedStream<D> {
 
 	pub fn write(&self, buf: &[u8]) -> std::io::Result<usize> {
 		let n = unsafe {
-			libc::write(
+			libc::send(
 				self.as_raw_fd(),
 				buf.as_ptr() as *const libc::c_void,
 				buf.len(),
+				self.nosignal(),
 			)
 		};
 
@@ -64,13 +154,13 @@ impl<D: Domain> ConnectedStream<D> {
 		}
 	}
 
-	pub fn send_with_flags(&self, buf: &[u8], flags: i32) -> std::io::Result<usize> {
+	pub fn send_with_flags(&self, buf: &[u8], flags: SendFlags) -> std::io::Result<usize> {
 		let n = unsafe {
 			libc::send(
 				self.as_raw_fd(),
 				buf.as_ptr() as *const libc::c_void,
 				buf.len(),
-				flags,
+				flags.bits() | self.nosignal(),
 			)
 		};
 
@@ -81,13 +171,13 @@ impl<D: Domain> ConnectedStream<D> {
 		}
 	}
 
-	pub fn recv_with_flags(&self, buf: &mut [u8], flags: i32) -> std::io::Result<usize> {
+	pub fn recv_with_flags(&self, buf: &mut [u8], flags: RecvFlags) -> std::io::Result<usize> {
 		let n = unsafe {
 			libc::recv(
 				self.as_raw_fd(),
 				buf.as_mut_ptr() as *mut libc::c_void,
 				buf.len(),
-				flags,
+				flags.bits(),
 			)
 		};
 
@@ -98,21 +188,53 @@ impl<D: Domain> ConnectedStream<D> {
 		}
 	}
 
-	pub fn set_nonblocking(&self, nonblocking: bool) -> std::io::Result<()> {
-		let flags = unsafe { libc::fcntl(self.as_raw_fd(), libc::F_GETFL) };
-		if flags == -1 {
-			return Err(SocketError::GetOption { errno: errno(), option: "F_GETFL" }.into());
-		}
-		let new_flags = if nonblocking {
-			flags | libc::O_NONBLOCK
-		} else {
-			flags & !libc::O_NONBLOCK
-		};
-		let result = unsafe { libc::fcntl(self.as_raw_fd(), libc::F_SETFL, new_flags) };
-		if result == -1 {
-			return Err(SocketError::SetOption { errno: errno(), option: "O_NONBLOCK" }.into());
+	/// Copies up to `buf.len()` received bytes without consuming them
+	/// (MSG_PEEK); the next `read()` returns them again.
+	///
+	/// Blocks like `read()` until something arrives, then returns what's
+	/// there, which may be less than asked for.
+	pub fn peek(&self, buf: &mut [u8]) -> std::io::Result<usize> {
+		self.recv_with_flags(buf, RecvFlags::PEEK)
+	}
+
+	/// Fills `buf` completely, in one call (MSG_WAITALL).
+	///
+	/// Returns `buf.len()` once it's full. A shorter count means the read
+	/// stopped after that many bytes: the peer closed the connection, or
+	/// an error (reset, receive timeout, `WouldBlock` on a non-blocking
+	/// socket) came mid-way. The bytes already read are never dropped;
+	/// call again for the rest, and that call reports the error, or 0 at
+	/// end of stream. An error is returned only if nothing was read.
+	/// Signals don't cut the read short; it resumes where it stopped.
+	pub fn recv_exact(&self, buf: &mut [u8]) -> std::io::Result<usize> {
+		let mut filled = 0;
+		while filled < buf.len() {
+			let rest = &mut buf[filled..];
+			let n = unsafe {
+				libc::recv(
+					self.as_raw_fd(),
+					rest.as_mut_ptr() as *mut libc::c_void,
+					rest.len(),
+					libc::MSG_WAITALL,
+				)
+			};
+			match n {
+				0 => break,
+				-1 => match errno() {
+					libc::EINTR => continue,
+					// Those bytes are gone from the socket; hand them over
+					// and let the next call hit the error again.
+					_ if filled > 0 => break,
+					e => return Err(IoError::Read { errno: e }.into()),
+				},
+				n => filled += n as usize,
+			}
 		}
-		Ok(())
+		Ok(filled)
+	}
+
+	pub fn set_nonblocking(&self, nonblocking: bool) -> std::io::Result<()> {
+		super::mode::set_fd_nonblocking(self.as_raw_fd(), nonblocking)
 	}
 
 	pub fn readv(&self, bufs: &mut [IoSliceMut<'_>]) -> std::io::Result<usize> {
@@ -132,13 +254,11 @@ impl<D: Domain> ConnectedStream<D> {
 	}
 
 	pub fn writev(&self, bufs: &[IoSlice<'_>]) -> std::io::Result<usize> {
-		let n = unsafe {
-			libc::writev(
-				self.as_raw_fd(),
-				bufs.as_ptr() as *const libc::iovec,
-				bufs.len() as libc::c_int,
-			)
-		};
+		// sendmsg() rather than writev(), for MSG_NOSIGNAL.
+		let mut msg: libc::msghdr = unsafe { std::mem::zeroed() };
+		msg.msg_iov = bufs.as_ptr() as *mut libc::iovec;
+		msg.msg_iovlen = bufs.len();
+		let n = unsafe { libc::sendmsg(self.as_raw_fd(), &msg, self.nosignal()) };
 
 		if n == -1 {
 			Err(IoError::Write { errno: errno() }.into())
@@ -151,7 +271,8 @@ impl<D: Domain> ConnectedStream<D> {
 	///
 	/// Transfers `count` bytes from `file` starting at `offset`.
 	/// If `offset` is None, uses file's current position.
-	/// Returns number of bytes sent.
+	/// Returns number of bytes sent. Can raise SIGPIPE; see
+	/// `sendfile_all()`.
 	pub fn sendfile<F: std::os::fd::AsRawFd>(
 		&self,
 		file: &F,
@@ -178,6 +299,90 @@ impl<D: Domain> ConnectedStream<D> {
 			Ok(n as usize)
 		}
 	}
+
+	/// Sends all of `range` of `file`, looping over short sendfile() calls.
+	///
+	/// `progress` counts the bytes of `range` already sent: start it at 0
+	/// and pass it back unchanged to resume. Returns `Ready(total)` once
+	/// the whole range is out, or `WouldBlock` when a non-blocking socket
+	/// is full — wait for writability and call again. The file's own
+	/// position is never moved.
+	///
+	/// Files that can't be sendfile()d (EINVAL/ENOSYS: some FUSE and
+	/// network filesystems, /proc files) are copied with pread() and write()
+	/// instead. Fails with `IoError::FileEnded` if the file is shorter
+	/// than `range`.
+	///
+	/// # SIGPIPE
+	/// Unlike `write()` and the other send paths, this ignores
+	/// `set_sigpipe()`: sendfile() takes no flags, so a peer that reset
+	/// the connection raises SIGPIPE, which kills the process by default.
+	/// Ignore SIGPIPE process-wide (`signal(SIGPIPE, SIG_IGN)`) or block
+	/// it in the calling thread before using this on connections that may
+	/// reset; it then fails with EPIPE (`BrokenPipe`) like the others.
+	pub fn sendfile_all<F: std::os::fd::AsRawFd>(
+		&self,
+		file: &F,
+		range: std::ops::Range<u64>,
+		progress: &mut u64,
+	) -> std::io::Result<TryResult<u64>> {
+		// Linux never moves more than this per call anyway.
+		const MAX_CHUNK: u64 = 0x7fff_f000;
+		let total = range.end.saturating_sub(range.start);
+		let mut copy_buf: Option<Vec<u8>> = None;
+
+		while *progress < total {
+			let position = range.start + *progress;
+			let chunk = (total - *progress).min(MAX_CHUNK) as usize;
+
+			let sent = match copy_buf.as_mut() {
+				None => {
+					let mut offset = position as libc::off_t;
+					let n = unsafe { libc::sendfile(self.as_raw_fd(), file.as_raw_fd(), &mut offset, chunk) };
+					if n == -1 {
+						match errno() {
+							libc::EINTR => continue,
+							libc::EAGAIN => return Ok(TryResult::WouldBlock),
+							libc::EINVAL | libc::ENOSYS => {
+								copy_buf = Some(vec![0u8; 64 * 1024]);
+								continue;
+							}
+							e => return Err(IoError::Write { errno: e }.into()),
+						}
+					}
+					n as usize
+				}
+				Some(buf) => {
+					let want = chunk.min(buf.len());
+					let n = unsafe {
+						libc::pread(file.as_raw_fd(), buf.as_mut_ptr() as *mut libc::c_void, want, position as libc::off_t)
+					};
+					if n == -1 {
+						match errno() {
+							libc::EINTR => continue,
+							e => return Err(IoError::Read { errno: e }.into()),
+						}
+					}
+					if n == 0 {
+						0
+					} else {
+						match self.write(&buf[..n as usize]) {
+							Ok(written) => written,
+							Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+							Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => return Ok(TryResult::WouldBlock),
+							Err(e) => return Err(e),
+						}
+					}
+				}
+			};
+
+			if sent == 0 {
+				return Err(IoError::FileEnded { offset: position }.into());
+			}
+			*progress += sent as u64;
+		}
+		Ok(TryResult::Ready(total))
+	}
 }
 
 impl<D: Domain> std::os::fd::AsRawFd for ConnectedStream<D> {
@@ -268,20 +473,197 @@ impl<D: Domain> IntoRawFd for ConnectedStream<D> {
 	}
 }
 
+impl ConnectedStream<Unix> {
+	/// Creates a pair of connected Unix stream sockets.
+	///
+	/// The usual way to set up IPC before fork/exec (pass one end to the
+	/// child), or an in-process test fixture.
+	pub fn pair() -> std::io::Result<(Self, Self)> {
+		let (a, b) = super::raw::socketpair::<super::Stream>()?;
+		Ok((Self::from_fd(a), Self::from_fd(b)))
+	}
+
+	/// Returns the peer process's pid/uid/gid (SO_PEERCRED).
+	///
+	/// Taken when the peer connected — the usual way for a local daemon
+	/// to authenticate clients.
+	pub fn peer_credentials(&self) -> std::io::Result<super::Credentials> {
+		super::get_peer_credentials(self)
+	}
+
+	/// Sends `buf` with `fds` attached (SCM_RIGHTS) in one sendmsg().
+	///
+	/// The fds travel with the first byte of `buf`, which must not be
+	/// empty. At most 253 fds per call (SCM_MAX_FD).
+	pub fn send_with_fds(&self, buf: &[u8], fds: &[BorrowedFd<'_>]) -> std::io::Result<usize> {
+		if buf.is_empty() {
+			return Err(SocketError::InvalidInput { reason: "fds need at least one data byte" }.into());
+		}
+		if fds.len() > SCM_MAX_FD {
+			return Err(IoError::FrameTooLarge { len: fds.len(), max: SCM_MAX_FD }.into());
+		}
+
+		let fds_len = std::mem::size_of_val(fds) as u32;
+		let cmsg_space = unsafe { libc::CMSG_SPACE(fds_len) } as usize;
+		let mut cmsg_buf = vec![0u64; cmsg_space.div_ceil(8)];
+
+		let mut iov = libc::iovec {
+			iov_base: buf.as_ptr() as *mut libc::c_void,
+			iov_len: buf.len(),
+		};
+		let mut msg: libc::msghdr = unsafe { std::mem::zeroed() };
+		msg.msg_iov = &mut iov;
+		msg.msg_iovlen = 1;
+		if !fds.is_empty() {
+			msg.msg_control = cmsg_buf.as_mut_ptr() as *mut libc::c_void;
+			msg.msg_controllen = cmsg_space;
+			unsafe {
+				let cmsg = libc::CMSG_FIRSTHDR(&msg);
+				(*cmsg).cmsg_level = libc::SOL_SOCKET;
+				(*cmsg).cmsg_type = libc::SCM_RIGHTS;
+				(*cmsg).cmsg_len = libc::CMSG_LEN(fds_len) as usize;
+				// BorrowedFd is repr(transparent) over RawFd.
+				std::ptr::copy_nonoverlapping(fds.as_ptr() as *const RawFd, libc::CMSG_DATA(cmsg) as *mut RawFd, fds.len());
+			}
+		}
+
+		let n = unsafe { libc::sendmsg(self.as_raw_fd(), &msg, self.nosignal()) };
+		if n == -1 {
+			Err(IoError::Write { errno: errno() }.into())
+		} else {
+			Ok(n as usize)
+		}
+	}
+
+	/// Reads data and collects any fds that arrived with it.
+	///
+	/// Received fds (close-on-exec) are appended to `fd_out`; most reads
+	/// carry none. The kernel ends a read right after data that had fds
+	/// attached, so fds arrive in the same read as the last byte of the
+	/// `send_with_fds()` that carried them, never later.
+	///
+	/// Fails with `IoError::ControlTruncated` if the kernel couldn't pass
+	/// every fd (MSG_CTRUNC); the ones that did arrive are closed, and the
+	/// data of that read is lost with them.
+	pub fn recv_with_fds(&self, buf: &mut [u8], fd_out: &mut Vec<OwnedFd>) -> std::io::Result<usize> {
+		let cmsg_space = unsafe { libc::CMSG_SPACE((SCM_MAX_FD * std::mem::size_of::<RawFd>()) as u32) } as usize;
+		let mut cmsg_buf = vec![0u64; cmsg_space.div_ceil(8)];
+
+		let mut iov = libc::iovec {
+			iov_base: buf.as_mut_ptr() as *mut libc::c_void,
+			iov_len: buf.len(),
+		};
+		let mut msg: libc::msghdr = unsafe { std::mem::zeroed() };
+		msg.msg_iov = &mut iov;
+		msg.msg_iovlen = 1;
+		msg.msg_control = cmsg_buf.as_mut_ptr() as *mut libc::c_void;
+		msg.msg_controllen = cmsg_space;
+
+		let n = unsafe { libc::recvmsg(self.as_raw_fd(), &mut msg, libc::MSG_CMSG_CLOEXEC) };
+		if n == -1 {
+			return Err(IoError::Read { errno: errno() }.into());
+		}
+
+		let mut received = Vec::new();
+		unsafe {
+			let mut cmsg = libc::CMSG_FIRSTHDR(&msg);
+			while !cmsg.is_null() {
+				if (*cmsg).cmsg_level == libc::SOL_SOCKET && (*cmsg).cmsg_type == libc::SCM_RIGHTS {
+					let data = libc::CMSG_DATA(cmsg) as *const RawFd;
+					let count = ((*cmsg).cmsg_len - libc::CMSG_LEN(0) as usize) / std::mem::size_of::<RawFd>();
+					for i in 0..count {
+						let fd = std::ptr::read_unaligned(data.add(i));
+						crate::track(fd, "recv_with_fds()");
+						received.push(OwnedFd::from_raw_fd(fd));
+					}
+				}
+				cmsg = libc::CMSG_NXTHDR(&msg, cmsg);
+			}
+		}
+
+		if msg.msg_flags & libc::MSG_CTRUNC != 0 {
+			// Dropping `received` closes them.
+			return Err(IoError::ControlTruncated.into());
+		}
+		fd_out.append(&mut received);
+		Ok(n as usize)
+	}
+}
+
+/// Most fds one SCM_RIGHTS message can carry (linux/net/scm.h).
+const SCM_MAX_FD: usize = 253;
+
+// From linux/netfilter_ipv4.h and linux/netfilter_ipv6/ip6_tables.h.
+const SO_ORIGINAL_DST: libc::c_int = 80;
+const IP6T_SO_ORIGINAL_DST: libc::c_int = 80;
+
+impl ConnectedStream<Ipv4> {
+	/// Returns the address the client connected to before NAT (SO_ORIGINAL_DST).
+	///
+	/// For transparent proxies behind iptables/nftables REDIRECT or DNAT:
+	/// `local_addr()` is the proxy's own port, this is where the client
+	/// wanted to go. Fails with ENOENT if the connection wasn't NATed.
+	pub fn original_dst(&self) -> std::io::Result<SocketAddrV4> {
+		let mut raw: libc::sockaddr_in = unsafe { std::mem::zeroed() };
+		let mut len = std::mem::size_of::<libc::sockaddr_in>() as libc::socklen_t;
+		let result = unsafe {
+			libc::getsockopt(
+				self.as_raw_fd(),
+				libc::SOL_IP,
+				SO_ORIGINAL_DST,
+				&mut raw as *mut _ as *mut libc::c_void,
+				&mut len,
+			)
+		};
+		if result == -1 {
+			return Err(SocketError::GetOption { errno: errno(), option: "SO_ORIGINAL_DST" }.into());
+		}
+		Ok(SocketAddrV4::from_raw(&raw))
+	}
+}
+
+impl ConnectedStream<Ipv6> {
+	/// Returns the address the client connected to before NAT (IP6T_SO_ORIGINAL_DST).
+	///
+	/// The IPv6 counterpart, for ip6tables/nftables REDIRECT or DNAT.
+	pub fn original_dst(&self) -> std::io::Result<SocketAddrV6> {
+		let mut raw: libc::sockaddr_in6 = unsafe { std::mem::zeroed() };
+		let mut len = std::mem::size_of::<libc::sockaddr_in6>() as libc::socklen_t;
+		let result = unsafe {
+			libc::getsockopt(
+				self.as_raw_fd(),
+				libc::SOL_IPV6,
+				IP6T_SO_ORIGINAL_DST,
+				&mut raw as *mut _ as *mut libc::c_void,
+				&mut len,
+			)
+		};
+		if result == -1 {
+			return Err(SocketError::GetOption { errno: errno(), option: "IP6T_SO_ORIGINAL_DST" }.into());
+		}
+		Ok(SocketAddrV6::from_raw(&raw))
+	}
+}
+
 pub enum Shutdown {
 	Read,   // SHUT_RD
 	Write,  // SHUT_WR
 	ReadWrite,   // SHUT_RDWR
 }
-impl<D: Domain> ConnectedStream<D> {
-	pub fn shutdown(&self, how: Shutdown) -> std::io::Result<()> {
-		let how = match how {
+
+impl Shutdown {
+	pub(crate) fn as_raw(&self) -> libc::c_int {
+		match self {
 			Shutdown::Read => libc::SHUT_RD,
 			Shutdown::Write => libc::SHUT_WR,
 			Shutdown::ReadWrite => libc::SHUT_RDWR,
-		};
+		}
+	}
+}
 
-		let result = unsafe { libc::shutdown(self.as_raw_fd(), how) };
+impl<D: Domain> ConnectedStream<D> {
+	pub fn shutdown(&self, how: Shutdown) -> std::io::Result<()> {
+		let result = unsafe { libc::shutdown(self.as_raw_fd(), how.as_raw()) };
 
 		if result == -1 {
 			Err(SocketError::SetOption { errno: errno(), option: "shutdown" }.into())
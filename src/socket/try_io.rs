@@ -0,0 +1,92 @@
+use crate::addr::{Domain, ToSockAddr, FromSockAddr};
+use super::{ConnectedStream, BoundDatagram, ConnectedDatagram, SendFlags, RecvFlags};
+
+/// Outcome of a `try_*` call: done, or nothing doable without waiting.
+///
+/// Like `AcceptResult`, this is the result of a probe, not an error:
+/// `WouldBlock` means "come back later", and real failures still arrive
+/// as `Err`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TryResult<T> {
+	/// The call completed with this value.
+	Ready(T),
+
+	/// The socket buffer is empty (reads) or full (writes).
+	WouldBlock,
+}
+
+impl<T> TryResult<T> {
+	/// The value, or `None` for `WouldBlock`.
+	pub fn ready(self) -> Option<T> {
+		match self {
+			TryResult::Ready(value) => Some(value),
+			TryResult::WouldBlock => None,
+		}
+	}
+
+	pub fn is_would_block(&self) -> bool {
+		matches!(self, TryResult::WouldBlock)
+	}
+}
+
+/// Moves EAGAIN out of the error path.
+fn probe<T>(result: std::io::Result<T>) -> std::io::Result<TryResult<T>> {
+	match result {
+		Ok(value) => Ok(TryResult::Ready(value)),
+		Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => Ok(TryResult::WouldBlock),
+		Err(e) => Err(e),
+	}
+}
+
+impl<D: Domain> ConnectedStream<D> {
+	/// Reads whatever is already received, without waiting (MSG_DONTWAIT).
+	///
+	/// The socket stays blocking for every other call. `Ready(0)` is
+	/// end-of-stream, as with `read()`.
+	pub fn try_read(&self, buf: &mut [u8]) -> std::io::Result<TryResult<usize>> {
+		probe(self.recv_with_flags(buf, RecvFlags::DONTWAIT))
+	}
+
+	/// Writes what fits in the send buffer now, without waiting.
+	///
+	/// May write less than `buf.len()`.
+	pub fn try_write(&self, buf: &[u8]) -> std::io::Result<TryResult<usize>> {
+		probe(self.send_with_flags(buf, SendFlags::DONTWAIT))
+	}
+}
+
+impl<D: Domain> BoundDatagram<D>
+where
+	D::Addr: ToSockAddr + FromSockAddr,
+{
+	/// Receives a datagram if one is queued, without waiting.
+	pub fn try_recv_from(&self, buf: &mut [u8]) -> std::io::Result<TryResult<(usize, D::Addr)>> {
+		probe(self.recv_from_with_flags(buf, RecvFlags::DONTWAIT))
+	}
+
+	/// Sends a datagram if the send buffer has room, without waiting.
+	pub fn try_send_to(&self, buf: &[u8], addr: &D::Addr) -> std::io::Result<TryResult<usize>> {
+		probe(self.send_to_with_flags(buf, addr, SendFlags::DONTWAIT))
+	}
+}
+
+impl<D: Domain> ConnectedDatagram<D> {
+	/// Receives a datagram from the peer if one is queued, without waiting.
+	pub fn try_recv(&self, buf: &mut [u8]) -> std::io::Result<TryResult<usize>> {
+		probe(self.recv_with_flags(buf, RecvFlags::DONTWAIT))
+	}
+
+	/// Sends a datagram to the peer if the send buffer has room, without
+	/// waiting.
+	pub fn try_send(&self, buf: &[u8]) -> std::io::Result<TryResult<usize>> {
+		probe(self.send_with_flags(buf, SendFlags::DONTWAIT))
+	}
+}
+
+/*
+Why MSG_DONTWAIT instead of set_nonblocking() around the call?
+O_NONBLOCK belongs to the open file description, which every dup() and
+try_clone() of the socket shares: flipping it for one opportunistic
+read makes a blocking read on another thread fail with EAGAIN, and
+costs two fcntl() calls besides. The flag applies to one call only.
+*/
@@ -0,0 +1,149 @@
+use std::fmt::Debug;
+use crate::addr::{Domain, ToSockAddr, FromSockAddr};
+use super::{BoundDatagram, BufferPool, DatagramBuilder, DatagramLease, CancelHandle};
+
+/// A multi-threaded UDP receive loop.
+///
+/// `run(workers, handler)` binds one socket per worker with
+/// `DatagramBuilder::bind_sharded()`, so each flow sticks to one thread,
+/// and calls `handler` for every datagram. Each worker receives with
+/// `recv_batch_pooled()`: many datagrams per syscall, into buffers from
+/// a shared `BufferPool`. The handler gets the worker's socket for
+/// replies and owns the `DatagramLease`, so it may forward it elsewhere.
+///
+/// # Example
+/// ```ignore
+/// let server = UdpServer::<Ipv4>::new(SocketAddrV4::new([0, 0, 0, 0], 5353))?
+///     .builder(DatagramBuilder::new().buffers(BufferConfig::new().recv(4 << 20)))
+///     .batch(64);
+/// let stop = server.stop_handle();  // stop.cancel() from anywhere ends run()
+/// server.run(4, |socket, packet| {
+///     let _ = socket.send_to(&packet, packet.addr());
+/// })?;
+/// ```
+pub struct UdpServer<D: Domain> {
+	addr: D::Addr,
+	builder: DatagramBuilder<D>,
+	buf_size: usize,
+	batch: usize,
+	stop: CancelHandle,
+}
+
+impl<D: Domain + Sync> UdpServer<D>
+where
+	D::Addr: ToSockAddr + FromSockAddr + Clone + Debug,
+{
+	/// A server for `addr` with default socket options, 2048-byte
+	/// buffers and batches of 32.
+	pub fn new(addr: D::Addr) -> std::io::Result<Self> {
+		Ok(Self {
+			addr,
+			builder: DatagramBuilder::new(),
+			buf_size: 2048,
+			batch: 32,
+			stop: CancelHandle::new()?,
+		})
+	}
+
+	/// Set the options each worker socket is built with.
+	///
+	/// SO_REUSEPORT is turned on regardless.
+	pub fn builder(mut self, builder: DatagramBuilder<D>) -> Self {
+		self.builder = builder;
+		self
+	}
+
+	/// Set the receive buffer size. Longer datagrams are truncated.
+	pub fn buf_size(mut self, bytes: usize) -> Self {
+		self.buf_size = bytes.max(1);
+		self
+	}
+
+	/// Set the most datagrams one recvmmsg() returns.
+	pub fn batch(mut self, max: usize) -> Self {
+		self.batch = max.max(1);
+		self
+	}
+
+	/// Returns a handle whose `cancel()` makes `run()` return `Ok(())`.
+	pub fn stop_handle(&self) -> CancelHandle {
+		self.stop.clone()
+	}
+
+	/// Binds `workers` sockets and serves until stopped.
+	///
+	/// Blocks the calling thread. Returns `Ok(())` once the stop handle
+	/// is cancelled, or the first worker's error, after stopping the
+	/// rest. A panicking handler also stops every worker, and the panic
+	/// is resumed here. IP sockets only (needs SO_REUSEPORT).
+	pub fn run<F>(self, workers: u32, handler: F) -> std::io::Result<()>
+	where
+		F: Fn(&BoundDatagram<D>, DatagramLease<D::Addr>) + Sync,
+	{
+		let sockets = self.builder.bind_sharded(self.addr, workers)?;
+		// Enough for every worker to have one batch in flight and one queued.
+		let pool = BufferPool::new(self.buf_size, self.batch * sockets.len() * 2);
+		let (stop, batch, handler) = (&self.stop, self.batch, &handler);
+
+		let results: Vec<std::io::Result<()>> = std::thread::scope(|scope| {
+			let threads: Vec<_> = sockets.iter()
+				.map(|socket| {
+					let pool = pool.clone();
+					scope.spawn(move || {
+						let _guard = StopOnExit(stop);
+						serve(socket, &pool, batch, stop, handler)
+					})
+				})
+				.collect();
+			threads.into_iter()
+				.map(|t| t.join().unwrap_or_else(|panic| std::panic::resume_unwind(panic)))
+				.collect()
+		});
+		results.into_iter().collect()
+	}
+}
+
+/// One worker's loop: wait, receive a batch, hand it out.
+fn serve<D: Domain, F>(
+	socket: &BoundDatagram<D>,
+	pool: &BufferPool,
+	batch: usize,
+	stop: &CancelHandle,
+	handler: &F,
+) -> std::io::Result<()>
+where
+	D::Addr: ToSockAddr + FromSockAddr,
+	F: Fn(&BoundDatagram<D>, DatagramLease<D::Addr>),
+{
+	loop {
+		if let Err(e) = stop.wait(socket.as_raw_fd(), libc::POLLIN) {
+			return if stop.is_cancelled() { Ok(()) } else { Err(e) };
+		}
+		let packets = match socket.recv_batch_pooled(pool, batch) {
+			Ok(packets) => packets,
+			Err(e) if matches!(e.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::Interrupted) => continue,
+			Err(e) => return Err(e),
+		};
+		for packet in packets {
+			handler(socket, packet);
+		}
+	}
+}
+
+/// Stops the other workers when one exits, by error or by panic.
+struct StopOnExit<'a>(&'a CancelHandle);
+
+impl Drop for StopOnExit<'_> {
+	fn drop(&mut self) {
+		let _ = self.0.cancel();
+	}
+}
+
+/*
+Why threads per socket instead of one socket read by many threads?
+Several threads on one socket contend on its receive queue lock, and a
+flow's datagrams land on whichever thread reads first, so per-flow state
+needs its own locking. One reuseport socket per thread, sharded by flow
+hash, gives each worker its own queue and keeps a flow on one thread —
+the same layout ListenerBuilder::bind_sharded() gives TCP servers.
+*/
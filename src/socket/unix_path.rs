@@ -0,0 +1,92 @@
+use std::ffi::OsStr;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::FileTypeExt;
+use std::path::{Path, PathBuf};
+use crate::addr::{Unix, UnixAddr, ToSockAddr};
+use super::{RawSocket, Stream};
+
+/// Removes a Unix socket file when dropped.
+///
+/// `bind()` creates the file, but closing the socket leaves it behind,
+/// so the next bind to that path fails with EADDRINUSE. Listeners built
+/// with `ListenerBuilder::<Unix>::unlink_on_drop(true)` hold one of these.
+#[derive(Debug)]
+pub struct SocketPathGuard {
+	path: Option<PathBuf>,
+}
+
+impl SocketPathGuard {
+	/// Guards `path`: it is removed when the guard drops.
+	pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+		Self { path: Some(path.into()) }
+	}
+
+	/// The guarded path.
+	pub fn path(&self) -> &Path {
+		self.path.as_deref().unwrap_or(Path::new(""))
+	}
+
+	/// Gives up the guard without removing the file.
+	pub fn disarm(mut self) -> PathBuf {
+		self.path.take().unwrap_or_default()
+	}
+}
+
+impl Drop for SocketPathGuard {
+	fn drop(&mut self) {
+		if let Some(path) = self.path.take() {
+			// Best effort: already gone or replaced is fine.
+			let _ = std::fs::remove_file(path);
+		}
+	}
+}
+
+/// Returns the filesystem path behind a sockaddr, if it is a named Unix one.
+///
+/// Abstract and unnamed addresses have no file, so they yield `None`.
+pub(crate) fn unix_path<A: ToSockAddr>(addr: &A) -> Option<PathBuf> {
+	addr.with_raw(|ptr, len| {
+		if len as usize <= std::mem::size_of::<libc::sa_family_t>() {
+			return None;
+		}
+		let raw = unsafe { &*(ptr as *const libc::sockaddr_un) };
+		if raw.sun_family as libc::c_int != libc::AF_UNIX || raw.sun_path[0] == 0 {
+			return None;
+		}
+
+		let max = len as usize - std::mem::size_of::<libc::sa_family_t>();
+		let bytes: Vec<u8> = raw.sun_path[..max.min(raw.sun_path.len())]
+			.iter()
+			.take_while(|&&c| c != 0)
+			.map(|&c| c as u8)
+			.collect();
+		Some(PathBuf::from(OsStr::from_bytes(&bytes)))
+	})
+	.ok()
+	.flatten()
+}
+
+/// Removes a leftover socket file at `path`, if nothing is listening on it.
+///
+/// Only socket files are touched, never regular files. A live server is
+/// detected by connecting first — its path is left alone, so the
+/// following bind fails with EADDRINUSE as it should.
+pub(crate) fn remove_stale(path: &Path) -> std::io::Result<()> {
+	match std::fs::symlink_metadata(path) {
+		Ok(meta) if meta.file_type().is_socket() => {}
+		_ => return Ok(()),
+	}
+
+	let Ok(addr) = UnixAddr::try_new(path.as_os_str().as_bytes()) else { return Ok(()) };
+	let probe = RawSocket::<Unix, Stream>::new()?;
+	match probe.connect(addr) {
+		Ok(_) => Ok(()),
+		Err(e) if e.kind() == std::io::ErrorKind::ConnectionRefused => {
+			match std::fs::remove_file(path) {
+				Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+				_ => Ok(()),
+			}
+		}
+		Err(_) => Ok(()),
+	}
+}
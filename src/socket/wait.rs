@@ -0,0 +1,146 @@
+use std::os::fd::RawFd;
+use std::time::{Duration, Instant};
+use crate::addr::Domain;
+use crate::error::{IoError, errno};
+use super::{Listener, Mode, ConnectedStream, BoundDatagram, ConnectedDatagram};
+
+/// Waits for `events` on a single fd with `poll()`.
+///
+/// `None` waits forever. Returns `Ok(true)` when the fd is ready
+/// (including error/hangup, since the next call won't block either),
+/// `Ok(false)` on timeout. Retries on EINTR with the remaining time.
+pub(crate) fn poll_fd(fd: RawFd, events: libc::c_short, timeout: Option<Duration>) -> std::io::Result<bool> {
+	let deadline = timeout.map(|t| Instant::now() + t);
+	let mut pfd = libc::pollfd { fd, events, revents: 0 };
+
+	loop {
+		let timeout_ms = match deadline {
+			None => -1,
+			Some(deadline) => to_poll_ms(deadline.saturating_duration_since(Instant::now())),
+		};
+
+		let n = unsafe { libc::poll(&mut pfd, 1, timeout_ms) };
+
+		match n {
+			-1 if errno() == libc::EINTR => continue,
+			-1 => return Err(IoError::Poll { errno: errno() }.into()),
+			0 => return Ok(false),
+			_ => return Ok(true),
+		}
+	}
+}
+
+/// Converts to poll() milliseconds, rounding up so short timeouts don't become 0.
+pub(crate) fn to_poll_ms(d: Duration) -> libc::c_int {
+	let ms = d.as_nanos().div_ceil(1_000_000);
+	ms.min(libc::c_int::MAX as u128) as libc::c_int
+}
+
+/*
+Why poll() and not select()?
+select() can't handle fds >= FD_SETSIZE (1024) — a server with many
+connections will exceed that. poll() takes the fd directly, no limit.
+For a single fd, poll() is the simplest blocking wait with a timeout.
+*/
+
+impl<D: Domain> ConnectedStream<D> {
+	/// Blocks until the stream is readable or `timeout` elapses.
+	///
+	/// Returns `false` on timeout. `None` waits forever.
+	/// EOF and errors count as readable — the next read returns immediately.
+	pub fn wait_readable(&self, timeout: Option<Duration>) -> std::io::Result<bool> {
+		poll_fd(self.as_raw_fd(), libc::POLLIN, timeout)
+	}
+
+	/// Blocks until the stream is writable or `timeout` elapses.
+	///
+	/// Returns `false` on timeout. `None` waits forever.
+	pub fn wait_writable(&self, timeout: Option<Duration>) -> std::io::Result<bool> {
+		poll_fd(self.as_raw_fd(), libc::POLLOUT, timeout)
+	}
+
+	/// Writes `buf` unless the peer has already disconnected.
+	///
+	/// `PeerClosed` means "stop producing output", unlike the
+	/// `ConnectionClosed` that reads return when the stream ends.
+	///
+	/// Checks for hangup/error/peer shutdown (POLLRDHUP) without blocking
+	/// first, and fails with `IoError::PeerClosed` (kind `BrokenPipe`)
+	/// instead of writing. EPIPE/ECONNRESET from the write itself map to
+	/// the same error. SIGPIPE is raised only after `set_sigpipe(true)`.
+	///
+	/// Costs one extra `poll()`, so use it before large or expensive
+	/// responses. A peer that only half-closed (shutdown of its write side)
+	/// also counts as gone — don't use this for protocols where clients do that.
+	pub fn write_checked(&self, buf: &[u8]) -> std::io::Result<usize> {
+		let mut pfd = libc::pollfd { fd: self.as_raw_fd(), events: libc::POLLRDHUP, revents: 0 };
+		let n = unsafe { libc::poll(&mut pfd, 1, 0) };
+		if n == -1 && errno() != libc::EINTR {
+			return Err(IoError::Poll { errno: errno() }.into());
+		}
+		if n > 0 && pfd.revents & (libc::POLLRDHUP | libc::POLLHUP | libc::POLLERR) != 0 {
+			return Err(IoError::PeerClosed.into());
+		}
+
+		let n = unsafe {
+			libc::send(self.as_raw_fd(), buf.as_ptr() as *const libc::c_void, buf.len(), self.nosignal())
+		};
+		if n == -1 {
+			return match errno() {
+				libc::EPIPE | libc::ECONNRESET => Err(IoError::PeerClosed.into()),
+				e => Err(IoError::Write { errno: e }.into()),
+			};
+		}
+		Ok(n as usize)
+	}
+}
+
+impl<D: Domain, M: Mode> Listener<D, M> {
+	/// Blocks until a connection is pending or `timeout` elapses.
+	///
+	/// Returns `false` on timeout. `None` waits forever.
+	/// A following `accept_nonblocking()` may still return `WouldBlock`
+	/// if another thread accepted first.
+	pub fn wait_readable(&self, timeout: Option<Duration>) -> std::io::Result<bool> {
+		poll_fd(self.as_raw_fd(), libc::POLLIN, timeout)
+	}
+
+	/// Blocks until the listener is writable or `timeout` elapses.
+	///
+	/// Listeners never become writable in practice; provided for symmetry.
+	pub fn wait_writable(&self, timeout: Option<Duration>) -> std::io::Result<bool> {
+		poll_fd(self.as_raw_fd(), libc::POLLOUT, timeout)
+	}
+}
+
+impl<D: Domain> BoundDatagram<D> {
+	/// Blocks until a datagram is queued or `timeout` elapses.
+	///
+	/// Returns `false` on timeout. `None` waits forever.
+	pub fn wait_readable(&self, timeout: Option<Duration>) -> std::io::Result<bool> {
+		poll_fd(self.as_raw_fd(), libc::POLLIN, timeout)
+	}
+
+	/// Blocks until there is send buffer space or `timeout` elapses.
+	///
+	/// Returns `false` on timeout. `None` waits forever.
+	pub fn wait_writable(&self, timeout: Option<Duration>) -> std::io::Result<bool> {
+		poll_fd(self.as_raw_fd(), libc::POLLOUT, timeout)
+	}
+}
+
+impl<D: Domain> ConnectedDatagram<D> {
+	/// Blocks until a datagram from the peer is queued or `timeout` elapses.
+	///
+	/// Returns `false` on timeout. `None` waits forever.
+	pub fn wait_readable(&self, timeout: Option<Duration>) -> std::io::Result<bool> {
+		poll_fd(self.as_raw_fd(), libc::POLLIN, timeout)
+	}
+
+	/// Blocks until there is send buffer space or `timeout` elapses.
+	///
+	/// Returns `false` on timeout. `None` waits forever.
+	pub fn wait_writable(&self, timeout: Option<Duration>) -> std::io::Result<bool> {
+		poll_fd(self.as_raw_fd(), libc::POLLOUT, timeout)
+	}
+}
@@ -0,0 +1,479 @@
+//! AF_XDP sockets (experimental, `xdp` feature).
+//!
+//! An XDP socket receives frames straight from a NIC queue into a
+//! user-registered memory area (UMEM), bypassing the network stack.
+//! Four single-producer/single-consumer rings shared with the kernel
+//! move frame addresses around:
+//!
+//! - fill: app → kernel, empty frames to receive into
+//! - rx: kernel → app, received frames
+//! - tx: app → kernel, frames to send
+//! - completion: kernel → app, sent frames free again
+//!
+//! Loading the XDP program that redirects packets to the socket (an
+//! XSKMAP entry keyed by queue id) is left to a BPF loader; insert this
+//! socket's fd into that map.
+
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::sync::atomic::{AtomicU32, Ordering};
+use crate::error::{SocketError, errno};
+
+/// UMEM and ring sizes for `XdpSocket::bind()`.
+///
+/// All counts must be powers of two.
+#[derive(Debug, Clone, Copy)]
+pub struct XdpConfig {
+	pub frame_count: u32,
+	pub frame_size: u32,
+	pub ring_size: u32,
+	pub zero_copy: bool,
+}
+
+impl Default for XdpConfig {
+	fn default() -> Self {
+		Self {
+			frame_count: 4096,
+			frame_size: 2048,
+			ring_size: 2048,
+			zero_copy: false,
+		}
+	}
+}
+
+impl XdpConfig {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Number of frames in the UMEM. Default: 4096.
+	pub fn frame_count(mut self, count: u32) -> Self {
+		self.frame_count = count;
+		self
+	}
+
+	/// Bytes per frame, 2048 or 4096. Default: 2048.
+	pub fn frame_size(mut self, size: u32) -> Self {
+		self.frame_size = size;
+		self
+	}
+
+	/// Entries in each of the four rings. Default: 2048.
+	pub fn ring_size(mut self, size: u32) -> Self {
+		self.ring_size = size;
+		self
+	}
+
+	/// Require zero-copy mode (XDP_ZEROCOPY); bind fails if the driver
+	/// can't. Default: copy mode, which works on any interface.
+	pub fn zero_copy(mut self, enable: bool) -> Self {
+		self.zero_copy = enable;
+		self
+	}
+}
+
+/// A frame in the UMEM: offset from the UMEM start and length.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct XdpDesc {
+	pub addr: u64,
+	pub len: u32,
+}
+
+/// Kernel drop counters (XDP_STATISTICS).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct XdpStats {
+	/// Dropped for reasons other than invalid descriptors.
+	pub rx_dropped: u64,
+	pub rx_invalid_descs: u64,
+	pub tx_invalid_descs: u64,
+}
+
+/// An mmap()ed region, unmapped on drop.
+struct Mmap {
+	ptr: *mut u8,
+	len: usize,
+}
+
+impl Mmap {
+	fn new(fd: libc::c_int, len: usize, offset: libc::off_t, region: &'static str) -> std::io::Result<Self> {
+		let (flags, fd) = if fd < 0 {
+			(libc::MAP_PRIVATE | libc::MAP_ANONYMOUS, -1)
+		} else {
+			(libc::MAP_SHARED | libc::MAP_POPULATE, fd)
+		};
+		let ptr = unsafe {
+			libc::mmap(std::ptr::null_mut(), len, libc::PROT_READ | libc::PROT_WRITE, flags, fd, offset)
+		};
+		if ptr == libc::MAP_FAILED {
+			return Err(SocketError::Mmap { errno: errno(), region }.into());
+		}
+		Ok(Self { ptr: ptr as *mut u8, len })
+	}
+}
+
+impl Drop for Mmap {
+	fn drop(&mut self) {
+		unsafe { libc::munmap(self.ptr as *mut libc::c_void, self.len) };
+	}
+}
+
+/// One of the four shared rings. `T` is `u64` (fill/completion) or
+/// `xdp_desc` (rx/tx).
+struct XskRing<T> {
+	_map: Mmap,
+	producer: *const AtomicU32,
+	consumer: *const AtomicU32,
+	flags: *const AtomicU32,
+	descs: *mut T,
+	size: u32,
+}
+
+impl<T: Copy> XskRing<T> {
+	fn map(fd: libc::c_int, off: &libc::xdp_ring_offset, size: u32, pgoff: u64, region: &'static str) -> std::io::Result<Self> {
+		let len = off.desc as usize + size as usize * std::mem::size_of::<T>();
+		let map = Mmap::new(fd, len, pgoff as libc::off_t, region)?;
+		let at = |offset: u64| unsafe { map.ptr.add(offset as usize) };
+		Ok(Self {
+			producer: at(off.producer) as *const AtomicU32,
+			consumer: at(off.consumer) as *const AtomicU32,
+			flags: at(off.flags) as *const AtomicU32,
+			descs: at(off.desc) as *mut T,
+			size,
+			_map: map,
+		})
+	}
+
+	/// Producer side: queues as many of `items` as fit.
+	fn push(&mut self, items: &[T]) -> usize {
+		let (producer, consumer) = unsafe { (&*self.producer, &*self.consumer) };
+		let prod = producer.load(Ordering::Relaxed);
+		let free = self.size - prod.wrapping_sub(consumer.load(Ordering::Acquire));
+		let n = (free as usize).min(items.len());
+
+		for (i, item) in items[..n].iter().enumerate() {
+			let slot = prod.wrapping_add(i as u32) & (self.size - 1);
+			unsafe { self.descs.add(slot as usize).write(*item) };
+		}
+		producer.store(prod.wrapping_add(n as u32), Ordering::Release);
+		n
+	}
+
+	/// Consumer side: takes up to `max` entries.
+	fn pop(&mut self, out: &mut Vec<T>, max: usize) -> usize {
+		let (producer, consumer) = unsafe { (&*self.producer, &*self.consumer) };
+		let cons = consumer.load(Ordering::Relaxed);
+		let available = producer.load(Ordering::Acquire).wrapping_sub(cons);
+		let n = (available as usize).min(max);
+
+		for i in 0..n {
+			let slot = cons.wrapping_add(i as u32) & (self.size - 1);
+			out.push(unsafe { self.descs.add(slot as usize).read() });
+		}
+		consumer.store(cons.wrapping_add(n as u32), Ordering::Release);
+		n
+	}
+
+	fn needs_wakeup(&self) -> bool {
+		unsafe { &*self.flags }.load(Ordering::Acquire) & libc::XDP_RING_NEED_WAKEUP != 0
+	}
+}
+
+/// An AF_XDP socket bound to one queue of one interface, with its own UMEM.
+///
+/// Frames cycle between an internal free list and the rings:
+/// `bind()` hands frames to the fill ring, `recv()` returns filled ones,
+/// and `release()` gives them back. For sending, take a frame with
+/// `tx_frame()`, write it, `send()` it, and `reap_completions()` later
+/// returns it to the free list.
+///
+/// Needs CAP_NET_RAW (and CAP_BPF/CAP_NET_ADMIN for the XDP program).
+/// Not `Sync`: the rings are single-producer/single-consumer.
+///
+/// # Example
+/// ```ignore
+/// let mut xsk = XdpSocket::bind(ifindex, 0, XdpConfig::new())?;
+/// let mut rx = Vec::new();
+/// loop {
+///     xsk.recv(&mut rx, 64)?;
+///     for desc in rx.drain(..) {
+///         handle(unsafe { xsk.frame(&desc) });
+///         xsk.release(desc.addr);
+///     }
+///     xsk.refill();
+/// }
+/// ```
+pub struct XdpSocket {
+	// Rings are unmapped before the socket closes; the UMEM goes last.
+	rx: XskRing<libc::xdp_desc>,
+	tx: XskRing<libc::xdp_desc>,
+	fill: XskRing<u64>,
+	completion: XskRing<u64>,
+	fd: OwnedFd,
+	umem: Mmap,
+	frame_size: u32,
+	free: Vec<u64>,
+	scratch: Vec<u64>,
+}
+
+unsafe impl Send for XdpSocket {}
+
+impl XdpSocket {
+	/// Creates the socket and UMEM, maps the rings, and binds to
+	/// `ifindex`/`queue_id`. Half the frames start in the fill ring
+	/// (up to its size); the rest stay free for sending.
+	pub fn bind(ifindex: u32, queue_id: u32, config: XdpConfig) -> std::io::Result<Self> {
+		for n in [config.frame_count, config.frame_size, config.ring_size] {
+			if !n.is_power_of_two() {
+				return Err(SocketError::InvalidInput { reason: "XDP frame count, frame size and ring size must be powers of two" }.into());
+			}
+		}
+
+		let fd = unsafe { libc::socket(libc::AF_XDP, libc::SOCK_RAW | libc::SOCK_CLOEXEC, 0) };
+		if fd == -1 {
+			return Err(SocketError::Create { errno: errno() }.into());
+		}
+		crate::track(fd, "socket(AF_XDP)");
+		let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+		let raw = fd.as_raw_fd();
+
+		let umem_len = config.frame_count as usize * config.frame_size as usize;
+		let umem = Mmap::new(-1, umem_len, 0, "UMEM")?;
+
+		let reg = libc::xdp_umem_reg {
+			addr: umem.ptr as u64,
+			len: umem_len as u64,
+			chunk_size: config.frame_size,
+			headroom: 0,
+			flags: 0,
+			tx_metadata_len: 0,
+		};
+		set_xdp_opt(raw, libc::XDP_UMEM_REG, &reg, "XDP_UMEM_REG")?;
+		set_xdp_opt(raw, libc::XDP_UMEM_FILL_RING, &config.ring_size, "XDP_UMEM_FILL_RING")?;
+		set_xdp_opt(raw, libc::XDP_UMEM_COMPLETION_RING, &config.ring_size, "XDP_UMEM_COMPLETION_RING")?;
+		set_xdp_opt(raw, libc::XDP_RX_RING, &config.ring_size, "XDP_RX_RING")?;
+		set_xdp_opt(raw, libc::XDP_TX_RING, &config.ring_size, "XDP_TX_RING")?;
+
+		let off: libc::xdp_mmap_offsets = get_xdp_opt(raw, libc::XDP_MMAP_OFFSETS, "XDP_MMAP_OFFSETS")?;
+		let size = config.ring_size;
+		let rx = XskRing::map(raw, &off.rx, size, libc::XDP_PGOFF_RX_RING as u64, "XDP rx ring")?;
+		let tx = XskRing::map(raw, &off.tx, size, libc::XDP_PGOFF_TX_RING as u64, "XDP tx ring")?;
+		let fill = XskRing::map(raw, &off.fr, size, libc::XDP_UMEM_PGOFF_FILL_RING, "XDP fill ring")?;
+		let completion = XskRing::map(raw, &off.cr, size, libc::XDP_UMEM_PGOFF_COMPLETION_RING, "XDP completion ring")?;
+
+		let mut flags = libc::XDP_USE_NEED_WAKEUP;
+		flags |= if config.zero_copy { libc::XDP_ZEROCOPY } else { libc::XDP_COPY };
+		let addr = libc::sockaddr_xdp {
+			sxdp_family: libc::AF_XDP as u16,
+			sxdp_flags: flags,
+			sxdp_ifindex: ifindex,
+			sxdp_queue_id: queue_id,
+			sxdp_shared_umem_fd: 0,
+		};
+		let result = unsafe {
+			libc::bind(
+				raw,
+				&addr as *const _ as *const libc::sockaddr,
+				std::mem::size_of::<libc::sockaddr_xdp>() as libc::socklen_t,
+			)
+		};
+		if result == -1 {
+			return Err(SocketError::Bind {
+				errno: errno(),
+				addr: format!("ifindex {ifindex} queue {queue_id}"),
+			}.into());
+		}
+
+		// Highest address last, so tx_frame() pops from the top.
+		let free = (0..config.frame_count as u64).rev().map(|i| i * config.frame_size as u64).collect();
+		let mut socket = Self {
+			rx, tx, fill, completion, fd, umem,
+			frame_size: config.frame_size,
+			free,
+			scratch: Vec::new(),
+		};
+		let initial = (config.frame_count / 2).min(config.ring_size) as usize;
+		socket.refill_up_to(initial);
+		Ok(socket)
+	}
+
+	/// Moves free frames into the fill ring. Returns how many.
+	///
+	/// Call after `release()`-ing received frames; an empty fill ring
+	/// makes the kernel drop packets (see `stats().rx_dropped`).
+	pub fn refill(&mut self) -> usize {
+		self.refill_up_to(usize::MAX)
+	}
+
+	fn refill_up_to(&mut self, max: usize) -> usize {
+		let n = self.free.len().min(max);
+		let start = self.free.len() - n;
+		let pushed = self.fill.push(&self.free[start..]);
+		self.free.truncate(self.free.len() - pushed);
+		pushed
+	}
+
+	/// Appends up to `max` received frames to `out`. Never blocks.
+	///
+	/// Poll the fd for readability to wait. Each frame is the
+	/// application's until passed to `release()`.
+	pub fn recv(&mut self, out: &mut Vec<XdpDesc>, max: usize) -> std::io::Result<usize> {
+		let mut descs = Vec::with_capacity(max.min(self.rx.size as usize));
+		let n = self.rx.pop(&mut descs, max);
+		out.extend(descs.iter().map(|d| XdpDesc { addr: d.addr, len: d.len }));
+
+		if n == 0 && self.fill.needs_wakeup() {
+			self.kick_rx()?;
+		}
+		Ok(n)
+	}
+
+	/// Returns a received frame to the free list.
+	pub fn release(&mut self, addr: u64) {
+		self.free.push(addr - addr % self.frame_size as u64);
+	}
+
+	/// Takes a free frame to build an outgoing packet in.
+	pub fn tx_frame(&mut self) -> Option<u64> {
+		self.free.pop()
+	}
+
+	/// Queues frames for sending and wakes the kernel if it needs it.
+	///
+	/// Returns how many were queued (fewer if the tx ring is full). Sent
+	/// frames come back through `reap_completions()`.
+	pub fn send(&mut self, descs: &[XdpDesc]) -> std::io::Result<usize> {
+		let raw: Vec<libc::xdp_desc> = descs.iter()
+			.map(|d| libc::xdp_desc { addr: d.addr, len: d.len, options: 0 })
+			.collect();
+		let n = self.tx.push(&raw);
+
+		if n > 0 && self.tx.needs_wakeup() {
+			let result = unsafe {
+				libc::sendto(self.fd.as_raw_fd(), std::ptr::null(), 0, libc::MSG_DONTWAIT, std::ptr::null(), 0)
+			};
+			if result == -1 && !matches!(errno(), libc::EAGAIN | libc::EBUSY | libc::ENOBUFS) {
+				return Err(crate::error::IoError::Write { errno: errno() }.into());
+			}
+		}
+		Ok(n)
+	}
+
+	/// Returns sent frames to the free list. Returns how many.
+	pub fn reap_completions(&mut self) -> usize {
+		self.scratch.clear();
+		let n = self.completion.pop(&mut self.scratch, usize::MAX);
+		self.free.extend_from_slice(&self.scratch);
+		n
+	}
+
+	/// Frames neither in a ring nor held by the application.
+	pub fn free_frames(&self) -> usize {
+		self.free.len()
+	}
+
+	/// The bytes of a received frame.
+	///
+	/// # Safety
+	/// `desc` must come from `recv()` and not be released yet — otherwise
+	/// the kernel may be writing to the frame.
+	///
+	/// # Panics
+	/// If `desc` lies outside the UMEM.
+	pub unsafe fn frame(&self, desc: &XdpDesc) -> &[u8] {
+		let (start, len) = self.bounds(desc.addr, desc.len as usize);
+		unsafe { std::slice::from_raw_parts(self.umem.ptr.add(start), len) }
+	}
+
+	/// The whole frame at `addr`, for writing an outgoing packet.
+	///
+	/// # Safety
+	/// `addr` must come from `tx_frame()` and not be passed to `send()` yet.
+	///
+	/// # Panics
+	/// If `addr` lies outside the UMEM.
+	pub unsafe fn frame_mut(&mut self, addr: u64) -> &mut [u8] {
+		let (start, len) = self.bounds(addr, self.frame_size as usize);
+		unsafe { std::slice::from_raw_parts_mut(self.umem.ptr.add(start), len) }
+	}
+
+	fn bounds(&self, addr: u64, len: usize) -> (usize, usize) {
+		let start = addr as usize;
+		assert!(start.checked_add(len).is_some_and(|end| end <= self.umem.len), "XDP frame outside UMEM");
+		(start, len)
+	}
+
+	/// Reads the kernel's drop counters.
+	pub fn stats(&self) -> std::io::Result<XdpStats> {
+		let raw: libc::xdp_statistics_v1 = get_xdp_opt(self.fd.as_raw_fd(), libc::XDP_STATISTICS, "XDP_STATISTICS")?;
+		Ok(XdpStats {
+			rx_dropped: raw.rx_dropped,
+			rx_invalid_descs: raw.rx_invalid_descs,
+			tx_invalid_descs: raw.tx_invalid_descs,
+		})
+	}
+
+	/// Nudges the kernel to process the fill ring (need_wakeup mode).
+	fn kick_rx(&self) -> std::io::Result<()> {
+		let result = unsafe {
+			libc::recvfrom(self.fd.as_raw_fd(), std::ptr::null_mut(), 0, libc::MSG_DONTWAIT, std::ptr::null_mut(), std::ptr::null_mut())
+		};
+		if result == -1 && !matches!(errno(), libc::EAGAIN | libc::EBUSY) {
+			return Err(crate::error::IoError::Read { errno: errno() }.into());
+		}
+		Ok(())
+	}
+}
+
+fn set_xdp_opt<T>(fd: libc::c_int, opt: libc::c_int, val: &T, name: &'static str) -> std::io::Result<()> {
+	let result = unsafe {
+		libc::setsockopt(
+			fd,
+			libc::SOL_XDP,
+			opt,
+			val as *const T as *const libc::c_void,
+			std::mem::size_of::<T>() as libc::socklen_t,
+		)
+	};
+	if result == -1 {
+		return Err(SocketError::SetOption { errno: errno(), option: name }.into());
+	}
+	Ok(())
+}
+
+fn get_xdp_opt<T>(fd: libc::c_int, opt: libc::c_int, name: &'static str) -> std::io::Result<T> {
+	let mut val: T = unsafe { std::mem::zeroed() };
+	let mut len = std::mem::size_of::<T>() as libc::socklen_t;
+	let result = unsafe {
+		libc::getsockopt(fd, libc::SOL_XDP, opt, &mut val as *mut T as *mut libc::c_void, &mut len)
+	};
+	if result == -1 {
+		return Err(SocketError::GetOption { errno: errno(), option: name }.into());
+	}
+	Ok(val)
+}
+
+impl std::os::fd::AsRawFd for XdpSocket {
+	fn as_raw_fd(&self) -> std::os::fd::RawFd {
+		self.fd.as_raw_fd()
+	}
+}
+
+impl std::os::fd::AsFd for XdpSocket {
+	fn as_fd(&self) -> std::os::fd::BorrowedFd<'_> {
+		self.fd.as_fd()
+	}
+}
+
+/*
+Why not a Domain/SockType typestate like the other sockets?
+AF_XDP has no addresses to send to or receive from and no
+connect/listen/accept — its "I/O" is ring manipulation over shared
+memory. Forcing it into RawSocket<D, T> would mean a Domain whose
+Addr is never used and methods that all fail. A standalone type keeps
+the experimental API from leaking into the stable ones.
+
+Why a free list inside the socket?
+Every frame must be in exactly one place: a ring, the app, or free.
+Letting the app manage addresses makes double-fills (kernel writes
+into a frame the app is reading) an easy mistake; owning the list
+here keeps the common receive/release loop correct by default.
+*/
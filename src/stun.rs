@@ -0,0 +1,223 @@
+//! Minimal STUN client (RFC 8489) for NAT discovery.
+//!
+//! Sends a Binding request from an existing datagram socket and returns
+//! the reflexive address the server saw — the public address and port a
+//! NAT mapped the socket to. Use the same socket afterwards so the
+//! mapping stays valid.
+//!
+//! # Example
+//! ```ignore
+//! use wirelane::stun;
+//!
+//! let socket = DatagramBuilder::<Ipv4>::new().bind(SocketAddrV4::new([0, 0, 0, 0], 0))?;
+//! let server = SocketAddrV4::new([74, 125, 250, 129], 19302);
+//! let public = stun::binding(&socket, &server, Duration::from_secs(3))?;
+//! println!("reachable at {:?}", public);
+//! ```
+
+use std::time::{Duration, Instant};
+use crate::addr::{Domain, ToSockAddr, FromSockAddr, SocketAddrV4, SocketAddrV6};
+use crate::error::IoError;
+use crate::socket::{BoundDatagram, ConnectedDatagram, poll_fd};
+
+const MAGIC_COOKIE: u32 = 0x2112_A442;
+const HEADER_LEN: usize = 20;
+const BINDING_REQUEST: u16 = 0x0001;
+const BINDING_SUCCESS: u16 = 0x0101;
+const BINDING_ERROR: u16 = 0x0111;
+const ATTR_MAPPED_ADDRESS: u16 = 0x0001;
+const ATTR_XOR_MAPPED_ADDRESS: u16 = 0x0020;
+const FAMILY_V4: u8 = 0x01;
+const FAMILY_V6: u8 = 0x02;
+
+/// First retransmission timeout; doubles after each attempt (RFC 8489 §6.2.1).
+const INITIAL_RTO: Duration = Duration::from_millis(500);
+
+/// An address type a STUN mapped address can be decoded into.
+pub trait MappedAddr: Sized {
+	/// Builds the address from raw IP bytes and port, or `None` if the
+	/// family doesn't match.
+	fn from_mapped(ip: &[u8], port: u16) -> Option<Self>;
+}
+
+impl MappedAddr for SocketAddrV4 {
+	fn from_mapped(ip: &[u8], port: u16) -> Option<Self> {
+		Some(SocketAddrV4::new(ip.try_into().ok()?, port))
+	}
+}
+
+impl MappedAddr for SocketAddrV6 {
+	fn from_mapped(ip: &[u8], port: u16) -> Option<Self> {
+		Some(SocketAddrV6::new(ip.try_into().ok()?, port))
+	}
+}
+
+/// Asks `server` for this socket's reflexive address.
+///
+/// Retransmits with exponential backoff (500ms, 1s, 2s, ...) until a
+/// response arrives or `timeout` elapses, which fails with `TimedOut`.
+/// Datagrams from other senders, or with a different transaction id,
+/// are read and dropped — don't run this while the socket is serving
+/// other traffic.
+pub fn binding<D: Domain>(socket: &BoundDatagram<D>, server: &D::Addr, timeout: Duration) -> std::io::Result<D::Addr>
+where
+	D::Addr: ToSockAddr + FromSockAddr + MappedAddr + PartialEq,
+{
+	let mut buf = [0u8; 576];
+	exchange(
+		socket.as_raw_fd(),
+		timeout,
+		|request| socket.send_to(request, server).map(drop),
+		|| {
+			let (n, from) = socket.recv_from(&mut buf)?;
+			Ok((from == *server).then(|| buf[..n].to_vec()))
+		},
+	)
+}
+
+/// `binding()` for a socket connected to the STUN server.
+pub fn binding_connected<D: Domain>(socket: &ConnectedDatagram<D>, timeout: Duration) -> std::io::Result<D::Addr>
+where
+	D::Addr: MappedAddr,
+{
+	let mut buf = [0u8; 576];
+	exchange(
+		socket.as_raw_fd(),
+		timeout,
+		|request| socket.send(request).map(drop),
+		|| {
+			let n = socket.recv(&mut buf)?;
+			Ok(Some(buf[..n].to_vec()))
+		},
+	)
+}
+
+/// Sends the request, retransmitting until a matching response arrives.
+///
+/// `recv` returns `None` for datagrams that aren't from the server.
+fn exchange<A: MappedAddr>(
+	fd: libc::c_int,
+	timeout: Duration,
+	send: impl Fn(&[u8]) -> std::io::Result<()>,
+	mut recv: impl FnMut() -> std::io::Result<Option<Vec<u8>>>,
+) -> std::io::Result<A> {
+	let transaction = transaction_id();
+	let request = encode_request(&transaction);
+	let deadline = Instant::now() + timeout;
+	let mut rto = INITIAL_RTO;
+
+	loop {
+		let now = Instant::now();
+		if now >= deadline {
+			return Err(IoError::Read { errno: libc::ETIMEDOUT }.into());
+		}
+		send(&request)?;
+		let retransmit = (now + rto).min(deadline);
+		rto *= 2;
+
+		loop {
+			let remaining = retransmit.saturating_duration_since(Instant::now());
+			if !poll_fd(fd, libc::POLLIN, Some(remaining))? {
+				break;
+			}
+			let Some(response) = recv()? else { continue };
+			if let Some(addr) = decode_response(&response, &transaction)? {
+				return Ok(addr);
+			}
+		}
+	}
+}
+
+fn encode_request(transaction: &[u8; 12]) -> [u8; HEADER_LEN] {
+	let mut msg = [0u8; HEADER_LEN];
+	msg[0..2].copy_from_slice(&BINDING_REQUEST.to_be_bytes());
+	// Length (bytes 2..4) stays 0: no attributes.
+	msg[4..8].copy_from_slice(&MAGIC_COOKIE.to_be_bytes());
+	msg[8..20].copy_from_slice(transaction);
+	msg
+}
+
+/// Parses a Binding response to our transaction.
+///
+/// `Ok(None)` means "not ours": not STUN, or another transaction.
+fn decode_response<A: MappedAddr>(msg: &[u8], transaction: &[u8; 12]) -> std::io::Result<Option<A>> {
+	if msg.len() < HEADER_LEN
+		|| msg[4..8] != MAGIC_COOKIE.to_be_bytes()
+		|| msg[8..20] != transaction[..]
+	{
+		return Ok(None);
+	}
+
+	let ty = u16::from_be_bytes([msg[0], msg[1]]);
+	let len = u16::from_be_bytes([msg[2], msg[3]]) as usize;
+	let body = msg.get(HEADER_LEN..HEADER_LEN + len)
+		.ok_or(IoError::MalformedFrame { reason: "STUN message shorter than its length field" })?;
+
+	match ty {
+		BINDING_SUCCESS => {}
+		BINDING_ERROR => return Err(IoError::MalformedFrame { reason: "STUN server returned an error response" }.into()),
+		_ => return Ok(None),
+	}
+
+	let mut mapped = None;
+	let mut rest = body;
+	while rest.len() >= 4 {
+		let attr = u16::from_be_bytes([rest[0], rest[1]]);
+		let attr_len = u16::from_be_bytes([rest[2], rest[3]]) as usize;
+		let value = rest.get(4..4 + attr_len)
+			.ok_or(IoError::MalformedFrame { reason: "STUN attribute overruns message" })?;
+
+		match attr {
+			ATTR_XOR_MAPPED_ADDRESS => return decode_address(value, Some(transaction)).map(Some),
+			// Pre-RFC 5389 servers only send the plain form.
+			ATTR_MAPPED_ADDRESS => mapped = Some(decode_address(value, None)?),
+			_ => {}
+		}
+		// Attributes are padded to a multiple of 4 bytes.
+		let padded = (4 + attr_len).next_multiple_of(4).min(rest.len());
+		rest = &rest[padded..];
+	}
+
+	mapped.map(Some).ok_or(IoError::MalformedFrame { reason: "STUN response has no mapped address" }.into())
+}
+
+/// Decodes a (XOR-)MAPPED-ADDRESS value; `transaction` is `Some` for the XOR form.
+fn decode_address<A: MappedAddr>(value: &[u8], transaction: Option<&[u8; 12]>) -> std::io::Result<A> {
+	let ip_len = match value.get(1) {
+		Some(&FAMILY_V4) => 4,
+		Some(&FAMILY_V6) => 16,
+		_ => return Err(IoError::MalformedFrame { reason: "unknown STUN address family" }.into()),
+	};
+	if value.len() < 4 + ip_len {
+		return Err(IoError::MalformedFrame { reason: "truncated STUN address" }.into());
+	}
+
+	let mut port = u16::from_be_bytes([value[2], value[3]]);
+	let mut ip = value[4..4 + ip_len].to_vec();
+	if let Some(transaction) = transaction {
+		port ^= (MAGIC_COOKIE >> 16) as u16;
+		let mut key = MAGIC_COOKIE.to_be_bytes().to_vec();
+		key.extend_from_slice(transaction);
+		for (byte, k) in ip.iter_mut().zip(key) {
+			*byte ^= k;
+		}
+	}
+
+	A::from_mapped(&ip, port)
+		.ok_or(IoError::MalformedFrame { reason: "STUN address family doesn't match the socket" }.into())
+}
+
+fn transaction_id() -> [u8; 12] {
+	let mut id = [0u8; 12];
+	crate::fill_random(&mut id);
+	id
+}
+
+/*
+Why a blocking exchange instead of an encoder/decoder pair?
+Reflexive-address discovery runs once at startup or on network change,
+and the only subtle parts are retransmission timing and matching the
+transaction id. Keeping both inside one call means callers can't get
+them wrong; apps that multiplex STUN with their own traffic on one
+socket need a full ICE stack anyway, which is out of scope here.
+*/
@@ -0,0 +1,143 @@
+//! Helpers for tests: catching sockets that were never closed.
+//!
+//! A socket leaked on an error path doesn't fail anything until the
+//! process runs out of fds. `FdGuard` makes it fail the test that leaked it.
+//! Behind the `testing` feature; enable it in `[dev-dependencies]` only,
+//! so release and downstream debug builds don't pay for the tracking.
+//! Address generators for property tests and fuzzing are in `arbitrary`
+//! (`proptest` / `arbitrary` features).
+
+use std::collections::{BTreeSet, HashMap};
+use std::os::fd::RawFd;
+use std::sync::Mutex;
+
+#[cfg(any(feature = "proptest", feature = "arbitrary"))]
+pub mod arbitrary;
+
+/// Snapshots the open fds and checks that none were added at scope end.
+///
+/// Panics on drop (unless already panicking) listing every fd opened
+/// since `new()` and still open. fds wirelane created itself name the
+/// call that created them.
+///
+/// fds are per process, so an fd opened by another test running in
+/// parallel counts too — guard tests in a binary run with
+/// `--test-threads=1`, or in their own integration test file.
+///
+/// # Example
+/// ```ignore
+/// #[test]
+/// fn connect_error_closes_socket() {
+///     let _guard = FdGuard::new();
+///     assert!(ConnectorBuilder::<Ipv4>::new().connect(unreachable).is_err());
+/// } // panics here if the failed connect left its socket open
+/// ```
+pub struct FdGuard {
+	before: BTreeSet<RawFd>,
+}
+
+/// An fd opened inside an `FdGuard` scope and never closed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LeakedFd {
+	pub fd: RawFd,
+	/// What /proc/self/fd points at, e.g. "socket:[123456]".
+	pub target: String,
+	/// The wirelane call that created it; `None` for fds from elsewhere.
+	pub created_by: Option<&'static str>,
+}
+
+impl FdGuard {
+	/// Records the currently open fds.
+	pub fn new() -> Self {
+		Self { before: open_fds() }
+	}
+
+	/// Returns the fds opened since `new()` that are still open.
+	pub fn leaked(&self) -> Vec<LeakedFd> {
+		open_fds()
+			.difference(&self.before)
+			.map(|&fd| LeakedFd {
+				fd,
+				target: std::fs::read_link(format!("/proc/self/fd/{fd}"))
+					.map(|p| p.to_string_lossy().into_owned())
+					.unwrap_or_default(),
+				created_by: created_by(fd),
+			})
+			.collect()
+	}
+
+	/// Panics if any fd leaked, listing them.
+	pub fn assert_no_leaks(&self) {
+		let leaked = self.leaked();
+		if !leaked.is_empty() {
+			let list: Vec<String> = leaked.iter()
+				.map(|l| match l.created_by {
+					Some(call) => format!("fd {} ({}) from {}", l.fd, l.target, call),
+					None => format!("fd {} ({})", l.fd, l.target),
+				})
+				.collect();
+			panic!("{} fd(s) leaked: {}", leaked.len(), list.join(", "));
+		}
+	}
+}
+
+impl Default for FdGuard {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl Drop for FdGuard {
+	fn drop(&mut self) {
+		if !std::thread::panicking() {
+			self.assert_no_leaks();
+		}
+	}
+}
+
+/// Lists /proc/self/fd, minus the directory fd used to read it.
+fn open_fds() -> BTreeSet<RawFd> {
+	let listed: BTreeSet<RawFd> = match std::fs::read_dir("/proc/self/fd") {
+		Ok(dir) => dir
+			.filter_map(|entry| entry.ok()?.file_name().to_str()?.parse().ok())
+			.collect(),
+		Err(_) => return BTreeSet::new(),
+	};
+	// The read_dir fd is closed by now; F_GETFD fails for it.
+	listed.into_iter()
+		.filter(|&fd| unsafe { libc::fcntl(fd, libc::F_GETFD) } != -1)
+		.collect()
+}
+
+/// fd → (inode at creation, creating call). The inode tells a wirelane
+/// fd apart from a later fd that reused the number.
+static TRACKED: Mutex<Option<HashMap<RawFd, (u64, &'static str)>>> = Mutex::new(None);
+
+fn inode(fd: RawFd) -> Option<u64> {
+	let mut st: libc::stat = unsafe { std::mem::zeroed() };
+	if unsafe { libc::fstat(fd, &mut st) } == -1 {
+		return None;
+	}
+	Some(st.st_ino)
+}
+
+/// Records an fd wirelane just created; called through `crate::track`.
+pub(crate) fn track(fd: RawFd, what: &'static str) {
+	let Some(ino) = inode(fd) else { return };
+	let mut tracked = TRACKED.lock().unwrap_or_else(|e| e.into_inner());
+	tracked.get_or_insert_with(HashMap::new).insert(fd, (ino, what));
+}
+
+fn created_by(fd: RawFd) -> Option<&'static str> {
+	let tracked = TRACKED.lock().unwrap_or_else(|e| e.into_inner());
+	let &(ino, what) = tracked.as_ref()?.get(&fd)?;
+	(inode(fd) == Some(ino)).then_some(what)
+}
+
+/*
+Why compare against a snapshot instead of counting wirelane's own fds?
+A count of live wirelane sockets would miss fds leaked through
+into_raw_fd() or handed to std types, and would need a Drop hook on
+every socket type. The fd table is the ground truth; the tracking map
+only adds a name to entries that are already known to have leaked.
+*/
@@ -0,0 +1,389 @@
+//! A ready-made epoll TCP server, for trying the crate out and for
+//! quick echo or benchmark servers.
+//!
+//! `TcpServer` wires together `ListenerBuilder`, `Poller`, batched
+//! non-blocking accepts and per-connection output queues; you supply what
+//! happens to the bytes. Single-threaded: run one per core behind
+//! `ListenerBuilder::bind_sharded()` to scale out.
+//!
+//! # Example
+//! ```ignore
+//! use wirelane::turnkey::{self, TcpServer};
+//!
+//! let server = TcpServer::<Ipv4>::bind(SocketAddrV4::new([0, 0, 0, 0], 7000))?;
+//! let stop = server.stop_handle();  // stop.cancel() ends run()
+//! let stats = server.run(turnkey::echo)?;
+//! println!("{} connections, {} bytes echoed", stats.accepted, stats.bytes_written);
+//! ```
+
+use std::fmt::Debug;
+use std::time::Duration;
+use crate::addr::{Domain, ToSockAddr, FromSockAddr};
+use crate::error::SocketError;
+use crate::socket::{
+	ListenerBuilder, Listener, NonBlocking, ConnectedStream, AcceptResult,
+	Poller, Events, Token, Interest, CancelHandle, SendFlags, RecvFlags,
+};
+
+const LISTENER: Token = Token(0);
+const STOP: Token = Token(1);
+/// Connection `i` is registered as `Token(FIRST_CONN + i)`.
+const FIRST_CONN: usize = 2;
+/// How long accepting stays paused after running out of fds, unless a
+/// connection event comes first.
+const ACCEPT_BACKOFF: Duration = Duration::from_millis(100);
+
+/// What the server calls as connections come and go.
+///
+/// Only `on_read` is required. Any `FnMut(&mut Connection<D>, &[u8])`
+/// is a handler that implements just that.
+pub trait Handler<D: Domain> {
+	/// A connection was accepted. Output queued here goes out first.
+	fn on_connect(&mut self, _conn: &mut Connection<D>) {}
+
+	/// Bytes arrived. Reply with `conn.write()`, hang up with `conn.close()`.
+	fn on_read(&mut self, conn: &mut Connection<D>, data: &[u8]);
+
+	/// The output queue just emptied.
+	///
+	/// Called again each time it empties for as long as this queues more,
+	/// so a streaming (benchmark) handler queues its next chunk here.
+	fn on_writable(&mut self, _conn: &mut Connection<D>) {}
+
+	/// The connection is about to be closed.
+	fn on_close(&mut self, _conn: &Connection<D>) {}
+}
+
+impl<D: Domain, F: FnMut(&mut Connection<D>, &[u8])> Handler<D> for F {
+	fn on_read(&mut self, conn: &mut Connection<D>, data: &[u8]) {
+		self(conn, data)
+	}
+}
+
+/// Echo handler: writes every byte back.
+pub fn echo<D: Domain>(conn: &mut Connection<D>, data: &[u8]) {
+	conn.write(data);
+}
+
+/// One accepted connection, as handlers see it.
+pub struct Connection<D: Domain> {
+	stream: ConnectedStream<D>,
+	peer: D::Addr,
+	out: Vec<u8>,
+	/// Close once `out` is flushed.
+	closing: bool,
+	/// The peer sent EOF; stop watching for input.
+	eof: bool,
+	/// The last `on_writable()` queued output; call it again when that's sent.
+	streaming: bool,
+	interest: Interest,
+}
+
+impl<D: Domain> Connection<D> {
+	/// The client's address.
+	pub fn peer_addr(&self) -> &D::Addr {
+		&self.peer
+	}
+
+	/// The underlying non-blocking stream, e.g. for socket options.
+	pub fn stream(&self) -> &ConnectedStream<D> {
+		&self.stream
+	}
+
+	/// Queues `data`; the server sends it as the socket allows.
+	pub fn write(&mut self, data: &[u8]) {
+		self.out.extend_from_slice(data);
+	}
+
+	/// Bytes queued and not yet sent.
+	pub fn pending(&self) -> usize {
+		self.out.len()
+	}
+
+	/// Closes the connection once queued output is sent.
+	pub fn close(&mut self) {
+		self.closing = true;
+	}
+}
+
+/// Counters from a finished `TcpServer::run()`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ServerStats {
+	/// Connections accepted.
+	pub accepted: u64,
+	/// accept() failures. Out of fds (EMFILE/ENFILE), accepting pauses
+	/// until the next connection event or for 100ms, whichever is first.
+	pub accept_errors: u64,
+	/// Bytes read from all connections.
+	pub bytes_read: u64,
+	/// Bytes written to all connections.
+	pub bytes_written: u64,
+}
+
+/// A single-threaded epoll TCP (or Unix stream) server.
+pub struct TcpServer<D: Domain> {
+	listener: Listener<D, NonBlocking>,
+	stop: CancelHandle,
+	accept_batch: usize,
+	read_buf: usize,
+}
+
+impl<D: Domain> TcpServer<D>
+where
+	D::Addr: ToSockAddr + FromSockAddr + Debug,
+{
+	/// Binds a listener with `ListenerBuilder` defaults.
+	pub fn bind(addr: D::Addr) -> std::io::Result<Self> {
+		Self::from_listener(ListenerBuilder::<D>::new().bind(addr)?)
+	}
+
+	/// Serves on an existing listener (switched to non-blocking).
+	pub fn from_listener(listener: Listener<D>) -> std::io::Result<Self> {
+		Ok(Self {
+			listener: listener.into_nonblocking()?,
+			stop: CancelHandle::new()?,
+			accept_batch: 64,
+			read_buf: 16 * 1024,
+		})
+	}
+
+	/// Set the most connections accepted per listener wakeup. Default: 64.
+	pub fn accept_batch(mut self, max: usize) -> Self {
+		self.accept_batch = max.max(1);
+		self
+	}
+
+	/// Set the most bytes handed to one `on_read()`. Default: 16 KiB.
+	pub fn read_buf(mut self, bytes: usize) -> Self {
+		self.read_buf = bytes.max(1);
+		self
+	}
+
+	/// Returns the address the server listens on.
+	pub fn local_addr(&self) -> std::io::Result<D::Addr> {
+		self.listener.local_addr()
+	}
+
+	/// Returns a handle whose `cancel()` makes `run()` return.
+	pub fn stop_handle(&self) -> CancelHandle {
+		self.stop.clone()
+	}
+
+	/// Serves until stopped, then closes every connection.
+	///
+	/// Fails only if epoll itself does; a connection that errors is
+	/// just closed.
+	pub fn run<H: Handler<D>>(self, mut handler: H) -> std::io::Result<ServerStats> {
+		let poller = Poller::new()?;
+		poller.register(&self.listener, LISTENER, Interest::READABLE)?;
+		poller.register(&self.stop, STOP, Interest::READABLE)?;
+
+		let mut conns: Vec<Option<Connection<D>>> = Vec::new();
+		let mut free = Vec::new();
+		let mut events = Events::with_capacity(256);
+		let mut buf = vec![0u8; self.read_buf];
+		let mut stats = ServerStats::default();
+
+		let mut accept_paused = false;
+		loop {
+			poller.wait(&mut events, accept_paused.then_some(ACCEPT_BACKOFF))?;
+			if accept_paused {
+				poller.register(&self.listener, LISTENER, Interest::READABLE)?;
+				accept_paused = false;
+			}
+			for event in events.iter() {
+				match event.token() {
+					STOP => {
+						for conn in conns.iter().flatten() {
+							handler.on_close(conn);
+						}
+						return Ok(stats);
+					}
+					LISTENER => accept_paused = self.accept_some(&poller, &mut conns, &mut free, &mut handler, &mut stats)?,
+					Token(token) => {
+						let index = token - FIRST_CONN;
+						let Some(conn) = conns.get_mut(index).and_then(Option::as_mut) else {
+							continue;  // closed earlier in this batch
+						};
+						let mut open = true;
+						if event.is_readable() || event.is_hangup() || event.is_error() {
+							open = read_once(conn, &mut buf, &mut handler, &mut stats);
+						}
+						if !open || !settle(&poller, Token(token), conn, &mut handler, &mut stats)? {
+							close(&poller, &mut conns, &mut free, index, &mut handler);
+						}
+					}
+				}
+			}
+		}
+	}
+
+	/// Accepts up to `accept_batch` pending connections.
+	///
+	/// Returns true if it ran out of fds and took the listener out of the
+	/// poller: it stays readable, so level-triggered epoll would
+	/// otherwise wake the loop again at once.
+	fn accept_some<H: Handler<D>>(
+		&self,
+		poller: &Poller,
+		conns: &mut Vec<Option<Connection<D>>>,
+		free: &mut Vec<usize>,
+		handler: &mut H,
+		stats: &mut ServerStats,
+	) -> std::io::Result<bool> {
+		for _ in 0..self.accept_batch {
+			let (stream, peer) = match self.listener.accept_nonblocking() {
+				Ok(AcceptResult::Connection(stream, peer)) => (stream, peer),
+				Ok(AcceptResult::WouldBlock) => break,
+				Ok(AcceptResult::Interrupted) => continue,
+				Err(e) => {
+					stats.accept_errors += 1;
+					if is_out_of_fds(&e) {
+						poller.deregister(&self.listener)?;
+						return Ok(true);
+					}
+					break;
+				}
+			};
+			stats.accepted += 1;
+
+			let index = free.pop().unwrap_or_else(|| {
+				conns.push(None);
+				conns.len() - 1
+			});
+			let token = Token(FIRST_CONN + index);
+			poller.register(&stream, token, Interest::READABLE)?;
+
+			let mut conn = Connection {
+				stream,
+				peer,
+				out: Vec::new(),
+				closing: false,
+				eof: false,
+				streaming: false,
+				interest: Interest::READABLE,
+			};
+			handler.on_connect(&mut conn);
+			conns[index] = Some(conn);
+			let conn = conns[index].as_mut().expect("just inserted");
+			if !settle(poller, token, conn, handler, stats)? {
+				close(poller, conns, free, index, handler);
+			}
+		}
+		Ok(false)
+	}
+}
+
+/// Whether an accept() error means the process or system is out of fds.
+fn is_out_of_fds(err: &std::io::Error) -> bool {
+	matches!(
+		err.get_ref().and_then(|inner| inner.downcast_ref::<SocketError>()),
+		Some(SocketError::Accept { errno: libc::EMFILE | libc::ENFILE })
+	)
+}
+
+/// Reads once and feeds the handler. Returns false if the connection failed.
+fn read_once<D: Domain, H: Handler<D>>(
+	conn: &mut Connection<D>,
+	buf: &mut [u8],
+	handler: &mut H,
+	stats: &mut ServerStats,
+) -> bool {
+	if conn.eof {
+		return true;
+	}
+	match conn.stream.recv_with_flags(buf, RecvFlags::DONTWAIT) {
+		Ok(0) => {
+			conn.eof = true;
+			conn.closing = true;
+		}
+		Ok(n) => {
+			stats.bytes_read += n as u64;
+			handler.on_read(conn, &buf[..n]);
+		}
+		Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {}
+		Err(_) => return false,
+	}
+	true
+}
+
+/// Flushes output and updates the epoll interest to match.
+///
+/// Returns false when the connection should be closed: a write failed,
+/// or it's closing with nothing left to send.
+fn settle<D: Domain, H: Handler<D>>(
+	poller: &Poller,
+	token: Token,
+	conn: &mut Connection<D>,
+	handler: &mut H,
+	stats: &mut ServerStats,
+) -> std::io::Result<bool> {
+	let had_output = !conn.out.is_empty() || conn.streaming;
+	if !flush(conn, stats) {
+		return Ok(false);
+	}
+	if had_output && conn.out.is_empty() && !conn.closing {
+		handler.on_writable(conn);
+		conn.streaming = !conn.out.is_empty();
+		if !flush(conn, stats) {
+			return Ok(false);
+		}
+	}
+	if conn.closing && conn.out.is_empty() {
+		return Ok(false);
+	}
+
+	// While streaming, wait for writability even with the queue empty,
+	// so on_writable() runs again on the next loop turn.
+	let wants_write = !conn.out.is_empty() || conn.streaming;
+	let interest = match (conn.eof, wants_write) {
+		(true, _) => Interest::WRITABLE,
+		(false, false) => Interest::READABLE,
+		(false, true) => Interest::READABLE | Interest::WRITABLE,
+	};
+	if interest != conn.interest {
+		poller.modify(&conn.stream, token, interest)?;
+		conn.interest = interest;
+	}
+	Ok(true)
+}
+
+/// Sends as much queued output as the socket takes. False on error.
+fn flush<D: Domain>(conn: &mut Connection<D>, stats: &mut ServerStats) -> bool {
+	let mut sent = 0;
+	while sent < conn.out.len() {
+		match conn.stream.send_with_flags(&conn.out[sent..], SendFlags::NOSIGNAL | SendFlags::DONTWAIT) {
+			Ok(n) => sent += n,
+			Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => break,
+			Err(_) => return false,
+		}
+	}
+	stats.bytes_written += sent as u64;
+	conn.out.drain(..sent);
+	true
+}
+
+/// Tells the handler, deregisters and drops connection `index`.
+fn close<D: Domain, H: Handler<D>>(
+	poller: &Poller,
+	conns: &mut [Option<Connection<D>>],
+	free: &mut Vec<usize>,
+	index: usize,
+	handler: &mut H,
+) {
+	if let Some(conn) = conns[index].take() {
+		handler.on_close(&conn);
+		let _ = poller.deregister(&conn.stream);
+		free.push(index);
+	}
+}
+
+/*
+Why level-triggered epoll and one read per wakeup?
+Edge-triggered needs every socket drained to WouldBlock on each event,
+so one fast client can monopolize the loop. Level-triggered with a
+single read per event round-robins between ready connections for free,
+and WRITABLE is only requested while output is queued, so idle
+connections cost nothing. It's the simplest loop that stays fair, which
+is the point of a turnkey server.
+*/